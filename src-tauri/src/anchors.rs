@@ -0,0 +1,186 @@
+//! Template items placed relative to a phrase of the document rather
+//! than at a fixed spot : a signature anchored to "Lu et approuvé"
+//! follows the signature block when a revision of the contract moves
+//! it down half a page.
+//!
+//! Anchors are resolved when the template is applied, from the text
+//! layer of the target PDF (see `search`). Items whose phrase is not
+//! found keep their stored page and rect and are reported, so the
+//! caller decides whether that is acceptable.
+
+use std::collections::HashMap;
+
+use lopdf::Document;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::items::{Item, PdfPoint};
+use crate::pages::PdfSource;
+use crate::search::{self, SearchHit};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Anchor {
+    /// Matched like a `search_pdf` query : case, accents and line
+    /// breaks do not matter.
+    pub text: String,
+    /// From the bottom-left corner of the phrase (of its first line
+    /// when it spans several) to that of the item, in points.
+    #[serde(default)]
+    pub offset: PdfPoint,
+    /// Which match, 1-indexed in reading order ; negative counts from
+    /// the end, -1 being the last one.
+    #[serde(default = "first_occurrence")]
+    pub occurrence: i32,
+}
+
+fn first_occurrence() -> i32 {
+    1
+}
+
+/// An item as stored in a template : the overlay item itself, plus the
+/// anchor that overrides its page and position when there is one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateItem {
+    #[serde(flatten)]
+    pub item: Item,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<Anchor>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingAnchor {
+    pub item_id: String,
+    pub text: String,
+    pub occurrence: i32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedItems {
+    pub items: Vec<Item>,
+    pub missing: Vec<MissingAnchor>,
+}
+
+fn pick(hits: &[SearchHit], occurrence: i32) -> Option<&SearchHit> {
+    let index = match occurrence {
+        0 => return None,
+        n if n > 0 => usize::try_from(n - 1).ok()?,
+        n => hits.len().checked_sub(usize::try_from(n.unsigned_abs()).ok()?)?,
+    };
+    hits.get(index)
+}
+
+/// Place the anchored items of `items` on `doc`. The text layer is
+/// searched once per phrase.
+pub fn resolve(doc: &Document, items: &[TemplateItem]) -> ResolvedItems {
+    let mut hits: HashMap<&str, Vec<SearchHit>> = HashMap::new();
+    let mut resolved = ResolvedItems { items: Vec::with_capacity(items.len()), missing: Vec::new() };
+    for TemplateItem { item, anchor } in items {
+        let mut item = item.clone();
+        if let Some(anchor) = anchor {
+            // An empty phrase is an error for `search` ; here it simply
+            // matches nothing.
+            let found =
+                hits.entry(&anchor.text).or_insert_with(|| search::search(doc, &anchor.text).unwrap_or_default());
+            match pick(found, anchor.occurrence).and_then(|hit| Some((hit.page, *hit.rects.first()?))) {
+                Some((page, rect)) => item.place(page, rect.x + anchor.offset.x, rect.y + anchor.offset.y),
+                None => resolved.missing.push(MissingAnchor {
+                    item_id: item.id().to_string(),
+                    text: anchor.text.clone(),
+                    occurrence: anchor.occurrence,
+                }),
+            }
+        }
+        resolved.items.push(item);
+    }
+    resolved
+}
+
+/// Resolve the anchors of a template being applied to `document`.
+#[tauri::command(async)]
+pub fn resolve_template_anchors(
+    app: tauri::AppHandle,
+    document: PdfSource,
+    items: Vec<TemplateItem>,
+) -> Result<ResolvedItems> {
+    if items.iter().all(|item| item.anchor.is_none()) {
+        return Ok(ResolvedItems { items: items.into_iter().map(|item| item.item).collect(), missing: Vec::new() });
+    }
+    let doc = search::read_text_source(&app, document)?;
+    Ok(resolve(&doc, &items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::tests::with_content;
+
+    fn template_items(json: serde_json::Value) -> Vec<TemplateItem> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn anchored_items_follow_their_phrase() {
+        let (doc, _) = with_content(
+            b"BT /F1 12 Tf 72 700 Td (Contrat de prestation) Tj ET\n\
+              BT /F1 10 Tf 72 300 Td (Lu et approuv\\351) Tj ET\n\
+              BT /F1 10 Tf 300 300 Td (Lu et approuv\\351) Tj ET",
+        );
+        let items = template_items(serde_json::json!([
+            { "id": "s", "type": "signature", "page": 3, "rect": { "x": 72, "y": 500, "w": 150, "h": 50 },
+              "signatureId": "sig-1", "anchor": { "text": "lu et approuve", "offset": { "x": 0, "y": -60 } } },
+            { "id": "l", "type": "line", "page": 3, "rect": { "x": 0, "y": 0, "w": 100, "h": 10 },
+              "start": { "x": 0, "y": 5 }, "end": { "x": 100, "y": 5 }, "color": "#000000", "strokeWidth": 1,
+              "anchor": { "text": "Lu et approuvé", "occurrence": -1 } },
+            { "id": "t", "type": "text", "page": 2, "rect": { "x": 10, "y": 20, "w": 100, "h": 14 },
+              "value": "Fixe", "fontSize": 12, "color": "#000000" }
+        ]));
+
+        let resolved = resolve(&doc, &items);
+        assert!(resolved.missing.is_empty());
+        // Helvetica : the box of the phrase starts at its baseline minus
+        // a quarter of the size.
+        match &resolved.items[0] {
+            Item::Signature(item) => {
+                assert_eq!(item.page, 1);
+                assert!((item.rect.x - 72.0).abs() < 1e-9 && (item.rect.y - 237.5).abs() < 1e-9);
+                assert_eq!((item.rect.w, item.rect.h), (150.0, 50.0));
+            }
+            other => panic!("unexpected item {other:?}"),
+        }
+        match &resolved.items[1] {
+            Item::Line(line) => {
+                assert_eq!(line.start, Some(PdfPoint { x: 300.0, y: 302.5 }));
+                assert_eq!(line.end, Some(PdfPoint { x: 400.0, y: 302.5 }));
+            }
+            other => panic!("unexpected item {other:?}"),
+        }
+        assert_eq!((resolved.items[2].page(), resolved.items[2].id()), (2, "t"));
+    }
+
+    #[test]
+    fn reports_anchors_that_are_not_found() {
+        let (doc, _) = with_content(b"BT /F1 12 Tf 72 700 Td (Lu et approuv\\351) Tj ET");
+        let items = template_items(serde_json::json!([
+            { "id": "a", "type": "signature", "page": 2, "rect": { "x": 72, "y": 100, "w": 150, "h": 50 },
+              "signatureId": "sig-1", "anchor": { "text": "Bon pour accord" } },
+            { "id": "b", "type": "signature", "page": 2, "rect": { "x": 72, "y": 100, "w": 150, "h": 50 },
+              "signatureId": "sig-1", "anchor": { "text": "Lu et approuvé", "occurrence": 2 } }
+        ]));
+        let resolved = resolve(&doc, &items);
+        assert_eq!(
+            resolved.missing,
+            [
+                MissingAnchor { item_id: "a".into(), text: "Bon pour accord".into(), occurrence: 1 },
+                MissingAnchor { item_id: "b".into(), text: "Lu et approuvé".into(), occurrence: 2 },
+            ]
+        );
+        // Left where the template put them.
+        assert!(resolved.items.iter().all(|item| item.page() == 2));
+        // The anchor is kept when the template is saved again.
+        assert_eq!(serde_json::to_value(&items[0]).unwrap()["anchor"]["text"], "Bon pour accord");
+    }
+}
@@ -0,0 +1,551 @@
+//! Whole-profile backup and restore, to move a user's galleries,
+//! templates and settings to another machine.
+//!
+//! A backup is a single JSON document listing the raw bytes of every
+//! profile file with its SHA-256. Files are copied as they are on disk,
+//! the database through a consistent copy : sealed images stay sealed
+//! and travel with `vault.json`, so the same passphrase unlocks them on
+//! the new machine. Machine-specific state (recent files, approved
+//! folders, store history) is left out.
+//!
+//! Backups made before the database hold the JSON stores instead ;
+//! restoring one drops the database, which then imports them.
+//!
+//! Restoring is a two-step operation : the chosen backup is checked and
+//! previewed (the database row by row, its bytes differing on every
+//! copy), then applied. Before anything is replaced, the current
+//! profile is saved under `pre-restore/` so the restore can be undone.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::schema;
+use crate::{db, images, store, vault};
+
+const FORMAT: &str = "cerfini-backup";
+const BACKUP_VERSION: u32 = 1;
+const EXTENSION: &str = "cerfini-backup";
+const SNAPSHOT_DIR: &str = "pre-restore";
+/// Pre-restore snapshots kept ; older ones are deleted.
+const MAX_SNAPSHOTS: usize = 5;
+
+/// Every file making up a profile, relative to `app_data_dir`.
+const PROFILE_FILES: &[&str] = &[
+    db::DB_FILE,
+    crate::SIGNATURES_FILE,
+    crate::PARAPHS_FILE,
+    crate::TEMPLATES_FILE,
+    crate::SNIPPETS_FILE,
+    vault::VAULT_FILE,
+    "trusted_certificates.json",
+    "tsa.json",
+    images::SETTINGS_FILE,
+];
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupFile {
+    name: String,
+    /// Hex SHA-256 of the decoded `content`.
+    sha256: String,
+    /// Base64 of the file bytes.
+    content: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Archive {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    app_version: String,
+    #[serde(default)]
+    created_at: String,
+    files: Vec<BackupFile>,
+}
+
+/// A parsed backup : file names with their verified bytes.
+struct Profile {
+    app_version: String,
+    created_at: String,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    /// Not present locally.
+    Added,
+    Replaced,
+    Unchanged,
+    /// Present locally but not in the backup.
+    Removed,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    name: String,
+    /// Size in the backup ; 0 for removed files.
+    size: usize,
+    change: Change,
+    /// For the database, the rows that differ. Its bytes always do,
+    /// so `change` says whether any row does.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    records: Vec<RecordPreview>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordPreview {
+    store: &'static str,
+    id: String,
+    name: String,
+    change: Change,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorePreview {
+    path: String,
+    app_version: String,
+    created_at: String,
+    files: Vec<FilePreview>,
+}
+
+/// The backup picked by `preview_profile_restore`, waiting for the
+/// user's confirmation. The renderer never names the file itself.
+#[derive(Default)]
+pub struct PendingRestore(Mutex<Option<Profile>>);
+
+fn invalid() -> Error {
+    Error::new(ErrorKind::CorruptData, "backup_invalid")
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    crate::pdfio::hex(&Sha256::digest(bytes)).to_lowercase()
+}
+
+/// Current bytes of a profile file. A corrupt primary is replaced by
+/// its `.bak`, like on load, so a backup never captures a torn file.
+fn read_current(dir: &Path, name: &str) -> Result<Option<Vec<u8>>> {
+    if name == db::DB_FILE {
+        return db::export_copy(dir);
+    }
+    store::load_with(&dir.join(name), |bytes| {
+        serde_json::from_slice::<serde_json::Value>(bytes)
+            .map(|_| bytes.to_vec())
+            .map_err(|e| Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e))
+    })
+}
+
+fn collect(dir: &Path) -> Result<Profile> {
+    let mut files = Vec::new();
+    for name in PROFILE_FILES {
+        let bytes = read_current(dir, name)?;
+        if let Some(bytes) = bytes {
+            files.push((name.to_string(), bytes));
+        }
+    }
+    Ok(Profile {
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        files,
+    })
+}
+
+fn encode(profile: &Profile) -> Result<Vec<u8>> {
+    let archive = Archive {
+        format: FORMAT.into(),
+        version: BACKUP_VERSION,
+        app_version: profile.app_version.clone(),
+        created_at: profile.created_at.clone(),
+        files: profile
+            .files
+            .iter()
+            .map(|(name, bytes)| BackupFile { name: name.clone(), sha256: sha256_hex(bytes), content: BASE64.encode(bytes) })
+            .collect(),
+    };
+    serde_json::to_vec_pretty(&archive)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))
+}
+
+/// Decode a backup and check it file by file : known names only, each
+/// at most once, matching checksum, and no store from a newer version
+/// of the app (restoring it would leave the profile unreadable).
+fn parse(bytes: &[u8]) -> Result<Profile> {
+    let archive: Archive = serde_json::from_slice(bytes).map_err(|e| invalid().with_details(e))?;
+    if archive.format != FORMAT {
+        return Err(invalid().with_details(archive.format));
+    }
+    if archive.version > BACKUP_VERSION {
+        return Err(Error::new(ErrorKind::NewerVersion, "backup_version_newer")
+            .with_details(format!("version {}", archive.version)));
+    }
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    for file in archive.files {
+        if !PROFILE_FILES.contains(&file.name.as_str()) || files.iter().any(|(name, _)| *name == file.name) {
+            return Err(invalid().with_details(file.name));
+        }
+        let content = BASE64
+            .decode(&file.content)
+            .map_err(|e| invalid().with_details(format!("{}: {e}", file.name)))?;
+        if sha256_hex(&content) != file.sha256.to_lowercase() {
+            return Err(Error::new(ErrorKind::CorruptData, "backup_checksum_mismatch").with_details(file.name));
+        }
+        if file.name == db::DB_FILE {
+            db::check_copy(&content).map_err(|err| err.with_details(&file.name))?;
+        } else {
+            let value: serde_json::Value = serde_json::from_slice(&content)
+                .map_err(|e| invalid().with_details(format!("{}: {e}", file.name)))?;
+            // Sealed galleries cannot be inspected without the key.
+            if let Some(schema) = schema::for_store(&file.name).filter(|_| value.get("cipher").is_none()) {
+                schema::ensure_writable(schema, value).map_err(|err| err.with_details(&file.name))?;
+            }
+        }
+        files.push((file.name, content));
+    }
+    Ok(Profile { app_version: archive.app_version, created_at: archive.created_at, files })
+}
+
+/// Rows added, replaced or removed when `current` gives way to
+/// `incoming`, matched by store and id.
+fn record_changes(current: &[db::Record], incoming: &[db::Record]) -> Vec<RecordPreview> {
+    let same = |a: &db::Record, b: &db::Record| a.store == b.store && a.id == b.id;
+    let mut changes: Vec<RecordPreview> = incoming
+        .iter()
+        .filter_map(|record| {
+            let change = match current.iter().find(|other| same(other, record)) {
+                None => Change::Added,
+                Some(other) if other == record => return None,
+                Some(_) => Change::Replaced,
+            };
+            Some(RecordPreview { store: record.store, id: record.id.clone(), name: record.name.clone(), change })
+        })
+        .collect();
+    changes.extend(current.iter().filter(|record| !incoming.iter().any(|other| same(other, record))).map(|record| {
+        RecordPreview { store: record.store, id: record.id.clone(), name: record.name.clone(), change: Change::Removed }
+    }));
+    changes
+}
+
+/// The database compared row by row ; `None` when either side cannot
+/// be read, to fall back on comparing bytes.
+fn database_changes(dir: &Path, incoming: Option<&[u8]>) -> Option<Vec<RecordPreview>> {
+    let current = db::records(dir).ok()?;
+    let incoming = match incoming {
+        Some(bytes) => db::copy_records(dir, bytes).ok()?,
+        None => Vec::new(),
+    };
+    Some(record_changes(&current, &incoming))
+}
+
+fn preview(dir: &Path, profile: &Profile) -> Vec<FilePreview> {
+    PROFILE_FILES
+        .iter()
+        .filter_map(|name| {
+            let current = read_current(dir, name).ok().flatten();
+            let incoming = profile.files.iter().find(|(file, _)| file == name).map(|(_, bytes)| bytes);
+            let mut change = match (current, incoming) {
+                (None, None) => return None,
+                (None, Some(_)) => Change::Added,
+                (Some(_), None) => Change::Removed,
+                (Some(current), Some(incoming)) if current == *incoming => Change::Unchanged,
+                (Some(_), Some(_)) => Change::Replaced,
+            };
+            let mut records = Vec::new();
+            if *name == db::DB_FILE {
+                if let Some(changes) = database_changes(dir, incoming.map(Vec::as_slice)) {
+                    if change == Change::Replaced && changes.is_empty() {
+                        change = Change::Unchanged;
+                    }
+                    records = changes;
+                }
+            }
+            Some(FilePreview { name: name.to_string(), size: incoming.map(Vec::len).unwrap_or(0), change, records })
+        })
+        .collect()
+}
+
+/// Make the profile files in `dir` exactly those of `profile`. The
+/// `.bak` generations and the database journal are dropped : they
+/// belong to the replaced profile and must not resurface.
+fn apply(dir: &Path, profile: &Profile) -> Result<()> {
+    std::fs::create_dir_all(dir).map_err(|e| Error::io("create_dir_failed", dir, &e))?;
+    for name in PROFILE_FILES {
+        let path = dir.join(name);
+        let backup = store::backup_path(&path);
+        match profile.files.iter().find(|(file, _)| file == name) {
+            Some((_, bytes)) => {
+                store::write_atomic(&path, bytes, false).map_err(|e| Error::io("store_write_failed", &path, &e))?
+            }
+            None => remove_if_exists(&path)?,
+        }
+        remove_if_exists(&backup)?;
+    }
+    remove_if_exists(&dir.join(format!("{}-journal", db::DB_FILE)))?;
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::io("store_write_failed", path, &e)),
+        _ => Ok(()),
+    }
+}
+
+/// Snapshot names sort by date : `20261016T044208.123Z.cerfini-backup`.
+fn snapshots(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir.join(SNAPSHOT_DIR))
+        .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    paths.retain(|path| path.extension().and_then(|ext| ext.to_str()) == Some(EXTENSION));
+    paths.sort();
+    paths
+}
+
+/// Save the current profile under `pre-restore/`, then apply `profile`.
+fn restore(dir: &Path, profile: &Profile) -> Result<()> {
+    let snapshot_dir = dir.join(SNAPSHOT_DIR);
+    std::fs::create_dir_all(&snapshot_dir).map_err(|e| Error::io("create_dir_failed", &snapshot_dir, &e))?;
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+    let snapshot = snapshot_dir.join(format!("{stamp}.{EXTENSION}"));
+    store::write_atomic(&snapshot, &encode(&collect(dir)?)?, false)
+        .map_err(|e| Error::io("backup_write_failed", &snapshot, &e))?;
+
+    apply(dir, profile)?;
+
+    let existing = snapshots(dir);
+    for old in existing.iter().take(existing.len().saturating_sub(MAX_SNAPSHOTS)) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(())
+}
+
+/// Put back the profile saved by the latest restore and drop that
+/// snapshot. Returns false when there is nothing to undo.
+fn undo(dir: &Path) -> Result<bool> {
+    let Some(latest) = snapshots(dir).pop() else {
+        return Ok(false);
+    };
+    let bytes = std::fs::read(&latest).map_err(|e| Error::io("backup_read_failed", &latest, &e))?;
+    apply(dir, &parse(&bytes).map_err(|err| err.with_path(&latest))?)?;
+    remove_if_exists(&latest)?;
+    Ok(true)
+}
+
+/// The replaced profile may use another vault : drop the current key
+/// and let the frontend reload every store.
+fn after_restore(app: &tauri::AppHandle) {
+    app.state::<vault::Vault>().lock();
+    if let Err(err) = app.emit("profile-restored", ()) {
+        eprintln!("emit profile-restored failed: {err}");
+    }
+}
+
+fn picked_path(picked: tauri_plugin_dialog::FilePath) -> Result<PathBuf> {
+    picked
+        .into_path()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "path_invalid").with_details(e))
+}
+
+/// Write the whole profile to a file chosen through the save dialog.
+/// Returns the written path, or `None` when the user cancelled.
+#[tauri::command(async)]
+pub fn backup_profile(app: tauri::AppHandle) -> Result<Option<String>> {
+    let bytes = encode(&collect(&crate::app_data_dir(&app)?)?)?;
+    let suggested = format!("cerfini-{}.{EXTENSION}", chrono::Utc::now().format("%Y-%m-%d"));
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .add_filter("Cerfini backup", &[EXTENSION])
+        .set_file_name(suggested)
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = picked_path(picked)?;
+    store::write_atomic(&path, &bytes, false).map_err(|e| Error::io("backup_write_failed", &path, &e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Pick a backup through the open dialog, check it and describe what
+/// restoring it would change. Nothing is written until
+/// `restore_profile`. Returns `None` when the user cancelled.
+#[tauri::command(async)]
+pub fn preview_profile_restore(
+    app: tauri::AppHandle,
+    pending: tauri::State<PendingRestore>,
+) -> Result<Option<RestorePreview>> {
+    let Some(picked) = app.dialog().file().add_filter("Cerfini backup", &[EXTENSION]).blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = picked_path(picked)?;
+    let bytes = std::fs::read(&path).map_err(|e| Error::io("backup_read_failed", &path, &e))?;
+    let profile = parse(&bytes).map_err(|err| err.with_path(&path))?;
+    let result = RestorePreview {
+        path: path.to_string_lossy().to_string(),
+        app_version: profile.app_version.clone(),
+        created_at: profile.created_at.clone(),
+        files: preview(&crate::app_data_dir(&app)?, &profile),
+    };
+    *pending.0.lock().unwrap() = Some(profile);
+    Ok(Some(result))
+}
+
+/// Apply the backup previewed last. The current profile is kept under
+/// `pre-restore/` for `undo_profile_restore`. Emits `"profile-restored"`.
+#[tauri::command]
+pub fn restore_profile(app: tauri::AppHandle, pending: tauri::State<PendingRestore>) -> Result<()> {
+    let Some(profile) = pending.0.lock().unwrap().take() else {
+        return Err(Error::new(ErrorKind::InvalidInput, "backup_not_previewed"));
+    };
+    restore(&crate::app_data_dir(&app)?, &profile)?;
+    after_restore(&app);
+    Ok(())
+}
+
+/// Return to the profile as it was before the last restore. Returns
+/// false when no restore can be undone.
+#[tauri::command]
+pub fn undo_profile_restore(app: tauri::AppHandle) -> Result<bool> {
+    let undone = undo(&crate::app_data_dir(&app)?)?;
+    if undone {
+        after_restore(&app);
+    }
+    Ok(undone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{asset, tiny_png};
+    use crate::store::tests::scratch_dir;
+
+    fn read(dir: &Path, name: &str) -> Option<String> {
+        std::fs::read_to_string(dir.join(name)).ok()
+    }
+
+    #[test]
+    fn backup_round_trips_every_profile_file() {
+        let dir = scratch_dir("round-trip");
+        db::save_snippets(&dir, &["Lu et approuvé".to_string()]).unwrap();
+        std::fs::write(dir.join("tsa.json"), br#"{"url":"https://tsa.example"}"#).unwrap();
+        std::fs::write(dir.join("recent.json"), b"[]").unwrap();
+
+        let profile = parse(&encode(&collect(&dir).unwrap()).unwrap()).unwrap();
+        let names: Vec<&str> = profile.files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec![db::DB_FILE, "tsa.json"]);
+        assert_eq!(profile.files[1].1, br#"{"url":"https://tsa.example"}"#);
+
+        let target = scratch_dir("round-trip-target");
+        apply(&target, &profile).unwrap();
+        assert_eq!(db::load_snippets(&target).unwrap(), vec!["Lu et approuvé"]);
+    }
+
+    #[test]
+    fn tampered_or_foreign_files_are_refused() {
+        let dir = scratch_dir("tampered");
+        std::fs::write(dir.join("tsa.json"), b"{}").unwrap();
+        let mut archive: serde_json::Value = serde_json::from_slice(&encode(&collect(&dir).unwrap()).unwrap()).unwrap();
+
+        archive["files"][0]["content"] = BASE64.encode(b"{\"url\":\"x\"}").into();
+        let bytes = serde_json::to_vec(&archive).unwrap();
+        assert_eq!(parse(&bytes).err().unwrap().code, "backup_checksum_mismatch");
+
+        archive["files"][0]["name"] = "../../.bashrc".into();
+        let bytes = serde_json::to_vec(&archive).unwrap();
+        assert_eq!(parse(&bytes).err().unwrap().code, "backup_invalid");
+    }
+
+    #[test]
+    fn store_from_a_newer_app_is_refused() {
+        let content = br#"{"version":99,"data":[]}"#;
+        let archive = serde_json::json!({
+            "format": FORMAT,
+            "version": BACKUP_VERSION,
+            "files": [{ "name": crate::TEMPLATES_FILE, "sha256": sha256_hex(content), "content": BASE64.encode(content) }]
+        });
+        let err = parse(&serde_json::to_vec(&archive).unwrap()).err().unwrap();
+        assert_eq!(err.kind, ErrorKind::NewerVersion);
+    }
+
+    #[test]
+    fn database_preview_lists_the_rows_that_differ() {
+        let vault = vault::Vault::default();
+        let source = scratch_dir("rows-source");
+        for id in ["a", "b"] {
+            db::add_asset(&vault, &source, db::Gallery::Signatures, asset(id, "image/png", tiny_png())).unwrap();
+        }
+        db::save_snippets(&source, &["Lu et approuvé".to_string()]).unwrap();
+        let profile = collect(&source).unwrap();
+
+        // Same signatures, written in another order.
+        let dir = scratch_dir("rows-target");
+        for id in ["b", "a"] {
+            db::add_asset(&vault, &dir, db::Gallery::Signatures, asset(id, "image/png", tiny_png())).unwrap();
+        }
+        db::add_asset(&vault, &dir, db::Gallery::Paraphs, asset("p", "image/png", tiny_png())).unwrap();
+        db::save_snippets(&dir, &["Bon pour accord".to_string()]).unwrap();
+
+        let database = |dir: &Path| preview(dir, &profile).into_iter().find(|file| file.name == db::DB_FILE).unwrap();
+        let changes: Vec<(&str, String, Change)> =
+            database(&dir).records.into_iter().map(|record| (record.store, record.id, record.change)).collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("snippets", "Lu et approuvé".to_string(), Change::Added),
+                ("paraphs", "p".to_string(), Change::Removed),
+                ("snippets", "Bon pour accord".to_string(), Change::Removed),
+            ]
+        );
+
+        db::delete_asset(&vault, &dir, db::Gallery::Paraphs, "p").unwrap();
+        db::save_snippets(&dir, &["Lu et approuvé".to_string()]).unwrap();
+        assert_ne!(read_current(&dir, db::DB_FILE).unwrap().unwrap(), profile.files[0].1);
+        let file = database(&dir);
+        assert_eq!((file.change, file.records.len()), (Change::Unchanged, 0));
+    }
+
+    #[test]
+    fn restore_previews_replaces_and_can_be_undone() {
+        let source = scratch_dir("source");
+        std::fs::write(source.join(crate::SNIPPETS_FILE), b"[\"new\"]").unwrap();
+        std::fs::write(source.join("tsa.json"), b"{}").unwrap();
+        let profile = collect(&source).unwrap();
+
+        let dir = scratch_dir("target");
+        std::fs::write(dir.join(crate::SNIPPETS_FILE), b"[\"old\"]").unwrap();
+        std::fs::write(store::backup_path(&dir.join(crate::SNIPPETS_FILE)), b"[\"older\"]").unwrap();
+        std::fs::write(dir.join(crate::TEMPLATES_FILE), b"[]").unwrap();
+        std::fs::write(dir.join("tsa.json"), b"{}").unwrap();
+
+        let changes: Vec<(String, Change)> = preview(&dir, &profile).into_iter().map(|file| (file.name, file.change)).collect();
+        assert_eq!(
+            changes,
+            vec![
+                (crate::TEMPLATES_FILE.to_string(), Change::Removed),
+                (crate::SNIPPETS_FILE.to_string(), Change::Replaced),
+                ("tsa.json".to_string(), Change::Unchanged),
+            ]
+        );
+
+        restore(&dir, &profile).unwrap();
+        assert_eq!(read(&dir, crate::SNIPPETS_FILE).as_deref(), Some("[\"new\"]"));
+        assert!(read(&dir, crate::TEMPLATES_FILE).is_none());
+        assert!(!store::backup_path(&dir.join(crate::SNIPPETS_FILE)).exists());
+
+        assert!(undo(&dir).unwrap());
+        assert_eq!(read(&dir, crate::SNIPPETS_FILE).as_deref(), Some("[\"old\"]"));
+        assert_eq!(read(&dir, crate::TEMPLATES_FILE).as_deref(), Some("[]"));
+        assert!(!undo(&dir).unwrap());
+    }
+}
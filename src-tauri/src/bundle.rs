@@ -0,0 +1,567 @@
+//! Portable template bundles (`.cerfini` files) for sharing templates
+//! between machines.
+//!
+//! A bundle is a single JSON document holding the selected templates
+//! (in the same versioned envelope as `templates.json`) together with
+//! the signature and paraph images they reference, which only exist in
+//! the local galleries. On import, images are validated and matched
+//! against the local galleries by content ; templates are merged by
+//! name with the conflict policy picked by the user.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::Emitter;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::anchors::TemplateItem;
+use crate::error::{Error, ErrorKind, Result};
+use crate::{db, images, schema, store, vault, StoredSignature};
+
+const FORMAT: &str = "cerfini-bundle";
+const BUNDLE_VERSION: u32 = 1;
+const EXTENSION: &str = "cerfini";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    format: String,
+    version: u32,
+    #[serde(default)]
+    app_version: String,
+    #[serde(default)]
+    created_at: String,
+    /// `{ version, data }` like `templates.json`, so templates from an
+    /// older app go through the same migrations.
+    templates: Value,
+    #[serde(default)]
+    signatures: Vec<StoredSignature>,
+    #[serde(default)]
+    paraphs: Vec<StoredSignature>,
+}
+
+/// What to do with an imported template whose name (or id) already
+/// exists locally.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    Skip,
+    Rename,
+    Overwrite,
+}
+
+#[derive(Serialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Names of the templates added without conflict.
+    imported: Vec<String>,
+    /// New names of the templates imported under another name.
+    renamed: Vec<String>,
+    overwritten: Vec<String>,
+    skipped: Vec<String>,
+    signatures_added: usize,
+    paraphs_added: usize,
+    /// Image ids the imported templates use but the bundle does not
+    /// carry : those items show nothing unless the local gallery
+    /// already has an image with that id.
+    missing_signatures: Vec<String>,
+    missing_paraphs: Vec<String>,
+}
+
+/// Local state the import merges into.
+#[derive(Default)]
+struct Stores {
+    templates: Vec<Value>,
+    signatures: Vec<StoredSignature>,
+    paraphs: Vec<StoredSignature>,
+}
+
+fn invalid_template() -> Error {
+    Error::new(ErrorKind::CorruptData, "bundle_template_invalid")
+}
+
+/// Random id in the same shape as `crypto.randomUUID()` on the frontend.
+fn new_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex = crate::pdfio::hex(&bytes).to_lowercase();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn signature_refs(template: &Value) -> impl Iterator<Item = &str> {
+    template
+        .get("items")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|item| text(item, "type") == Some("signature"))
+        .filter_map(|item| text(item, "signatureId"))
+}
+
+fn paraph_ref(template: &Value) -> Option<&str> {
+    template.get("paraph").and_then(|paraph| text(paraph, "assetId"))
+}
+
+/// Point the template's image references at their local ids.
+fn remap_refs(template: &mut Value, signatures: &HashMap<String, String>, paraphs: &HashMap<String, String>) {
+    if let Some(items) = template.get_mut("items").and_then(Value::as_array_mut) {
+        for item in items.iter_mut().filter(|item| text(item, "type") == Some("signature")) {
+            if let Some(local) = text(item, "signatureId").and_then(|id| signatures.get(id)) {
+                item["signatureId"] = Value::String(local.clone());
+            }
+        }
+    }
+    if let Some(paraph) = template.get_mut("paraph").filter(|paraph| paraph.is_object()) {
+        if let Some(local) = text(paraph, "assetId").and_then(|id| paraphs.get(id)) {
+            paraph["assetId"] = Value::String(local.clone());
+        }
+    }
+}
+
+/// Images go through [`images::normalize`] : the bundle's `mime`,
+/// `naturalW` and `naturalH` are not trusted.
+fn validate_image(asset: StoredSignature) -> Result<StoredSignature> {
+    let id = asset.id.clone();
+    images::normalize(asset, None)
+        .map_err(|err| Error::new(ErrorKind::CorruptData, "bundle_image_invalid").with_details(format!("{id}: {err}")))
+}
+
+/// Add the bundle images referenced by `wanted` to `gallery` and return
+/// the bundle id -> local id map. An image already present (same bytes)
+/// is reused ; a bundle id already taken by another image gets a new id.
+fn merge_assets(
+    gallery: &mut Vec<StoredSignature>,
+    incoming: Vec<StoredSignature>,
+    wanted: &HashSet<String>,
+) -> Result<(HashMap<String, String>, usize)> {
+    let mut ids = HashMap::new();
+    let mut added = 0;
+    for asset in incoming.into_iter().filter(|asset| wanted.contains(&asset.id)) {
+        if ids.contains_key(&asset.id) {
+            continue;
+        }
+        let mut asset = validate_image(asset)?;
+        let bundle_id = asset.id.clone();
+        if let Some(existing) = gallery.iter().find(|local| local.mime == asset.mime && local.bytes == asset.bytes) {
+            ids.insert(bundle_id, existing.id.clone());
+            continue;
+        }
+        if gallery.iter().any(|local| local.id == asset.id) {
+            asset.id = new_id();
+        }
+        ids.insert(bundle_id, asset.id.clone());
+        gallery.push(asset);
+        added += 1;
+    }
+    Ok((ids, added))
+}
+
+/// The ids of `wanted` that no image of `incoming` has, sorted.
+fn missing_ids(wanted: &HashSet<String>, incoming: &[StoredSignature]) -> Vec<String> {
+    let mut missing: Vec<String> =
+        wanted.iter().filter(|id| !incoming.iter().any(|asset| asset.id == **id)).cloned().collect();
+    missing.sort();
+    missing
+}
+
+/// `name (2)`, `name (3)`… : the first not already in `taken`.
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|idx| format!("{name} ({idx})"))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_default()
+}
+
+fn build(templates: Vec<Value>, signatures: &[StoredSignature], paraphs: &[StoredSignature]) -> Result<Bundle> {
+    let signature_ids: HashSet<&str> = templates.iter().flat_map(signature_refs).collect();
+    let paraph_ids: HashSet<&str> = templates.iter().filter_map(paraph_ref).collect();
+    let pick = |gallery: &[StoredSignature], ids: &HashSet<&str>| -> Vec<StoredSignature> {
+        gallery.iter().filter(|asset| ids.contains(asset.id.as_str())).cloned().collect()
+    };
+    let bundle = Bundle {
+        format: FORMAT.into(),
+        version: BUNDLE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").into(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        signatures: pick(signatures, &signature_ids),
+        paraphs: pick(paraphs, &paraph_ids),
+        templates: Value::Null,
+    };
+    Ok(Bundle { templates: schema::envelope(&schema::TEMPLATES, &templates)?, ..bundle })
+}
+
+fn parse(bytes: &[u8]) -> Result<Bundle> {
+    let bundle: Bundle = serde_json::from_slice(bytes)
+        .map_err(|e| Error::new(ErrorKind::CorruptData, "bundle_invalid").with_details(e))?;
+    if bundle.format != FORMAT {
+        return Err(Error::new(ErrorKind::CorruptData, "bundle_invalid").with_details(bundle.format));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(Error::new(ErrorKind::NewerVersion, "bundle_version_newer")
+            .with_details(format!("version {}", bundle.version)));
+    }
+    Ok(bundle)
+}
+
+/// Every template must at least have an id, a name and items the
+/// current app understands ; a bundle with one bad template is refused
+/// as a whole rather than half-imported.
+fn bundle_templates(templates: Value) -> Result<Vec<Value>> {
+    let templates: Vec<Value> = schema::decode(&schema::TEMPLATES, templates)?;
+    for template in &templates {
+        let name = text(template, "name").ok_or_else(invalid_template)?;
+        text(template, "id").ok_or_else(|| invalid_template().with_details(name))?;
+        serde_json::from_value::<Vec<TemplateItem>>(template.get("items").cloned().unwrap_or(Value::Null))
+            .map_err(|e| invalid_template().with_details(format!("{name}: {e}")))?;
+    }
+    Ok(templates)
+}
+
+fn merge(bundle: Bundle, local: &mut Stores, conflict: Conflict) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut accepted = Vec::new();
+    let mut taken: HashSet<String> = local.templates.iter().filter_map(|t| text(t, "name")).map(String::from).collect();
+
+    for mut template in bundle_templates(bundle.templates)? {
+        let name = text(&template, "name").unwrap_or_default().to_string();
+        let id = text(&template, "id").unwrap_or_default().to_string();
+        let same = |t: &Value| text(t, "id") == Some(id.as_str()) || text(t, "name") == Some(name.as_str());
+        let existing = local
+            .templates
+            .iter()
+            .position(|t| text(t, "id") == Some(id.as_str()))
+            .or_else(|| local.templates.iter().position(|t| text(t, "name") == Some(name.as_str())));
+        // An earlier template of the bundle conflicts too.
+        let in_bundle = accepted.iter().any(|(t, _)| same(t));
+        let target = match (existing, conflict) {
+            (None, _) if !in_bundle => {
+                report.imported.push(name.clone());
+                None
+            }
+            (_, Conflict::Skip) => {
+                report.skipped.push(name);
+                continue;
+            }
+            (Some(pos), Conflict::Overwrite) if !in_bundle => {
+                template["id"] = local.templates[pos]["id"].clone();
+                report.overwritten.push(name.clone());
+                Some(pos)
+            }
+            // Overwriting with a second template of the bundle would
+            // drop the first one : it is renamed instead.
+            (_, Conflict::Rename | Conflict::Overwrite) => {
+                let renamed = unique_name(&name, &taken);
+                template["id"] = Value::String(new_id());
+                template["name"] = Value::String(renamed.clone());
+                report.renamed.push(renamed.clone());
+                taken.insert(renamed);
+                None
+            }
+        };
+        taken.insert(name);
+        accepted.push((template, target));
+    }
+
+    let wanted_signatures: HashSet<String> =
+        accepted.iter().flat_map(|(t, _)| signature_refs(t)).map(String::from).collect();
+    let wanted_paraphs: HashSet<String> = accepted.iter().filter_map(|(t, _)| paraph_ref(t)).map(String::from).collect();
+    report.missing_signatures = missing_ids(&wanted_signatures, &bundle.signatures);
+    report.missing_paraphs = missing_ids(&wanted_paraphs, &bundle.paraphs);
+    let (signature_ids, added) = merge_assets(&mut local.signatures, bundle.signatures, &wanted_signatures)?;
+    report.signatures_added = added;
+    let (paraph_ids, added) = merge_assets(&mut local.paraphs, bundle.paraphs, &wanted_paraphs)?;
+    report.paraphs_added = added;
+
+    for (mut template, target) in accepted {
+        remap_refs(&mut template, &signature_ids, &paraph_ids);
+        match target {
+            Some(pos) => local.templates[pos] = template,
+            None => local.templates.push(template),
+        }
+    }
+    Ok(report)
+}
+
+fn with_extension(path: PathBuf) -> PathBuf {
+    let has_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case(EXTENSION))
+        .unwrap_or(false);
+    if has_extension {
+        path
+    } else {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(EXTENSION);
+        path.with_file_name(name)
+    }
+}
+
+fn picked_path(picked: tauri_plugin_dialog::FilePath) -> Result<PathBuf> {
+    picked
+        .into_path()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "path_invalid").with_details(e))
+}
+
+/// Write the templates with `template_ids` to a bundle chosen through
+/// the save dialog. Returns the written path, or `None` when the user
+/// cancelled.
+#[tauri::command(async)]
+pub fn export_template_bundle(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    template_ids: Vec<String>,
+) -> Result<Option<String>> {
+    let dir = crate::app_data_dir(&app)?;
+    let templates: Vec<Value> = db::load_templates(&dir)?
+        .into_iter()
+        .filter(|template| text(template, "id").is_some_and(|id| template_ids.iter().any(|wanted| wanted == id)))
+        .collect();
+    if templates.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "bundle_empty"));
+    }
+
+    // The galleries may be sealed : only touch them when needed.
+    let signatures: Vec<StoredSignature> = if templates.iter().flat_map(signature_refs).next().is_some() {
+        db::load_assets(&vault, &dir, db::Gallery::Signatures)?
+    } else {
+        Vec::new()
+    };
+    let paraphs: Vec<StoredSignature> = if templates.iter().any(|t| paraph_ref(t).is_some()) {
+        db::load_assets(&vault, &dir, db::Gallery::Paraphs)?
+    } else {
+        Vec::new()
+    };
+
+    let suggested = match templates.as_slice() {
+        [single] => format!("{}.{EXTENSION}", text(single, "name").unwrap_or("template")),
+        _ => format!("templates.{EXTENSION}"),
+    };
+    let suggested = Path::new(&suggested)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("templates.cerfini")
+        .to_string();
+    let bundle = build(templates, &signatures, &paraphs)?;
+
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .add_filter("Cerfini", &[EXTENSION])
+        .set_file_name(suggested)
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = with_extension(picked_path(picked)?);
+    let bytes = serde_json::to_vec_pretty(&bundle)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))?;
+    store::write_atomic(&path, &bytes, false).map_err(|e| Error::io("bundle_write_failed", &path, &e))?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Import a bundle chosen through the open dialog and merge it into the
+/// local stores. Emits `"templates-imported"` so the frontend reloads
+/// the templates and galleries before it next saves them. Returns
+/// `None` when the user cancelled.
+#[tauri::command(async)]
+pub fn import_template_bundle(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    conflict: Conflict,
+) -> Result<Option<ImportReport>> {
+    let Some(picked) = app.dialog().file().add_filter("Cerfini", &[EXTENSION]).blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = picked_path(picked)?;
+    let bytes = std::fs::read(&path).map_err(|e| Error::io("bundle_read_failed", &path, &e))?;
+    let bundle = parse(&bytes).map_err(|err| err.with_path(&path))?;
+
+    let dir = crate::app_data_dir(&app)?;
+    let has_signatures = !bundle.signatures.is_empty();
+    let has_paraphs = !bundle.paraphs.is_empty();
+    let mut local = Stores {
+        templates: db::load_templates(&dir)?,
+        ..Stores::default()
+    };
+    if has_signatures {
+        local.signatures = db::load_assets(&vault, &dir, db::Gallery::Signatures)?;
+    }
+    if has_paraphs {
+        local.paraphs = db::load_assets(&vault, &dir, db::Gallery::Paraphs)?;
+    }
+
+    let report = merge(bundle, &mut local, conflict).map_err(|err| err.with_path(&path))?;
+
+    // Images first : a template never points at an image not yet saved.
+    if report.signatures_added > 0 {
+        db::save_assets(&vault, &dir, db::Gallery::Signatures, local.signatures)?;
+    }
+    if report.paraphs_added > 0 {
+        db::save_assets(&vault, &dir, db::Gallery::Paraphs, local.paraphs)?;
+    }
+    db::save_templates(&dir, &local.templates)?;
+    if let Err(err) = app.emit("templates-imported", &report) {
+        eprintln!("emit templates-imported failed: {err}");
+    }
+    Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{asset, tiny_png};
+    use serde_json::json;
+
+    fn template(id: &str, name: &str, signature: &str, paraph: &str) -> Value {
+        json!({
+            "id": id,
+            "name": name,
+            "updatedAt": "2026-01-01T00:00:00.000Z",
+            "paraph": { "assetId": paraph, "rect": { "x": 500, "y": 20, "w": 60, "h": 30 } },
+            "items": [
+                { "id": "s", "type": "signature", "page": 1, "rect": { "x": 10, "y": 10, "w": 100, "h": 40 }, "signatureId": signature }
+            ]
+        })
+    }
+
+    fn other_png() -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, 1, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0]).unwrap();
+        drop(writer);
+        out
+    }
+
+    /// Bundle of one template using signature `sig` and paraph `par`.
+    fn shared_bundle() -> Bundle {
+        let signatures = vec![asset("sig", "image/png", tiny_png()), asset("unused", "image/png", other_png())];
+        let paraphs = vec![asset("par", "image/png", other_png())];
+        let bytes = serde_json::to_vec(&build(vec![template("t1", "Contrat", "sig", "par")], &signatures, &paraphs).unwrap()).unwrap();
+        parse(&bytes).unwrap()
+    }
+
+    #[test]
+    fn export_embeds_only_referenced_images() {
+        let bundle = shared_bundle();
+        assert_eq!(bundle.signatures.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), vec!["sig"]);
+        assert_eq!(bundle.paraphs.len(), 1);
+    }
+
+    #[test]
+    fn import_into_empty_stores_keeps_ids() {
+        let mut local = Stores::default();
+        let report = merge(shared_bundle(), &mut local, Conflict::Rename).unwrap();
+        assert_eq!(report.imported, vec!["Contrat"]);
+        assert_eq!((report.signatures_added, report.paraphs_added), (1, 1));
+        assert_eq!(local.templates[0]["items"][0]["signatureId"], "sig");
+        assert_eq!((local.signatures[0].natural_w, local.signatures[0].natural_h), (2, 1));
+    }
+
+    #[test]
+    fn identical_images_are_reused_and_taken_ids_remapped() {
+        let mut local = Stores {
+            // Same bytes under another id, and another image squatting "par".
+            signatures: vec![asset("mine", "image/png", tiny_png())],
+            paraphs: vec![asset("par", "image/png", tiny_png())],
+            ..Stores::default()
+        };
+        let report = merge(shared_bundle(), &mut local, Conflict::Rename).unwrap();
+        assert_eq!((report.signatures_added, report.paraphs_added), (0, 1));
+        let template = &local.templates[0];
+        assert_eq!(template["items"][0]["signatureId"], "mine");
+        let paraph_id = template["paraph"]["assetId"].as_str().unwrap();
+        assert_ne!(paraph_id, "par");
+        assert_eq!(local.paraphs.iter().find(|a| a.id == paraph_id).unwrap().bytes, other_png());
+    }
+
+    #[test]
+    fn name_conflicts_follow_the_chosen_policy() {
+        let local_template = template("local", "Contrat", "x", "y");
+
+        let mut local = Stores { templates: vec![local_template.clone()], ..Stores::default() };
+        let report = merge(shared_bundle(), &mut local, Conflict::Skip).unwrap();
+        assert_eq!(report.skipped, vec!["Contrat"]);
+        assert_eq!(local.templates, vec![local_template.clone()]);
+        assert!(local.signatures.is_empty());
+
+        let mut local = Stores { templates: vec![local_template.clone()], ..Stores::default() };
+        let report = merge(shared_bundle(), &mut local, Conflict::Rename).unwrap();
+        assert_eq!(report.renamed, vec!["Contrat (2)"]);
+        assert_eq!(local.templates.len(), 2);
+        assert_ne!(local.templates[1]["id"], "t1");
+
+        let mut local = Stores { templates: vec![local_template], ..Stores::default() };
+        let report = merge(shared_bundle(), &mut local, Conflict::Overwrite).unwrap();
+        assert_eq!(report.overwritten, vec!["Contrat"]);
+        assert_eq!(local.templates.len(), 1);
+        assert_eq!(local.templates[0]["id"], "local");
+        assert_eq!(local.templates[0]["items"][0]["signatureId"], "sig");
+    }
+
+    #[test]
+    fn duplicate_names_within_the_bundle_follow_the_policy() {
+        let templates = vec![template("t1", "Contrat", "sig", "par"), template("t2", "Contrat", "sig", "par")];
+        let bundle = || {
+            let signatures = [asset("sig", "image/png", tiny_png())];
+            let paraphs = [asset("par", "image/png", other_png())];
+            parse(&serde_json::to_vec(&build(templates.clone(), &signatures, &paraphs).unwrap()).unwrap()).unwrap()
+        };
+
+        let mut local = Stores::default();
+        let report = merge(bundle(), &mut local, Conflict::Rename).unwrap();
+        assert_eq!((report.imported, report.renamed), (vec!["Contrat".to_string()], vec!["Contrat (2)".to_string()]));
+
+        let mut local = Stores::default();
+        let report = merge(bundle(), &mut local, Conflict::Overwrite).unwrap();
+        assert!(report.overwritten.is_empty());
+        let names: Vec<&str> = local.templates.iter().filter_map(|t| text(t, "name")).collect();
+        assert_eq!(names, vec!["Contrat", "Contrat (2)"]);
+
+        let mut local = Stores::default();
+        let report = merge(bundle(), &mut local, Conflict::Skip).unwrap();
+        assert_eq!((report.imported.len(), report.skipped.len(), local.templates.len()), (1, 1, 1));
+    }
+
+    #[test]
+    fn references_the_bundle_lacks_are_reported() {
+        let bundle = build(vec![template("t1", "Contrat", "gone", "par")], &[], &[]).unwrap();
+        let bytes = serde_json::to_vec(&bundle).unwrap();
+        let mut local = Stores::default();
+        let report = merge(parse(&bytes).unwrap(), &mut local, Conflict::Rename).unwrap();
+        assert_eq!((report.missing_signatures, report.missing_paraphs), (vec!["gone".into()], vec!["par".into()]));
+        assert_eq!(report.imported, vec!["Contrat"]);
+
+        let report = merge(shared_bundle(), &mut Stores::default(), Conflict::Rename).unwrap();
+        assert!(report.missing_signatures.is_empty() && report.missing_paraphs.is_empty());
+    }
+
+    #[test]
+    fn image_that_does_not_decode_is_refused() {
+        let mut bundle = shared_bundle();
+        bundle.signatures[0].bytes = b"<script>".to_vec();
+        let mut local = Stores::default();
+        let err = merge(bundle, &mut local, Conflict::Rename).unwrap_err();
+        assert_eq!(err.code, "bundle_image_invalid");
+        assert!(local.templates.is_empty());
+    }
+
+    #[test]
+    fn newer_bundle_is_refused() {
+        let bytes = serde_json::to_vec(&json!({ "format": FORMAT, "version": 99, "templates": [] })).unwrap();
+        assert_eq!(parse(&bytes).err().unwrap().kind, ErrorKind::NewerVersion);
+    }
+}
@@ -0,0 +1,478 @@
+//! Headless command-line mode for batch processing on machines without
+//! a display :
+//!
+//! ```text
+//! cerfini apply --template "Contrat NEXT2" --out signed/ *.pdf
+//! ```
+//!
+//! The template, signatures and paraphs come from the same stores as
+//! the desktop app. A JSON summary is printed on stdout ; the exit code
+//! is 0 when every file was written, 1 when some failed and 2 when
+//! nothing could be attempted (bad arguments, unknown template, locked
+//! vault).
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::anchors::{self, TemplateItem};
+use crate::error::{Error, ErrorKind, Result};
+use crate::export::{self, FormValues, Overlay};
+use crate::pdfio::IncrementalUpdate;
+use crate::items::{Item, Paraph};
+use crate::{db, vault, StoredSignature};
+
+/// Same as `identifier` in `tauri.conf.json` ; Tauri's app data dir is
+/// the platform data dir joined with it.
+const APP_IDENTIFIER: &str = "com.coubiac.cerfini";
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_PARTIAL: i32 = 1;
+pub const EXIT_FAILURE: i32 = 2;
+
+#[derive(Parser)]
+#[command(name = "cerfini", version, about = "Fill & sign PDFs from the command line")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply a saved template to PDFs and write flattened copies.
+    Apply(ApplyArgs),
+}
+
+#[derive(Args)]
+struct ApplyArgs {
+    /// Template name (or id) as saved in the app. With several
+    /// templates of the same name, the most recently updated wins.
+    #[arg(long)]
+    template: String,
+    /// Output directory, created when missing.
+    #[arg(long)]
+    out: PathBuf,
+    /// Locale of the date written into date items.
+    #[arg(long, value_enum, default_value_t = DateLocale::Fr)]
+    locale: DateLocale,
+    /// Read stores from this directory instead of the app data dir.
+    #[arg(long)]
+    data_dir: Option<PathBuf>,
+    /// Environment variable holding the vault passphrase ; without it
+    /// the key is looked up in the OS keyring.
+    #[arg(long)]
+    passphrase_env: Option<String>,
+    /// PDF files to process.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+}
+
+/// The app's locales, for `autoDate` items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateLocale {
+    #[default]
+    Fr,
+    En,
+    De,
+    Es,
+    Zh,
+    Ja,
+    Ar,
+    Uk,
+}
+
+/// Same output as `formatLocaleDate` (`Intl.DateTimeFormat` with
+/// 2-digit day and month, numeric year) for each supported locale.
+fn format_locale_date(locale: DateLocale, date: NaiveDate) -> String {
+    let (day, month, year) = (date.day(), date.month(), date.year());
+    match locale {
+        DateLocale::Fr | DateLocale::Es => format!("{day:02}/{month:02}/{year}"),
+        DateLocale::En => format!("{month:02}/{day:02}/{year}"),
+        DateLocale::De | DateLocale::Uk => format!("{day:02}.{month:02}.{year}"),
+        DateLocale::Zh | DateLocale::Ja => format!("{year}/{month:02}/{day:02}"),
+        DateLocale::Ar => {
+            let arabic = |value: String| -> String {
+                value
+                    .chars()
+                    .map(|c| c.to_digit(10).and_then(|d| char::from_u32(0x0660 + d)).unwrap_or(c))
+                    .collect()
+            };
+            // Intl separates the fields with a right-to-left mark.
+            format!(
+                "{}\u{200f}/{}\u{200f}/{}",
+                arabic(format!("{day:02}")),
+                arabic(format!("{month:02}")),
+                arabic(year.to_string())
+            )
+        }
+    }
+}
+
+/// The fields of a saved template the flattening needs. Templates are
+/// persisted as opaque JSON, so entries that do not fit are skipped.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredTemplate {
+    id: String,
+    name: String,
+    #[serde(default)]
+    updated_at: String,
+    items: Vec<TemplateItem>,
+    #[serde(default)]
+    paraph: Option<Paraph>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileResult {
+    input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Error>,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template: Option<String>,
+    succeeded: usize,
+    failed: usize,
+    files: Vec<FileResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Error>,
+}
+
+/// Whether `args` (program name included) ask for a subcommand rather
+/// than files to open in the GUI.
+pub fn is_command(args: &[OsString]) -> bool {
+    let Some(first) = args.get(1).and_then(|arg| arg.to_str()) else {
+        return false;
+    };
+    Cli::command().get_subcommands().any(|sub| sub.get_name() == first)
+}
+
+fn default_data_dir() -> Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| Error::new(ErrorKind::Unavailable, "app_data_dir_unavailable"))
+}
+
+fn find_template(dir: &Path, wanted: &str) -> Result<StoredTemplate> {
+    let templates: Vec<StoredTemplate> = db::load_templates(dir)?
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect();
+    if let Some(pos) = templates.iter().position(|template| template.id == wanted) {
+        return Ok(templates.into_iter().nth(pos).unwrap());
+    }
+    templates
+        .into_iter()
+        .filter(|template| template.name == wanted)
+        .max_by(|a, b| a.updated_at.cmp(&b.updated_at))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "template_not_found").with_details(wanted))
+}
+
+/// A template ready to be applied : date items filled with today's
+/// date and the images it references loaded. Anchored items are placed
+/// for each file.
+pub struct Job {
+    pub template_name: String,
+    items: Vec<TemplateItem>,
+    signatures: Vec<StoredSignature>,
+    paraph: Option<(Paraph, StoredSignature)>,
+    form_values: FormValues,
+}
+
+impl Job {
+    /// Look up `template` (name or id) in the stores of `dir`.
+    pub fn prepare(vault: &vault::Vault, dir: &Path, template: &str, locale: DateLocale) -> Result<Job> {
+        let template = find_template(dir, template)?;
+        let date = format_locale_date(locale, chrono::Local::now().date_naive());
+        let items: Vec<TemplateItem> = template
+            .items
+            .into_iter()
+            .map(|mut stored| {
+                if let Item::Text(text) = &mut stored.item {
+                    if text.auto_date == Some(true) {
+                        text.value = date.clone();
+                    }
+                }
+                stored
+            })
+            .collect();
+
+        let signatures: Vec<StoredSignature> = db::load_assets(vault, dir, db::Gallery::Signatures)?;
+        let paraph = match template.paraph {
+            Some(paraph) => {
+                let asset = db::load_assets(vault, dir, db::Gallery::Paraphs)?
+                    .into_iter()
+                    .find(|asset| asset.id == paraph.asset_id)
+                    .ok_or_else(|| Error::new(ErrorKind::NotFound, "paraph_not_found").with_details(&paraph.asset_id))?;
+                Some((paraph, asset))
+            }
+            None => None,
+        };
+        Ok(Job { template_name: template.name, items, signatures, paraph, form_values: FormValues::new() })
+    }
+
+    /// The items for the PDF `original`. A file where an anchor is not
+    /// found fails rather than getting a signature in the wrong spot.
+    pub fn place(&self, original: &[u8]) -> Result<Vec<Item>> {
+        if self.items.iter().all(|stored| stored.anchor.is_none()) {
+            return Ok(self.items.iter().map(|stored| stored.item.clone()).collect());
+        }
+        let doc = lopdf::Document::load_mem(original)
+            .map_err(|e| Error::new(ErrorKind::InvalidPdf, "pdf_invalid").with_details(e))?;
+        let resolved = anchors::resolve(&doc, &self.items);
+        if !resolved.missing.is_empty() {
+            let phrases: Vec<&str> = resolved.missing.iter().map(|missing| missing.text.as_str()).collect();
+            return Err(Error::new(ErrorKind::NotFound, "anchor_not_found").with_details(phrases.join(", ")));
+        }
+        Ok(resolved.items)
+    }
+
+    pub fn overlay<'a>(&'a self, items: &'a [Item]) -> Overlay<'a> {
+        Overlay {
+            items,
+            signatures: &self.signatures,
+            paraph: self.paraph.as_ref().map(|(paraph, asset)| (paraph, asset)),
+            form_values: &self.form_values,
+        }
+    }
+}
+
+/// Output name for `input`, following the GUI's suggestion
+/// (`<name>-signed.pdf`) and `next_available_path` on collisions.
+fn output_path(out_dir: &Path, input: &Path) -> PathBuf {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("document");
+    let file_name = crate::sanitize_file_name(&format!("{stem}-signed.pdf"));
+    crate::next_available_path(out_dir.to_path_buf(), &file_name)
+}
+
+/// Flatten the template of `job` onto `input` and write the copy into
+/// `out_dir`.
+pub fn process(input: &Path, out_dir: &Path, job: &Job) -> Result<PathBuf> {
+    if !crate::is_pdf_path(input) {
+        return Err(Error::new(ErrorKind::InvalidPdf, "not_a_pdf").with_path(input));
+    }
+    let original = std::fs::read(input).map_err(|e| Error::io("pdf_read_failed", input, &e))?;
+    let items = job.place(&original).map_err(|err| err.with_path(input))?;
+    let tail = IncrementalUpdate::new(&original)
+        .and_then(|update| export::flatten(update, &job.overlay(&items)))
+        .and_then(|update| update.tail())
+        .map_err(|details| Error::new(ErrorKind::InvalidPdf, "export_failed").with_path(input).with_details(details))?;
+    drop(original);
+    let target = output_path(out_dir, input);
+    export::write_export(input, &target, &tail)?;
+    Ok(target)
+}
+
+fn apply(args: ApplyArgs, summary: &mut Summary) -> Result<()> {
+    let dir = match args.data_dir {
+        Some(dir) => dir,
+        None => default_data_dir()?,
+    };
+    let passphrase = match &args.passphrase_env {
+        Some(var) => Some(
+            std::env::var(var)
+                .map_err(|_| Error::new(ErrorKind::InvalidInput, "passphrase_env_missing").with_details(var))?,
+        ),
+        None => None,
+    };
+    let vault = vault::Vault::default();
+    vault::unlock_headless(&vault, &dir, passphrase.as_deref())?;
+
+    let job = Job::prepare(&vault, &dir, &args.template, args.locale)?;
+    summary.template = Some(job.template_name.clone());
+
+    std::fs::create_dir_all(&args.out).map_err(|e| Error::io("create_dir_failed", &args.out, &e))?;
+    for input in &args.inputs {
+        let result = process(input, &args.out, &job);
+        let (output, error) = match result {
+            Ok(path) => {
+                summary.succeeded += 1;
+                (Some(path.to_string_lossy().to_string()), None)
+            }
+            Err(err) => {
+                summary.failed += 1;
+                (None, Some(err))
+            }
+        };
+        summary.files.push(FileResult { input: input.to_string_lossy().to_string(), output, error });
+    }
+    Ok(())
+}
+
+/// Run the subcommand in `args` and return the process exit code.
+pub fn run(args: Vec<OsString>) -> i32 {
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return if err.use_stderr() { EXIT_FAILURE } else { EXIT_OK };
+        }
+    };
+    let mut summary = Summary::default();
+    let code = match cli.command {
+        Command::Apply(args) => match apply(args, &mut summary) {
+            Err(err) => {
+                summary.error = Some(err);
+                EXIT_FAILURE
+            }
+            Ok(()) if summary.failed > 0 => EXIT_PARTIAL,
+            Ok(()) => EXIT_OK,
+        },
+    };
+    match serde_json::to_string_pretty(&summary) {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("summary serialization failed: {err}"),
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::scratch_dir;
+    use crate::pdfio::tests::sample_pdf;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn identifier_matches_tauri_config() {
+        let config: serde_json::Value = serde_json::from_str(include_str!("../tauri.conf.json")).unwrap();
+        assert_eq!(config["identifier"], APP_IDENTIFIER);
+    }
+
+    #[test]
+    fn only_subcommands_leave_the_gui_path() {
+        assert!(is_command(&args(&["cerfini", "apply", "--template", "x"])));
+        assert!(!is_command(&args(&["cerfini", "/tmp/apply.pdf"])));
+        assert!(!is_command(&args(&["cerfini"])));
+    }
+
+    #[test]
+    fn formats_dates_like_intl() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 7).unwrap();
+        assert_eq!(format_locale_date(DateLocale::Fr, date), "07/03/2026");
+        assert_eq!(format_locale_date(DateLocale::En, date), "03/07/2026");
+        assert_eq!(format_locale_date(DateLocale::De, date), "07.03.2026");
+        assert_eq!(format_locale_date(DateLocale::Ja, date), "2026/03/07");
+        assert_eq!(format_locale_date(DateLocale::Ar, date), "٠٧\u{200f}/٠٣\u{200f}/٢٠٢٦");
+    }
+
+    #[test]
+    fn apply_writes_numbered_outputs_and_reports_failures() {
+        let dir = scratch_dir("apply");
+        let data = dir.join("data");
+        let out = dir.join("out");
+        crate::db::save_templates(
+            &data,
+            serde_json::json!([
+                { "id": "old", "name": "Contrat", "updatedAt": "2025-01-01T00:00:00Z", "items": [], "paraph": null },
+                { "id": "new", "name": "Contrat", "updatedAt": "2026-01-01T00:00:00Z", "paraph": null, "items": [
+                    { "id": "d", "type": "text", "page": 1, "rect": { "x": 10, "y": 10, "w": 200, "h": 20 },
+                      "value": "", "fontSize": 12, "color": "#000000", "fontFamily": "sans",
+                      "bold": false, "underline": false, "strike": false, "autoDate": true }
+                ] }
+            ])
+            .as_array()
+            .unwrap(),
+        )
+        .unwrap();
+        let input = dir.join("contrat.pdf");
+        std::fs::write(&input, sample_pdf(1, "Contrat")).unwrap();
+        std::fs::create_dir_all(&out).unwrap();
+        std::fs::write(out.join("contrat-signed.pdf"), b"existing").unwrap();
+
+        let apply_args = ApplyArgs {
+            template: "Contrat".into(),
+            out: out.clone(),
+            locale: DateLocale::Fr,
+            data_dir: Some(data),
+            passphrase_env: None,
+            inputs: vec![input.clone(), dir.join("missing.pdf")],
+        };
+        let mut summary = Summary::default();
+        apply(apply_args, &mut summary).unwrap();
+
+        assert_eq!((summary.succeeded, summary.failed), (1, 1));
+        let written = PathBuf::from(summary.files[0].output.as_ref().unwrap());
+        assert_eq!(written, out.join("contrat-signed (1).pdf"));
+        let doc = lopdf::Document::load(&written).unwrap();
+        let content = doc.get_and_decode_page_content(doc.get_pages()[&1]).unwrap();
+        let today = format_locale_date(DateLocale::Fr, chrono::Local::now().date_naive());
+        assert!(content
+            .operations
+            .iter()
+            .any(|op| op.operator == "Tj" && op.operands[0].as_str().ok() == Some(today.as_bytes())));
+        assert_eq!(summary.files[1].error.as_ref().map(|err| err.kind), Some(ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn files_without_the_anchor_phrase_fail() {
+        let dir = scratch_dir("anchors");
+        let data = dir.join("data");
+        let out = dir.join("out");
+        crate::db::save_templates(
+            &data,
+            serde_json::json!([
+                { "id": "a", "name": "Ancré", "updatedAt": "2026-01-01T00:00:00Z", "paraph": null, "items": [
+                    { "id": "v", "type": "text", "page": 2, "rect": { "x": 400, "y": 10, "w": 100, "h": 20 },
+                      "value": "Vu", "fontSize": 12, "color": "#000000",
+                      "anchor": { "text": "contrat", "offset": { "x": 0, "y": -40 } } }
+                ] }
+            ])
+            .as_array()
+            .unwrap(),
+        )
+        .unwrap();
+        let contract = dir.join("contrat.pdf");
+        let invoice = dir.join("facture.pdf");
+        std::fs::write(&contract, sample_pdf(1, "Contrat")).unwrap();
+        std::fs::write(&invoice, sample_pdf(1, "Facture")).unwrap();
+        std::fs::create_dir_all(&out).unwrap();
+
+        let vault = vault::Vault::default();
+        vault::unlock_headless(&vault, &data, None).unwrap();
+        let job = Job::prepare(&vault, &data, "Ancré", DateLocale::Fr).unwrap();
+
+        let written = process(&contract, &out, &job).unwrap();
+        let doc = lopdf::Document::load(&written).unwrap();
+        let content = doc.get_and_decode_page_content(doc.get_pages()[&1]).unwrap();
+        assert!(content
+            .operations
+            .iter()
+            .any(|op| op.operator == "Tj" && op.operands[0].as_str().ok() == Some(&b"Vu"[..])));
+
+        let err = process(&invoice, &out, &job).unwrap_err();
+        assert_eq!((err.code, err.details.as_deref()), ("anchor_not_found", Some("contrat")));
+        assert!(!out.join("facture-signed.pdf").exists());
+    }
+
+    #[test]
+    fn unknown_template_fails_before_touching_files() {
+        let dir = scratch_dir("unknown");
+        let code = run(args(&[
+            "cerfini",
+            "apply",
+            "--template",
+            "Nope",
+            "--out",
+            dir.join("out").to_str().unwrap(),
+            "--data-dir",
+            dir.to_str().unwrap(),
+            "a.pdf",
+        ]));
+        assert_eq!(code, EXIT_FAILURE);
+        assert!(!dir.join("out").exists());
+    }
+}
@@ -0,0 +1,756 @@
+//! Password-protected PDFs : the standard security handler of ISO
+//! 32000 (RC4 40 to 128 bits, AES-128 and AES-256, revisions 2 to 6).
+//!
+//! Neither pdf.js nor pdf-lib get to see encrypted bytes : on open the
+//! document is decrypted here, with the user password (or the owner
+//! one) and rewritten in clear for the renderer. On save, the bytes
+//! produced by pdf-lib can be encrypted again, always with AES-256
+//! (revision 6), the only variant current readers do not flag as weak.
+//!
+//! Passwords are taken as typed : Latin-1 for the RC4 and AES-128
+//! revisions, UTF-8 (without SASLprep) for AES-256.
+//!
+//! The password a file was opened with is kept in memory for the
+//! session ([`Passwords`]), so the commands that read the file again by
+//! path (native export, redaction, search, page edits) see it in clear
+//! too.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::{Aes128, Aes256};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use lopdf::xref::XrefEntry;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, ObjectStream, Reader, StringFormat};
+use md5::Md5;
+use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// Padding string of algorithm 2, also the user entry of revision 2.
+const PAD: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08, 0x2E, 0x2E, 0x00,
+    0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+/// AES-256 passwords are cut to this many bytes.
+const MAX_PASSWORD: usize = 127;
+
+/// Permission bits of `/P`, numbered from 1 as in the specification.
+const PERMIT_PRINT: u32 = 1 << 2;
+const PERMIT_MODIFY: u32 = 1 << 3;
+const PERMIT_COPY: u32 = 1 << 4;
+const PERMIT_ANNOTATE: u32 = 1 << 5;
+const PERMIT_ASSEMBLE: u32 = 1 << 10;
+const PERMIT_PRINT_HIGH: u32 = 1 << 11;
+
+/// Options of `save_pdf_to_path` for a protected copy.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Protection {
+    /// Needed to open the document ; empty opens without a prompt and
+    /// only the permissions apply.
+    #[serde(default)]
+    pub user_password: String,
+    /// Lifts the permissions ; when empty a random one is used, so
+    /// they cannot be lifted at all.
+    #[serde(default)]
+    pub owner_password: String,
+    #[serde(default)]
+    pub no_print: bool,
+    #[serde(default)]
+    pub no_modify: bool,
+    #[serde(default)]
+    pub no_copy: bool,
+}
+
+impl Protection {
+    /// `/P` : everything allowed but what was asked to be denied, the
+    /// reserved bits set.
+    fn permissions(&self) -> u32 {
+        let mut bits = 0xFFFF_FFFC;
+        if self.no_print {
+            bits &= !(PERMIT_PRINT | PERMIT_PRINT_HIGH);
+        }
+        if self.no_modify {
+            bits &= !(PERMIT_MODIFY | PERMIT_ANNOTATE | PERMIT_ASSEMBLE);
+        }
+        if self.no_copy {
+            bits &= !PERMIT_COPY;
+        }
+        bits
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Method {
+    Identity,
+    Rc4,
+    Aes128,
+    Aes256,
+}
+
+/// What a string or stream is, for the few that stay in clear.
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    String,
+    Stream,
+}
+
+/// The file key of an opened document and how it applies.
+struct Handler {
+    key: Vec<u8>,
+    strings: Method,
+    streams: Method,
+    encrypt_metadata: bool,
+}
+
+fn invalid(details: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidPdf, "pdf_encryption_invalid").with_details(details)
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j = 0u8;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, usize::from(j));
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[usize::from(i)]);
+            state.swap(usize::from(i), usize::from(j));
+            byte ^ state[usize::from(state[usize::from(i)].wrapping_add(state[usize::from(j)]))]
+        })
+        .collect()
+}
+
+/// RC4 under `key` XOR `round` for each round in turn, as algorithms 5
+/// and 7 do.
+fn rc4_rounds(key: &[u8], data: &[u8], rounds: impl Iterator<Item = u8>) -> Vec<u8> {
+    rounds.fold(data.to_vec(), |data, round| {
+        let key: Vec<u8> = key.iter().map(|byte| byte ^ round).collect();
+        rc4(&key, &data)
+    })
+}
+
+/// `iv || ciphertext`, as strings and streams are stored.
+fn aes_encrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let iv: [u8; 16] = random();
+    let body = match key.len() {
+        16 => cbc::Encryptor::<Aes128>::new_from_slices(key, &iv).map(|c| c.encrypt_padded_vec_mut::<Pkcs7>(data)),
+        _ => cbc::Encryptor::<Aes256>::new_from_slices(key, &iv).map(|c| c.encrypt_padded_vec_mut::<Pkcs7>(data)),
+    }
+    .expect("key and iv lengths are fixed");
+    [iv.as_slice(), body.as_slice()].concat()
+}
+
+/// `None` when `data` is not a whole number of blocks or its padding
+/// is wrong.
+fn aes_decrypt(key: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 16 || data.len() % 16 != 0 {
+        return None;
+    }
+    let (iv, body) = data.split_at(16);
+    if body.is_empty() {
+        return Some(Vec::new());
+    }
+    match key.len() {
+        16 => cbc::Decryptor::<Aes128>::new_from_slices(key, iv).ok()?.decrypt_padded_vec_mut::<Pkcs7>(body).ok(),
+        _ => cbc::Decryptor::<Aes256>::new_from_slices(key, iv).ok()?.decrypt_padded_vec_mut::<Pkcs7>(body).ok(),
+    }
+}
+
+/// AES-256 without IV nor padding, for `/UE`, `/OE` and `/Perms`.
+fn aes256_block(key: &[u8], data: &[u8], encrypt: bool) -> Vec<u8> {
+    let iv = [0u8; 16];
+    if encrypt {
+        cbc::Encryptor::<Aes256>::new_from_slices(key, &iv).map(|c| c.encrypt_padded_vec_mut::<NoPadding>(data))
+    } else {
+        cbc::Decryptor::<Aes256>::new_from_slices(key, &iv)
+            .map(|c| c.decrypt_padded_vec_mut::<NoPadding>(data).unwrap_or_default())
+    }
+    .unwrap_or_default()
+}
+
+/// Algorithm 2.B (revision 6), or plain SHA-256 for revision 5.
+fn password_hash(revision: i64, password: &[u8], salt: &[u8], user_entry: &[u8]) -> Vec<u8> {
+    let mut k = Sha256::new().chain_update(password).chain_update(salt).chain_update(user_entry).finalize().to_vec();
+    if revision == 5 {
+        return k;
+    }
+    let mut round = 0usize;
+    loop {
+        let block = [password, k.as_slice(), user_entry].concat().repeat(64);
+        let e = cbc::Encryptor::<Aes128>::new_from_slices(&k[..16], &k[16..32])
+            .expect("key and iv lengths are fixed")
+            .encrypt_padded_vec_mut::<NoPadding>(&block);
+        // The first 16 bytes as a big number modulo 3 ; 256 is 1 mod 3.
+        k = match e[..16].iter().map(|&byte| u32::from(byte)).sum::<u32>() % 3 {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && usize::from(e[e.len() - 1]) <= round - 32 {
+            break;
+        }
+    }
+    k.truncate(32);
+    k
+}
+
+/// Latin-1, which PDFDocEncoding matches for printable characters.
+fn legacy_password(password: &str) -> Vec<u8> {
+    password.chars().map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?')).collect()
+}
+
+fn padded(password: &[u8]) -> Vec<u8> {
+    let len = password.len().min(32);
+    [&password[..len], &PAD[..32 - len]].concat()
+}
+
+fn name<'a>(dict: &'a Dictionary, key: &[u8]) -> Option<&'a [u8]> {
+    dict.get(key).and_then(Object::as_name).ok()
+}
+
+fn bytes<'a>(dict: &'a Dictionary, key: &[u8]) -> Result<&'a [u8]> {
+    dict.get(key).and_then(Object::as_str).map_err(|_| invalid(format!("/{} missing", String::from_utf8_lossy(key))))
+}
+
+/// The `/Encrypt` dictionary and its object id when it is indirect.
+fn encryption(doc: &Document) -> Result<Option<(Option<ObjectId>, Dictionary)>> {
+    let Ok(entry) = doc.trailer.get(b"Encrypt") else {
+        return Ok(None);
+    };
+    let (id, object) = doc.dereference(entry).map_err(invalid)?;
+    let dict = object.as_dict().map_err(invalid)?.clone();
+    Ok(Some((id, dict)))
+}
+
+fn first_id(doc: &Document) -> Vec<u8> {
+    doc.trailer
+        .get(b"ID")
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|ids| ids.first())
+        .and_then(|id| id.as_str().ok())
+        .map(<[u8]>::to_vec)
+        .unwrap_or_default()
+}
+
+/// Crypt filter `key` (`/StmF` or `/StrF`) of a version 4 or 5 handler.
+fn crypt_filter(dict: &Dictionary, key: &[u8]) -> Result<Method> {
+    let Some(filter) = name(dict, key) else {
+        return Ok(Method::Identity);
+    };
+    if filter == b"Identity" {
+        return Ok(Method::Identity);
+    }
+    let method = dict
+        .get(b"CF")
+        .and_then(Object::as_dict)
+        .and_then(|filters| filters.get(filter))
+        .and_then(Object::as_dict)
+        .ok()
+        .and_then(|filter| name(filter, b"CFM"));
+    match method {
+        Some(b"V2") => Ok(Method::Rc4),
+        Some(b"AESV2") => Ok(Method::Aes128),
+        Some(b"AESV3") => Ok(Method::Aes256),
+        Some(b"None") | None => Ok(Method::Identity),
+        Some(other) => Err(Error::new(ErrorKind::Unavailable, "pdf_encryption_unsupported")
+            .with_details(String::from_utf8_lossy(other))),
+    }
+}
+
+impl Handler {
+    /// Check `password` against the user then the owner entry.
+    fn open(doc: &Document, dict: &Dictionary, password: &str) -> Result<Handler> {
+        if name(dict, b"Filter") != Some(b"Standard".as_slice()) {
+            let filter = name(dict, b"Filter").map(String::from_utf8_lossy).unwrap_or_default();
+            return Err(Error::new(ErrorKind::Unavailable, "pdf_encryption_unsupported").with_details(filter));
+        }
+        let int = |key: &[u8]| dict.get(key).and_then(Object::as_i64).ok();
+        let version = int(b"V").unwrap_or(0);
+        let revision = int(b"R").ok_or_else(|| invalid("/R missing"))?;
+        let encrypt_metadata = dict.get(b"EncryptMetadata").and_then(Object::as_bool).unwrap_or(true);
+        let (strings, streams) = match version {
+            1 | 2 => (Method::Rc4, Method::Rc4),
+            4 | 5 => (crypt_filter(dict, b"StrF")?, crypt_filter(dict, b"StmF")?),
+            other => {
+                return Err(Error::new(ErrorKind::Unavailable, "pdf_encryption_unsupported").with_details(format!("V {other}")))
+            }
+        };
+        let key = match revision {
+            2..=4 => {
+                let len = match version {
+                    1 => 5,
+                    4 => 16,
+                    _ => (int(b"Length").unwrap_or(40) / 8).clamp(5, 16) as usize,
+                };
+                legacy_key(doc, dict, revision, len, encrypt_metadata, &legacy_password(password))?
+            }
+            5 | 6 => aes256_key(dict, revision, password.as_bytes())?,
+            other => {
+                return Err(Error::new(ErrorKind::Unavailable, "pdf_encryption_unsupported").with_details(format!("R {other}")))
+            }
+        };
+        let key = key.ok_or_else(|| Error::new(ErrorKind::AuthenticationFailed, "pdf_password_incorrect"))?;
+        Ok(Handler { key, strings, streams, encrypt_metadata })
+    }
+
+    /// Algorithm 1 : the key of one object.
+    fn object_key(&self, id: ObjectId, aes: bool) -> Vec<u8> {
+        let mut hasher = Md5::new();
+        hasher.update(&self.key);
+        hasher.update(&id.0.to_le_bytes()[..3]);
+        hasher.update(&id.1.to_le_bytes()[..2]);
+        if aes {
+            hasher.update(b"sAlT");
+        }
+        hasher.finalize()[..(self.key.len() + 5).min(16)].to_vec()
+    }
+
+    fn decrypt(&self, id: ObjectId, target: Target, data: &[u8]) -> Vec<u8> {
+        let method = if target == Target::String { self.strings } else { self.streams };
+        let decrypted = match method {
+            Method::Identity => None,
+            Method::Rc4 => Some(rc4(&self.object_key(id, false), data)),
+            Method::Aes128 => aes_decrypt(&self.object_key(id, true), data),
+            Method::Aes256 => aes_decrypt(&self.key, data),
+        };
+        // Some writers leave a few strings in clear : keep what does
+        // not decrypt rather than refuse the whole document.
+        decrypted.unwrap_or_else(|| data.to_vec())
+    }
+}
+
+/// Algorithms 2, 6 and 7 : the file key from a user password, or from
+/// an owner password through the user password it hides in `/O`.
+fn legacy_key(
+    doc: &Document,
+    dict: &Dictionary,
+    revision: i64,
+    len: usize,
+    encrypt_metadata: bool,
+    password: &[u8],
+) -> Result<Option<Vec<u8>>> {
+    let owner = bytes(dict, b"O")?;
+    let user = bytes(dict, b"U")?;
+    let permissions = dict.get(b"P").and_then(Object::as_i64).map_err(|_| invalid("/P missing"))? as u32;
+    let id = first_id(doc);
+    let file_key = |padded_password: &[u8]| {
+        let mut hasher = Md5::new();
+        hasher.update(padded_password);
+        hasher.update(owner);
+        hasher.update(permissions.to_le_bytes());
+        hasher.update(&id);
+        if revision >= 4 && !encrypt_metadata {
+            hasher.update([0xFF; 4]);
+        }
+        let mut key = hasher.finalize().to_vec();
+        if revision >= 3 {
+            for _ in 0..50 {
+                key = Md5::digest(&key[..len]).to_vec();
+            }
+        }
+        key.truncate(len);
+        key
+    };
+    let user_matches = |key: &[u8]| {
+        if revision == 2 {
+            rc4(key, &PAD) == user
+        } else {
+            let hash = Md5::new().chain_update(PAD).chain_update(&id).finalize();
+            user.len() >= 16 && rc4_rounds(key, &hash, 0..=19) == user[..16]
+        }
+    };
+
+    let key = file_key(&padded(password));
+    if user_matches(&key) {
+        return Ok(Some(key));
+    }
+    let mut owner_key = Md5::digest(padded(password)).to_vec();
+    if revision >= 3 {
+        for _ in 0..50 {
+            owner_key = Md5::digest(&owner_key).to_vec();
+        }
+    }
+    owner_key.truncate(len);
+    let user_password = if revision == 2 { rc4(&owner_key, owner) } else { rc4_rounds(&owner_key, owner, (0..=19).rev()) };
+    let key = file_key(&padded(&user_password));
+    Ok(user_matches(&key).then_some(key))
+}
+
+/// Algorithms 2.A and 11/12 : the file key sealed in `/UE` or `/OE`.
+fn aes256_key(dict: &Dictionary, revision: i64, password: &[u8]) -> Result<Option<Vec<u8>>> {
+    let password = &password[..password.len().min(MAX_PASSWORD)];
+    let (owner, user) = (bytes(dict, b"O")?, bytes(dict, b"U")?);
+    let (owner_key, user_key) = (bytes(dict, b"OE")?, bytes(dict, b"UE")?);
+    if owner.len() < 48 || user.len() < 48 || owner_key.len() != 32 || user_key.len() != 32 {
+        return Err(invalid("/O /U /OE /UE entries truncated"));
+    }
+    let user = &user[..48];
+    if password_hash(revision, password, &user[32..40], &[]) == user[..32] {
+        let key = password_hash(revision, password, &user[40..48], &[]);
+        return Ok(Some(aes256_block(&key, user_key, false)));
+    }
+    if password_hash(revision, password, &owner[32..40], user) == owner[..32] {
+        let key = password_hash(revision, password, &owner[40..48], user);
+        return Ok(Some(aes256_block(&key, owner_key, false)));
+    }
+    Ok(None)
+}
+
+/// Apply `transform` to every string and stream content of `object`.
+/// Cross-reference streams, the `/Contents` of signature dictionaries
+/// and, unless `metadata`, XMP streams are never encrypted.
+fn walk(object: &mut Object, metadata: bool, transform: &mut dyn FnMut(Target, &[u8]) -> Vec<u8>) {
+    match object {
+        Object::String(bytes, format) => {
+            *bytes = transform(Target::String, bytes);
+            *format = StringFormat::Hexadecimal;
+        }
+        Object::Array(items) => {
+            for item in items {
+                walk(item, metadata, transform);
+            }
+        }
+        Object::Dictionary(dict) => walk_dictionary(dict, metadata, transform),
+        Object::Stream(stream) => {
+            walk_dictionary(&mut stream.dict, metadata, transform);
+            if !stream.dict.type_is(b"XRef") && (metadata || !stream.dict.type_is(b"Metadata")) {
+                let content = transform(Target::Stream, &stream.content);
+                stream.set_content(content);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk_dictionary(dict: &mut Dictionary, metadata: bool, transform: &mut dyn FnMut(Target, &[u8]) -> Vec<u8>) {
+    let signature = dict.has(b"ByteRange");
+    for (key, value) in dict.iter_mut() {
+        if signature && key == b"Contents" {
+            continue;
+        }
+        walk(value, metadata, transform);
+    }
+}
+
+/// Objects stored in object streams : lopdf could not read those
+/// streams while they were encrypted, so read them again from the file
+/// and decrypt them before parsing.
+fn recover_object_streams(doc: &mut Document, original: &[u8], handler: &Handler) {
+    let containers: BTreeSet<u32> = doc
+        .reference_table
+        .entries
+        .values()
+        .filter_map(|entry| match entry {
+            XrefEntry::Compressed { container, .. } => Some(*container),
+            _ => None,
+        })
+        .collect();
+    let mut lookup = Document::new();
+    lookup.reference_table = doc.reference_table.clone();
+    let reader = Reader { buffer: original, document: lookup };
+    for container in containers {
+        let Some(XrefEntry::Normal { generation, .. }) = doc.reference_table.get(container) else {
+            continue;
+        };
+        let id = (container, *generation);
+        let Ok(Object::Stream(mut stream)) = reader.get_object(id, &mut HashSet::new()) else {
+            continue;
+        };
+        let content = handler.decrypt(id, Target::Stream, &stream.content);
+        stream.set_content(content);
+        if let Ok(objects) = ObjectStream::new(&mut stream) {
+            for (id, object) in objects.objects {
+                doc.objects.entry(id).or_insert(object);
+            }
+        }
+    }
+}
+
+fn save(mut doc: Document) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    doc.save_to(&mut out).map_err(|e| Error::new(ErrorKind::InvalidPdf, "pdf_write_failed").with_details(e))?;
+    Ok(out)
+}
+
+/// Passwords of the protected files opened this session, by canonical
+/// path. Never written anywhere.
+#[derive(Default)]
+pub struct Passwords(Mutex<HashMap<PathBuf, String>>);
+
+impl Passwords {
+    /// Remember the password `path` was decrypted with, or forget it
+    /// when the file was opened in clear.
+    pub fn remember(&self, path: &Path, password: Option<&str>) {
+        let mut passwords = self.0.lock().unwrap();
+        match password {
+            Some(password) => passwords.insert(path.to_path_buf(), password.to_string()),
+            None => passwords.remove(path),
+        };
+    }
+
+    pub fn get(&self, path: &Path) -> Option<String> {
+        self.0.lock().unwrap().get(path).cloned()
+    }
+}
+
+/// `path` as the renderer got it : decrypted with the password it was
+/// opened with this session (or the empty one), as is when it is not
+/// encrypted.
+pub fn read_clear(passwords: &Passwords, path: &Path) -> Result<Vec<u8>> {
+    let bytes = std::fs::read(path).map_err(|e| Error::io("pdf_read_failed", path, &e))?;
+    let password = passwords.get(path);
+    Ok(decrypt(&bytes, password.as_deref()).map_err(|err| err.with_path(path))?.unwrap_or(bytes))
+}
+
+/// The document in clear, or `None` when it is not encrypted. Without a
+/// password, the empty one is tried : many files only restrict
+/// permissions and open without a prompt elsewhere.
+pub fn decrypt(original: &[u8], password: Option<&str>) -> Result<Option<Vec<u8>>> {
+    if !original.windows(8).any(|window| window == b"/Encrypt") {
+        return Ok(None);
+    }
+    let mut doc = Document::load_mem(original).map_err(|e| Error::new(ErrorKind::InvalidPdf, "pdf_invalid").with_details(e))?;
+    let Some((encrypt_id, dict)) = encryption(&doc)? else {
+        return Ok(None);
+    };
+    let handler = match Handler::open(&doc, &dict, password.unwrap_or_default()) {
+        Err(err) if password.is_none() && err.code == "pdf_password_incorrect" => {
+            return Err(Error::new(ErrorKind::AuthenticationFailed, "pdf_password_required"));
+        }
+        other => other?,
+    };
+
+    for (&id, object) in doc.objects.iter_mut() {
+        if Some(id) == encrypt_id {
+            continue;
+        }
+        walk(object, handler.encrypt_metadata, &mut |target, data| handler.decrypt(id, target, data));
+    }
+    recover_object_streams(&mut doc, original, &handler);
+    doc.trailer.remove(b"Encrypt");
+    if let Some(id) = encrypt_id {
+        doc.objects.remove(&id);
+    }
+    doc.prune_objects();
+    save(doc).map(Some)
+}
+
+/// Encrypt `bytes` with AES-256 (revision 6) under `protection`.
+pub fn encrypt(bytes: &[u8], protection: &Protection) -> Result<Vec<u8>> {
+    if protection.user_password.is_empty() && protection.owner_password.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "pdf_password_missing"));
+    }
+    let mut doc = Document::load_mem(bytes).map_err(|e| Error::new(ErrorKind::InvalidPdf, "pdf_invalid").with_details(e))?;
+    if doc.trailer.has(b"Encrypt") {
+        return Err(Error::new(ErrorKind::InvalidInput, "pdf_already_encrypted"));
+    }
+    // Object streams and cross-reference streams were expanded on load
+    // and are written again unencrypted by lopdf, if at all.
+    doc.prune_objects();
+
+    let key: [u8; 32] = random();
+    let user_password = &protection.user_password.as_bytes()[..protection.user_password.len().min(MAX_PASSWORD)];
+    let random_owner: [u8; 32] = random();
+    let owner_password = match protection.owner_password.as_bytes() {
+        [] => random_owner.as_slice(),
+        owner => &owner[..owner.len().min(MAX_PASSWORD)],
+    };
+
+    let (user_salt, user_key_salt): ([u8; 8], [u8; 8]) = (random(), random());
+    let user = [password_hash(6, user_password, &user_salt, &[]).as_slice(), &user_salt[..], &user_key_salt[..]].concat();
+    let user_key = aes256_block(&password_hash(6, user_password, &user_key_salt, &[]), &key, true);
+    let (owner_salt, owner_key_salt): ([u8; 8], [u8; 8]) = (random(), random());
+    let owner = [password_hash(6, owner_password, &owner_salt, &user).as_slice(), &owner_salt[..], &owner_key_salt[..]].concat();
+    let owner_key = aes256_block(&password_hash(6, owner_password, &owner_key_salt, &user), &key, true);
+    let permissions = protection.permissions();
+    let perms = [&permissions.to_le_bytes()[..], &[0xFF; 4][..], &b"Tadb"[..], &random::<4>()[..]].concat();
+    let perms = aes256_block(&key, &perms, true);
+
+    for object in doc.objects.values_mut() {
+        walk(object, true, &mut |_, data| aes_encrypt(&key, data));
+    }
+    if !doc.trailer.has(b"ID") {
+        let id = Object::String(random::<16>().to_vec(), StringFormat::Hexadecimal);
+        doc.trailer.set("ID", vec![id.clone(), id]);
+    }
+    let string = |bytes: Vec<u8>| Object::String(bytes, StringFormat::Hexadecimal);
+    let encrypt_id = doc.add_object(dictionary! {
+        "Filter" => "Standard",
+        "V" => 5,
+        "R" => 6,
+        "Length" => 256,
+        "CF" => dictionary! {
+            "StdCF" => dictionary! { "CFM" => "AESV3", "AuthEvent" => "DocOpen", "Length" => 32 },
+        },
+        "StmF" => "StdCF",
+        "StrF" => "StdCF",
+        "O" => string(owner),
+        "U" => string(user),
+        "OE" => string(owner_key),
+        "UE" => string(user_key),
+        "P" => i64::from(permissions as i32),
+        "Perms" => string(perms),
+        "EncryptMetadata" => true,
+    });
+    doc.trailer.set("Encrypt", encrypt_id);
+    save(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdfio::tests::sample_pdf;
+
+    fn page_text(bytes: &[u8], page: u32) -> Vec<u8> {
+        let doc = Document::load_mem(bytes).unwrap();
+        let content = doc.get_and_decode_page_content(doc.get_pages()[&page]).unwrap();
+        let tj = content.operations.iter().find(|op| op.operator == "Tj").unwrap();
+        tj.operands[0].as_str().unwrap().to_vec()
+    }
+
+    /// Algorithms 3 and 5 : a revision 3 (RC4 128) or 4 (AES-128)
+    /// copy of `bytes`, as older writers produce.
+    fn legacy_encrypt(bytes: &[u8], user_password: &str, owner_password: &str, aes: bool) -> Vec<u8> {
+        let mut doc = Document::load_mem(bytes).unwrap();
+        let id = b"0123456789abcdef".to_vec();
+        doc.trailer.set("ID", vec![Object::string_literal(id.clone()), Object::string_literal(id.clone())]);
+        let revision = if aes { 4 } else { 3 };
+        let permissions = Protection { no_print: true, ..Protection::default() }.permissions();
+
+        let mut owner_key = Md5::digest(padded(owner_password.as_bytes())).to_vec();
+        for _ in 0..50 {
+            owner_key = Md5::digest(&owner_key).to_vec();
+        }
+        let owner = rc4_rounds(&owner_key, &padded(user_password.as_bytes()), 0..=19);
+
+        let mut key = Md5::new()
+            .chain_update(padded(user_password.as_bytes()))
+            .chain_update(&owner)
+            .chain_update(permissions.to_le_bytes())
+            .chain_update(&id)
+            .finalize()
+            .to_vec();
+        for _ in 0..50 {
+            key = Md5::digest(&key).to_vec();
+        }
+        let hash = Md5::new().chain_update(PAD).chain_update(&id).finalize();
+        let user = [rc4_rounds(&key, &hash, 0..=19), vec![0; 16]].concat();
+
+        let method = if aes { Method::Aes128 } else { Method::Rc4 };
+        let handler = Handler { key, strings: method, streams: method, encrypt_metadata: true };
+        for (&object_id, object) in doc.objects.iter_mut() {
+            walk(object, true, &mut |_, data| match method {
+                Method::Aes128 => aes_encrypt(&handler.object_key(object_id, true), data),
+                _ => rc4(&handler.object_key(object_id, false), data),
+            });
+        }
+        let cfm = if aes { "AESV2" } else { "V2" };
+        let encrypt_id = doc.add_object(dictionary! {
+            "Filter" => "Standard",
+            "V" => if aes { 4 } else { 2 },
+            "R" => revision,
+            "Length" => 128,
+            "CF" => dictionary! { "StdCF" => dictionary! { "CFM" => cfm, "Length" => 16 } },
+            "StmF" => "StdCF",
+            "StrF" => "StdCF",
+            "O" => Object::String(owner, StringFormat::Hexadecimal),
+            "U" => Object::String(user, StringFormat::Hexadecimal),
+            "P" => i64::from(permissions as i32),
+        });
+        doc.trailer.set("Encrypt", encrypt_id);
+        save(doc).unwrap()
+    }
+
+    #[test]
+    fn rc4_matches_the_reference_vector() {
+        assert_eq!(rc4(b"Key", b"Plaintext"), [0xBB, 0xF3, 0x16, 0xE8, 0xD9, 0x40, 0xAF, 0x0A, 0xD3]);
+    }
+
+    #[test]
+    fn aes256_copies_open_with_either_password_only() {
+        let original = sample_pdf(2, "Contrat");
+        let protection = Protection {
+            user_password: "lecture".into(),
+            owner_password: "proprio".into(),
+            no_copy: true,
+            ..Protection::default()
+        };
+        let encrypted = encrypt(&original, &protection).unwrap();
+        assert!(!encrypted.windows(9).any(|window| window == b"Contrat 1"));
+
+        let err = decrypt(&encrypted, None).unwrap_err();
+        assert_eq!((err.kind, err.code), (ErrorKind::AuthenticationFailed, "pdf_password_required"));
+        assert_eq!(decrypt(&encrypted, Some("mauvais")).unwrap_err().code, "pdf_password_incorrect");
+        for password in ["lecture", "proprio"] {
+            let clear = decrypt(&encrypted, Some(password)).unwrap().unwrap();
+            assert_eq!(page_text(&clear, 2), b"Contrat 2");
+            assert!(!Document::load_mem(&clear).unwrap().trailer.has(b"Encrypt"));
+        }
+
+        let doc = Document::load_mem(&encrypted).unwrap();
+        let (_, dict) = encryption(&doc).unwrap().unwrap();
+        assert_eq!(dict.get(b"P").unwrap().as_i64().unwrap() as u32 & PERMIT_COPY, 0);
+    }
+
+    #[test]
+    fn permission_only_copies_open_without_a_prompt() {
+        let protection = Protection { owner_password: "proprio".into(), no_print: true, ..Protection::default() };
+        let encrypted = encrypt(&sample_pdf(1, "Contrat"), &protection).unwrap();
+        let clear = decrypt(&encrypted, None).unwrap().unwrap();
+        assert_eq!(page_text(&clear, 1), b"Contrat 1");
+    }
+
+    #[test]
+    fn legacy_rc4_and_aes128_files_are_decrypted() {
+        let original = sample_pdf(1, "Contrat");
+        for aes in [false, true] {
+            let encrypted = legacy_encrypt(&original, "lecture", "proprio", aes);
+            assert_eq!(decrypt(&encrypted, Some("mauvais")).unwrap_err().code, "pdf_password_incorrect", "aes {aes}");
+            for password in ["lecture", "proprio"] {
+                let clear = decrypt(&encrypted, Some(password)).unwrap().unwrap();
+                assert_eq!(page_text(&clear, 1), b"Contrat 1", "aes {aes}, {password}");
+            }
+        }
+    }
+
+    #[test]
+    fn clear_documents_are_left_alone() {
+        assert!(decrypt(&sample_pdf(1, "Contrat"), Some("x")).unwrap().is_none());
+        let empty = Protection::default();
+        assert_eq!(encrypt(&sample_pdf(1, "Contrat"), &empty).unwrap_err().code, "pdf_password_missing");
+    }
+
+    #[test]
+    fn files_opened_with_a_password_are_read_in_clear() {
+        let dir = crate::store::tests::scratch_dir("crypt-passwords");
+        let path = dir.join("contrat.pdf");
+        let protection = Protection { user_password: "lecture".into(), ..Protection::default() };
+        std::fs::write(&path, encrypt(&sample_pdf(1, "Contrat"), &protection).unwrap()).unwrap();
+
+        let passwords = Passwords::default();
+        assert_eq!(read_clear(&passwords, &path).unwrap_err().code, "pdf_password_required");
+        passwords.remember(&path, Some("lecture"));
+        assert_eq!(page_text(&read_clear(&passwords, &path).unwrap(), 1), b"Contrat 1");
+        passwords.remember(&path, None);
+        assert!(passwords.get(&path).is_none());
+    }
+}
@@ -0,0 +1,944 @@
+//! Embedded SQLite database (`cerfini.db` in `app_data_dir`) holding
+//! the user stores : signature and paraph galleries, templates and
+//! snippets.
+//!
+//! Images are BLOB rows written one at a time, instead of JSON number
+//! arrays rewritten in full on every save. When the vault is enabled
+//! each image is sealed on its own, bound to its row. Templates stay
+//! opaque JSON documents, each tagged with the [`schema::TEMPLATES`]
+//! version it was written with and upgraded on read.
+//!
+//! The JSON files of the previous releases (`signatures.json`, …) are
+//! imported the first time their store is accessed, replacing its
+//! rows. The template and snippet files are then renamed to
+//! `<name>.imported` ; the gallery files, which hold the images in
+//! plain unless the vault sealed them, are deleted. Dropping such a
+//! file back into `app_data_dir` (profile restore, history snapshot)
+//! therefore replaces the store again.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Transaction};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::vault::{self, Key, Vault};
+use crate::{history, images, schema, store, StoredSignature};
+
+pub const DB_FILE: &str = "cerfini.db";
+const IMPORTED_SUFFIX: &str = ".imported";
+
+/// `MIGRATIONS[n]` takes the database from `user_version` n to n + 1.
+const MIGRATIONS: &[&str] = &["
+    CREATE TABLE image_assets (
+        gallery TEXT NOT NULL,
+        id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        mime TEXT NOT NULL,
+        natural_w INTEGER NOT NULL,
+        natural_h INTEGER NOT NULL,
+        -- Hex SHA-256 of the plaintext image, to compare without reading blobs.
+        sha256 TEXT NOT NULL,
+        sealed INTEGER NOT NULL,
+        bytes BLOB NOT NULL,
+        PRIMARY KEY (gallery, id)
+    );
+    CREATE TABLE templates (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        version INTEGER NOT NULL,
+        body TEXT NOT NULL
+    );
+    CREATE TABLE snippets (
+        position INTEGER PRIMARY KEY,
+        text TEXT NOT NULL
+    );
+", "
+    -- JSON pen strokes of a drawn image (`ink::InkDrawing`), sealed like `bytes`.
+    ALTER TABLE image_assets ADD COLUMN ink BLOB;
+"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gallery {
+    Signatures,
+    Paraphs,
+}
+
+impl Gallery {
+    fn kind(self) -> &'static str {
+        match self {
+            Gallery::Signatures => "signatures",
+            Gallery::Paraphs => "paraphs",
+        }
+    }
+
+    /// Legacy store file, also the name of its history.
+    fn file(self) -> &'static str {
+        match self {
+            Gallery::Signatures => crate::SIGNATURES_FILE,
+            Gallery::Paraphs => crate::PARAPHS_FILE,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        let corrupt = matches!(
+            &err,
+            rusqlite::Error::SqliteFailure(failure, _)
+                if matches!(failure.code, ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+        );
+        if corrupt {
+            Error::new(ErrorKind::CorruptData, "db_corrupt").with_details(err)
+        } else {
+            Error::new(ErrorKind::Io, "db_failed").with_details(err)
+        }
+    }
+}
+
+fn json_invalid(e: serde_json::Error) -> Error {
+    Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e)
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version as usize > MIGRATIONS.len() {
+        return Err(Error::new(ErrorKind::NewerVersion, "store_version_newer")
+            .with_details(format!("database version {version}, at most {} expected", MIGRATIONS.len())));
+    }
+    for (idx, sql) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", idx as i64 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn open(dir: &Path) -> Result<Connection> {
+    std::fs::create_dir_all(dir).map_err(|e| Error::io("create_dir_failed", dir, &e))?;
+    let path = dir.join(DB_FILE);
+    let mut conn = Connection::open(&path).map_err(|err| Error::from(err).with_path(&path))?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    // Freed pages would otherwise keep the plaintext of a deleted or
+    // sealed image until SQLite reuses them.
+    conn.pragma_update(None, "secure_delete", true)?;
+    migrate(&mut conn).map_err(|err| err.with_path(&path))?;
+    Ok(conn)
+}
+
+/// The legacy JSON file of store `name`, when one waits to be imported.
+fn pending_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = dir.join(name);
+    (path.exists() || store::backup_path(&path).exists()).then_some(path)
+}
+
+/// Where [`mark_imported`] moves the legacy file `path`.
+pub fn imported_path(path: &Path) -> PathBuf {
+    let mut target = path.to_path_buf().into_os_string();
+    target.push(IMPORTED_SUFFIX);
+    PathBuf::from(target)
+}
+
+/// Move an imported file (and its `.bak`) out of the way ; the import
+/// is committed, so a crash before this only means importing it again.
+fn mark_imported(path: &Path) -> Result<()> {
+    for file in [path.to_path_buf(), store::backup_path(path)] {
+        match std::fs::rename(&file, imported_path(&file)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(Error::io("store_write_failed", &file, &e)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Delete an imported gallery file and its `.bak`, like
+/// [`mark_imported`] : its images now live in the database only.
+fn remove_imported(path: &Path) -> Result<()> {
+    for file in [path.to_path_buf(), store::backup_path(path)] {
+        match std::fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(Error::io("store_write_failed", &file, &e)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Copy of the database for a profile backup, or `None` before the
+/// first store access created it.
+pub fn export_copy(dir: &Path) -> Result<Option<Vec<u8>>> {
+    let path = dir.join(DB_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let copy = dir.join(format!("{DB_FILE}.export"));
+    let _ = std::fs::remove_file(&copy);
+    let conn = Connection::open(&path).map_err(|err| Error::from(err).with_path(&path))?;
+    conn.execute("VACUUM INTO ?1", [copy.to_string_lossy().to_string()])
+        .map_err(|err| Error::from(err).with_path(&path))?;
+    let bytes = std::fs::read(&copy).map_err(|e| Error::io("store_read_failed", &copy, &e));
+    let _ = std::fs::remove_file(&copy);
+    bytes.map(Some)
+}
+
+/// Check that `bytes` is a database this build can open : SQLite
+/// header, and a `user_version` (big-endian at offset 60) it knows.
+pub fn check_copy(bytes: &[u8]) -> Result<()> {
+    if bytes.len() < 100 || !bytes.starts_with(b"SQLite format 3\0") {
+        return Err(Error::new(ErrorKind::CorruptData, "db_corrupt"));
+    }
+    let version = u32::from_be_bytes([bytes[60], bytes[61], bytes[62], bytes[63]]);
+    if version as usize > MIGRATIONS.len() {
+        return Err(Error::new(ErrorKind::NewerVersion, "store_version_newer")
+            .with_details(format!("database version {version}, at most {} expected", MIGRATIONS.len())));
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------
+// Galleries
+
+/// Row metadata, enough to tell whether an image changed.
+struct AssetRow {
+    id: String,
+    name: String,
+    mime: String,
+    natural_w: u32,
+    natural_h: u32,
+    sha256: String,
+}
+
+fn aad(gallery: Gallery, id: &str) -> String {
+    format!("{}/{id}", gallery.kind())
+}
+
+fn ink_aad(gallery: Gallery, id: &str) -> String {
+    format!("{}/ink", aad(gallery, id))
+}
+
+/// The `sha256` column : of the image, followed by the strokes' JSON
+/// when there are some, so rows without strokes keep their digest.
+fn digest(asset: &StoredSignature) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(&asset.bytes);
+    if let Some(ink) = ink_json(asset)? {
+        hasher.update(ink);
+    }
+    Ok(crate::pdfio::hex(&hasher.finalize()).to_lowercase())
+}
+
+fn ink_json(asset: &StoredSignature) -> Result<Option<Vec<u8>>> {
+    asset
+        .ink
+        .as_ref()
+        .map(serde_json::to_vec)
+        .transpose()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))
+}
+
+/// The vault key when the galleries are sealed. Fails while locked.
+fn gallery_key(vault: &Vault, dir: &Path) -> Result<Option<Key>> {
+    if vault::is_initialized(dir) {
+        vault.key().map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn asset_rows(conn: &Connection, gallery: Gallery) -> Result<Vec<AssetRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, mime, natural_w, natural_h, sha256 FROM image_assets WHERE gallery = ?1 ORDER BY position",
+    )?;
+    let rows = stmt.query_map([gallery.kind()], |row| {
+        Ok(AssetRow {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            mime: row.get(2)?,
+            natural_w: row.get(3)?,
+            natural_h: row.get(4)?,
+            sha256: row.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn read_assets(conn: &Connection, key: Option<&Key>, gallery: Gallery) -> Result<Vec<StoredSignature>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, mime, natural_w, natural_h, sealed, bytes, ink FROM image_assets WHERE gallery = ?1 ORDER BY position",
+    )?;
+    let mut rows = stmt.query([gallery.kind()])?;
+    let mut assets = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: String = row.get(0)?;
+        let sealed: bool = row.get(5)?;
+        let open_column = |blob: Vec<u8>, aad: String| match (sealed, key) {
+            (false, _) => Ok(blob),
+            (true, Some(key)) => vault::open_blob(key, &aad, &blob),
+            (true, None) => Err(Error::new(ErrorKind::VaultLocked, "vault_locked")),
+        };
+        let bytes = open_column(row.get(6)?, aad(gallery, &id))?;
+        let ink = match row.get::<_, Option<Vec<u8>>>(7)? {
+            Some(blob) => Some(serde_json::from_slice(&open_column(blob, ink_aad(gallery, &id))?).map_err(json_invalid)?),
+            None => None,
+        };
+        assets.push(StoredSignature {
+            name: row.get(1)?,
+            mime: row.get(2)?,
+            bytes,
+            natural_w: row.get(3)?,
+            natural_h: row.get(4)?,
+            ink,
+            id,
+        });
+    }
+    Ok(assets)
+}
+
+fn write_asset(tx: &Transaction, key: Option<&Key>, gallery: Gallery, position: i64, asset: &StoredSignature) -> Result<()> {
+    let mut ink = ink_json(asset)?;
+    let sealed = match key {
+        Some(key) => {
+            if let Some(json) = &ink {
+                ink = Some(vault::seal_blob(key, &ink_aad(gallery, &asset.id), json)?);
+            }
+            Some(vault::seal_blob(key, &aad(gallery, &asset.id), &asset.bytes)?)
+        }
+        None => None,
+    };
+    tx.execute(
+        "INSERT OR REPLACE INTO image_assets
+             (gallery, id, position, name, mime, natural_w, natural_h, sha256, sealed, bytes, ink)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            gallery.kind(),
+            asset.id,
+            position,
+            asset.name,
+            asset.mime,
+            asset.natural_w,
+            asset.natural_h,
+            digest(asset)?,
+            sealed.is_some(),
+            sealed.as_deref().unwrap_or(&asset.bytes),
+            ink,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Keep the gallery's current content in its history, before a change
+/// that drops some of it. Unlike the other stores it is not snapshotted
+/// after every write : dumping every image on each add or rename would
+/// cost as much as the JSON files the database replaced.
+fn snapshot_assets(conn: &Connection, vault: &Vault, key: Option<&Key>, dir: &Path, gallery: Gallery) -> Result<()> {
+    let assets = read_assets(conn, key, gallery)?;
+    if !assets.is_empty() {
+        let bytes = vault::encode_store(vault, dir, gallery.file(), &schema::IMAGE_ASSETS, &assets)?;
+        // Sealing makes every encoding differ : the rows stand for the
+        // content instead.
+        let identity = assets
+            .iter()
+            .map(|asset| {
+                let StoredSignature { id, name, mime, natural_w, natural_h, .. } = asset;
+                Ok(format!("{id}\0{name}\0{mime}\0{natural_w}x{natural_h}\0{}\n", digest(asset)?))
+            })
+            .collect::<Result<String>>()?;
+        history::record_as(dir, gallery.file(), &bytes, identity.as_bytes());
+    }
+    Ok(())
+}
+
+/// Make the gallery hold exactly `assets`, in that order, and return
+/// them as stored. Unchanged images are not rewritten, only moved ; new
+/// or changed ones go through `normalize` first.
+fn replace_assets(
+    conn: &mut Connection,
+    vault: &Vault,
+    key: Option<&Key>,
+    dir: &Path,
+    gallery: Gallery,
+    assets: Vec<StoredSignature>,
+    normalize: &dyn Fn(StoredSignature) -> Result<StoredSignature>,
+) -> Result<Vec<StoredSignature>> {
+    let current = asset_rows(conn, gallery)?;
+    let unchanged = |row: &AssetRow, asset: &StoredSignature| {
+        row.mime == asset.mime
+            && row.natural_w == asset.natural_w
+            && row.natural_h == asset.natural_h
+            && digest(asset).is_ok_and(|digest| row.sha256 == digest)
+    };
+    let assets = assets
+        .into_iter()
+        .map(|asset| match current.iter().find(|row| row.id == asset.id) {
+            Some(row) if unchanged(row, &asset) => Ok(asset),
+            _ => normalize(asset),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let drops = current.iter().any(|row| match assets.iter().find(|asset| asset.id == row.id) {
+        Some(asset) => row.name != asset.name || !unchanged(row, asset),
+        None => true,
+    });
+    if drops {
+        snapshot_assets(conn, vault, key, dir, gallery)?;
+    }
+
+    let tx = conn.transaction()?;
+    for (position, asset) in assets.iter().enumerate() {
+        match current.iter().find(|row| row.id == asset.id) {
+            Some(row) if unchanged(row, asset) => {
+                tx.execute(
+                    "UPDATE image_assets SET position = ?3, name = ?4 WHERE gallery = ?1 AND id = ?2",
+                    params![gallery.kind(), asset.id, position as i64, asset.name],
+                )?;
+            }
+            _ => write_asset(&tx, key, gallery, position as i64, asset)?,
+        }
+    }
+    let removed: Vec<&AssetRow> = current.iter().filter(|row| !assets.iter().any(|asset| asset.id == row.id)).collect();
+    for row in &removed {
+        tx.execute("DELETE FROM image_assets WHERE gallery = ?1 AND id = ?2", params![gallery.kind(), row.id])?;
+    }
+    tx.commit()?;
+    if !removed.is_empty() {
+        conn.execute_batch("VACUUM")?;
+    }
+    Ok(assets)
+}
+
+/// Open the database for a gallery operation : resolves the key and
+/// imports a pending legacy file first. Its images are taken as they
+/// are : they were accepted by the release that wrote them, and one
+/// that no longer passes must not block the whole import.
+fn gallery_conn(vault: &Vault, dir: &Path, gallery: Gallery) -> Result<(Connection, Option<Key>)> {
+    let key = gallery_key(vault, dir)?;
+    let mut conn = open(dir)?;
+    if let Some(path) = pending_file(dir, gallery.file()) {
+        let assets: Vec<StoredSignature> =
+            vault::load_store(vault, dir, &path, &schema::IMAGE_ASSETS)?.unwrap_or_default();
+        replace_assets(&mut conn, vault, key.as_ref(), dir, gallery, assets, &|asset| Ok(asset))?;
+        remove_imported(&path)?;
+    }
+    Ok((conn, key))
+}
+
+fn asset_not_found(id: &str) -> Error {
+    Error::new(ErrorKind::NotFound, "asset_not_found").with_details(id)
+}
+
+fn has_asset(conn: &Connection, gallery: Gallery, id: &str) -> Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM image_assets WHERE gallery = ?1 AND id = ?2", params![gallery.kind(), id], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+pub fn load_assets(vault: &Vault, dir: &Path, gallery: Gallery) -> Result<Vec<StoredSignature>> {
+    let (conn, key) = gallery_conn(vault, dir, gallery)?;
+    read_assets(&conn, key.as_ref(), gallery)
+}
+
+/// Replace the gallery with `assets` and return them normalized.
+pub fn save_assets(vault: &Vault, dir: &Path, gallery: Gallery, assets: Vec<StoredSignature>) -> Result<Vec<StoredSignature>> {
+    let normalize = images::normalizer(dir)?;
+    let (mut conn, key) = gallery_conn(vault, dir, gallery)?;
+    replace_assets(&mut conn, vault, key.as_ref(), dir, gallery, assets, &normalize)
+}
+
+/// Append `asset` to the gallery and return it normalized.
+pub fn add_asset(vault: &Vault, dir: &Path, gallery: Gallery, asset: StoredSignature) -> Result<StoredSignature> {
+    let asset = images::normalizer(dir)?(asset)?;
+    let (mut conn, key) = gallery_conn(vault, dir, gallery)?;
+    if has_asset(&conn, gallery, &asset.id)? {
+        return Err(Error::new(ErrorKind::InvalidInput, "asset_exists").with_details(&asset.id));
+    }
+    let tx = conn.transaction()?;
+    let position: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM image_assets WHERE gallery = ?1",
+        [gallery.kind()],
+        |row| row.get(0),
+    )?;
+    write_asset(&tx, key.as_ref(), gallery, position, &asset)?;
+    tx.commit()?;
+    Ok(asset)
+}
+
+pub fn rename_asset(vault: &Vault, dir: &Path, gallery: Gallery, id: &str, name: &str) -> Result<()> {
+    let (conn, key) = gallery_conn(vault, dir, gallery)?;
+    let current: String = conn
+        .query_row("SELECT name FROM image_assets WHERE gallery = ?1 AND id = ?2", params![gallery.kind(), id], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .ok_or_else(|| asset_not_found(id))?;
+    if current == name {
+        return Ok(());
+    }
+    snapshot_assets(&conn, vault, key.as_ref(), dir, gallery)?;
+    conn.execute(
+        "UPDATE image_assets SET name = ?3 WHERE gallery = ?1 AND id = ?2",
+        params![gallery.kind(), id, name],
+    )?;
+    Ok(())
+}
+
+pub fn delete_asset(vault: &Vault, dir: &Path, gallery: Gallery, id: &str) -> Result<()> {
+    let (conn, key) = gallery_conn(vault, dir, gallery)?;
+    if !has_asset(&conn, gallery, id)? {
+        return Err(asset_not_found(id));
+    }
+    snapshot_assets(&conn, vault, key.as_ref(), dir, gallery)?;
+    conn.execute("DELETE FROM image_assets WHERE gallery = ?1 AND id = ?2", params![gallery.kind(), id])?;
+    conn.execute_batch("VACUUM")?;
+    Ok(())
+}
+
+/// Gallery, id, image and ink of a row stored in clear.
+type PlainAsset = (String, String, Vec<u8>, Option<Vec<u8>>);
+
+/// Seal the images stored before the vault was set up. Called on every
+/// unlock ; a no-op once everything is sealed.
+pub fn seal_assets(dir: &Path, key: &Key) -> Result<()> {
+    let mut conn = open(dir)?;
+    let tx = conn.transaction()?;
+    let plaintext: Vec<PlainAsset> = {
+        let mut stmt = tx.prepare("SELECT gallery, id, bytes, ink FROM image_assets WHERE sealed = 0")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    let sealed_any = !plaintext.is_empty();
+    for (gallery, id, bytes, ink) in plaintext {
+        let blob = vault::seal_blob(key, &format!("{gallery}/{id}"), &bytes)?;
+        let ink = ink.map(|ink| vault::seal_blob(key, &format!("{gallery}/{id}/ink"), &ink)).transpose()?;
+        tx.execute(
+            "UPDATE image_assets SET sealed = 1, bytes = ?3, ink = ?4 WHERE gallery = ?1 AND id = ?2",
+            params![gallery, id, blob, ink],
+        )?;
+    }
+    tx.commit()?;
+    if sealed_any {
+        // `secure_delete` overwrites what the rows freed ; rebuilding
+        // the file also leaves no stale page behind.
+        conn.execute_batch("VACUUM")?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------
+// Templates
+
+fn template_id(template: &Value) -> Result<&str> {
+    template
+        .get("id")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "template_invalid"))
+}
+
+/// Bring a template row written with `version` to the current schema.
+fn upgrade_template(version: u32, body: &str) -> Result<Value> {
+    let body: Value = serde_json::from_str(body).map_err(json_invalid)?;
+    let upgraded = schema::upgrade(&schema::TEMPLATES, json!({ "version": version, "data": [body] }))?;
+    upgraded
+        .as_array()
+        .and_then(|templates| templates.first())
+        .cloned()
+        .ok_or_else(|| Error::new(ErrorKind::CorruptData, "store_format_unknown"))
+}
+
+fn read_templates(conn: &Connection) -> Result<Vec<Value>> {
+    let mut stmt = conn.prepare("SELECT version, body FROM templates ORDER BY position")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?;
+    rows.map(|row| -> Result<Value> {
+        let (version, body) = row?;
+        upgrade_template(version, &body)
+    })
+    .collect()
+}
+
+fn write_template(tx: &Transaction, position: i64, template: &Value) -> Result<()> {
+    let body = serde_json::to_string(template)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))?;
+    tx.execute(
+        "INSERT OR REPLACE INTO templates (id, position, version, body) VALUES (?1, ?2, ?3, ?4)",
+        params![template_id(template)?, position, schema::TEMPLATES.version(), body],
+    )?;
+    Ok(())
+}
+
+/// Keep the current templates in their history, like
+/// `snapshot_assets`. Also refuses the change when a newer build wrote
+/// some of them, as reading them does.
+fn snapshot_templates(conn: &Connection, dir: &Path) -> Result<()> {
+    let templates = read_templates(conn)?;
+    if !templates.is_empty() {
+        let bytes = serde_json::to_vec(&schema::envelope(&schema::TEMPLATES, &templates)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))?;
+        history::record(dir, crate::TEMPLATES_FILE, &bytes);
+    }
+    Ok(())
+}
+
+fn replace_templates(conn: &mut Connection, dir: &Path, templates: &[Value]) -> Result<()> {
+    let current = read_templates(conn)?;
+    if current.iter().any(|template| !templates.contains(template)) {
+        snapshot_templates(conn, dir)?;
+    }
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM templates", [])?;
+    for (position, template) in templates.iter().enumerate() {
+        write_template(&tx, position as i64, template)?;
+    }
+    tx.commit()?;
+    snapshot_templates(conn, dir)
+}
+
+fn templates_conn(dir: &Path) -> Result<Connection> {
+    let mut conn = open(dir)?;
+    if let Some(path) = pending_file(dir, crate::TEMPLATES_FILE) {
+        let templates: Vec<Value> = schema::load(&schema::TEMPLATES, &path)?.unwrap_or_default();
+        replace_templates(&mut conn, dir, &templates)?;
+        mark_imported(&path)?;
+    }
+    Ok(conn)
+}
+
+pub fn load_templates(dir: &Path) -> Result<Vec<Value>> {
+    read_templates(&templates_conn(dir)?)
+}
+
+pub fn save_templates(dir: &Path, templates: &[Value]) -> Result<()> {
+    replace_templates(&mut templates_conn(dir)?, dir, templates)
+}
+
+/// Insert `template`, or replace the one with the same id in place.
+pub fn save_template(dir: &Path, template: Value) -> Result<()> {
+    let mut conn = templates_conn(dir)?;
+    let id = template_id(&template)?.to_string();
+    let existing: Option<(i64, u32, String)> = conn
+        .query_row("SELECT position, version, body FROM templates WHERE id = ?1", [&id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .optional()?;
+    let position = match existing {
+        Some((position, version, body)) => {
+            if upgrade_template(version, &body)? != template {
+                snapshot_templates(&conn, dir)?;
+            }
+            position
+        }
+        None => conn.query_row("SELECT COALESCE(MAX(position) + 1, 0) FROM templates", [], |row| row.get(0))?,
+    };
+    let tx = conn.transaction()?;
+    write_template(&tx, position, &template)?;
+    tx.commit()?;
+    snapshot_templates(&conn, dir)
+}
+
+pub fn delete_template(dir: &Path, id: &str) -> Result<()> {
+    let conn = templates_conn(dir)?;
+    let exists = conn.query_row("SELECT 1 FROM templates WHERE id = ?1", [id], |_| Ok(())).optional()?.is_some();
+    if !exists {
+        return Err(Error::new(ErrorKind::NotFound, "template_not_found").with_details(id));
+    }
+    snapshot_templates(&conn, dir)?;
+    conn.execute("DELETE FROM templates WHERE id = ?1", [id])?;
+    snapshot_templates(&conn, dir)
+}
+
+// ---------------------------------------------------------------------
+// Snippets
+
+fn read_snippets(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT text FROM snippets ORDER BY position")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Keep the current snippets in their history, like `snapshot_assets`.
+fn snapshot_snippets(conn: &Connection, dir: &Path) -> Result<()> {
+    let snippets = read_snippets(conn)?;
+    if !snippets.is_empty() {
+        let bytes = serde_json::to_vec(&schema::envelope(&schema::SNIPPETS, &snippets)?)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))?;
+        history::record(dir, crate::SNIPPETS_FILE, &bytes);
+    }
+    Ok(())
+}
+
+fn replace_snippets(conn: &mut Connection, dir: &Path, snippets: &[String]) -> Result<()> {
+    let current = read_snippets(conn)?;
+    if current.iter().any(|snippet| !snippets.contains(snippet)) {
+        snapshot_snippets(conn, dir)?;
+    }
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM snippets", [])?;
+    for (position, snippet) in snippets.iter().enumerate() {
+        tx.execute("INSERT INTO snippets (position, text) VALUES (?1, ?2)", params![position as i64, snippet])?;
+    }
+    tx.commit()?;
+    snapshot_snippets(conn, dir)
+}
+
+fn snippets_conn(dir: &Path) -> Result<Connection> {
+    let mut conn = open(dir)?;
+    if let Some(path) = pending_file(dir, crate::SNIPPETS_FILE) {
+        let snippets: Vec<String> = schema::load(&schema::SNIPPETS, &path)?.unwrap_or_default();
+        replace_snippets(&mut conn, dir, &snippets)?;
+        mark_imported(&path)?;
+    }
+    Ok(conn)
+}
+
+pub fn load_snippets(dir: &Path) -> Result<Vec<String>> {
+    read_snippets(&snippets_conn(dir)?)
+}
+
+pub fn save_snippets(dir: &Path, snippets: &[String]) -> Result<()> {
+    replace_snippets(&mut snippets_conn(dir)?, dir, snippets)
+}
+
+// ---------------------------------------------------------------------
+// Restore preview
+
+/// A row of any store, as the restore preview compares them : what the
+/// user knows it by, and a digest of the rest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    /// `signatures`, `paraphs`, `templates` or `snippets`.
+    pub store: &'static str,
+    /// The text itself for snippets, which have no id.
+    pub id: String,
+    pub name: String,
+    digest: String,
+}
+
+fn read_records(conn: &Connection) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for gallery in [Gallery::Signatures, Gallery::Paraphs] {
+        // `sha256` is taken before sealing, so a sealed and a plain copy
+        // of an image compare equal.
+        records.extend(asset_rows(conn, gallery)?.into_iter().map(|row| Record {
+            store: gallery.kind(),
+            digest: format!("{} {}x{} {}", row.mime, row.natural_w, row.natural_h, row.sha256),
+            id: row.id,
+            name: row.name,
+        }));
+    }
+    for template in read_templates(conn)? {
+        let name = template.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+        records.push(Record { store: "templates", id: template_id(&template)?.to_string(), name, digest: template.to_string() });
+    }
+    records.extend(
+        read_snippets(conn)?
+            .into_iter()
+            .map(|text| Record { store: "snippets", id: text.clone(), name: text, digest: String::new() }),
+    );
+    Ok(records)
+}
+
+/// Records of the database in `dir` ; none when it does not exist yet.
+/// Legacy files still waiting to be imported are not looked at.
+pub fn records(dir: &Path) -> Result<Vec<Record>> {
+    if !dir.join(DB_FILE).exists() {
+        return Ok(Vec::new());
+    }
+    read_records(&open(dir)?)
+}
+
+/// Records of `bytes`, a database copy as [`export_copy`] makes them,
+/// read through a scratch file next to the live database.
+pub fn copy_records(dir: &Path, bytes: &[u8]) -> Result<Vec<Record>> {
+    check_copy(bytes)?;
+    let copy = dir.join(format!("{DB_FILE}.preview"));
+    std::fs::write(&copy, bytes).map_err(|e| Error::io("store_write_failed", &copy, &e))?;
+    let records = (|| {
+        let mut conn = Connection::open(&copy)?;
+        migrate(&mut conn)?;
+        read_records(&conn)
+    })();
+    let _ = std::fs::remove_file(&copy);
+    records.map_err(|err| err.with_path(&copy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::scratch_dir;
+    use crate::export::tests::{asset, tiny_png};
+
+    fn ids(assets: &[StoredSignature]) -> Vec<&str> {
+        assets.iter().map(|asset| asset.id.as_str()).collect()
+    }
+
+    fn snapshots(dir: &Path, store: &str) -> usize {
+        std::fs::read_dir(dir.join("history").join(store.trim_end_matches(".json")))
+            .map(|entries| entries.count())
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn legacy_files_are_imported_once() {
+        let dir = scratch_dir("import");
+        let templates = dir.join(crate::TEMPLATES_FILE);
+        std::fs::write(&templates, include_str!("../fixtures/stores/templates.v0.json")).unwrap();
+        std::fs::write(dir.join(crate::SNIPPETS_FILE), r#"["Lu et approuvé"]"#).unwrap();
+
+        let loaded = load_templates(&dir).unwrap();
+        assert!(!loaded.is_empty());
+        assert!(loaded.iter().all(|template| template.get("updatedAt").is_some()));
+        assert!(!templates.exists());
+        assert!(dir.join(format!("{}{IMPORTED_SUFFIX}", crate::TEMPLATES_FILE)).exists());
+        assert_eq!(load_templates(&dir).unwrap(), loaded);
+        assert_eq!(load_snippets(&dir).unwrap(), vec!["Lu et approuvé"]);
+    }
+
+    #[test]
+    fn legacy_galleries_are_deleted_once_imported() {
+        let dir = scratch_dir("import-gallery");
+        let signatures = dir.join(crate::SIGNATURES_FILE);
+        std::fs::write(&signatures, include_str!("../fixtures/stores/signatures.v0.json")).unwrap();
+        std::fs::copy(&signatures, store::backup_path(&signatures)).unwrap();
+
+        let loaded = load_assets(&Vault::default(), &dir, Gallery::Signatures).unwrap();
+        assert!(!loaded.is_empty());
+        let left: Vec<_> = std::fs::read_dir(&*dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert!(left.iter().all(|name| !name.to_string_lossy().starts_with("signatures")), "{left:?}");
+        assert_eq!(load_assets(&Vault::default(), &dir, Gallery::Signatures).unwrap().len(), loaded.len());
+    }
+
+    #[test]
+    fn gallery_crud_keeps_order_and_what_it_drops() {
+        let dir = scratch_dir("crud");
+        let vault = Vault::default();
+        let gallery = Gallery::Signatures;
+        add_asset(&vault, &dir, gallery, asset("a", "image/png", tiny_png())).unwrap();
+        add_asset(&vault, &dir, gallery, asset("b", "image/png", tiny_png())).unwrap();
+        assert_eq!(add_asset(&vault, &dir, gallery, asset("a", "image/png", tiny_png())).err().unwrap().code, "asset_exists");
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 0);
+
+        rename_asset(&vault, &dir, gallery, "a", "Paraphe").unwrap();
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 1);
+        let mut assets = load_assets(&vault, &dir, gallery).unwrap();
+        assert_eq!((ids(&assets), assets[0].name.as_str()), (vec!["a", "b"], "Paraphe"));
+
+        // Moving images, or keeping a name, drops nothing.
+        assets.reverse();
+        save_assets(&vault, &dir, gallery, assets).unwrap();
+        rename_asset(&vault, &dir, gallery, "a", "Paraphe").unwrap();
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 1);
+        rename_asset(&vault, &dir, gallery, "a", "Autre").unwrap();
+        rename_asset(&vault, &dir, gallery, "a", "Paraphe").unwrap();
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 3);
+        // The same content again is not copied twice.
+        delete_asset(&vault, &dir, gallery, "a").unwrap();
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 3);
+        assert_eq!(ids(&load_assets(&vault, &dir, gallery).unwrap()), vec!["b"]);
+        assert_eq!(delete_asset(&vault, &dir, gallery, "a").unwrap_err().kind, ErrorKind::NotFound);
+        assert!(load_assets(&vault, &dir, Gallery::Paraphs).unwrap().is_empty());
+    }
+
+    #[test]
+    fn images_are_sealed_row_by_row_once_the_vault_exists() {
+        let dir = scratch_dir("sealed");
+        add_asset(&Vault::default(), &dir, Gallery::Paraphs, asset("p", "image/png", tiny_png())).unwrap();
+        let vault = vault::tests::unlocked(&dir);
+        seal_assets(&dir, &vault.key().unwrap()).unwrap();
+        add_asset(&vault, &dir, Gallery::Paraphs, asset("q", "image/png", tiny_png())).unwrap();
+
+        let conn = open(&dir).unwrap();
+        let raw: Vec<(bool, Vec<u8>)> = conn
+            .prepare("SELECT sealed, bytes FROM image_assets ORDER BY position")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(raw.iter().all(|(sealed, bytes)| *sealed && *bytes != tiny_png()));
+        let stored = images::normalize(asset("p", "image/png", tiny_png()), None).unwrap().bytes;
+        let assets = load_assets(&vault, &dir, Gallery::Paraphs).unwrap();
+        assert!(assets.iter().all(|asset| asset.bytes == stored));
+
+        vault.lock();
+        assert_eq!(load_assets(&vault, &dir, Gallery::Paraphs).err().unwrap().kind, ErrorKind::VaultLocked);
+    }
+
+    /// A PNG of `size` × `size` pixels that hardly compresses, one per
+    /// `seed`.
+    fn noisy_png(size: u32, mut seed: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, size, size);
+        encoder.set_color(png::ColorType::Rgba);
+        let mut writer = encoder.write_header().unwrap();
+        let pixels: Vec<u8> = (0..size * size * 4)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        writer.write_image_data(&pixels).unwrap();
+        drop(writer);
+        out
+    }
+
+    #[test]
+    fn no_plaintext_image_is_left_in_freed_pages() {
+        let dir = scratch_dir("freed");
+        let contains = |needle: &[u8]| {
+            let file = std::fs::read(dir.join(DB_FILE)).unwrap();
+            file.windows(needle.len()).any(|window| window == needle)
+        };
+        let image = |id: &str, seed| {
+            StoredSignature { natural_w: 64, natural_h: 64, ..asset(id, "image/png", noisy_png(64, seed)) }
+        };
+        // A stretch of pixel data, past the headers the images share.
+        let sample = |bytes: Vec<u8>| bytes[bytes.len() / 2..][..64].to_vec();
+        let sealed = sample(add_asset(&Vault::default(), &dir, Gallery::Signatures, image("s", 1)).unwrap().bytes);
+        let deleted = sample(add_asset(&Vault::default(), &dir, Gallery::Signatures, image("d", 2)).unwrap().bytes);
+        assert!(contains(&sealed) && contains(&deleted));
+
+        delete_asset(&Vault::default(), &dir, Gallery::Signatures, "d").unwrap();
+        assert!(!contains(&deleted));
+        let vault = vault::tests::unlocked(&dir);
+        seal_assets(&dir, &vault.key().unwrap()).unwrap();
+        assert!(!contains(&sealed));
+    }
+
+    #[test]
+    fn drawn_strokes_are_stored_and_sealed_with_their_image() {
+        let dir = scratch_dir("ink");
+        let strokes = vec![vec![(10.0, 20.0, 0.5), (30.0, 40.0, 0.7)]];
+        let drawn = StoredSignature { ink: Some(crate::ink::tests::drawing(strokes)), ..asset("d", "image/png", tiny_png()) };
+        add_asset(&Vault::default(), &dir, Gallery::Signatures, drawn.clone()).unwrap();
+        add_asset(&Vault::default(), &dir, Gallery::Signatures, asset("r", "image/png", tiny_png())).unwrap();
+        rename_asset(&Vault::default(), &dir, Gallery::Signatures, "r", "Renommée").unwrap();
+        let vault = vault::tests::unlocked(&dir);
+        seal_assets(&dir, &vault.key().unwrap()).unwrap();
+
+        let conn = open(&dir).unwrap();
+        let ink: Vec<u8> = conn.query_row("SELECT ink FROM image_assets WHERE id = 'd'", [], |row| row.get(0)).unwrap();
+        assert!(!ink.windows(7).any(|window| window == b"strokes"));
+        let assets = load_assets(&vault, &dir, Gallery::Signatures).unwrap();
+        assert_eq!((&assets[0].ink, &assets[1].ink), (&drawn.ink, &None));
+
+        // Saved back as loaded, nothing counts as changed.
+        save_assets(&vault, &dir, Gallery::Signatures, assets).unwrap();
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 1);
+        // Sealed, the content taken in plain is still recognized.
+        rename_asset(&vault, &dir, Gallery::Signatures, "r", "r").unwrap();
+        rename_asset(&vault, &dir, Gallery::Signatures, "r", "Renommée").unwrap();
+        assert_eq!(snapshots(&dir, crate::SIGNATURES_FILE), 2);
+    }
+
+    #[test]
+    fn database_from_a_newer_app_is_refused() {
+        let dir = scratch_dir("newer");
+        let conn = Connection::open(dir.join(DB_FILE)).unwrap();
+        conn.pragma_update(None, "user_version", 99).unwrap();
+        drop(conn);
+        assert_eq!(load_snippets(&dir).unwrap_err().kind, ErrorKind::NewerVersion);
+    }
+}
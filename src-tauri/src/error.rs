@@ -0,0 +1,140 @@
+//! The error every Tauri command returns.
+//!
+//! It serializes to `{ kind, code, path?, ioKind?, details? }` : the
+//! frontend picks its translated message from `kind` (and `code` when
+//! it wants to be more specific) ; `details` carries the underlying
+//! diagnostic, always in English whatever the UI language, and is
+//! meant for logs rather than for users.
+
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The file does not exist.
+    NotFound,
+    /// The OS refused access to the file.
+    PermissionDenied,
+    /// Any other filesystem failure.
+    Io,
+    /// A store or file exists but its content cannot be decoded.
+    CorruptData,
+    /// The caller sent an argument we refuse (bad extension, URL, …).
+    InvalidInput,
+    /// The path was not handed to the app by the user.
+    OutOfScope,
+    /// Not a PDF, or one we cannot process.
+    InvalidPdf,
+    /// Unusable certificate or PKCS#12 file.
+    Certificate,
+    /// Wrong passphrase or password.
+    AuthenticationFailed,
+    /// The vault must be unlocked first.
+    VaultLocked,
+    /// A remote service (timestamp authority) failed.
+    Network,
+    /// A platform facility is missing (keyring, app data dir).
+    Unavailable,
+    /// A store written by a newer version of the app ; left untouched.
+    NewerVersion,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Error {
+    pub kind: ErrorKind,
+    /// Stable machine code, finer than `kind` (e.g. `store_write_failed`).
+    pub code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// `std::io::ErrorKind` of the underlying failure, e.g. `NotFound`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub io_kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(kind: ErrorKind, code: &'static str) -> Self {
+        Self { kind, code, path: None, io_kind: None, details: None }
+    }
+
+    /// A filesystem failure on `path` ; `kind` follows the io error so
+    /// "missing" and "permission denied" stay distinguishable.
+    pub fn io(code: &'static str, path: &Path, err: &std::io::Error) -> Self {
+        let kind = match err.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+            _ => ErrorKind::Io,
+        };
+        Self {
+            kind,
+            code,
+            path: Some(path.to_string_lossy().to_string()),
+            io_kind: Some(format!("{:?}", err.kind())),
+            details: Some(err.to_string()),
+        }
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_string_lossy().to_string());
+        self
+    }
+
+    pub fn with_details(mut self, details: impl fmt::Display) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    /// Wrap a lower-level message (the PDF and crypto helpers still
+    /// report plain strings) under `kind` / `code`.
+    pub fn wrap(kind: ErrorKind, code: &'static str) -> impl FnOnce(String) -> Self {
+        move |details| Self::new(kind, code).with_details(details)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code)?;
+        if let Some(path) = &self.path {
+            write!(f, " ({path})")?;
+        }
+        if let Some(details) = &self.details {
+            write!(f, ": {details}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_keep_their_kind_and_path() {
+        let err = Error::io(
+            "store_read_failed",
+            Path::new("/data/signatures.json"),
+            &std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(err.kind, ErrorKind::PermissionDenied);
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["kind"], "permission_denied");
+        assert_eq!(json["code"], "store_read_failed");
+        assert_eq!(json["path"], "/data/signatures.json");
+        assert_eq!(json["ioKind"], "PermissionDenied");
+    }
+
+    #[test]
+    fn optional_fields_are_omitted() {
+        let json = serde_json::to_value(Error::new(ErrorKind::VaultLocked, "vault_locked")).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "vault_locked", "code": "vault_locked" }));
+    }
+}
@@ -0,0 +1,1470 @@
+//! Rust-side flattening, the native counterpart of `exportFlattenedPdf`
+//! (`src/pdf/exportPdf.ts`) : form values are written back, overlay
+//! items are burned into the page content and the result lands on disk
+//! without the document ever crossing the IPC bridge.
+//!
+//! The drawing mirrors pdf-lib operator for operator (same geometry,
+//! same standard fonts, same 24 pt line height when wrapping) so both
+//! exports render the same. The output is an incremental update of the
+//! original : the source file is copied as-is and only the new objects
+//! are appended, which also keeps existing signatures intact.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Object, ObjectId, Stream, StringFormat};
+use serde::Deserialize;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{self, Error, ErrorKind};
+use crate::fonts::{encode_win_ansi, StandardFont};
+use crate::ink::{self, InkDrawing};
+use crate::items::{parse_hex_color, Item, LineItem, Paraph, PdfPoint, HIGHLIGHT_OPACITY};
+use crate::pdfio::{real, IncrementalUpdate, PdfRect};
+use crate::text;
+use crate::{crypt, db, store, vault, StoredSignature};
+
+/// pdf-lib's default page line height, used between wrapped lines.
+const LINE_HEIGHT: f64 = 24.0;
+/// Bézier approximation of a quarter circle, as in pdf-lib.
+const KAPPA: f64 = 0.552_284_749_830_793_6;
+const MAX_DEPTH: usize = 32;
+
+// Field flags (ISO 32000-1 tables 226, 228 and 230).
+const FF_MULTILINE: i64 = 1 << 12;
+const FF_RADIO: i64 = 1 << 15;
+const FF_PUSHBUTTON: i64 = 1 << 16;
+const FF_COMBO: i64 = 1 << 17;
+const FF_EDIT: i64 = 1 << 18;
+/// Inset of generated field appearances : a 1 pt border plus pdf-lib's
+/// 1 pt padding.
+const FIELD_PADDING: f64 = 2.0;
+/// Size picked for auto-sized (`0 Tf`) fields, at most.
+const FIELD_MAX_FONT_SIZE: f64 = 12.0;
+
+/// Native AcroForm value ; `null` on the wire (a `None` in the map)
+/// clears a radio group.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum FormValue {
+    Flag(bool),
+    Text(String),
+}
+
+pub type FormValues = HashMap<String, Option<FormValue>>;
+
+/// Everything drawn on top of the original, with image assets already
+/// resolved from the galleries.
+pub struct Overlay<'a> {
+    pub items: &'a [Item],
+    pub signatures: &'a [StoredSignature],
+    pub paraph: Option<(&'a Paraph, &'a StoredSignature)>,
+    pub form_values: &'a FormValues,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRequest {
+    pub source_path: String,
+    /// Suggested to the save dialog ; the target itself is picked there
+    /// so the renderer cannot choose which file gets written.
+    #[serde(default)]
+    pub file_name: Option<String>,
+    #[serde(default)]
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub paraph: Option<Paraph>,
+    #[serde(default)]
+    pub form_values: FormValues,
+    /// Encrypt the export, as `save_pdf_to_path` does.
+    #[serde(default)]
+    pub protection: Option<crypt::Protection>,
+}
+
+fn rgb(color: &str) -> [Object; 3] {
+    let [r, g, b] = parse_hex_color(color).unwrap_or([0, 0, 0]);
+    [r, g, b].map(|channel| real(f64::from(channel) / 255.0))
+}
+
+fn op(operator: &str, operands: Vec<Object>) -> Operation {
+    Operation::new(operator, operands)
+}
+
+/// Split `text` the way pdf-lib's `breakTextIntoLines` does : words
+/// keep their trailing space, a word that would overflow `max_width`
+/// starts a new line, and empty lines are dropped.
+pub fn break_lines(text: &str, font: StandardFont, size: f64, max_width: f64) -> Vec<Vec<u8>> {
+    let cleaned: String = text
+        .replace(['\t', '\u{85}', '\u{2028}', '\u{2029}'], "    ")
+        .replace(['\u{8}', '\u{b}'], "")
+        .replace("\r\n", "\n")
+        .replace(['\r', '\u{c}'], "\n");
+
+    let mut lines = Vec::new();
+    for paragraph in cleaned.split('\n') {
+        let mut line = Vec::new();
+        let mut line_width = 0.0;
+        for word in paragraph.split_inclusive(' ') {
+            let encoded = encode_win_ansi(word);
+            let width = font.width_of(&encoded, size);
+            if line_width + width > max_width && !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+            }
+            line.extend_from_slice(&encoded);
+            line_width += width;
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Content-stream operators accumulated for one page, plus the
+/// resources they reference.
+#[derive(Default)]
+struct PageCanvas {
+    operations: Vec<Operation>,
+    fonts: BTreeMap<String, ObjectId>,
+    images: BTreeMap<String, ObjectId>,
+    states: BTreeMap<String, ObjectId>,
+}
+
+impl PageCanvas {
+    fn line(&mut self, start: PdfPoint, end: PdfPoint, color: &str, thickness: f64) {
+        let [r, g, b] = rgb(color);
+        self.operations.extend([
+            op("q", vec![]),
+            op("RG", vec![r, g, b]),
+            op("w", vec![real(thickness)]),
+            op("m", vec![real(start.x), real(start.y)]),
+            op("l", vec![real(end.x), real(end.y)]),
+            op("S", vec![]),
+            op("Q", vec![]),
+        ]);
+    }
+
+    fn image(&mut self, name: String, id: ObjectId, rect: PdfRect) {
+        self.operations.extend([
+            op("q", vec![]),
+            op("cm", vec![real(rect.w), real(0.0), real(0.0), real(rect.h), real(rect.x), real(rect.y)]),
+            op("Do", vec![Object::Name(name.clone().into_bytes())]),
+            op("Q", vec![]),
+        ]);
+        self.images.insert(name, id);
+    }
+}
+
+/// Lazily created document-level resources shared by every page.
+struct Resources {
+    fonts: HashMap<StandardFont, ObjectId>,
+    images: HashMap<String, ObjectId>,
+    highlight_state: Option<ObjectId>,
+}
+
+impl Resources {
+    fn font(&mut self, update: &mut IncrementalUpdate, font: StandardFont) -> (String, ObjectId) {
+        let id = *self.fonts.entry(font).or_insert_with(|| {
+            update.add(Object::Dictionary(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => font.base_font(),
+                "Encoding" => "WinAnsiEncoding",
+            }))
+        });
+        (format!("CfF{}", id.0), id)
+    }
+
+    fn image(&mut self, update: &mut IncrementalUpdate, asset: &StoredSignature) -> Result<(String, ObjectId), String> {
+        let id = match self.images.get(&asset.id) {
+            Some(id) => *id,
+            None => {
+                let id = match &asset.ink {
+                    Some(drawing) => embed_ink(update, drawing)?,
+                    None => embed_image(update, &asset.mime, &asset.bytes)?,
+                };
+                self.images.insert(asset.id.clone(), id);
+                id
+            }
+        };
+        Ok((format!("CfIm{}", id.0), id))
+    }
+
+    fn highlight_state(&mut self, update: &mut IncrementalUpdate) -> (String, ObjectId) {
+        let id = *self.highlight_state.get_or_insert_with(|| {
+            update.add(Object::Dictionary(dictionary! {
+                "Type" => "ExtGState",
+                "ca" => real(HIGHLIGHT_OPACITY),
+            }))
+        });
+        (format!("CfGs{}", id.0), id)
+    }
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).map_err(|e| format!("compression failed: {e}"))?;
+    encoder.finish().map_err(|e| format!("compression failed: {e}"))
+}
+
+/// Width, height and component count from the first SOF marker.
+pub fn jpeg_info(bytes: &[u8]) -> Result<(u32, u32, u8), String> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return Err("invalid jpeg".into());
+    }
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            pos += 1;
+            continue;
+        }
+        let marker = bytes[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD8).contains(&marker) {
+            pos += 2;
+            continue;
+        }
+        let length = usize::from(u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]));
+        let is_sof = (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            let segment = bytes.get(pos + 4..pos + 10).ok_or("truncated jpeg")?;
+            let height = u32::from(u16::from_be_bytes([segment[1], segment[2]]));
+            let width = u32::from(u16::from_be_bytes([segment[3], segment[4]]));
+            return Ok((width, height, segment[5]));
+        }
+        pos += 2 + length;
+    }
+    Err("invalid jpeg: dimensions not found".into())
+}
+
+fn image_dictionary(width: u32, height: u32, color_space: &str) -> Dictionary {
+    dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => Object::Integer(i64::from(width)),
+        "Height" => Object::Integer(i64::from(height)),
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => Object::Integer(8),
+    }
+}
+
+/// Embed a PNG or JPEG asset as an image XObject, like `embedPng` /
+/// `embedJpg` : JPEG data is passed through, PNG pixels are re-encoded
+/// with Flate and their alpha channel becomes a soft mask.
+pub fn embed_image(update: &mut IncrementalUpdate, mime: &str, bytes: &[u8]) -> Result<ObjectId, String> {
+    if mime == "image/jpeg" {
+        let (width, height, components) = jpeg_info(bytes)?;
+        let color_space = match components {
+            1 => "DeviceGray",
+            4 => "DeviceCMYK",
+            _ => "DeviceRGB",
+        };
+        let mut dict = image_dictionary(width, height, color_space);
+        dict.set("Filter", "DCTDecode");
+        if components == 4 {
+            // Adobe writes inverted CMYK JPEGs.
+            dict.set("Decode", Object::Array([1, 0, 1, 0, 1, 0, 1, 0].map(Object::Integer).to_vec()));
+        }
+        return Ok(update.add(Object::Stream(Stream::new(dict, bytes.to_vec()))));
+    }
+    if mime != "image/png" {
+        return Err(format!("unsupported image format: {mime}"));
+    }
+
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| format!("invalid png: {e}"))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(|e| format!("invalid png: {e}"))?;
+    let pixels = &buffer[..frame.buffer_size()];
+
+    let (color, alpha, color_space) = match frame.color_type {
+        png::ColorType::Grayscale => (pixels.to_vec(), None, "DeviceGray"),
+        png::ColorType::Rgb => (pixels.to_vec(), None, "DeviceRGB"),
+        png::ColorType::GrayscaleAlpha => (
+            pixels.chunks_exact(2).map(|px| px[0]).collect(),
+            Some(pixels.chunks_exact(2).map(|px| px[1]).collect::<Vec<u8>>()),
+            "DeviceGray",
+        ),
+        png::ColorType::Rgba => (
+            pixels.chunks_exact(4).flat_map(|px| [px[0], px[1], px[2]]).collect(),
+            Some(pixels.chunks_exact(4).map(|px| px[3]).collect::<Vec<u8>>()),
+            "DeviceRGB",
+        ),
+        png::ColorType::Indexed => return Err("invalid png: palette not expanded".into()),
+    };
+
+    let mut dict = image_dictionary(frame.width, frame.height, color_space);
+    dict.set("Filter", "FlateDecode");
+    if let Some(alpha) = alpha {
+        let mut mask = image_dictionary(frame.width, frame.height, "DeviceGray");
+        mask.set("Filter", "FlateDecode");
+        let mask_id = update.add(Object::Stream(Stream::new(mask, deflate(&alpha)?)));
+        dict.set("SMask", mask_id);
+    }
+    Ok(update.add(Object::Stream(Stream::new(dict, deflate(&color)?))))
+}
+
+/// Embed drawn strokes as a Form XObject whose matrix maps the pad onto
+/// the unit square, so it is placed exactly like an image.
+pub fn embed_ink(update: &mut IncrementalUpdate, drawing: &InkDrawing) -> Result<ObjectId, String> {
+    ink::validate(drawing).map_err(|e| e.to_string())?;
+    let content = Content { operations: ink::operations(drawing) }
+        .encode()
+        .map_err(|e| format!("invalid content: {e}"))?;
+    let (w, h) = (drawing.width, drawing.height);
+    let dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => vec![real(0.0), real(0.0), real(w), real(h)],
+        // The pad's y axis points down.
+        "Matrix" => vec![real(1.0 / w), real(0.0), real(0.0), real(-1.0 / h), real(0.0), real(1.0)],
+        "Resources" => Dictionary::new(),
+        "Filter" => "FlateDecode",
+    };
+    Ok(update.add(Object::Stream(Stream::new(dict, deflate(&content)?))))
+}
+
+fn draw_text(
+    canvas: &mut PageCanvas,
+    update: &mut IncrementalUpdate,
+    resources: &mut Resources,
+    text: &TextRun,
+) {
+    let PdfRect { x, y, w, h } = text.rect;
+    let size = text.font_size;
+    let (font_name, font_id) = resources.font(update, text.font);
+    canvas.fonts.insert(font_name.clone(), font_id);
+
+    // pdf-lib places text by its baseline ; the JS export approximates
+    // a top-left anchor by shifting Y, and so do we.
+    let y_draw = y + (h - size).max(0.0);
+    let lines = break_lines(text.value, text.font, size, w);
+    if !lines.is_empty() {
+        let [r, g, b] = rgb(text.color);
+        canvas.operations.extend([
+            op("q", vec![]),
+            op("rg", vec![r, g, b]),
+            op("BT", vec![]),
+            op("Tf", vec![Object::Name(font_name.into_bytes()), real(size)]),
+            op("TL", vec![real(LINE_HEIGHT)]),
+            op("Tm", vec![real(1.0), real(0.0), real(0.0), real(1.0), real(x), real(y_draw)]),
+        ]);
+        for line in lines {
+            canvas.operations.push(op("Tj", vec![Object::String(line, StringFormat::Hexadecimal)]));
+            canvas.operations.push(op("T*", vec![]));
+        }
+        canvas.operations.extend([op("ET", vec![]), op("Q", vec![])]);
+    }
+
+    if text.underline || text.strike {
+        let single_line = text.value.replace(['\r', '\n'], "");
+        let text_width = w.min(text.font.width_of(&encode_win_ansi(&single_line), size));
+        let thickness = (size / 14.0).max(1.0);
+        for (enabled, offset) in [(text.underline, -size * 0.15), (text.strike, size * 0.3)] {
+            if enabled {
+                canvas.line(
+                    PdfPoint { x, y: y_draw + offset },
+                    PdfPoint { x: x + text_width, y: y_draw + offset },
+                    text.color,
+                    thickness,
+                );
+            }
+        }
+    }
+}
+
+/// Common view of text and check items.
+struct TextRun<'a> {
+    rect: PdfRect,
+    value: &'a str,
+    font_size: f64,
+    color: &'a str,
+    font: StandardFont,
+    underline: bool,
+    strike: bool,
+}
+
+fn draw_ellipse(canvas: &mut PageCanvas, rect: PdfRect, color: &str, stroke_width: f64) {
+    let (cx, cy) = (rect.x + rect.w / 2.0, rect.y + rect.h / 2.0);
+    let (rx, ry) = (rect.w / 2.0, rect.h / 2.0);
+    let (ox, oy) = (rx * KAPPA, ry * KAPPA);
+    let point = |px: f64, py: f64| [real(px), real(py)];
+    let curve = |points: [[Object; 2]; 3]| op("c", points.into_iter().flatten().collect());
+    let [r, g, b] = rgb(color);
+    canvas.operations.extend([
+        op("q", vec![]),
+        op("RG", vec![r, g, b]),
+        op("w", vec![real(stroke_width)]),
+        op("m", point(cx - rx, cy).to_vec()),
+        curve([point(cx - rx, cy + oy), point(cx - ox, cy + ry), point(cx, cy + ry)]),
+        curve([point(cx + ox, cy + ry), point(cx + rx, cy + oy), point(cx + rx, cy)]),
+        curve([point(cx + rx, cy - oy), point(cx + ox, cy - ry), point(cx, cy - ry)]),
+        curve([point(cx - ox, cy - ry), point(cx - rx, cy - oy), point(cx - rx, cy)]),
+        op("S", vec![]),
+        op("Q", vec![]),
+    ]);
+}
+
+fn draw_arrow(canvas: &mut PageCanvas, arrow: &LineItem) {
+    let (start, end) = arrow.endpoints();
+    let angle = (end.y - start.y).atan2(end.x - start.x);
+    let head_length = (arrow.stroke_width * 3.0).max(6.0);
+    let head_width = head_length * 0.7;
+    let (hx, hy) = (angle.cos() * head_length, angle.sin() * head_length);
+    let (px, py) = (-angle.sin() * head_width * 0.5, angle.cos() * head_width * 0.5);
+    let base = PdfPoint { x: end.x - hx, y: end.y - hy };
+
+    canvas.line(start, base, &arrow.color, arrow.stroke_width);
+    canvas.line(end, PdfPoint { x: base.x + px, y: base.y + py }, &arrow.color, arrow.stroke_width);
+    canvas.line(end, PdfPoint { x: base.x - px, y: base.y - py }, &arrow.color, arrow.stroke_width);
+}
+
+fn draw_highlight(canvas: &mut PageCanvas, update: &mut IncrementalUpdate, resources: &mut Resources, rect: PdfRect, color: &str) {
+    let (state_name, state_id) = resources.highlight_state(update);
+    canvas.states.insert(state_name.clone(), state_id);
+    let [r, g, b] = rgb(color);
+    canvas.operations.extend([
+        op("q", vec![]),
+        op("gs", vec![Object::Name(state_name.into_bytes())]),
+        op("rg", vec![r, g, b]),
+        op("re", vec![real(rect.x), real(rect.y), real(rect.w), real(rect.h)]),
+        op("f", vec![]),
+        op("Q", vec![]),
+    ]);
+}
+
+/// The page's effective `/Resources`, walking up `/Parent` for
+/// inherited ones.
+fn inherited_resources(update: &IncrementalUpdate, page: &Dictionary) -> Dictionary {
+    let mut node = page.clone();
+    for _ in 0..MAX_DEPTH {
+        if let Some(Object::Dictionary(resources)) = node.get(b"Resources").ok().and_then(|r| update.resolve(r).ok()) {
+            return resources;
+        }
+        match node.get(b"Parent").ok().and_then(|p| update.resolve(p).ok()) {
+            Some(Object::Dictionary(parent)) => node = parent,
+            _ => break,
+        }
+    }
+    Dictionary::new()
+}
+
+/// Append the canvas to the page : the original content is wrapped in
+/// `q` / `Q` so a graphics state it leaves behind cannot leak into the
+/// overlay, as pdf-lib does.
+fn commit_page(update: &mut IncrementalUpdate, page_id: ObjectId, canvas: PageCanvas) -> Result<(), String> {
+    let mut page = update.get_dictionary(page_id)?;
+
+    let mut contents = match page.get(b"Contents").ok().map(|c| update.resolve(c)).transpose()? {
+        Some(Object::Array(parts)) => parts,
+        Some(Object::Stream(_)) => vec![page.get(b"Contents").cloned().map_err(|e| e.to_string())?],
+        _ => Vec::new(),
+    };
+    let body = Content { operations: canvas.operations }
+        .encode()
+        .map_err(|e| format!("invalid content: {e}"))?;
+    let mut overlay = b"Q\n".to_vec();
+    overlay.extend_from_slice(&body);
+    let open_id = update.add(Object::Stream(Stream::new(Dictionary::new(), b"q\n".to_vec())));
+    let overlay_id = update.add(Object::Stream(Stream::new(
+        dictionary! { "Filter" => "FlateDecode" },
+        deflate(&overlay)?,
+    )));
+    contents.insert(0, Object::Reference(open_id));
+    contents.push(Object::Reference(overlay_id));
+    page.set("Contents", Object::Array(contents));
+
+    let mut resources = inherited_resources(update, &page);
+    for (category, entries) in [
+        (&b"Font"[..], canvas.fonts),
+        (&b"XObject"[..], canvas.images),
+        (&b"ExtGState"[..], canvas.states),
+    ] {
+        if entries.is_empty() {
+            continue;
+        }
+        let mut dict = match resources.get(category).ok().map(|d| update.resolve(d)).transpose()? {
+            Some(Object::Dictionary(dict)) => dict,
+            _ => Dictionary::new(),
+        };
+        for (name, id) in entries {
+            dict.set(name.into_bytes(), id);
+        }
+        resources.set(category, Object::Dictionary(dict));
+    }
+    page.set("Resources", Object::Dictionary(resources));
+    update.set(page_id, Object::Dictionary(page));
+    Ok(())
+}
+
+pub(crate) fn decode_text(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => {
+            let units: Vec<u16> = rest.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => bytes.iter().map(|&byte| char::from(byte)).collect(),
+    }
+}
+
+/// A text string in PDFDocEncoding when ASCII suffices, UTF-16BE
+/// otherwise (the encoding `PDFHexString.fromText` picks).
+pub(crate) fn text_string(value: &str) -> Object {
+    if value.is_ascii() {
+        Object::String(value.as_bytes().to_vec(), StringFormat::Literal)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(value.encode_utf16().flat_map(u16::to_be_bytes));
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+struct FormField {
+    name: String,
+    id: ObjectId,
+    kind: Vec<u8>,
+    flags: i64,
+    widgets: Vec<ObjectId>,
+}
+
+fn collect_fields(
+    update: &IncrementalUpdate,
+    fields: &[Object],
+    parent: Option<(&str, &[u8], i64)>,
+    depth: usize,
+    out: &mut Vec<FormField>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    for field in fields {
+        let Object::Reference(id) = field else {
+            continue;
+        };
+        let Ok(dict) = update.get_dictionary(*id) else {
+            continue;
+        };
+        let partial = dict.get(b"T").ok().and_then(|t| t.as_str().ok()).map(decode_text);
+        let name = match (parent.map(|p| p.0), partial) {
+            (Some(parent), Some(partial)) => format!("{parent}.{partial}"),
+            (None, Some(partial)) => partial,
+            (Some(parent), None) => parent.to_string(),
+            (None, None) => continue,
+        };
+        let kind = dict.get(b"FT").and_then(Object::as_name).map(<[u8]>::to_vec).unwrap_or_else(|_| parent.map(|p| p.1.to_vec()).unwrap_or_default());
+        let flags = dict.get(b"Ff").and_then(Object::as_i64).unwrap_or_else(|_| parent.map(|p| p.2).unwrap_or(0));
+
+        let kids: Vec<Object> = match dict.get(b"Kids").ok().map(|k| update.resolve(k)) {
+            Some(Ok(Object::Array(kids))) => kids,
+            _ => Vec::new(),
+        };
+        let has_child_fields = kids.iter().any(|kid| match kid {
+            Object::Reference(kid_id) => update.get_dictionary(*kid_id).map(|d| d.has(b"T")).unwrap_or(false),
+            _ => false,
+        });
+        if has_child_fields {
+            collect_fields(update, &kids, Some((&name, &kind, flags)), depth + 1, out);
+            continue;
+        }
+        let widgets = if kids.is_empty() {
+            vec![*id]
+        } else {
+            kids.iter().filter_map(|kid| kid.as_reference().ok()).collect()
+        };
+        out.push(FormField { name, id: *id, kind, flags, widgets });
+    }
+}
+
+/// Appearance state a widget shows when on (its `/AP /N` key other
+/// than `Off`).
+fn on_state(update: &IncrementalUpdate, widget: ObjectId) -> Option<Vec<u8>> {
+    let dict = update.get_dictionary(widget).ok()?;
+    let appearance = update.resolve(dict.get(b"AP").ok()?).ok()?;
+    let normal = update.resolve(appearance.as_dict().ok()?.get(b"N").ok()?).ok()?;
+    let states = normal.as_dict().ok()?;
+    states.iter().map(|(key, _)| key.clone()).find(|key| key.as_slice() != b"Off")
+}
+
+fn options(update: &IncrementalUpdate, field: &Dictionary) -> Vec<(String, String)> {
+    let Some(Ok(Object::Array(entries))) = field.get(b"Opt").ok().map(|o| update.resolve(o)) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| match update.resolve(entry).ok()? {
+            Object::String(value, _) => {
+                let value = decode_text(&value);
+                Some((value.clone(), value))
+            }
+            Object::Array(pair) => {
+                let export = decode_text(pair.first()?.as_str().ok()?);
+                let display = decode_text(pair.get(1)?.as_str().ok()?);
+                Some((export, display))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn update_dictionary(update: &mut IncrementalUpdate, id: ObjectId, edit: impl FnOnce(&mut Dictionary)) -> Result<(), String> {
+    let mut dict = update.get_dictionary(id)?;
+    edit(&mut dict);
+    update.set(id, Object::Dictionary(dict));
+    Ok(())
+}
+
+/// What writing a value did to a field.
+#[derive(PartialEq)]
+enum Written {
+    Unchanged,
+    /// With appearances matching the new value.
+    Drawn,
+    /// The stale appearances were dropped for the viewer to redraw.
+    NeedsAppearances,
+}
+
+/// Form-wide defaults of variable text fields (ISO 32000-1 12.7.3.3).
+struct FormDefaults {
+    da: Vec<u8>,
+    quadding: i64,
+    /// `/DR /Font` : the fonts `/DA` strings name.
+    fonts: Dictionary,
+    /// Standard font added for fields whose `/DA` names none we have.
+    helvetica: Option<ObjectId>,
+}
+
+impl FormDefaults {
+    fn new(update: &IncrementalUpdate, acroform: &Dictionary) -> Self {
+        let resolve = |key: &[u8]| acroform.get(key).ok().and_then(|o| update.resolve(o).ok());
+        let fonts = match resolve(b"DR") {
+            Some(Object::Dictionary(resources)) => match resources.get(b"Font").ok().map(|f| update.resolve(f)) {
+                Some(Ok(Object::Dictionary(fonts))) => fonts,
+                _ => Dictionary::new(),
+            },
+            _ => Dictionary::new(),
+        };
+        FormDefaults {
+            da: match resolve(b"DA") {
+                Some(Object::String(da, _)) => da,
+                _ => Vec::new(),
+            },
+            quadding: resolve(b"Q").and_then(|q| q.as_i64().ok()).unwrap_or(0),
+            fonts,
+            helvetica: None,
+        }
+    }
+}
+
+/// `key` of `id`, or of the nearest `/Parent` that has one.
+fn inherited(update: &IncrementalUpdate, id: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node = update.get_dictionary(id).ok()?;
+    for _ in 0..MAX_DEPTH {
+        if let Ok(value) = node.get(key) {
+            return update.resolve(value).ok();
+        }
+        node = update.get_dictionary(node.get(b"Parent").ok()?.as_reference().ok()?).ok()?;
+    }
+    None
+}
+
+/// Width and height of a widget's `/Rect`.
+fn widget_size(update: &IncrementalUpdate, widget: ObjectId) -> Result<(f64, f64), String> {
+    let dict = update.get_dictionary(widget)?;
+    let rect = match update.numbers(dict.get(b"Rect").map_err(|e| e.to_string())?)[..] {
+        [x0, y0, x1, y1] => text::normalized(PdfRect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 }),
+        _ => return Err(format!("invalid pdf: /Rect of widget {} {}", widget.0, widget.1)),
+    };
+    Ok((rect.w, rect.h))
+}
+
+fn form_xobject(
+    update: &mut IncrementalUpdate,
+    size: (f64, f64),
+    resources: Dictionary,
+    operations: Vec<Operation>,
+) -> Result<ObjectId, String> {
+    let content = Content { operations }.encode().map_err(|e| format!("invalid content: {e}"))?;
+    let dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => vec![real(0.0), real(0.0), real(size.0), real(size.1)],
+        "Resources" => resources,
+        "Filter" => "FlateDecode",
+    };
+    Ok(update.add(Object::Stream(Stream::new(dict, deflate(&content)?))))
+}
+
+/// Normal appearance of a text or combo box widget showing `value`,
+/// laid out from its `/DA` and `/Q` : clipped inside the border, one
+/// line centred vertically or, for multiline fields, lines from the
+/// top. A `0 Tf` size fits the line in the box.
+fn text_appearance(
+    update: &mut IncrementalUpdate,
+    defaults: &mut FormDefaults,
+    widget: ObjectId,
+    value: &str,
+    multiline: bool,
+) -> Result<ObjectId, String> {
+    let (w, h) = widget_size(update, widget)?;
+    let da = match inherited(update, widget, b"DA") {
+        Some(Object::String(da, _)) => da,
+        _ => defaults.da.clone(),
+    };
+    let quadding = inherited(update, widget, b"Q").and_then(|q| q.as_i64().ok()).unwrap_or(defaults.quadding);
+
+    // `/DA` holds a `Tf` and the colour operators to replay as is.
+    let mut style = Content::decode(&da).map(|content| content.operations).unwrap_or_default();
+    style.retain(|op| matches!(op.operator.as_str(), "Tf" | "g" | "rg" | "k"));
+    let tf = style.iter().position(|op| op.operator == "Tf").map(|index| style.remove(index));
+    let (font_name, font_size) = match &tf {
+        Some(tf) => (
+            tf.operands.first().and_then(|name| name.as_name().ok()).map(<[u8]>::to_vec),
+            tf.operands.get(1).and_then(text::number).unwrap_or(0.0),
+        ),
+        None => (None, 0.0),
+    };
+    let declared = font_name.as_ref().and_then(|name| Some((name.clone(), defaults.fonts.get(name).ok()?.clone())));
+    let (font_name, font) = match declared {
+        Some(declared) => declared,
+        None => {
+            let id = *defaults.helvetica.get_or_insert_with(|| {
+                update.add(Object::Dictionary(dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "Type1",
+                    "BaseFont" => StandardFont::Helvetica.base_font(),
+                    "Encoding" => "WinAnsiEncoding",
+                }))
+            });
+            (format!("CfF{}", id.0).into_bytes(), Object::Reference(id))
+        }
+    };
+    // Metrics of the standard face closest to the font, Helvetica's
+    // when nothing matches.
+    let metrics = match update.resolve(&font) {
+        Ok(Object::Dictionary(dict)) => dict
+            .get(b"BaseFont")
+            .and_then(Object::as_name)
+            .ok()
+            .and_then(|name| StandardFont::from_base_font(&String::from_utf8_lossy(name))),
+        _ => None,
+    }
+    .unwrap_or(StandardFont::Helvetica);
+
+    let (inner_w, inner_h) = ((w - 2.0 * FIELD_PADDING).max(0.0), (h - 2.0 * FIELD_PADDING).max(0.0));
+    let size = if font_size > 0.0 {
+        font_size
+    } else {
+        let widest = value.lines().map(|line| metrics.width_of(&encode_win_ansi(line), 1.0)).fold(0.0, f64::max);
+        let fit = if multiline || widest == 0.0 { FIELD_MAX_FONT_SIZE } else { inner_w / widest };
+        fit.min(inner_h / 1.2).clamp(1.0, FIELD_MAX_FONT_SIZE)
+    };
+    let lines = if multiline {
+        break_lines(value, metrics, size, inner_w)
+    } else {
+        vec![encode_win_ansi(&value.replace(['\r', '\n'], " "))]
+    };
+
+    let mut operations = vec![
+        op("BMC", vec![Object::Name(b"Tx".to_vec())]),
+        op("q", vec![]),
+        op("re", vec![real(1.0), real(1.0), real((w - 2.0).max(0.0)), real((h - 2.0).max(0.0))]),
+        op("W", vec![]),
+        op("n", vec![]),
+        op("BT", vec![]),
+    ];
+    operations.extend(style);
+    operations.push(op("Tf", vec![Object::Name(font_name.clone()), real(size)]));
+    // Baselines : centred on the cap height for one line, one line
+    // height apart from the top otherwise.
+    let line_height = size * 1.2;
+    let first_baseline = if multiline { h - FIELD_PADDING - size } else { (h - size * 0.7) / 2.0 };
+    for (index, line) in lines.into_iter().enumerate() {
+        let width = metrics.width_of(&line, size);
+        let x = match quadding {
+            1 => (w - width) / 2.0,
+            2 => w - FIELD_PADDING - width,
+            _ => FIELD_PADDING,
+        };
+        let y = first_baseline - line_height * index as f64;
+        operations.push(op("Tm", vec![real(1.0), real(0.0), real(0.0), real(1.0), real(x), real(y)]));
+        operations.push(op("Tj", vec![Object::String(line, StringFormat::Literal)]));
+    }
+    operations.extend([op("ET", vec![]), op("Q", vec![]), op("EMC", vec![])]);
+
+    let resources = dictionary! { "Font" => dictionary! { font_name => font } };
+    form_xobject(update, (w, h), resources, operations)
+}
+
+/// `/AP` of a checkbox widget that has none : a ZapfDingbats check mark
+/// under `on`, as pdf-lib draws it, and an empty `Off`.
+fn check_appearance(update: &mut IncrementalUpdate, widget: ObjectId, on: &[u8]) -> Result<Dictionary, String> {
+    let (w, h) = widget_size(update, widget)?;
+    // '4' is the check mark ; its box is 0.846 em wide.
+    let size = ((w.min(h) - 2.0 * FIELD_PADDING) / 0.846).max(1.0);
+    let mark = vec![
+        op("q", vec![]),
+        op("BT", vec![]),
+        op("g", vec![real(0.0)]),
+        op("Tf", vec![Object::Name(b"ZaDb".to_vec()), real(size)]),
+        op("Td", vec![real((w - size * 0.846) / 2.0), real((h - size * 0.7) / 2.0)]),
+        op("Tj", vec![Object::string_literal("4")]),
+        op("ET", vec![]),
+        op("Q", vec![]),
+    ];
+    let zapf = dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "ZapfDingbats" };
+    let on_id = form_xobject(update, (w, h), dictionary! { "Font" => dictionary! { "ZaDb" => zapf } }, mark)?;
+    let off_id = form_xobject(update, (w, h), Dictionary::new(), Vec::new())?;
+    let mut normal = Dictionary::new();
+    normal.set(on.to_vec(), on_id);
+    normal.set("Off", off_id);
+    Ok(dictionary! { "N" => normal })
+}
+
+/// Write one value, with the same per-kind rules as the JS export, and
+/// the appearances showing it.
+fn apply_form_value(
+    update: &mut IncrementalUpdate,
+    defaults: &mut FormDefaults,
+    field: &FormField,
+    value: &Option<FormValue>,
+) -> Result<Written, String> {
+    let dict = update.get_dictionary(field.id)?;
+    let shown = match (field.kind.as_slice(), value) {
+        (b"Tx", Some(FormValue::Text(text))) => {
+            update_dictionary(update, field.id, |d| d.set("V", text_string(text)))?;
+            text.clone()
+        }
+        (b"Btn", _) if field.flags & FF_PUSHBUTTON != 0 => return Ok(Written::Unchanged),
+        (b"Btn", _) if field.flags & FF_RADIO != 0 => {
+            let selected = match value {
+                None => None,
+                Some(FormValue::Text(option)) if option.is_empty() => None,
+                Some(FormValue::Text(option)) => {
+                    let exports = options(update, &dict);
+                    let widget = if exports.is_empty() {
+                        field.widgets.iter().find(|w| on_state(update, **w).as_deref() == Some(option.as_bytes())).copied()
+                    } else {
+                        exports.iter().position(|(export, _)| export == option).and_then(|idx| field.widgets.get(idx).copied())
+                    };
+                    match widget.and_then(|w| on_state(update, w)) {
+                        Some(state) => Some(state),
+                        // Unknown option : left as is, like pdf-lib's `select` throwing.
+                        None => return Ok(Written::Unchanged),
+                    }
+                }
+                Some(FormValue::Flag(_)) => return Ok(Written::Unchanged),
+            };
+            let value = selected.clone().unwrap_or_else(|| b"Off".to_vec());
+            update_dictionary(update, field.id, |d| d.set("V", Object::Name(value)))?;
+            for widget in &field.widgets {
+                let state = match (&selected, on_state(update, *widget)) {
+                    (Some(selected), Some(own)) if *selected == own => own,
+                    _ => b"Off".to_vec(),
+                };
+                update_dictionary(update, *widget, |d| d.set("AS", Object::Name(state)))?;
+            }
+            return Ok(Written::Drawn);
+        }
+        (b"Btn", Some(FormValue::Flag(checked))) => {
+            let on = field.widgets.iter().find_map(|w| on_state(update, *w)).unwrap_or_else(|| b"Yes".to_vec());
+            let value = if *checked { on.clone() } else { b"Off".to_vec() };
+            update_dictionary(update, field.id, |d| d.set("V", Object::Name(value.clone())))?;
+            for widget in &field.widgets {
+                // A widget without appearances gets some, so the box
+                // shows its state whatever the viewer.
+                let own = match on_state(update, *widget) {
+                    Some(own) => own,
+                    None => {
+                        let appearance = check_appearance(update, *widget, &on)?;
+                        update_dictionary(update, *widget, |d| d.set("AP", appearance))?;
+                        on.clone()
+                    }
+                };
+                let state = if *checked { own } else { b"Off".to_vec() };
+                update_dictionary(update, *widget, |d| d.set("AS", Object::Name(state)))?;
+            }
+            return Ok(Written::Drawn);
+        }
+        (b"Ch", Some(FormValue::Text(choice))) => {
+            let exports = options(update, &dict);
+            if choice.is_empty() {
+                update_dictionary(update, field.id, |d| {
+                    d.remove(b"V");
+                })?;
+            } else {
+                let editable = field.flags & FF_EDIT != 0;
+                let known = exports.iter().any(|(export, display)| export == choice || display == choice);
+                if !known && !editable {
+                    return Ok(Written::Unchanged);
+                }
+                update_dictionary(update, field.id, |d| d.set("V", text_string(choice)))?;
+            }
+            if field.flags & FF_COMBO == 0 {
+                // A list box draws every option with the selected one
+                // highlighted : its stale appearance is dropped and
+                // left to the viewer (`NeedAppearances`).
+                for widget in &field.widgets {
+                    update_dictionary(update, *widget, |d| {
+                        d.remove(b"AP");
+                    })?;
+                }
+                return Ok(Written::NeedsAppearances);
+            }
+            let display = exports.into_iter().find(|(export, _)| export == choice);
+            display.map_or_else(|| choice.clone(), |(_, display)| display)
+        }
+        _ => return Ok(Written::Unchanged),
+    };
+    let multiline = field.kind == b"Tx" && field.flags & FF_MULTILINE != 0;
+    for widget in &field.widgets {
+        let normal = text_appearance(update, defaults, *widget, &shown, multiline)?;
+        update_dictionary(update, *widget, |d| d.set("AP", dictionary! { "N" => normal }))?;
+    }
+    Ok(Written::Drawn)
+}
+
+/// Write `values` into the AcroForm. Names that match no field are
+/// skipped : the map may hold stale entries from a previous document.
+fn apply_form_values(update: &mut IncrementalUpdate, values: &FormValues) -> Result<(), String> {
+    if values.is_empty() {
+        return Ok(());
+    }
+    let catalog_id = update
+        .trailer()
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|_| "invalid pdf: catalog not found".to_string())?;
+    let catalog = update.get_dictionary(catalog_id)?;
+    let (acroform_id, mut acroform) = match catalog.get(b"AcroForm").ok().cloned() {
+        Some(Object::Reference(id)) => (Some(id), update.get_dictionary(id)?),
+        Some(Object::Dictionary(dict)) => (None, dict),
+        // No form : nothing to write, not an error.
+        _ => return Ok(()),
+    };
+    let fields = match acroform.get(b"Fields").ok().map(|f| update.resolve(f)) {
+        Some(Ok(Object::Array(fields))) => fields,
+        _ => return Ok(()),
+    };
+    let mut found = Vec::new();
+    collect_fields(update, &fields, None, 0, &mut found);
+
+    let mut defaults = FormDefaults::new(update, &acroform);
+    let mut needs_appearances = false;
+    for field in &found {
+        if let Some(value) = values.get(&field.name) {
+            needs_appearances |= apply_form_value(update, &mut defaults, field, value)? == Written::NeedsAppearances;
+        }
+    }
+    if needs_appearances {
+        acroform.set("NeedAppearances", Object::Boolean(true));
+        match acroform_id {
+            Some(id) => update.set(id, Object::Dictionary(acroform)),
+            None => {
+                let mut catalog = catalog;
+                catalog.set("AcroForm", Object::Dictionary(acroform));
+                update.set(catalog_id, Object::Dictionary(catalog));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Add the flattened overlay to `update`. Items pointing at a missing
+/// page or a missing signature asset are skipped, as in the JS export.
+pub fn flatten<'a>(mut update: IncrementalUpdate<'a>, overlay: &Overlay) -> Result<IncrementalUpdate<'a>, String> {
+    apply_form_values(&mut update, overlay.form_values)?;
+
+    let pages = update.pages()?;
+    let mut resources = Resources { fonts: HashMap::new(), images: HashMap::new(), highlight_state: None };
+    let mut canvases: BTreeMap<u32, PageCanvas> = BTreeMap::new();
+
+    for item in overlay.items {
+        if !pages.contains_key(&item.page()) {
+            continue;
+        }
+        let canvas = canvases.entry(item.page()).or_default();
+        match item {
+            Item::Text(text) => {
+                let run = TextRun {
+                    rect: text.rect,
+                    value: &text.value,
+                    font_size: text.font_size,
+                    color: &text.color,
+                    font: StandardFont::for_style(text.font_family, text.bold),
+                    underline: text.underline,
+                    strike: text.strike,
+                };
+                draw_text(canvas, &mut update, &mut resources, &run);
+            }
+            Item::Check(check) => {
+                let run = TextRun {
+                    rect: check.rect,
+                    value: &check.value,
+                    font_size: check.font_size,
+                    color: &check.color,
+                    font: StandardFont::Helvetica,
+                    underline: false,
+                    strike: false,
+                };
+                draw_text(canvas, &mut update, &mut resources, &run);
+            }
+            Item::Ellipse(ellipse) => draw_ellipse(canvas, ellipse.rect, &ellipse.color, ellipse.stroke_width),
+            Item::Line(line) => {
+                let (start, end) = line.endpoints();
+                canvas.line(start, end, &line.color, line.stroke_width);
+            }
+            Item::Arrow(arrow) => draw_arrow(canvas, arrow),
+            Item::Highlight(highlight) => draw_highlight(canvas, &mut update, &mut resources, highlight.rect, &highlight.color),
+            Item::Signature(signature) => {
+                let Some(asset) = overlay.signatures.iter().find(|s| s.id == signature.signature_id) else {
+                    continue;
+                };
+                let (image_name, image_id) = resources.image(&mut update, asset)?;
+                canvas.image(image_name, image_id, signature.rect);
+            }
+        }
+    }
+
+    // The paraph goes on every page after the items so initials sit on
+    // top of any background drawing.
+    if let Some((paraph, asset)) = overlay.paraph {
+        let (image_name, image_id) = resources.image(&mut update, asset)?;
+        for page_number in pages.keys() {
+            canvases.entry(*page_number).or_default().image(image_name.clone(), image_id, paraph.rect);
+        }
+    }
+
+    for (page_number, canvas) in canvases {
+        if !canvas.operations.is_empty() {
+            commit_page(&mut update, pages[&page_number], canvas)?;
+        }
+    }
+    Ok(update)
+}
+
+/// Copy `source` next to `target` and append `tail` to the copy before
+/// moving it into place, so a failed export never leaves a truncated
+/// file behind. The copy is streamed by the OS, which also makes it safe
+/// when `target` is `source` : the rename only happens once it is done.
+pub fn write_export(source: &Path, target: &Path, tail: &[u8]) -> error::Result<()> {
+    let mut tmp_name = target.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = target.with_file_name(tmp_name);
+    let result = (|| {
+        std::fs::copy(source, &tmp)?;
+        let mut file = OpenOptions::new().append(true).open(&tmp)?;
+        file.write_all(tail)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, target)
+    })();
+    if let Err(err) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(Error::io("pdf_write_failed", target, &err));
+    }
+    Ok(())
+}
+
+/// Native alternative to `exportFlattenedPdf` + `save_pdf_to_path` :
+/// the frontend only sends the overlay, signature images are read
+/// from the galleries (through the vault when it is enabled) and the
+/// target is chosen through the save dialog. A source opened with a
+/// password is read in clear, and `protection` encrypts the result.
+/// Returns the written path, or `None` when the user cancelled.
+#[tauri::command(async)]
+pub fn export_flattened_pdf(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    passwords: tauri::State<crypt::Passwords>,
+    request: ExportRequest,
+) -> error::Result<Option<String>> {
+    let source = crate::scope::readable_pdf(&app, Path::new(&request.source_path))?;
+    let suggested = crate::sanitize_file_name(request.file_name.as_deref().unwrap_or("document-signed.pdf"));
+    let Some(picked) = app.dialog().file().add_filter("PDF", &["pdf"]).set_file_name(suggested).blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let target = picked.into_path().map_err(|e| Error::new(ErrorKind::InvalidInput, "path_invalid").with_details(e))?;
+    if !crate::is_pdf_path(&target) {
+        return Err(Error::new(ErrorKind::InvalidInput, "not_a_pdf_target").with_path(&target));
+    }
+
+    let data_dir = crate::app_data_dir(&app)?;
+    let wants_signatures = request.items.iter().any(|item| matches!(item, Item::Signature(_)));
+    let signatures: Vec<StoredSignature> = if wants_signatures {
+        db::load_assets(&vault, &data_dir, db::Gallery::Signatures)?
+    } else {
+        Vec::new()
+    };
+    let paraphs: Vec<StoredSignature> = if request.paraph.is_some() {
+        db::load_assets(&vault, &data_dir, db::Gallery::Paraphs)?
+    } else {
+        Vec::new()
+    };
+    let paraph = request
+        .paraph
+        .as_ref()
+        .and_then(|paraph| paraphs.iter().find(|asset| asset.id == paraph.asset_id).map(|asset| (paraph, asset)));
+
+    let overlay = Overlay {
+        items: &request.items,
+        signatures: &signatures,
+        paraph,
+        form_values: &request.form_values,
+    };
+    let failed =
+        |details: String| Error::new(ErrorKind::InvalidPdf, "export_failed").with_path(&source).with_details(details);
+    if passwords.get(&source).is_none() && request.protection.is_none() {
+        // Only the cross-reference sections and the objects the overlay
+        // touches are read from the source ; the rest is copied as is.
+        let file = std::fs::File::open(&source).map_err(|e| Error::io("pdf_read_failed", &source, &e))?;
+        let tail = IncrementalUpdate::from_reader(file)
+            .and_then(|update| flatten(update, &overlay))
+            .and_then(|update| update.tail())
+            .map_err(failed)?;
+        write_export(&source, &target, &tail)?;
+    } else {
+        // A protected source is flattened in clear, like the renderer
+        // sees it, and the whole result is encrypted again on request.
+        let clear = crypt::read_clear(&passwords, &source)?;
+        let mut bytes = IncrementalUpdate::new(&clear)
+            .and_then(|update| flatten(update, &overlay))
+            .and_then(|update| update.to_bytes())
+            .map_err(failed)?;
+        drop(clear);
+        if let Some(protection) = &request.protection {
+            bytes = crypt::encrypt(&bytes, protection)?;
+        }
+        store::write_atomic(&target, &bytes, false).map_err(|e| Error::io("pdf_write_failed", &target, &e))?;
+    }
+    crate::recent::record(&app, &target);
+    Ok(Some(target.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::items::{CheckItem, EllipseItem, HighlightItem, SignatureItem, TextItem};
+    use crate::pdfio::tests::sample_pdf;
+    use lopdf::Document;
+
+    fn rect(x: f64, y: f64, w: f64, h: f64) -> PdfRect {
+        PdfRect { x, y, w, h }
+    }
+
+    fn text_item(page: u32, value: &str) -> Item {
+        Item::Text(TextItem {
+            id: "t".into(),
+            page,
+            rect: rect(50.0, 700.0, 200.0, 20.0),
+            value: value.into(),
+            font_size: 12.0,
+            color: "#ff0000".into(),
+            font_family: Default::default(),
+            bold: false,
+            underline: true,
+            strike: false,
+            auto_date: None,
+        })
+    }
+
+    /// 2x1 RGBA PNG : one opaque red pixel, one transparent one.
+    pub fn tiny_png() -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 255, 0, 0, 0, 0]).unwrap();
+        drop(writer);
+        out
+    }
+
+    pub fn asset(id: &str, mime: &str, bytes: Vec<u8>) -> StoredSignature {
+        StoredSignature { id: id.into(), name: id.into(), mime: mime.into(), bytes, natural_w: 2, natural_h: 1, ink: None }
+    }
+
+    fn export(original: &[u8], overlay: &Overlay) -> Document {
+        let bytes = flatten(IncrementalUpdate::new(original).unwrap(), overlay).unwrap().to_bytes().unwrap();
+        assert!(bytes.starts_with(original), "original bytes must be preserved");
+        Document::load_mem(&bytes).unwrap()
+    }
+
+    fn page_operations(doc: &Document, page: u32) -> Vec<Operation> {
+        let page_id = doc.get_pages()[&page];
+        doc.get_and_decode_page_content(page_id).unwrap().operations
+    }
+
+    fn operators(operations: &[Operation]) -> Vec<&str> {
+        operations.iter().map(|op| op.operator.as_str()).collect()
+    }
+
+    #[test]
+    fn breaks_lines_like_pdf_lib() {
+        let lines = break_lines("aaa bbb ccc\n\nddd", StandardFont::Courier, 10.0, 50.0);
+        // Courier is 6 pt per glyph at 10 pt : "aaa " + "bbb " = 48 pt fits.
+        assert_eq!(lines, vec![b"aaa bbb ".to_vec(), b"ccc".to_vec(), b"ddd".to_vec()]);
+    }
+
+    #[test]
+    fn round_trip_burns_text_and_shapes_on_the_right_page() {
+        let original = sample_pdf(2, "Hello");
+        let items = vec![
+            text_item(2, "Signé à Paris"),
+            Item::Ellipse(EllipseItem {
+                id: "e".into(),
+                page: 2,
+                rect: rect(10.0, 10.0, 40.0, 20.0),
+                color: "#0000ff".into(),
+                stroke_width: 2.0,
+            }),
+            Item::Highlight(HighlightItem { id: "h".into(), page: 2, rect: rect(0.0, 0.0, 10.0, 10.0), color: "#ffff00".into() }),
+            Item::Check(CheckItem {
+                id: "c".into(),
+                page: 9,
+                rect: rect(0.0, 0.0, 10.0, 10.0),
+                value: "X".into(),
+                font_size: 10.0,
+                color: "#000000".into(),
+            }),
+        ];
+        let values = FormValues::new();
+        let doc = export(&original, &Overlay { items: &items, signatures: &[], paraph: None, form_values: &values });
+
+        // Page 1 is untouched, page 9 does not exist.
+        assert_eq!(operators(&page_operations(&doc, 1)), vec!["BT", "Tf", "Td", "Tj", "ET"]);
+
+        let operations = page_operations(&doc, 2);
+        let ops = operators(&operations);
+        assert_eq!(ops.first(), Some(&"q"));
+        let shown = operations.iter().find(|op| op.operator == "Tj" && op.operands[0].as_str().unwrap() != b"Hello 2").unwrap();
+        assert_eq!(shown.operands[0].as_str().unwrap(), encode_win_ansi("Signé à Paris").as_slice());
+        let tm = operations.iter().find(|op| op.operator == "Tm").unwrap();
+        assert_eq!(tm.operands[5].as_float().unwrap(), 708.0);
+        assert_eq!(ops.iter().filter(|op| **op == "c").count(), 4);
+        assert!(ops.contains(&"re") && ops.contains(&"gs"));
+        // Underline : one stroked line below the baseline.
+        assert!(operations.iter().any(|op| op.operator == "m" && op.operands[1].as_float().unwrap() == 706.2));
+
+        let page = doc.get_dictionary(doc.get_pages()[&2]).unwrap();
+        let resources = page.get(b"Resources").and_then(Object::as_dict).unwrap();
+        let fonts = resources.get(b"Font").and_then(Object::as_dict).unwrap();
+        assert!(fonts.has(b"F1"), "existing resources are kept");
+        assert_eq!(fonts.len(), 2);
+        let states = resources.get(b"ExtGState").and_then(Object::as_dict).unwrap();
+        let (_, state) = states.iter().next().unwrap();
+        let state = doc.get_dictionary(state.as_reference().unwrap()).unwrap();
+        assert!((state.get(b"ca").unwrap().as_float().unwrap() - 0.35).abs() < 1e-6);
+    }
+
+    #[test]
+    fn round_trip_embeds_signature_and_paraph_images() {
+        let original = sample_pdf(3, "Page");
+        let items = vec![Item::Signature(SignatureItem {
+            id: "s".into(),
+            page: 1,
+            rect: rect(100.0, 100.0, 120.0, 60.0),
+            signature_id: "sig".into(),
+        })];
+        let signatures = vec![asset("sig", "image/png", tiny_png())];
+        let paraph_asset = asset("par", "image/png", tiny_png());
+        let paraph = Paraph { asset_id: "par".into(), rect: rect(500.0, 20.0, 40.0, 20.0) };
+        let values = FormValues::new();
+        let doc = export(
+            &original,
+            &Overlay { items: &items, signatures: &signatures, paraph: Some((&paraph, &paraph_asset)), form_values: &values },
+        );
+
+        for page in 1..=3 {
+            let draws = page_operations(&doc, page).into_iter().filter(|op| op.operator == "Do").count();
+            assert_eq!(draws, if page == 1 { 2 } else { 1 });
+        }
+        let page = doc.get_dictionary(doc.get_pages()[&1]).unwrap();
+        let xobjects = page
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|r| r.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        let (_, image) = xobjects.iter().next().unwrap();
+        let image = doc.get_object(image.as_reference().unwrap()).and_then(Object::as_stream).unwrap();
+        assert_eq!(image.dict.get(b"Width").and_then(Object::as_i64).unwrap(), 2);
+        let mask = image.dict.get(b"SMask").and_then(Object::as_reference).unwrap();
+        // lopdf refuses to decompress image streams.
+        let mut mask = doc.get_object(mask).and_then(Object::as_stream).unwrap().clone();
+        mask.dict.remove(b"Subtype");
+        assert_eq!(mask.decompressed_content().unwrap(), vec![255, 0]);
+    }
+
+    #[test]
+    fn drawn_signature_is_embedded_as_vectors() {
+        let original = sample_pdf(1, "Page");
+        let items = vec![Item::Signature(SignatureItem {
+            id: "s".into(),
+            page: 1,
+            rect: rect(100.0, 100.0, 120.0, 60.0),
+            signature_id: "sig".into(),
+        })];
+        let strokes = vec![vec![(10.0, 10.0, 0.5), (200.0, 150.0, 0.5), (390.0, 20.0, 0.5)]];
+        let drawn = StoredSignature {
+            ink: Some(crate::ink::tests::drawing(strokes)),
+            ..asset("sig", "image/png", tiny_png())
+        };
+        let values = FormValues::new();
+        let doc = export(&original, &Overlay { items: &items, signatures: &[drawn], paraph: None, form_values: &values });
+
+        let page = doc.get_dictionary(doc.get_pages()[&1]).unwrap();
+        let xobjects = page
+            .get(b"Resources")
+            .and_then(Object::as_dict)
+            .and_then(|r| r.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .unwrap();
+        let (_, form) = xobjects.iter().next().unwrap();
+        let form = doc.get_object(form.as_reference().unwrap()).and_then(Object::as_stream).unwrap();
+        assert_eq!(form.dict.get(b"Subtype").and_then(Object::as_name).unwrap(), b"Form");
+        let content = Content::decode(&form.decompressed_content().unwrap()).unwrap();
+        let operators: Vec<&str> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        assert!(operators.contains(&"c") && operators.contains(&"S"), "{operators:?}");
+    }
+
+    #[test]
+    fn missing_signature_asset_is_skipped() {
+        let original = sample_pdf(1, "Page");
+        let items = vec![Item::Signature(SignatureItem {
+            id: "s".into(),
+            page: 1,
+            rect: rect(0.0, 0.0, 10.0, 10.0),
+            signature_id: "gone".into(),
+        })];
+        let values = FormValues::new();
+        let update = flatten(IncrementalUpdate::new(&original).unwrap(), &Overlay { items: &items, signatures: &[], paraph: None, form_values: &values }).unwrap();
+        assert!(update.tail().unwrap().is_empty());
+    }
+
+    /// One-page document with a text field, a checkbox and a checkbox
+    /// without appearances.
+    fn form_pdf() -> Vec<u8> {
+        let mut doc = Document::load_mem(&sample_pdf(1, "Form")).unwrap();
+        let page_id = doc.get_pages()[&1];
+        let on = doc.add_object(Stream::new(Dictionary::new(), Vec::new()));
+        let text_id = doc.add_object(dictionary! {
+            "FT" => "Tx", "T" => Object::string_literal("nom"), "Type" => "Annot", "Subtype" => "Widget",
+            "Rect" => rect(0.0, 0.0, 100.0, 20.0).to_array(), "P" => page_id,
+            "AP" => dictionary! { "N" => on },
+        });
+        let check_id = doc.add_object(dictionary! {
+            "FT" => "Btn", "T" => Object::string_literal("ok"), "Type" => "Annot", "Subtype" => "Widget",
+            "Rect" => rect(0.0, 30.0, 10.0, 10.0).to_array(), "P" => page_id, "AS" => "Off",
+            "AP" => dictionary! { "N" => dictionary! { "Oui" => on, "Off" => on } },
+        });
+        let bare_id = doc.add_object(dictionary! {
+            "FT" => "Btn", "T" => Object::string_literal("nu"), "Type" => "Annot", "Subtype" => "Widget",
+            "Rect" => rect(20.0, 30.0, 10.0, 10.0).to_array(), "P" => page_id,
+        });
+        let parent = doc.add_object(dictionary! {
+            "T" => Object::string_literal("client"), "Kids" => vec![Object::Reference(text_id)], "Q" => 2,
+        });
+        doc.get_dictionary_mut(text_id).unwrap().set("Parent", parent);
+        let helv = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" });
+        let catalog_id = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        doc.get_dictionary_mut(catalog_id).unwrap().set(
+            "AcroForm",
+            dictionary! {
+                "Fields" => vec![Object::Reference(parent), Object::Reference(check_id), Object::Reference(bare_id)],
+                "DA" => Object::string_literal("/Helv 10 Tf 0 0 1 rg"),
+                "DR" => dictionary! { "Font" => dictionary! { "Helv" => helv } },
+            },
+        );
+        let mut out = Vec::new();
+        doc.save_to(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn round_trip_writes_form_values() {
+        let original = form_pdf();
+        let mut values = FormValues::new();
+        values.insert("client.nom".into(), Some(FormValue::Text("Zoé".into())));
+        values.insert("ok".into(), Some(FormValue::Flag(true)));
+        values.insert("stale".into(), Some(FormValue::Text("x".into())));
+        let doc = export(&original, &Overlay { items: &[], signatures: &[], paraph: None, form_values: &values });
+
+        let acroform = doc.catalog().unwrap().get(b"AcroForm").and_then(Object::as_dict).unwrap();
+        assert!(!acroform.has(b"NeedAppearances"), "appearances are written, not left to the viewer");
+        let fields = acroform.get(b"Fields").and_then(Object::as_array).unwrap();
+        let parent = doc.get_dictionary(fields[0].as_reference().unwrap()).unwrap();
+        let kid = parent.get(b"Kids").and_then(Object::as_array).unwrap()[0].as_reference().unwrap();
+        let text = doc.get_dictionary(kid).unwrap();
+        assert_eq!(decode_text(text.get(b"V").and_then(Object::as_str).unwrap()), "Zoé");
+
+        let check = doc.get_dictionary(fields[1].as_reference().unwrap()).unwrap();
+        assert_eq!(check.get(b"V").and_then(Object::as_name).unwrap(), b"Oui");
+        assert_eq!(check.get(b"AS").and_then(Object::as_name).unwrap(), b"Oui");
+    }
+
+    fn normal_appearance(doc: &Document, widget: &Dictionary) -> Object {
+        let appearance = widget.get(b"AP").and_then(Object::as_dict).unwrap();
+        let normal = appearance.get(b"N").unwrap();
+        doc.dereference(normal).unwrap().1.clone()
+    }
+
+    #[test]
+    fn form_values_get_matching_appearances() {
+        let original = form_pdf();
+        let mut values = FormValues::new();
+        values.insert("client.nom".into(), Some(FormValue::Text("Zoé".into())));
+        values.insert("nu".into(), Some(FormValue::Flag(true)));
+        let doc = export(&original, &Overlay { items: &[], signatures: &[], paraph: None, form_values: &values });
+        let acroform = doc.catalog().unwrap().get(b"AcroForm").and_then(Object::as_dict).unwrap();
+        let fields = acroform.get(b"Fields").and_then(Object::as_array).unwrap();
+
+        // The text widget draws its value with the form's /DA, right
+        // aligned as its parent's /Q asks.
+        let parent = doc.get_dictionary(fields[0].as_reference().unwrap()).unwrap();
+        let kid = parent.get(b"Kids").and_then(Object::as_array).unwrap()[0].as_reference().unwrap();
+        let stream = normal_appearance(&doc, doc.get_dictionary(kid).unwrap()).as_stream().unwrap().clone();
+        assert_eq!(stream.dict.get(b"BBox").and_then(Object::as_array).unwrap()[2].as_float().unwrap(), 100.0);
+        let resources = stream.dict.get(b"Resources").and_then(Object::as_dict).unwrap();
+        assert!(resources.get(b"Font").and_then(Object::as_dict).unwrap().has(b"Helv"));
+        let operations = Content::decode(&stream.decompressed_content().unwrap()).unwrap().operations;
+        let tf = operations.iter().find(|op| op.operator == "Tf").unwrap();
+        assert_eq!((tf.operands[0].as_name().unwrap(), tf.operands[1].as_float().unwrap()), (&b"Helv"[..], 10.0));
+        assert!(operations.iter().any(|op| op.operator == "rg"));
+        let shown = operations.iter().find(|op| op.operator == "Tj").unwrap();
+        assert_eq!(shown.operands[0].as_str().unwrap(), encode_win_ansi("Zoé").as_slice());
+        let tm = operations.iter().find(|op| op.operator == "Tm").unwrap();
+        let width = StandardFont::Helvetica.width_of(&encode_win_ansi("Zoé"), 10.0);
+        assert!((tm.operands[4].as_float().unwrap() as f64 - (100.0 - FIELD_PADDING - width)).abs() < 1e-3);
+
+        // The checkbox that had no appearance gets one for "Yes".
+        let bare = doc.get_dictionary(fields[2].as_reference().unwrap()).unwrap();
+        assert_eq!(bare.get(b"AS").and_then(Object::as_name).unwrap(), b"Yes");
+        let normal = normal_appearance(&doc, bare);
+        let states = normal.as_dict().unwrap();
+        assert!(states.has(b"Yes") && states.has(b"Off"));
+        let on = states.get(b"Yes").and_then(Object::as_reference).unwrap();
+        let on = doc.get_object(on).and_then(Object::as_stream).unwrap();
+        let operations = Content::decode(&on.decompressed_content().unwrap()).unwrap().operations;
+        assert!(operations.iter().any(|op| op.operator == "Tj"));
+    }
+
+    #[test]
+    fn reads_jpeg_dimensions() {
+        // SOI, APP0 stub, SOF0 for a 3x2 RGB image.
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x02, 0x00, 0x03, 0x03,
+        ];
+        assert_eq!(jpeg_info(&jpeg).unwrap(), (3, 2, 3));
+        assert!(jpeg_info(b"not a jpeg").is_err());
+    }
+}
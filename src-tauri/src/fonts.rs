@@ -0,0 +1,200 @@
+//! The standard 14 fonts used by the export (Helvetica, Times, Courier
+//! and their bold faces) : WinAnsi encoding and advance widths, as
+//! pdf-lib uses them for line breaking and text decoration.
+
+use crate::items::FontFamily;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StandardFont {
+    Helvetica,
+    HelveticaBold,
+    TimesRoman,
+    TimesBold,
+    Courier,
+    CourierBold,
+}
+
+// Advance widths (1/1000 em) of the printable ASCII range 0x20..=0x7E,
+// from the Adobe AFM files.
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611,
+    556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389,
+    280, 389, 584,
+];
+const TIMES_ROMAN: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 278, 278, 564, 564, 564, 444, 921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889,
+    722, 722, 556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500, 333, 444, 500, 444, 500,
+    444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480,
+    200, 480, 541,
+];
+const TIMES_BOLD: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 333, 333, 570, 570, 570, 500, 930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944,
+    722, 778, 611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333, 278, 333, 581, 500, 333, 500, 556, 444, 556,
+    444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500, 556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394,
+    220, 394, 520,
+];
+
+/// WinAnsi code points 0x80..=0x9F that differ from Latin-1.
+const WIN_ANSI_HIGH: [(char, u8); 27] = [
+    ('€', 0x80), ('‚', 0x82), ('ƒ', 0x83), ('„', 0x84), ('…', 0x85), ('†', 0x86), ('‡', 0x87), ('ˆ', 0x88),
+    ('‰', 0x89), ('Š', 0x8A), ('‹', 0x8B), ('Œ', 0x8C), ('Ž', 0x8E), ('‘', 0x91), ('’', 0x92), ('“', 0x93),
+    ('”', 0x94), ('•', 0x95), ('–', 0x96), ('—', 0x97), ('˜', 0x98), ('™', 0x99), ('š', 0x9A), ('›', 0x9B),
+    ('œ', 0x9C), ('ž', 0x9E), ('Ÿ', 0x9F),
+];
+
+impl StandardFont {
+    pub fn for_style(family: FontFamily, bold: bool) -> Self {
+        match (family, bold) {
+            (FontFamily::Sans, false) => StandardFont::Helvetica,
+            (FontFamily::Sans, true) => StandardFont::HelveticaBold,
+            (FontFamily::Serif, false) => StandardFont::TimesRoman,
+            (FontFamily::Serif, true) => StandardFont::TimesBold,
+            (FontFamily::Mono, false) => StandardFont::Courier,
+            (FontFamily::Mono, true) => StandardFont::CourierBold,
+        }
+    }
+
+    pub fn base_font(self) -> &'static str {
+        match self {
+            StandardFont::Helvetica => "Helvetica",
+            StandardFont::HelveticaBold => "Helvetica-Bold",
+            StandardFont::TimesRoman => "Times-Roman",
+            StandardFont::TimesBold => "Times-Bold",
+            StandardFont::Courier => "Courier",
+            StandardFont::CourierBold => "Courier-Bold",
+        }
+    }
+
+    /// The face whose metrics stand in for `/BaseFont` when a font of a
+    /// PDF we read has no `/Widths` ; obliques share the upright widths
+    /// and Arial those of Helvetica.
+    pub fn from_base_font(name: &str) -> Option<Self> {
+        // Subset fonts carry a "ABCDEF+" prefix.
+        let name = name.split_once('+').map_or(name, |(_, name)| name);
+        Some(match name {
+            "Helvetica" | "Helvetica-Oblique" | "Arial" | "ArialMT" | "Arial-ItalicMT" => StandardFont::Helvetica,
+            "Helvetica-Bold" | "Helvetica-BoldOblique" | "Arial-BoldMT" | "Arial-BoldItalicMT" => {
+                StandardFont::HelveticaBold
+            }
+            "Times-Roman" | "Times-Italic" | "TimesNewRomanPSMT" | "TimesNewRomanPS-ItalicMT" => StandardFont::TimesRoman,
+            "Times-Bold" | "Times-BoldItalic" | "TimesNewRomanPS-BoldMT" => StandardFont::TimesBold,
+            "Courier" | "Courier-Oblique" | "CourierNewPSMT" => StandardFont::Courier,
+            "Courier-Bold" | "Courier-BoldOblique" | "CourierNewPS-BoldMT" => StandardFont::CourierBold,
+            _ => return None,
+        })
+    }
+
+    fn ascii_widths(self) -> Option<&'static [u16; 95]> {
+        match self {
+            StandardFont::Helvetica => Some(&HELVETICA),
+            StandardFont::HelveticaBold => Some(&HELVETICA_BOLD),
+            StandardFont::TimesRoman => Some(&TIMES_ROMAN),
+            StandardFont::TimesBold => Some(&TIMES_BOLD),
+            StandardFont::Courier | StandardFont::CourierBold => None,
+        }
+    }
+
+    /// Advance width of one encoded byte. Outside ASCII, accented
+    /// letters take the width of their base letter and the remaining
+    /// symbols that of 'n' ; close enough for line breaking.
+    fn byte_width(self, byte: u8) -> u16 {
+        let Some(widths) = self.ascii_widths() else {
+            return 600;
+        };
+        let ascii = if (0x20..=0x7E).contains(&byte) { byte } else { base_letter(byte).unwrap_or(b'n') };
+        widths[usize::from(ascii - 0x20)]
+    }
+
+    /// Width of already-encoded text at `size`, in points.
+    pub fn width_of(self, encoded: &[u8], size: f64) -> f64 {
+        let units: u32 = encoded.iter().map(|&byte| u32::from(self.byte_width(byte))).sum();
+        f64::from(units) * size / 1000.0
+    }
+}
+
+fn base_letter(byte: u8) -> Option<u8> {
+    Some(match byte {
+        0xC0..=0xC5 => b'A',
+        0xC7 => b'C',
+        0xC8..=0xCB => b'E',
+        0xCC..=0xCF => b'I',
+        0xD1 => b'N',
+        0xD2..=0xD6 | 0xD8 => b'O',
+        0xD9..=0xDC => b'U',
+        0xDD | 0x9F => b'Y',
+        0xE0..=0xE5 => b'a',
+        0xE7 => b'c',
+        0xE8..=0xEB => b'e',
+        0xEC..=0xEF => b'i',
+        0xF1 => b'n',
+        0xF2..=0xF6 | 0xF8 => b'o',
+        0xF9..=0xFC => b'u',
+        0xFD | 0xFF => b'y',
+        0x8A => b'S',
+        0x9A => b's',
+        0x8E => b'Z',
+        0x9E => b'z',
+        0xA0 => b' ',
+        _ => return None,
+    })
+}
+
+/// Encode `text` in WinAnsi. pdf-lib refuses characters outside the
+/// encoding ; they become '?' here so one stray glyph does not abort
+/// the whole export.
+pub fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| {
+            let code = u32::from(ch);
+            if (0x20..=0x7E).contains(&code) || (0xA0..=0xFF).contains(&code) {
+                code as u8
+            } else {
+                WIN_ANSI_HIGH
+                    .iter()
+                    .find(|(candidate, _)| *candidate == ch)
+                    .map(|(_, byte)| *byte)
+                    .unwrap_or(b'?')
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_french_text_in_win_ansi() {
+        assert_eq!(encode_win_ansi("Été €"), vec![0xC9, b't', 0xE9, b' ', 0x80]);
+        assert_eq!(encode_win_ansi("日"), vec![b'?']);
+    }
+
+    #[test]
+    fn measures_text_with_afm_widths() {
+        // "Hi" in Helvetica : 722 + 222 units.
+        assert!((StandardFont::Helvetica.width_of(b"Hi", 10.0) - 9.44).abs() < 1e-9);
+        assert!((StandardFont::Courier.width_of(b"Hi", 10.0) - 12.0).abs() < 1e-9);
+        assert_eq!(
+            StandardFont::TimesRoman.width_of(&encode_win_ansi("é"), 10.0),
+            StandardFont::TimesRoman.width_of(b"e", 10.0)
+        );
+    }
+
+    #[test]
+    fn recognizes_standard_faces_by_base_font() {
+        assert_eq!(StandardFont::from_base_font("ABCDEF+Arial-BoldMT"), Some(StandardFont::HelveticaBold));
+        assert_eq!(StandardFont::from_base_font("Times-Italic"), Some(StandardFont::TimesRoman));
+        assert_eq!(StandardFont::from_base_font("Garamond"), None);
+    }
+}
@@ -0,0 +1,267 @@
+//! Timestamped snapshots of the user stores, kept in
+//! `app_data_dir/history/<store>/` so a deletion persisted by mistake
+//! can be recovered in a later session.
+//!
+//! After every successful write, and before a change that drops data
+//! (delete, rename, replacing a list), the database hands over the
+//! store's current content (for the galleries, only before such a
+//! change), in the JSON store format of
+//! [`crate::schema`], which is written to `<stamp>-<hash>.json`. A
+//! content already in the history is not copied twice : its snapshot is
+//! renamed to the new stamp instead.
+//! Snapshots older than [`MAX_AGE_DAYS`] or beyond the newest
+//! [`MAX_PER_STORE`] are pruned. Gallery snapshots are sealed once the
+//! vault is enabled, those written before it included (see
+//! [`crate::vault`]).
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::Emitter;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::{schema, store};
+
+const HISTORY_DIR: &str = "history";
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+/// Hex digits of the SHA-256 kept in snapshot names.
+const HASH_LEN: usize = 16;
+const MAX_PER_STORE: usize = 20;
+const MAX_AGE_DAYS: i64 = 30;
+
+/// Stores with a history.
+const STORES: &[&str] = &[
+    crate::SIGNATURES_FILE,
+    crate::PARAPHS_FILE,
+    crate::TEMPLATES_FILE,
+    crate::SNIPPETS_FILE,
+];
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// `<store stem>/<file name>`, handed back to `restore_snapshot`.
+    id: String,
+    store: String,
+    created_at: String,
+    size: u64,
+}
+
+/// A snapshot file name split into its parts.
+struct Entry {
+    path: PathBuf,
+    stamp: DateTime<Utc>,
+    hash: String,
+}
+
+fn stem(store: &str) -> &str {
+    store.strip_suffix(".json").unwrap_or(store)
+}
+
+fn store_dir(dir: &Path, store: &str) -> PathBuf {
+    dir.join(HISTORY_DIR).join(stem(store))
+}
+
+fn parse_name(name: &str) -> Option<(DateTime<Utc>, String)> {
+    let (stamp, hash) = name.strip_suffix(".json")?.rsplit_once('-')?;
+    if hash.len() != HASH_LEN || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let stamp = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).ok()?.and_utc();
+    Some((stamp, hash.to_string()))
+}
+
+/// Snapshots of `store`, oldest first.
+fn entries(dir: &Path, store: &str) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = std::fs::read_dir(store_dir(dir, store))
+        .map(|read| read.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|path| {
+            let (stamp, hash) = parse_name(path.file_name()?.to_str()?)?;
+            Some(Entry { path, stamp, hash })
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.stamp);
+    entries
+}
+
+/// Snapshot files of `store`, oldest first.
+pub fn snapshot_files(dir: &Path, store: &str) -> Vec<PathBuf> {
+    entries(dir, store).into_iter().map(|entry| entry.path).collect()
+}
+
+fn prune(dir: &Path, store: &str, now: DateTime<Utc>) {
+    let entries = entries(dir, store);
+    let excess = entries.len().saturating_sub(MAX_PER_STORE);
+    let cutoff = now - chrono::Duration::days(MAX_AGE_DAYS);
+    for (idx, entry) in entries.iter().enumerate() {
+        if idx < excess || entry.stamp < cutoff {
+            let _ = std::fs::remove_file(&entry.path);
+        }
+    }
+}
+
+fn record_at(dir: &Path, name: &str, bytes: &[u8], identity: &[u8], now: DateTime<Utc>) -> Result<()> {
+    let hash = crate::pdfio::hex(&Sha256::digest(identity)).to_lowercase()[..HASH_LEN].to_string();
+    let target_dir = store_dir(dir, name);
+    std::fs::create_dir_all(&target_dir).map_err(|e| Error::io("create_dir_failed", &target_dir, &e))?;
+    let target = target_dir.join(format!("{}-{hash}.json", now.format(STAMP_FORMAT)));
+    let written = match entries(dir, name).into_iter().find(|entry| entry.hash == hash) {
+        Some(existing) => std::fs::rename(&existing.path, &target),
+        None => store::write_atomic(&target, bytes, false),
+    };
+    written.map_err(|e| Error::io("history_write_failed", &target, &e))?;
+
+    prune(dir, name, now);
+    Ok(())
+}
+
+/// Keep `bytes`, the content of the store `name` just written or about
+/// to be changed, in its history. Failures are logged : a missing snapshot must not
+/// prevent the change itself.
+pub fn record(dir: &Path, name: &str, bytes: &[u8]) {
+    record_as(dir, name, bytes, bytes);
+}
+
+/// Like [`record`], for bytes that differ from one write of the same
+/// content to the next (sealed stores) : `identity` stands for the
+/// content when looking for it in the history.
+pub fn record_as(dir: &Path, name: &str, bytes: &[u8], identity: &[u8]) {
+    if let Err(err) = record_at(dir, name, bytes, identity, Utc::now()) {
+        eprintln!("history snapshot failed: {err}");
+    }
+}
+
+fn list(dir: &Path, store: Option<&str>) -> Result<Vec<Snapshot>> {
+    let stores: Vec<&str> = match store {
+        Some(store) if STORES.contains(&store) => vec![store],
+        Some(store) => return Err(Error::new(ErrorKind::InvalidInput, "store_unknown").with_details(store)),
+        None => STORES.to_vec(),
+    };
+    let mut snapshots: Vec<Snapshot> = stores
+        .into_iter()
+        .flat_map(|store| {
+            entries(dir, store).into_iter().map(move |entry| Snapshot {
+                id: format!("{}/{}", stem(store), entry.path.file_name().unwrap_or_default().to_string_lossy()),
+                store: store.to_string(),
+                created_at: entry.stamp.to_rfc3339(),
+                size: std::fs::metadata(&entry.path).map(|meta| meta.len()).unwrap_or(0),
+            })
+        })
+        .collect();
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(snapshots)
+}
+
+/// Resolve a snapshot id to its store and file, refusing anything that
+/// does not name an existing snapshot of a known store.
+fn resolve(dir: &Path, id: &str) -> Result<(&'static str, PathBuf)> {
+    let unknown = || Error::new(ErrorKind::NotFound, "snapshot_not_found").with_details(id);
+    let (store_stem, name) = id.split_once('/').ok_or_else(unknown)?;
+    let store = STORES.iter().copied().find(|store| stem(store) == store_stem).ok_or_else(unknown)?;
+    parse_name(name).ok_or_else(unknown)?;
+    let path = store_dir(dir, store).join(name);
+    if !path.is_file() {
+        return Err(unknown());
+    }
+    Ok((store, path))
+}
+
+/// Put the snapshot `id` back as the store's JSON file, which the
+/// database imports in place of the store on its next access ; that
+/// import snapshots the content it replaces, so a restore can itself
+/// be undone.
+fn restore(dir: &Path, id: &str) -> Result<&'static str> {
+    let (name, snapshot) = resolve(dir, id)?;
+    let bytes = std::fs::read(&snapshot).map_err(|e| Error::io("snapshot_read_failed", &snapshot, &e))?;
+    let value: serde_json::Value = serde_json::from_slice(&bytes)
+        .map_err(|e| Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e).with_path(&snapshot))?;
+    // Sealed snapshots cannot be inspected without the key.
+    if let Some(schema) = schema::for_store(name).filter(|_| value.get("cipher").is_none()) {
+        schema::ensure_writable(schema, value).map_err(|err| err.with_path(&snapshot))?;
+    }
+    store::save_bytes(&dir.join(name), &bytes)?;
+    Ok(name)
+}
+
+/// Snapshots of `store` (e.g. `"templates.json"`), or of every store,
+/// newest first.
+#[tauri::command]
+pub fn list_snapshots(app: tauri::AppHandle, store: Option<String>) -> Result<Vec<Snapshot>> {
+    list(&crate::app_data_dir(&app)?, store.as_deref())
+}
+
+/// Restore a snapshot listed by `list_snapshots`. Emits
+/// `"store-restored"` with the store name so the frontend reloads it.
+#[tauri::command]
+pub fn restore_snapshot(app: tauri::AppHandle, id: String) -> Result<()> {
+    let store = restore(&crate::app_data_dir(&app)?, &id)?;
+    if let Err(err) = app.emit("store-restored", store) {
+        eprintln!("emit store-restored failed: {err}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::scratch_dir;
+
+    fn at(day: u32, second: u32) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(&format!("202610{day:02}T0000{second:02}.000Z"), STAMP_FORMAT)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn identical_content_is_kept_once() {
+        let dir = scratch_dir("dedup");
+        record_at(&dir, crate::SNIPPETS_FILE, b"[\"a\"]", b"[\"a\"]", at(1, 0)).unwrap();
+        record_at(&dir, crate::SNIPPETS_FILE, b"[\"b\"]", b"[\"b\"]", at(1, 1)).unwrap();
+        record_at(&dir, crate::SNIPPETS_FILE, b"[\"a\"]", b"[\"a\"]", at(1, 2)).unwrap();
+
+        let stamps: Vec<_> = entries(&dir, crate::SNIPPETS_FILE).into_iter().map(|entry| entry.stamp).collect();
+        assert_eq!(stamps, vec![at(1, 1), at(1, 2)]);
+    }
+
+    #[test]
+    fn old_and_excess_snapshots_are_pruned() {
+        let dir = scratch_dir("prune");
+        record_at(&dir, crate::SNIPPETS_FILE, b"[\"old\"]", b"[\"old\"]", at(1, 0)).unwrap();
+        for idx in 0..MAX_PER_STORE as u32 + 2 {
+            let bytes = format!("[{idx}]");
+            record_at(&dir, crate::SNIPPETS_FILE, bytes.as_bytes(), bytes.as_bytes(), at(2, idx)).unwrap();
+        }
+        assert_eq!(entries(&dir, crate::SNIPPETS_FILE).len(), MAX_PER_STORE);
+
+        let late = at(2, 0) + chrono::Duration::days(MAX_AGE_DAYS) + chrono::Duration::seconds(5);
+        record_at(&dir, crate::SNIPPETS_FILE, b"[\"late\"]", b"[\"late\"]", late).unwrap();
+        let left = entries(&dir, crate::SNIPPETS_FILE);
+        assert!(left.iter().all(|entry| entry.stamp > at(2, 4)), "{} left", left.len());
+    }
+
+    #[test]
+    fn deleted_template_comes_back_through_its_snapshot() {
+        let dir = scratch_dir("restore");
+        let template = serde_json::json!({ "id": "lost", "name": "Contrat", "updatedAt": "2026-01-01T00:00:00.000Z", "items": [], "paraph": null });
+        crate::db::save_template(&dir, template.clone()).unwrap();
+        crate::db::delete_template(&dir, "lost").unwrap();
+        assert!(crate::db::load_templates(&dir).unwrap().is_empty());
+
+        let snapshots = list(&dir, Some(crate::TEMPLATES_FILE)).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(restore(&dir, &snapshots[0].id).unwrap(), crate::TEMPLATES_FILE);
+        assert_eq!(crate::db::load_templates(&dir).unwrap(), vec![template]);
+    }
+
+    #[test]
+    fn ids_outside_the_history_are_refused() {
+        let dir = scratch_dir("resolve");
+        for id in ["templates/../../vault.json", "vault/20261001T000000.000Z-0123456789abcdef.json", "templates"] {
+            assert_eq!(resolve(&dir, id).err().unwrap().code, "snapshot_not_found", "{id}");
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! Validation and normalization of the signature and paraph images
+//! before they are stored.
+//!
+//! The renderer's `mime`, `naturalW` and `naturalH` are not trusted :
+//! the bytes are decoded, their format must match the declared MIME
+//! type, and the size is read back from the image. Metadata is dropped
+//! (PNG images are re-encoded with their pixels only, JPEG images lose
+//! their EXIF, XMP, IPTC and comment segments) and the EXIF orientation
+//! is applied to the pixels so the image keeps looking the same. When
+//! the user sets a maximum DPI, images larger than needed to print
+//! [`MAX_PRINT_INCHES`] at that resolution are downscaled.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::{export, ink, store, StoredSignature};
+
+pub const SETTINGS_FILE: &str = "image_settings.json";
+/// Signature scans are a few hundred kilobytes ; anything past this is
+/// not an image we can use.
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+const MAX_SIDE: u32 = 10_000;
+const MAX_PIXELS: u64 = 40_000_000;
+/// Widest a signature or paraph is drawn on a page : about 10 cm.
+const MAX_PRINT_INCHES: u32 = 4;
+const DPI_RANGE: std::ops::RangeInclusive<u32> = 72..=1200;
+const JPEG_QUALITY: u8 = 90;
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageSettings {
+    /// `None` keeps images at their imported resolution.
+    pub max_dpi: Option<u32>,
+}
+
+fn settings_path(dir: &Path) -> PathBuf {
+    dir.join(SETTINGS_FILE)
+}
+
+pub fn load_settings(dir: &Path) -> Result<ImageSettings> {
+    Ok(store::load_json(&settings_path(dir))?.unwrap_or_default())
+}
+
+/// Longest side, in pixels, an image may keep under `settings`.
+pub fn max_side(settings: &ImageSettings) -> Option<u32> {
+    settings.max_dpi.map(|dpi| dpi * MAX_PRINT_INCHES)
+}
+
+/// [`normalize`] with the user's settings for the profile in `dir`.
+pub fn normalizer(dir: &Path) -> Result<impl Fn(StoredSignature) -> Result<StoredSignature>> {
+    let max_side = max_side(&load_settings(dir)?);
+    Ok(move |asset| normalize(asset, max_side))
+}
+
+fn invalid(details: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidInput, "image_invalid").with_details(details)
+}
+
+fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(PNG_MAGIC) {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+fn check_size(width: u32, height: u32) -> Result<()> {
+    if width == 0 || height == 0 {
+        return Err(invalid("empty image"));
+    }
+    if width > MAX_SIDE || height > MAX_SIDE || u64::from(width) * u64::from(height) > MAX_PIXELS {
+        return Err(Error::new(ErrorKind::InvalidInput, "image_too_large").with_details(format!("{width}x{height}")));
+    }
+    Ok(())
+}
+
+/// Size of `width` x `height` once its longest side fits `max_side`.
+fn fitted(width: u32, height: u32, max_side: Option<u32>) -> Option<(u32, u32)> {
+    let max_side = max_side?;
+    let longest = width.max(height);
+    if longest <= max_side {
+        return None;
+    }
+    let scale = |side: u32| ((u64::from(side) * u64::from(max_side) + u64::from(longest) / 2) / u64::from(longest)).max(1) as u32;
+    Some((scale(width), scale(height)))
+}
+
+/// Check `asset` and return it with trusted dimensions, without
+/// metadata, and downscaled to `max_side` when set. The id and name
+/// are kept.
+pub fn normalize(asset: StoredSignature, max_side: Option<u32>) -> Result<StoredSignature> {
+    if let Some(drawing) = &asset.ink {
+        ink::validate(drawing)?;
+    }
+    if asset.bytes.len() > MAX_IMAGE_BYTES {
+        return Err(Error::new(ErrorKind::InvalidInput, "image_too_large")
+            .with_details(format!("{} bytes", asset.bytes.len())));
+    }
+    let Some(actual) = sniff(&asset.bytes) else {
+        return Err(invalid("neither PNG nor JPEG"));
+    };
+    if actual != asset.mime {
+        return Err(Error::new(ErrorKind::InvalidInput, "image_mime_mismatch")
+            .with_details(format!("{} declared, {actual} received", asset.mime)));
+    }
+    let (bytes, natural_w, natural_h) = match actual {
+        "image/png" => normalize_png(&asset.bytes, max_side)?,
+        _ => normalize_jpeg(&asset.bytes, max_side)?,
+    };
+    Ok(StoredSignature { bytes, natural_w, natural_h, ..asset })
+}
+
+// ---------------------------------------------------------------------
+// Pixels
+
+/// Average `channels`-byte pixels over the source area of each target
+/// pixel. With `alpha`, the last channel is the alpha and colours are
+/// weighted by it, so transparent pixels do not darken the edges.
+fn downscale(pixels: &[u8], width: u32, height: u32, channels: usize, alpha: bool, target: (u32, u32)) -> Vec<u8> {
+    let (target_w, target_h) = target;
+    let (w, h) = (width as usize, height as usize);
+    let span = |out: u32, total: usize, count: u32| {
+        let start = out as usize * total / count as usize;
+        let end = ((out as usize + 1) * total / count as usize).max(start + 1);
+        start..end
+    };
+    let mut out = Vec::with_capacity(target_w as usize * target_h as usize * channels);
+    for ty in 0..target_h {
+        let rows = span(ty, h, target_h);
+        for tx in 0..target_w {
+            let cols = span(tx, w, target_w);
+            let mut sums = vec![0u64; channels];
+            let mut count = 0u64;
+            for y in rows.clone() {
+                for x in cols.clone() {
+                    let px = &pixels[(y * w + x) * channels..][..channels];
+                    let weight = if alpha { u64::from(px[channels - 1]) } else { 1 };
+                    for (c, sum) in sums.iter_mut().enumerate() {
+                        *sum += if alpha && c == channels - 1 { u64::from(px[c]) } else { u64::from(px[c]) * weight };
+                    }
+                    count += 1;
+                }
+            }
+            let weights = if alpha { sums[channels - 1] } else { count };
+            for (c, sum) in sums.iter().enumerate() {
+                let value = match (alpha && c == channels - 1, weights) {
+                    (true, _) => sum / count,
+                    (false, 0) => 0,
+                    (false, weights) => sum / weights,
+                };
+                out.push(value as u8);
+            }
+        }
+    }
+    out
+}
+
+/// Apply EXIF orientation `orientation` (2 to 8) to the pixels.
+fn orient(pixels: &[u8], width: u32, height: u32, channels: usize, orientation: u16) -> (Vec<u8>, u32, u32) {
+    let (w, h) = (width as usize, height as usize);
+    let swaps = orientation >= 5;
+    let (out_w, out_h) = if swaps { (h, w) } else { (w, h) };
+    let mut out = Vec::with_capacity(pixels.len());
+    for y in 0..out_h {
+        for x in 0..out_w {
+            // Source pixel shown at (x, y).
+            let (sx, sy) = match orientation {
+                2 => (w - 1 - x, y),
+                3 => (w - 1 - x, h - 1 - y),
+                4 => (x, h - 1 - y),
+                5 => (y, x),
+                6 => (y, h - 1 - x),
+                7 => (w - 1 - y, h - 1 - x),
+                8 => (w - 1 - y, x),
+                _ => (x, y),
+            };
+            out.extend_from_slice(&pixels[(sy * w + sx) * channels..][..channels]);
+        }
+    }
+    (out, out_w as u32, out_h as u32)
+}
+
+// ---------------------------------------------------------------------
+// PNG
+
+/// Decode to 8-bit pixels, palettes expanded, with the colour type.
+fn decode_png(bytes: &[u8]) -> Result<(Vec<u8>, png::ColorType, u32, u32)> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| invalid(format!("invalid png: {e}")))?;
+    check_size(reader.info().width, reader.info().height)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer).map_err(|e| invalid(format!("invalid png: {e}")))?;
+    buffer.truncate(frame.buffer_size());
+    if frame.color_type == png::ColorType::Indexed {
+        return Err(invalid("invalid png: palette not expanded"));
+    }
+    Ok((buffer, frame.color_type, frame.width, frame.height))
+}
+
+pub fn encode_png(pixels: &[u8], width: u32, height: u32, color: png::ColorType) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let unencodable = |e: png::EncodingError| Error::new(ErrorKind::Io, "image_encode_failed").with_details(e);
+    let mut writer = encoder.write_header().map_err(unencodable)?;
+    writer.write_image_data(pixels).map_err(unencodable)?;
+    writer.finish().map_err(unencodable)?;
+    Ok(out)
+}
+
+/// Re-encode the pixels alone : every ancillary chunk (text, EXIF,
+/// timestamps, …) is dropped, palettes and 16-bit channels become
+/// plain 8-bit colour.
+fn normalize_png(bytes: &[u8], max_side: Option<u32>) -> Result<(Vec<u8>, u32, u32)> {
+    let (mut pixels, color, mut width, mut height) = decode_png(bytes)?;
+    if let Some(target) = fitted(width, height, max_side) {
+        let alpha = matches!(color, png::ColorType::GrayscaleAlpha | png::ColorType::Rgba);
+        pixels = downscale(&pixels, width, height, color.samples(), alpha, target);
+        (width, height) = target;
+    }
+    Ok((encode_png(&pixels, width, height, color)?, width, height))
+}
+
+// ---------------------------------------------------------------------
+// JPEG
+
+/// Orientation tag (0x0112) of an APP1 `Exif` payload.
+fn exif_orientation(payload: &[u8]) -> Option<u16> {
+    let tiff = payload.strip_prefix(b"Exif\0\0")?;
+    let big_endian = match tiff.get(..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let u16_at = |pos: usize| {
+        let raw = [*tiff.get(pos)?, *tiff.get(pos + 1)?];
+        Some(if big_endian { u16::from_be_bytes(raw) } else { u16::from_le_bytes(raw) })
+    };
+    let u32_at = |pos: usize| {
+        let raw = [*tiff.get(pos)?, *tiff.get(pos + 1)?, *tiff.get(pos + 2)?, *tiff.get(pos + 3)?];
+        Some(if big_endian { u32::from_be_bytes(raw) } else { u32::from_le_bytes(raw) })
+    };
+    let ifd = u32_at(4)? as usize;
+    let entries = u16_at(ifd)? as usize;
+    (0..entries)
+        .map(|idx| ifd + 2 + idx * 12)
+        .find(|&entry| u16_at(entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// Copy the JPEG without its metadata segments, and return the EXIF
+/// orientation found on the way. JFIF, ICC profiles and the Adobe
+/// segment describe the colours and are kept ; the scan data after the
+/// first SOS is copied as is.
+fn strip_jpeg(bytes: &[u8]) -> std::result::Result<(Vec<u8>, u16), String> {
+    let mut out = bytes[..2].to_vec();
+    let mut orientation = 1;
+    let mut pos = 2;
+    loop {
+        if pos + 4 > bytes.len() || bytes[pos] != 0xFF {
+            return Err("truncated jpeg".into());
+        }
+        let marker = bytes[pos + 1];
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0xDA {
+            out.extend_from_slice(&bytes[pos..]);
+            return Ok((out, orientation));
+        }
+        let length = usize::from(u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]));
+        let end = pos + 2 + length;
+        let payload = bytes.get(pos + 4..end).ok_or("truncated jpeg")?;
+        let keep = match marker {
+            0xE1 => {
+                orientation = exif_orientation(payload).unwrap_or(orientation);
+                false
+            }
+            0xE2 => payload.starts_with(b"ICC_PROFILE\0"),
+            0xE0 | 0xEE => true,
+            0xE3..=0xEF | 0xFE => false,
+            _ => true,
+        };
+        if keep {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+fn normalize_jpeg(bytes: &[u8], max_side: Option<u32>) -> Result<(Vec<u8>, u32, u32)> {
+    let (width, height, _) = export::jpeg_info(bytes).map_err(invalid)?;
+    check_size(width, height)?;
+    let (stripped, orientation) = strip_jpeg(bytes).map_err(invalid)?;
+    // Decoded even when the stripped bytes are kept, so a file whose
+    // headers are fine but whose scan is not is refused here rather
+    // than at export.
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode().map_err(|e| invalid(format!("invalid jpeg: {e}")))?;
+    let info = decoder.info().ok_or_else(|| invalid("invalid jpeg"))?;
+    if orientation == 1 && fitted(width, height, max_side).is_none() {
+        return Ok((stripped, width, height));
+    }
+
+    let (channels, color_type) = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => (1, jpeg_encoder::ColorType::Luma),
+        jpeg_decoder::PixelFormat::RGB24 => (3, jpeg_encoder::ColorType::Rgb),
+        // CMYK and 16-bit scans are too rare in signature scans to be
+        // worth re-encoding : they are only stripped.
+        _ => return Ok((stripped, width, height)),
+    };
+    let (mut pixels, mut width, mut height) = (pixels, u32::from(info.width), u32::from(info.height));
+    if orientation != 1 {
+        (pixels, width, height) = orient(&pixels, width, height, channels, orientation);
+    }
+    if let Some(target) = fitted(width, height, max_side) {
+        pixels = downscale(&pixels, width, height, channels, false, target);
+        (width, height) = target;
+    }
+
+    let unencodable = |e: jpeg_encoder::EncodingError| Error::new(ErrorKind::Io, "image_encode_failed").with_details(e);
+    let mut out = Vec::new();
+    let mut encoder = jpeg_encoder::Encoder::new(&mut out, JPEG_QUALITY);
+    if let Some(icc) = decoder.icc_profile() {
+        encoder.add_icc_profile(&icc).map_err(unencodable)?;
+    }
+    // Sides are bounded by `MAX_SIDE`, well within JPEG's 16 bits.
+    encoder.encode(&pixels, width as u16, height as u16, color_type).map_err(unencodable)?;
+    Ok((out, width, height))
+}
+
+/// RGBA pixels of a normalized asset, whose orientation is already
+/// applied.
+pub fn decode_rgba(asset: &StoredSignature) -> Result<(Vec<u8>, u32, u32)> {
+    let (pixels, channels, width, height) = if asset.mime == "image/png" {
+        let (pixels, color, width, height) = decode_png(&asset.bytes)?;
+        (pixels, color.samples(), width, height)
+    } else {
+        let mut decoder = jpeg_decoder::Decoder::new(asset.bytes.as_slice());
+        let pixels = decoder.decode().map_err(|e| invalid(format!("invalid jpeg: {e}")))?;
+        let info = decoder.info().ok_or_else(|| invalid("invalid jpeg"))?;
+        let channels = match info.pixel_format {
+            jpeg_decoder::PixelFormat::L8 => 1,
+            jpeg_decoder::PixelFormat::RGB24 => 3,
+            other => return Err(Error::new(ErrorKind::InvalidInput, "image_unsupported").with_details(format!("{other:?}"))),
+        };
+        (pixels, channels, u32::from(info.width), u32::from(info.height))
+    };
+    let rgba = pixels
+        .chunks_exact(channels)
+        .flat_map(|px| match *px {
+            [gray] => [gray, gray, gray, 255],
+            [gray, alpha] => [gray, gray, gray, alpha],
+            [r, g, b] => [r, g, b, 255],
+            [r, g, b, a] => [r, g, b, a],
+            _ => unreachable!("1 to 4 channels"),
+        })
+        .collect();
+    Ok((rgba, width, height))
+}
+
+#[tauri::command]
+pub fn get_image_settings(app: tauri::AppHandle) -> Result<ImageSettings> {
+    load_settings(&crate::app_data_dir(&app)?)
+}
+
+/// Applies to images added from now on ; stored ones are left as they
+/// are.
+#[tauri::command]
+pub fn set_image_settings(app: tauri::AppHandle, settings: ImageSettings) -> Result<()> {
+    if let Some(dpi) = settings.max_dpi.filter(|dpi| !DPI_RANGE.contains(dpi)) {
+        return Err(Error::new(ErrorKind::InvalidInput, "image_dpi_invalid").with_details(dpi));
+    }
+    store::save_json(&settings_path(&crate::app_data_dir(&app)?), &settings)
+}
+
+/// Check and normalize an image before the renderer adds it to a
+/// gallery, so it previews what will be stored.
+#[tauri::command]
+pub fn normalize_image_asset(app: tauri::AppHandle, asset: StoredSignature) -> Result<StoredSignature> {
+    normalizer(&crate::app_data_dir(&app)?)?(asset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tests::{asset, tiny_png};
+
+    fn png(width: u32, height: u32, color: png::ColorType, pixels: &[u8], text: Option<&str>) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        if let Some(text) = text {
+            encoder.add_text_chunk("Author".into(), text.into()).unwrap();
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(pixels).unwrap();
+        drop(writer);
+        out
+    }
+
+    /// 2x1 RGB JPEG with an EXIF segment carrying `orientation` and a
+    /// comment.
+    fn jpeg_with_exif(orientation: u16) -> Vec<u8> {
+        let mut plain = Vec::new();
+        jpeg_encoder::Encoder::new(&mut plain, 90)
+            .encode(&[255, 0, 0, 0, 0, 255], 2, 1, jpeg_encoder::ColorType::Rgb)
+            .unwrap();
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut segments = vec![0xFF, 0xE1];
+        segments.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        segments.extend_from_slice(&exif);
+        segments.extend_from_slice(&[0xFF, 0xFE, 0x00, 0x07]);
+        segments.extend_from_slice(b"Alice");
+        let mut out = plain[..2].to_vec();
+        out.extend_from_slice(&segments);
+        out.extend_from_slice(&plain[2..]);
+        out
+    }
+
+    fn has_segment(jpeg: &[u8], marker: u8) -> bool {
+        jpeg.windows(2).any(|window| window == [0xFF, marker])
+    }
+
+    #[test]
+    fn dimensions_come_from_the_image() {
+        let mut claimed = asset("s", "image/png", tiny_png());
+        (claimed.natural_w, claimed.natural_h) = (999, 999);
+        let normalized = normalize(claimed, None).unwrap();
+        assert_eq!((normalized.id.as_str(), normalized.natural_w, normalized.natural_h), ("s", 2, 1));
+    }
+
+    #[test]
+    fn mislabeled_or_foreign_bytes_are_refused() {
+        assert_eq!(normalize(asset("s", "image/jpeg", tiny_png()), None).err().unwrap().code, "image_mime_mismatch");
+        assert_eq!(normalize(asset("s", "image/png", b"<svg/>".to_vec()), None).err().unwrap().code, "image_invalid");
+        let wide = png(MAX_SIDE + 1, 1, png::ColorType::Grayscale, &vec![0; MAX_SIDE as usize + 1], None);
+        assert_eq!(normalize(asset("s", "image/png", wide), None).err().unwrap().code, "image_too_large");
+    }
+
+    #[test]
+    fn png_metadata_is_dropped_and_result_is_stable() {
+        let tagged = png(2, 1, png::ColorType::Rgba, &[255, 0, 0, 255, 0, 0, 0, 0], Some("Alice"));
+        let normalized = normalize(asset("s", "image/png", tagged), None).unwrap();
+        assert!(!normalized.bytes.windows(5).any(|window| window == b"Alice"));
+        let again = normalize(normalized.clone(), None).unwrap();
+        assert_eq!(again.bytes, normalized.bytes);
+    }
+
+    #[test]
+    fn large_images_are_downscaled_without_dark_fringes() {
+        // Opaque white next to fully transparent black.
+        let pixels: Vec<u8> = (0..8 * 4).flat_map(|idx| if idx % 2 == 0 { [255, 255, 255, 255] } else { [0, 0, 0, 0] }).collect();
+        let big = png(8, 4, png::ColorType::Rgba, &pixels, None);
+        let normalized = normalize(asset("s", "image/png", big), Some(4)).unwrap();
+        assert_eq!((normalized.natural_w, normalized.natural_h), (4, 2));
+
+        let mut reader = png::Decoder::new(normalized.bytes.as_slice()).read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], &[255, 255, 255, 127]);
+    }
+
+    #[test]
+    fn jpeg_loses_exif_but_keeps_its_orientation() {
+        let upright = normalize(asset("s", "image/jpeg", jpeg_with_exif(1)), None).unwrap();
+        assert!(!has_segment(&upright.bytes, 0xE1) && !has_segment(&upright.bytes, 0xFE));
+        assert_eq!((upright.natural_w, upright.natural_h), (2, 1));
+
+        let rotated = normalize(asset("s", "image/jpeg", jpeg_with_exif(6)), None).unwrap();
+        assert!(!has_segment(&rotated.bytes, 0xE1));
+        assert_eq!((rotated.natural_w, rotated.natural_h), (1, 2));
+        assert_eq!(export::jpeg_info(&rotated.bytes).unwrap().0, 1);
+    }
+
+    #[test]
+    fn jpeg_with_a_broken_scan_is_refused() {
+        let mut broken = jpeg_with_exif(1);
+        let scan = broken.windows(2).position(|window| window == [0xFF, 0xDA]).unwrap();
+        broken.truncate(scan + 14);
+        assert_eq!(normalize(asset("s", "image/jpeg", broken), None).err().unwrap().code, "image_invalid");
+    }
+
+    #[test]
+    fn orientation_maps_corners() {
+        // 2x1 : pixel 0 then pixel 1.
+        assert_eq!(orient(&[0, 1], 2, 1, 1, 6), (vec![0, 1], 1, 2));
+        assert_eq!(orient(&[0, 1], 2, 1, 1, 8), (vec![1, 0], 1, 2));
+        assert_eq!(orient(&[0, 1], 2, 1, 1, 3), (vec![1, 0], 2, 1));
+    }
+}
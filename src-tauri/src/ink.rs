@@ -0,0 +1,231 @@
+//! Drawn signatures kept as the pen strokes captured on the pad, next
+//! to their PNG rendering.
+//!
+//! The PNG remains what the renderer shows and what the pdf-lib export
+//! embeds ; the native export draws the strokes instead, as a Form
+//! XObject, so a signature stays sharp at any size. Images imported
+//! from files, and everything stored before strokes were kept, have no
+//! strokes and are exported as before.
+
+use lopdf::content::Operation;
+use lopdf::Object;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::items::parse_hex_color;
+use crate::pdfio::real;
+
+const MAX_STROKES: usize = 1_000;
+const MAX_POINTS: usize = 50_000;
+/// Same bound as the pixel side of an image.
+const MAX_SIDE: f64 = 10_000.0;
+const MAX_PEN_WIDTH: f64 = 100.0;
+/// Line widths are rounded to 1/20 of the pen width, so the small
+/// pressure changes of a stylus do not cut every stroke into one path
+/// per segment.
+const WIDTH_STEPS: f64 = 20.0;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InkDrawing {
+    /// Size of the pad the points were captured on, in CSS pixels,
+    /// with the origin at the top left.
+    pub width: f64,
+    pub height: f64,
+    /// Line width at the default pressure, in the same units.
+    pub pen_width: f64,
+    /// `#rrggbb`.
+    pub color: String,
+    pub strokes: Vec<Vec<InkPoint>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct InkPoint {
+    pub x: f64,
+    pub y: f64,
+    /// 0 to 1, as reported by the pointer ; mice report 0.5.
+    #[serde(default = "default_pressure")]
+    pub pressure: f64,
+    /// Milliseconds since the first point of the drawing.
+    pub t: f64,
+}
+
+fn default_pressure() -> f64 {
+    0.5
+}
+
+fn invalid(details: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidInput, "ink_invalid").with_details(details)
+}
+
+/// Refuse drawings the export could not render, or that are too large
+/// to be a signature.
+pub fn validate(drawing: &InkDrawing) -> Result<()> {
+    let in_range = |value: f64, max: f64| value.is_finite() && value > 0.0 && value <= max;
+    if !in_range(drawing.width, MAX_SIDE) || !in_range(drawing.height, MAX_SIDE) {
+        return Err(invalid(format!("area {}x{}", drawing.width, drawing.height)));
+    }
+    if !in_range(drawing.pen_width, MAX_PEN_WIDTH) {
+        return Err(invalid(format!("pen width {}", drawing.pen_width)));
+    }
+    if parse_hex_color(&drawing.color).is_none() {
+        return Err(invalid(format!("color {}", drawing.color)));
+    }
+    let points: usize = drawing.strokes.iter().map(Vec::len).sum();
+    if drawing.strokes.is_empty() || drawing.strokes.iter().any(Vec::is_empty) {
+        return Err(invalid("empty stroke"));
+    }
+    if drawing.strokes.len() > MAX_STROKES || points > MAX_POINTS {
+        return Err(invalid(format!("{} strokes, {points} points", drawing.strokes.len())));
+    }
+    let valid = |point: &InkPoint| {
+        point.x.is_finite()
+            && point.y.is_finite()
+            && (0.0..=1.0).contains(&point.pressure)
+            && point.t.is_finite()
+            && point.t >= 0.0
+    };
+    if let Some(point) = drawing.strokes.iter().flatten().find(|point| !valid(point)) {
+        return Err(invalid(format!("point {point:?}")));
+    }
+    Ok(())
+}
+
+fn op(operator: &str, operands: Vec<Object>) -> Operation {
+    Operation::new(operator, operands)
+}
+
+/// Line width under `pressure` : the pen width at 0.5, a quarter of it
+/// at 0 and 1.75 times it at 1.
+fn width_at(drawing: &InkDrawing, pressure: f64) -> f64 {
+    let factor = 0.25 + pressure * 1.5;
+    (factor * WIDTH_STEPS).round() / WIDTH_STEPS * drawing.pen_width
+}
+
+fn midpoint(a: &InkPoint, b: &InkPoint) -> (f64, f64) {
+    ((a.x + b.x) / 2.0, (a.y + b.y) / 2.0)
+}
+
+/// One piece of a stroke : its end point, the control point of the
+/// curve leading there (`None` for a straight line) and its width.
+struct Piece {
+    to: (f64, f64),
+    control: Option<(f64, f64)>,
+    width: f64,
+}
+
+/// A stroke is smoothed the way most ink pads do it : the curve runs
+/// through the midpoints of its segments, with each sample point as
+/// the control point of a quadratic curve ; the first and last half
+/// segments are straight.
+fn pieces(drawing: &InkDrawing, stroke: &[InkPoint]) -> Vec<Piece> {
+    let last = stroke.len() - 1;
+    let mut pieces = Vec::with_capacity(stroke.len() + 1);
+    for idx in 0..=last {
+        let to = if idx == last { (stroke[last].x, stroke[last].y) } else { midpoint(&stroke[idx], &stroke[idx + 1]) };
+        let control = (idx > 0 && idx < last).then(|| (stroke[idx].x, stroke[idx].y));
+        pieces.push(Piece { to, control, width: width_at(drawing, stroke[idx].pressure) });
+    }
+    pieces
+}
+
+/// Content of the Form XObject, in pad coordinates : every run of
+/// pieces of the same width is stroked as one path, with round caps
+/// and joins like the canvas. A single point becomes a dot.
+pub fn operations(drawing: &InkDrawing) -> Vec<Operation> {
+    let [r, g, b] = parse_hex_color(&drawing.color).unwrap_or([0, 0, 0]).map(|c| real(f64::from(c) / 255.0));
+    let mut ops = vec![
+        op("q", vec![]),
+        op("RG", vec![r, g, b]),
+        op("J", vec![Object::Integer(1)]),
+        op("j", vec![Object::Integer(1)]),
+    ];
+    let point = |(x, y): (f64, f64)| vec![real(x), real(y)];
+    for stroke in &drawing.strokes {
+        let mut from = (stroke[0].x, stroke[0].y);
+        let mut current_width = None;
+        for piece in pieces(drawing, stroke) {
+            if current_width != Some(piece.width) {
+                if current_width.is_some() {
+                    ops.push(op("S", vec![]));
+                }
+                ops.push(op("w", vec![real(piece.width)]));
+                ops.push(op("m", point(from)));
+                current_width = Some(piece.width);
+            }
+            match piece.control {
+                // Quadratic curve written as the equivalent cubic one.
+                Some((cx, cy)) => {
+                    let c1 = (from.0 + (cx - from.0) * 2.0 / 3.0, from.1 + (cy - from.1) * 2.0 / 3.0);
+                    let c2 = (piece.to.0 + (cx - piece.to.0) * 2.0 / 3.0, piece.to.1 + (cy - piece.to.1) * 2.0 / 3.0);
+                    ops.push(op("c", [point(c1), point(c2), point(piece.to)].concat()));
+                }
+                None => ops.push(op("l", point(piece.to))),
+            }
+            from = piece.to;
+        }
+        ops.push(op("S", vec![]));
+    }
+    ops.push(op("Q", vec![]));
+    ops
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn drawing(strokes: Vec<Vec<(f64, f64, f64)>>) -> InkDrawing {
+        InkDrawing {
+            width: 400.0,
+            height: 200.0,
+            pen_width: 2.0,
+            color: "#111111".into(),
+            strokes: strokes
+                .into_iter()
+                .map(|stroke| {
+                    stroke
+                        .into_iter()
+                        .enumerate()
+                        .map(|(idx, (x, y, pressure))| InkPoint { x, y, pressure, t: idx as f64 * 8.0 })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    fn operators(ops: &[Operation]) -> String {
+        ops.iter().map(|op| op.operator.as_str()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn mouse_stroke_is_one_smoothed_path() {
+        let ink = drawing(vec![vec![(10.0, 10.0, 0.5), (20.0, 30.0, 0.5), (40.0, 30.0, 0.5), (50.0, 10.0, 0.5)]]);
+        let ops = operations(&ink);
+        assert_eq!(operators(&ops), "q RG J j w m l c c l S Q");
+        assert_eq!(ops[4].operands, vec![real(2.0)]);
+    }
+
+    #[test]
+    fn pressure_changes_split_the_path_and_points_become_dots() {
+        let ink = drawing(vec![vec![(10.0, 10.0, 0.2), (20.0, 10.0, 0.2), (30.0, 10.0, 0.9)], vec![(60.0, 60.0, 0.5)]]);
+        assert_eq!(operators(&operations(&ink)), "q RG J j w m l c S w m l S w m l S Q");
+    }
+
+    #[test]
+    fn unusable_drawings_are_refused() {
+        let valid = drawing(vec![vec![(1.0, 1.0, 0.5)]]);
+        assert!(validate(&valid).is_ok());
+        let broken = [
+            InkDrawing { strokes: vec![], ..valid.clone() },
+            InkDrawing { strokes: vec![vec![]], ..valid.clone() },
+            InkDrawing { width: 0.0, ..valid.clone() },
+            InkDrawing { pen_width: f64::NAN, ..valid.clone() },
+            InkDrawing { color: "black".into(), ..valid.clone() },
+            drawing(vec![vec![(1.0, 1.0, 1.5)]]),
+            drawing(vec![vec![(f64::INFINITY, 1.0, 0.5)]]),
+        ];
+        for ink in broken {
+            assert_eq!(validate(&ink).unwrap_err().code, "ink_invalid", "{ink:?}");
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Rust mirror of the overlay items defined in `src/types.ts`. Field
+//! names and fallbacks follow the frontend so a document state or a
+//! template serialized by the webview deserializes here unchanged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pdfio::PdfRect;
+
+/// Same value as `HIGHLIGHT_OPACITY` in `src/constants.ts`.
+pub const HIGHLIGHT_OPACITY: f64 = 0.35;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub struct PdfPoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FontFamily {
+    #[default]
+    Sans,
+    Serif,
+    Mono,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextItem {
+    pub id: String,
+    /// 1-indexed, like every `page` in the frontend.
+    pub page: u32,
+    pub rect: PdfRect,
+    #[serde(default)]
+    pub value: String,
+    pub font_size: f64,
+    pub color: String,
+    #[serde(default)]
+    pub font_family: FontFamily,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub strike: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_date: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureItem {
+    pub id: String,
+    pub page: u32,
+    pub rect: PdfRect,
+    pub signature_id: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckItem {
+    pub id: String,
+    pub page: u32,
+    pub rect: PdfRect,
+    #[serde(default)]
+    pub value: String,
+    pub font_size: f64,
+    pub color: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EllipseItem {
+    pub id: String,
+    pub page: u32,
+    pub rect: PdfRect,
+    pub color: String,
+    pub stroke_width: f64,
+}
+
+/// Shared by lines and arrows. `start` / `end` are optional on legacy
+/// items, which span the rect at mid-height.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItem {
+    pub id: String,
+    pub page: u32,
+    pub rect: PdfRect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<PdfPoint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<PdfPoint>,
+    pub color: String,
+    pub stroke_width: f64,
+}
+
+impl LineItem {
+    pub fn endpoints(&self) -> (PdfPoint, PdfPoint) {
+        let PdfRect { x, y, w, h } = self.rect;
+        (
+            self.start.unwrap_or(PdfPoint { x, y: y + h / 2.0 }),
+            self.end.unwrap_or(PdfPoint { x: x + w, y: y + h / 2.0 }),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightItem {
+    pub id: String,
+    pub page: u32,
+    pub rect: PdfRect,
+    pub color: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Item {
+    Text(TextItem),
+    Signature(SignatureItem),
+    Check(CheckItem),
+    Ellipse(EllipseItem),
+    Line(LineItem),
+    Arrow(LineItem),
+    Highlight(HighlightItem),
+}
+
+impl Item {
+    pub fn id(&self) -> &str {
+        match self {
+            Item::Text(item) => &item.id,
+            Item::Signature(item) => &item.id,
+            Item::Check(item) => &item.id,
+            Item::Ellipse(item) => &item.id,
+            Item::Line(item) | Item::Arrow(item) => &item.id,
+            Item::Highlight(item) => &item.id,
+        }
+    }
+
+    pub fn page(&self) -> u32 {
+        match self {
+            Item::Text(item) => item.page,
+            Item::Signature(item) => item.page,
+            Item::Check(item) => item.page,
+            Item::Ellipse(item) => item.page,
+            Item::Line(item) | Item::Arrow(item) => item.page,
+            Item::Highlight(item) => item.page,
+        }
+    }
+
+    /// Move the item to `page`, the bottom-left corner of its rect at
+    /// (`x`, `y`) ; the endpoints of lines and arrows follow.
+    pub fn place(&mut self, page: u32, x: f64, y: f64) {
+        let (item_page, rect) = match self {
+            Item::Text(item) => (&mut item.page, &mut item.rect),
+            Item::Signature(item) => (&mut item.page, &mut item.rect),
+            Item::Check(item) => (&mut item.page, &mut item.rect),
+            Item::Ellipse(item) => (&mut item.page, &mut item.rect),
+            Item::Line(item) | Item::Arrow(item) => (&mut item.page, &mut item.rect),
+            Item::Highlight(item) => (&mut item.page, &mut item.rect),
+        };
+        let (dx, dy) = (x - rect.x, y - rect.y);
+        *item_page = page;
+        rect.x = x;
+        rect.y = y;
+        if let Item::Line(line) | Item::Arrow(line) = self {
+            for point in [&mut line.start, &mut line.end].into_iter().flatten() {
+                point.x += dx;
+                point.y += dy;
+            }
+        }
+    }
+}
+
+/// An image projected at the same rect on every page.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Paraph {
+    /// Id of the asset in the paraphs gallery.
+    pub asset_id: String,
+    pub rect: PdfRect,
+}
+
+/// 8-bit channels of a 6-digit hex color, with or without the leading
+/// '#' ; `None` on anything else, like `parseHexColor`.
+pub fn parse_hex_color(hex: &str) -> Option<[u8; 3]> {
+    let value = hex.replace('#', "");
+    let value = value.trim();
+    if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |idx: usize| u8::from_str_radix(&value[idx..idx + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_frontend_items() {
+        let items: Vec<Item> = serde_json::from_str(
+            r##"[
+                {"id":"a","type":"text","page":1,"rect":{"x":10,"y":20,"w":100,"h":14},"value":"Bonjour","fontSize":12,"color":"#112233","fontFamily":"serif","bold":true,"underline":false,"strike":false,"autoDate":true},
+                {"id":"b","type":"line","page":2,"rect":{"x":0,"y":0,"w":50,"h":10},"color":"#000000","strokeWidth":2},
+                {"id":"c","type":"signature","page":1,"rect":{"x":0,"y":0,"w":50,"h":10},"signatureId":"sig-1"}
+            ]"##,
+        )
+        .unwrap();
+        match &items[0] {
+            Item::Text(text) => {
+                assert_eq!(text.font_family, FontFamily::Serif);
+                assert_eq!(text.auto_date, Some(true));
+            }
+            other => panic!("unexpected item {other:?}"),
+        }
+        match &items[1] {
+            Item::Line(line) => {
+                let (start, end) = line.endpoints();
+                assert_eq!(start, PdfPoint { x: 0.0, y: 5.0 });
+                assert_eq!(end, PdfPoint { x: 50.0, y: 5.0 });
+            }
+            other => panic!("unexpected item {other:?}"),
+        }
+        assert_eq!(items[2].page(), 1);
+    }
+
+    #[test]
+    fn parses_only_six_digit_hex_colors() {
+        assert_eq!(parse_hex_color("#ff8000"), Some([255, 128, 0]));
+        assert_eq!(parse_hex_color("00ff00"), Some([0, 255, 0]));
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#gg0000"), None);
+    }
+}
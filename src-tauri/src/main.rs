@@ -3,15 +3,48 @@
     windows_subsystem = "windows"
 )]
 
+mod anchors;
+mod backup;
+mod bundle;
+mod cli;
+mod crypt;
+mod db;
+mod error;
+mod export;
+mod fonts;
+mod history;
+mod images;
+mod ink;
+mod items;
+mod pades;
+mod pages;
+mod pdfio;
+mod pdfread;
+mod recent;
+mod redact;
+mod scan;
+mod schema;
+mod scope;
+mod search;
+mod store;
+mod text;
+mod trust;
+mod tsa;
+mod vault;
+mod verify;
+mod watch;
+
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::Manager;
 use tauri::Emitter;
-use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::menu::{IsMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-#[derive(Serialize, Deserialize)]
+use error::{Error, ErrorKind};
+
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 struct StoredSignature {
     id: String,
@@ -20,6 +53,9 @@ struct StoredSignature {
     bytes: Vec<u8>,
     natural_w: u32,
     natural_h: u32,
+    /// Pen strokes of a drawn image, exported as vectors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ink: Option<ink::InkDrawing>,
 }
 
 
@@ -28,6 +64,12 @@ struct StoredSignature {
 struct LoadedPdf {
     bytes: Vec<u8>,
     name: String,
+    /// Present only when the document already carries digital
+    /// signatures, so the UI can warn before the user edits it.
+    signature_report: Option<verify::VerificationReport>,
+    /// The file was password-protected ; `bytes` is the decrypted
+    /// copy, so the UI can offer to protect the export again.
+    encrypted: bool,
 }
 
 #[derive(Clone, Serialize)]
@@ -36,181 +78,183 @@ struct OpenPdfPayload {
     path: String,
 }
 
+const SIGNATURES_FILE: &str = "signatures.json";
+const PARAPHS_FILE: &str = "paraphs.json";
+const TEMPLATES_FILE: &str = "templates.json";
+const SNIPPETS_FILE: &str = "snippets.json";
+
 #[derive(Default)]
 struct PendingOpen(Mutex<Vec<PathBuf>>);
 
-fn signatures_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
+fn app_data_dir(app: &tauri::AppHandle) -> error::Result<PathBuf> {
+    app.path()
         .app_data_dir()
-        .map_err(|e| format!("app data dir introuvable: {e}"))?;
-    Ok(dir.join("signatures.json"))
-}
-
-fn paraphs_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("app data dir introuvable: {e}"))?;
-    Ok(dir.join("paraphs.json"))
-}
-
-fn templates_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("app data dir introuvable: {e}"))?;
-    Ok(dir.join("templates.json"))
-}
-
-fn snippets_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("app data dir introuvable: {e}"))?;
-    Ok(dir.join("snippets.json"))
+        .map_err(|e| Error::new(ErrorKind::Unavailable, "app_data_dir_unavailable").with_details(e))
 }
 
 #[tauri::command]
-fn save_pdf_to_downloads(app: tauri::AppHandle, bytes: Vec<u8>, file_name: String) -> Result<String, String> {
+fn save_pdf_to_downloads(app: tauri::AppHandle, bytes: Vec<u8>, file_name: String) -> error::Result<String> {
     let downloads_dir = app
         .path()
         .download_dir()
-        .map_err(|e| format!("download dir introuvable: {e}"))?;
+        .map_err(|e| Error::new(ErrorKind::Unavailable, "download_dir_unavailable").with_details(e))?;
 
     let base_name = sanitize_file_name(&file_name);
     let target_path = next_available_path(downloads_dir, &base_name);
 
     std::fs::write(&target_path, bytes)
-        .map_err(|e| format!("ecriture impossible: {e}"))?;
+        .map_err(|e| Error::io("pdf_write_failed", &target_path, &e))?;
+    recent::record(&app, &target_path);
 
     Ok(target_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn load_signatures(app: tauri::AppHandle) -> Result<Vec<StoredSignature>, String> {
-    let path = signatures_path(&app)?;
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
+fn load_signatures(app: tauri::AppHandle, vault: tauri::State<vault::Vault>) -> error::Result<Vec<StoredSignature>> {
+    db::load_assets(&vault, &app_data_dir(&app)?, db::Gallery::Signatures)
+}
 
-    let bytes = std::fs::read(&path).map_err(|e| format!("lecture impossible: {e}"))?;
-    let signatures = serde_json::from_slice(&bytes).map_err(|e| format!("json invalide: {e}"))?;
-    Ok(signatures)
+#[tauri::command]
+fn save_signatures(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    signatures: Vec<StoredSignature>,
+) -> error::Result<Vec<StoredSignature>> {
+    db::save_assets(&vault, &app_data_dir(&app)?, db::Gallery::Signatures, signatures)
 }
 
 #[tauri::command]
-fn save_signatures(app: tauri::AppHandle, signatures: Vec<StoredSignature>) -> Result<(), String> {
-    let path = signatures_path(&app)?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("creation dossier impossible: {e}"))?;
-    }
+fn add_signature(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    signature: StoredSignature,
+) -> error::Result<StoredSignature> {
+    db::add_asset(&vault, &app_data_dir(&app)?, db::Gallery::Signatures, signature)
+}
 
-    let bytes = serde_json::to_vec(&signatures).map_err(|e| format!("json invalide: {e}"))?;
-    std::fs::write(&path, bytes).map_err(|e| format!("ecriture impossible: {e}"))?;
-    Ok(())
+#[tauri::command]
+fn rename_signature(app: tauri::AppHandle, vault: tauri::State<vault::Vault>, id: String, name: String) -> error::Result<()> {
+    db::rename_asset(&vault, &app_data_dir(&app)?, db::Gallery::Signatures, &id, &name)
 }
 
 #[tauri::command]
-fn load_paraphs(app: tauri::AppHandle) -> Result<Vec<StoredSignature>, String> {
-    let path = paraphs_path(&app)?;
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
+fn delete_signature(app: tauri::AppHandle, vault: tauri::State<vault::Vault>, id: String) -> error::Result<()> {
+    db::delete_asset(&vault, &app_data_dir(&app)?, db::Gallery::Signatures, &id)
+}
 
-    let bytes = std::fs::read(&path).map_err(|e| format!("lecture impossible: {e}"))?;
-    let paraphs = serde_json::from_slice(&bytes).map_err(|e| format!("json invalide: {e}"))?;
-    Ok(paraphs)
+#[tauri::command]
+fn load_paraphs(app: tauri::AppHandle, vault: tauri::State<vault::Vault>) -> error::Result<Vec<StoredSignature>> {
+    db::load_assets(&vault, &app_data_dir(&app)?, db::Gallery::Paraphs)
 }
 
 #[tauri::command]
-fn save_paraphs(app: tauri::AppHandle, paraphs: Vec<StoredSignature>) -> Result<(), String> {
-    let path = paraphs_path(&app)?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("creation dossier impossible: {e}"))?;
-    }
+fn save_paraphs(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    paraphs: Vec<StoredSignature>,
+) -> error::Result<Vec<StoredSignature>> {
+    db::save_assets(&vault, &app_data_dir(&app)?, db::Gallery::Paraphs, paraphs)
+}
 
-    let bytes = serde_json::to_vec(&paraphs).map_err(|e| format!("json invalide: {e}"))?;
-    std::fs::write(&path, bytes).map_err(|e| format!("ecriture impossible: {e}"))?;
-    Ok(())
+#[tauri::command]
+fn add_paraph(
+    app: tauri::AppHandle,
+    vault: tauri::State<vault::Vault>,
+    paraph: StoredSignature,
+) -> error::Result<StoredSignature> {
+    db::add_asset(&vault, &app_data_dir(&app)?, db::Gallery::Paraphs, paraph)
 }
 
-/// Templates contain nested items / paraph whose shape changes as
-/// the frontend gains item types. Persisting them as opaque JSON
-/// values means the Rust side never needs to mirror that schema —
-/// the frontend remains the single source of truth for the contract.
 #[tauri::command]
-fn load_templates(app: tauri::AppHandle) -> Result<Vec<serde_json::Value>, String> {
-    let path = templates_path(&app)?;
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
+fn rename_paraph(app: tauri::AppHandle, vault: tauri::State<vault::Vault>, id: String, name: String) -> error::Result<()> {
+    db::rename_asset(&vault, &app_data_dir(&app)?, db::Gallery::Paraphs, &id, &name)
+}
 
-    let bytes = std::fs::read(&path).map_err(|e| format!("lecture impossible: {e}"))?;
-    let templates = serde_json::from_slice(&bytes).map_err(|e| format!("json invalide: {e}"))?;
-    Ok(templates)
+#[tauri::command]
+fn delete_paraph(app: tauri::AppHandle, vault: tauri::State<vault::Vault>, id: String) -> error::Result<()> {
+    db::delete_asset(&vault, &app_data_dir(&app)?, db::Gallery::Paraphs, &id)
 }
 
+/// Templates go through as JSON values, upgraded to the current
+/// [`schema::TEMPLATES`] version on read. Their items are only typed
+/// ([`anchors::TemplateItem`]) where Rust applies them itself : batch
+/// runs, bundles, anchor resolution.
 #[tauri::command]
-fn save_templates(app: tauri::AppHandle, templates: Vec<serde_json::Value>) -> Result<(), String> {
-    let path = templates_path(&app)?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("creation dossier impossible: {e}"))?;
-    }
+fn load_templates(app: tauri::AppHandle) -> error::Result<Vec<serde_json::Value>> {
+    db::load_templates(&app_data_dir(&app)?)
+}
 
-    let bytes = serde_json::to_vec(&templates).map_err(|e| format!("json invalide: {e}"))?;
-    std::fs::write(&path, bytes).map_err(|e| format!("ecriture impossible: {e}"))?;
-    Ok(())
+#[tauri::command]
+fn save_templates(app: tauri::AppHandle, templates: Vec<serde_json::Value>) -> error::Result<()> {
+    db::save_templates(&app_data_dir(&app)?, &templates)
 }
 
+/// Insert a template, or replace the one with the same `id`.
 #[tauri::command]
-fn load_snippets(app: tauri::AppHandle) -> Result<Vec<String>, String> {
-    let path = snippets_path(&app)?;
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
+fn save_template(app: tauri::AppHandle, template: serde_json::Value) -> error::Result<()> {
+    db::save_template(&app_data_dir(&app)?, template)
+}
 
-    let bytes = std::fs::read(&path).map_err(|e| format!("lecture impossible: {e}"))?;
-    let snippets = serde_json::from_slice(&bytes).map_err(|e| format!("json invalide: {e}"))?;
-    Ok(snippets)
+#[tauri::command]
+fn delete_template(app: tauri::AppHandle, id: String) -> error::Result<()> {
+    db::delete_template(&app_data_dir(&app)?, &id)
 }
 
 #[tauri::command]
-fn save_snippets(app: tauri::AppHandle, snippets: Vec<String>) -> Result<(), String> {
-    let path = snippets_path(&app)?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| format!("creation dossier impossible: {e}"))?;
-    }
+fn load_snippets(app: tauri::AppHandle) -> error::Result<Vec<String>> {
+    db::load_snippets(&app_data_dir(&app)?)
+}
 
-    let bytes = serde_json::to_vec(&snippets).map_err(|e| format!("json invalide: {e}"))?;
-    std::fs::write(&path, bytes).map_err(|e| format!("ecriture impossible: {e}"))?;
-    Ok(())
+#[tauri::command]
+fn save_snippets(app: tauri::AppHandle, snippets: Vec<String>) -> error::Result<()> {
+    db::save_snippets(&app_data_dir(&app)?, &snippets)
 }
 
 #[tauri::command]
-fn save_pdf_to_path(bytes: Vec<u8>, path: String) -> Result<String, String> {
+fn save_pdf_to_path(bytes: Vec<u8>, path: String, protection: Option<crypt::Protection>) -> error::Result<String> {
     let target = PathBuf::from(path);
     // Defense in depth : the frontend always feeds this command a
     // path obtained via the OS save dialog, but we still refuse
     // anything that does not look like a PDF target so a compromised
     // renderer cannot use this command to overwrite arbitrary files.
     if !is_pdf_path(&target) {
-        return Err("destination invalide: extension .pdf attendue".into());
+        return Err(Error::new(ErrorKind::InvalidInput, "not_a_pdf_target").with_path(&target));
     }
-    std::fs::write(&target, bytes).map_err(|e| format!("ecriture impossible: {e}"))?;
+    let bytes = match protection {
+        Some(protection) => crypt::encrypt(&bytes, &protection)?,
+        None => bytes,
+    };
+    std::fs::write(&target, bytes).map_err(|e| Error::io("pdf_write_failed", &target, &e))?;
     Ok(target.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-fn load_pdf_from_path(path: String) -> Result<LoadedPdf, String> {
-    let target = PathBuf::from(&path);
-    let bytes = std::fs::read(&target).map_err(|e| format!("lecture impossible: {e}"))?;
-    let name = target
+fn load_pdf_from_path(app: tauri::AppHandle, path: String, password: Option<String>) -> error::Result<LoadedPdf> {
+    let requested = PathBuf::from(&path);
+    let target = scope::readable_pdf(&app, &requested)?;
+    let original = std::fs::read(&target).map_err(|e| Error::io("pdf_read_failed", &target, &e))?;
+    let decrypted = crypt::decrypt(&original, password.as_deref()).map_err(|err| err.with_path(&target))?;
+    let name = requested
         .file_name()
         .and_then(|s| s.to_str())
         .unwrap_or("document.pdf")
         .to_string();
-    Ok(LoadedPdf { bytes, name })
+    // A broken signature or an unreadable trust store must not prevent
+    // opening the document ; the report is best effort. It covers the
+    // file as signed, encrypted or not.
+    let signature_report = trust::load_anchors(&app)
+        .and_then(|anchors| {
+            verify::verify_pdf(&original, &anchors).map_err(Error::wrap(ErrorKind::InvalidPdf, "pdf_invalid"))
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("signature verification failed: {err}");
+            None
+        });
+    recent::record(&app, &target);
+    let encrypted = decrypted.is_some();
+    app.state::<crypt::Passwords>().remember(&target, encrypted.then(|| password.as_deref().unwrap_or_default()));
+    let bytes = decrypted.unwrap_or(original);
+    Ok(LoadedPdf { bytes, name, signature_report, encrypted })
 }
 
 #[tauri::command]
@@ -296,6 +340,7 @@ fn emit_open_pdf(app: &tauri::AppHandle, path: PathBuf) {
         return;
     }
 
+    app.state::<scope::PathScope>().allow(&path);
     if let Ok(mut guard) = app.state::<PendingOpen>().0.lock() {
         guard.push(path.clone());
     }
@@ -310,15 +355,69 @@ fn emit_open_pdf(app: &tauri::AppHandle, path: PathBuf) {
     }
 }
 
+/// Bring the main window to the front, e.g. when a later launch
+/// handed us a file.
+fn focus_main_window(app: &tauri::AppHandle) {
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let _ = window.unminimize();
+    let _ = window.show();
+    if let Err(err) = window.set_focus() {
+        eprintln!("focus main window failed: {err}");
+    }
+}
+
+/// A later launch (double-click in the file manager, "Open with")
+/// hands its arguments to the running instance instead of starting a
+/// second window. Relative paths are resolved against the launcher's
+/// working directory.
+fn forward_second_instance(app: &tauri::AppHandle, argv: Vec<String>, cwd: PathBuf) {
+    for arg in argv.into_iter().skip(1) {
+        let path = PathBuf::from(&arg);
+        let path = if path.is_relative() && Url::parse(&arg).is_err() {
+            cwd.join(path)
+        } else {
+            path
+        };
+        emit_open_pdf(app, path);
+    }
+    focus_main_window(app);
+}
+
 /// Build the application's native menu bar. Same structure on every
 /// platform (File / Edit / View / Help) ; on macOS it surfaces in the
 /// system menu bar, on Windows/Linux as an in-window menu.
 ///
 /// Menu clicks emit a single `"menu"` Tauri event whose payload is the
 /// item id (e.g. "open_pdf") — the frontend listens once and dispatches.
-fn build_app_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<Menu<R>, tauri::Error> {
+/// Open Recent entries are the exception : they go straight through
+/// `emit_open_pdf`, like a file opened from the OS.
+fn build_app_menu<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    recent_files: &[recent::RecentFile],
+) -> Result<Menu<R>, tauri::Error> {
+    let recent_items = recent_files
+        .iter()
+        .map(|entry| {
+            let id = format!("{}{}", recent::MENU_PREFIX, entry.path);
+            MenuItem::with_id(app, id, recent::menu_label(&entry.path), true, None::<&str>)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let no_recent = MenuItem::with_id(app, "recent_empty", "No Recent Files", false, None::<&str>)?;
+    let recent_separator = PredefinedMenuItem::separator(app)?;
+    let clear_recent = MenuItem::with_id(app, recent::MENU_CLEAR, "Clear Recent", !recent_files.is_empty(), None::<&str>)?;
+    let mut recent_entries: Vec<&dyn IsMenuItem<R>> = recent_items.iter().map(|item| item as &dyn IsMenuItem<R>).collect();
+    if recent_entries.is_empty() {
+        recent_entries.push(&no_recent);
+    }
+    recent_entries.push(&recent_separator);
+    recent_entries.push(&clear_recent);
+    let open_recent = Submenu::with_items(app, "Open Recent", true, &recent_entries)?;
+
     let file = Submenu::with_items(app, "File", true, &[
         &MenuItem::with_id(app, "open_pdf", "Open PDF…", true, Some("CmdOrCtrl+O"))?,
+        &open_recent,
         &MenuItem::with_id(app, "export_pdf", "Export PDF…", true, Some("CmdOrCtrl+S"))?,
         &MenuItem::with_id(app, "print_pdf", "Print", true, Some("CmdOrCtrl+P"))?,
         &PredefinedMenuItem::separator(app)?,
@@ -349,19 +448,74 @@ fn build_app_menu<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<Menu<R
     Menu::with_items(app, &[&file, &edit, &view, &help])
 }
 
+/// Release builds use the GUI subsystem on Windows, which starts
+/// without a console : the CLI borrows the one of the shell it was run
+/// from, if any, so its output and errors are not lost.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails when there is no parent console (double click, scheduler) ;
+    // there is nothing to print to then.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 fn main() {
-    let mut initial_paths: Vec<PathBuf> = std::env::args_os()
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    if cli::is_command(&args) {
+        #[cfg(windows)]
+        attach_parent_console();
+        std::process::exit(cli::run(args));
+    }
+
+    let mut initial_paths: Vec<PathBuf> = args
+        .into_iter()
         .skip(1)
         .map(PathBuf::from)
         .collect();
 
     let app = tauri::Builder::default()
+        // Must be registered first : a second launch exits right here
+        // after forwarding its arguments.
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            forward_second_instance(app, argv, PathBuf::from(cwd));
+        }))
         .manage(PendingOpen::default())
+        .manage(vault::Vault::default())
+        .manage(crypt::Passwords::default())
+        .manage(scope::PathScope::default())
+        .manage(backup::PendingRestore::default())
+        .manage(watch::HotFolder::default())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
-        .menu(|app| build_app_menu(app))
+        .setup(|app| {
+            vault::spawn_idle_watch(app.handle().clone());
+            if let Err(err) = watch::restart(app.handle()) {
+                eprintln!("hot folder not started: {err}");
+            }
+            Ok(())
+        })
+        .menu(|app| {
+            let recent_files = recent::load(app).unwrap_or_default();
+            build_app_menu(app, &recent_files)
+        })
         .on_menu_event(|app, event| {
             let action = event.id().as_ref().to_string();
+            if let Some(path) = action.strip_prefix(recent::MENU_PREFIX) {
+                emit_open_pdf(app, PathBuf::from(path));
+                return;
+            }
+            if action == recent::MENU_CLEAR {
+                if let Err(err) = recent::clear_recent_files(app.clone()) {
+                    eprintln!("clear recent files failed: {err}");
+                }
+                return;
+            }
             if let Err(err) = app.emit("menu", action) {
                 eprintln!("emit menu event failed: {err}");
             }
@@ -370,15 +524,68 @@ fn main() {
             save_pdf_to_downloads,
             load_signatures,
             save_signatures,
+            add_signature,
+            rename_signature,
+            delete_signature,
             load_paraphs,
             save_paraphs,
+            add_paraph,
+            rename_paraph,
+            delete_paraph,
+            images::normalize_image_asset,
+            images::get_image_settings,
+            images::set_image_settings,
+            scan::clean_signature_scan,
             load_templates,
             save_templates,
+            save_template,
+            delete_template,
+            bundle::export_template_bundle,
+            bundle::import_template_bundle,
+            anchors::resolve_template_anchors,
+            backup::backup_profile,
+            backup::preview_profile_restore,
+            backup::restore_profile,
+            backup::undo_profile_restore,
+            history::list_snapshots,
+            history::restore_snapshot,
             load_snippets,
             save_snippets,
             save_pdf_to_path,
+            export::export_flattened_pdf,
+            pades::save_signed_pdf_to_path,
             load_pdf_from_path,
-            take_pending_open_paths
+            verify::verify_pdf_signatures,
+            trust::list_trusted_certificates,
+            trust::add_trusted_certificate,
+            trust::remove_trusted_certificate,
+            tsa::get_tsa_settings,
+            tsa::set_tsa_settings,
+            take_pending_open_paths,
+            pages::merge_pdfs,
+            pages::extract_pages,
+            pages::reorder_pages,
+            pages::rotate_pages,
+            pages::delete_pages,
+            redact::redact_pdf,
+            search::extract_pdf_text,
+            search::search_pdf,
+            recent::list_recent_files,
+            recent::clear_recent_files,
+            scope::pick_pdf_file,
+            scope::list_approved_folders,
+            scope::add_approved_folder,
+            scope::remove_approved_folder,
+            vault::vault_status,
+            vault::unlock_vault,
+            vault::lock_vault,
+            vault::forget_vault_keyring,
+            vault::set_vault_idle_timeout,
+            watch::get_watch_folder,
+            watch::pick_watch_folder,
+            watch::set_watch_folder,
+            watch::watch_folder_log,
+            watch::clear_watch_folder_log
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
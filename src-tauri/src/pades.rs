@@ -0,0 +1,648 @@
+//! PAdES-B-B digital signatures (ETSI EN 319 142-1) for exported PDFs.
+//!
+//! The flattened bytes produced by the frontend are signed through an
+//! incremental update : a `/Sig` dictionary with `/SubFilter
+//! /ETSI.CAdES.detached` is appended together with a signature field
+//! widget, its `/Contents` is reserved as a zero-filled placeholder,
+//! and once the final byte layout is known we hash everything except
+//! that placeholder and fill it with a detached CMS `SignedData`.
+
+use std::path::PathBuf;
+
+use cms::builder::{SignedDataBuilder, SignerInfoBuilder};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::ContentInfo;
+use cms::signed_data::{EncapsulatedContentInfo, SignerIdentifier};
+use const_oid::db::rfc5911::ID_DATA;
+use const_oid::db::rfc5912::ID_SHA_256;
+use const_oid::ObjectIdentifier;
+use der::asn1::{OctetString, SetOfVec};
+use der::{Any, Decode, Encode, Sequence};
+use lopdf::{dictionary, Object, ObjectId, StringFormat};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tauri_plugin_dialog::DialogExt;
+use spki::AlgorithmIdentifierOwned;
+use x509_cert::attr::Attribute;
+use x509_cert::Certificate;
+
+use crate::error::{self, Error, ErrorKind};
+use crate::pdfio::{self, IncrementalUpdate, PdfRect};
+
+/// Bytes reserved for the DER-encoded CMS blob. Leaves room for a
+/// certificate chain of a few intermediates and an RFC 3161 token.
+pub const SIGNATURE_SIZE: usize = 16 * 1024;
+
+/// `id-aa-signingCertificateV2` (RFC 5035).
+pub const ID_AA_SIGNING_CERTIFICATE_V2: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.2.47");
+
+const BYTE_RANGE_PLACEHOLDER: &str = "[0 0000000000 0000000000 0000000000]";
+
+/// Where the signature field widget goes — typically the rect of the
+/// `SignatureItem` whose image was burnt into the page, so the visible
+/// signature and the cryptographic one coincide.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignaturePlacement {
+    /// 1-indexed, like `Item.page`.
+    pub page: u32,
+    pub rect: PdfRect,
+}
+
+enum SigningKey {
+    Rsa(Box<rsa::pkcs1v15::SigningKey<Sha256>>),
+    P256(p256::ecdsa::SigningKey),
+}
+
+/// Private key and certificate chain (leaf first) loaded from a
+/// PKCS#12 file.
+pub struct Credentials {
+    key: SigningKey,
+    chain: Vec<Certificate>,
+}
+
+impl Credentials {
+    pub fn from_pkcs12(der: &[u8], password: &str) -> Result<Self, String> {
+        let keystore = p12_keystore::KeyStore::from_pkcs12(der, password)
+            .map_err(|e| format!("unreadable certificate (wrong password?): {e}"))?;
+        let chain = keystore
+            .entries()
+            .find_map(|(_, entry)| match entry {
+                p12_keystore::KeyStoreEntry::PrivateKeyChain(chain) => Some(chain),
+                _ => None,
+            })
+            .ok_or_else(|| "certificate without a private key".to_string())?;
+
+        let certs = chain
+            .chain()
+            .iter()
+            .map(|cert| Certificate::from_der(cert.as_der()).map_err(|e| format!("invalid certificate: {e}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_pkcs8(chain.key(), certs)
+    }
+
+    /// `key` is a PKCS#8 private key ; RSA and ECDSA P-256 are the two
+    /// algorithms found on qualified signing certificates in practice.
+    pub fn from_pkcs8(key: &[u8], chain: Vec<Certificate>) -> Result<Self, String> {
+        use rsa::pkcs8::DecodePrivateKey;
+
+        if chain.is_empty() {
+            return Err("missing certificate".into());
+        }
+        let key = if let Ok(rsa) = rsa::RsaPrivateKey::from_pkcs8_der(key) {
+            SigningKey::Rsa(Box::new(rsa::pkcs1v15::SigningKey::new(rsa)))
+        } else if let Ok(ec) = p256::ecdsa::SigningKey::from_pkcs8_der(key) {
+            SigningKey::P256(ec)
+        } else {
+            return Err("unsupported key algorithm (RSA or P-256 expected)".into());
+        };
+        Ok(Self { key, chain })
+    }
+
+    pub fn leaf(&self) -> &Certificate {
+        &self.chain[0]
+    }
+
+    pub fn chain(&self) -> &[Certificate] {
+        &self.chain
+    }
+}
+
+#[derive(Sequence)]
+struct EssCertIdV2 {
+    // `hashAlgorithm` is omitted : SHA-256 is its DEFAULT value.
+    cert_hash: OctetString,
+}
+
+#[derive(Sequence)]
+struct SigningCertificateV2 {
+    certs: Vec<EssCertIdV2>,
+}
+
+/// `signing-certificate-v2` (RFC 5035), the attribute that makes a CMS
+/// signature CAdES and is mandatory for PAdES baseline signatures.
+fn signing_certificate_v2(cert: &Certificate) -> Result<Attribute, String> {
+    let cert_der = cert.to_der().map_err(|e| format!("invalid certificate: {e}"))?;
+    let value = SigningCertificateV2 {
+        certs: vec![EssCertIdV2 {
+            cert_hash: OctetString::new(Sha256::digest(&cert_der).to_vec()).map_err(|e| e.to_string())?,
+        }],
+    };
+    let any = Any::encode_from(&value).map_err(|e| e.to_string())?;
+    Ok(Attribute {
+        oid: ID_AA_SIGNING_CERTIFICATE_V2,
+        values: SetOfVec::try_from(vec![any]).map_err(|e| e.to_string())?,
+    })
+}
+
+fn build_signed_data<S, Sig>(signer: &S, chain: &[Certificate], digest: &[u8]) -> Result<ContentInfo, String>
+where
+    S: signature::Keypair + spki::DynSignatureAlgorithmIdentifier + signature::Signer<Sig>,
+    S::VerifyingKey: spki::EncodePublicKey,
+    Sig: spki::SignatureBitStringEncoding,
+{
+    let leaf = &chain[0];
+    let content = EncapsulatedContentInfo { econtent_type: ID_DATA, econtent: None };
+    let digest_algorithm = AlgorithmIdentifierOwned { oid: ID_SHA_256, parameters: None };
+    let sid = SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+        issuer: leaf.tbs_certificate.issuer.clone(),
+        serial_number: leaf.tbs_certificate.serial_number.clone(),
+    });
+
+    // PAdES forbids the CMS `signing-time` attribute (the claimed time
+    // lives in the `/M` entry of the signature dictionary), which is
+    // why only the ESS attribute is added on top of the mandatory
+    // content-type / message-digest the builder inserts.
+    let mut signer_info = SignerInfoBuilder::new(signer, sid, digest_algorithm.clone(), &content, Some(digest))
+        .map_err(|e| format!("signing failed: {e}"))?;
+    signer_info
+        .add_signed_attribute(signing_certificate_v2(leaf)?)
+        .map_err(|e| format!("signing failed: {e}"))?;
+
+    let mut builder = SignedDataBuilder::new(&content);
+    builder
+        .add_digest_algorithm(digest_algorithm)
+        .map_err(|e| format!("signing failed: {e}"))?;
+    for cert in chain {
+        builder
+            .add_certificate(CertificateChoices::Certificate(cert.clone()))
+            .map_err(|e| format!("signing failed: {e}"))?;
+    }
+    builder
+        .add_signer_info::<S, Sig>(signer_info)
+        .map_err(|e| format!("signing failed: {e}"))?
+        .build()
+        .map_err(|e| format!("signing failed: {e}"))
+}
+
+/// Detached CMS signature over `digest` (the SHA-256 of the byte range).
+pub fn sign_digest(credentials: &Credentials, digest: &[u8]) -> Result<ContentInfo, String> {
+    match &credentials.key {
+        SigningKey::Rsa(key) => build_signed_data::<_, rsa::pkcs1v15::Signature>(&**key, &credentials.chain, digest),
+        SigningKey::P256(key) => build_signed_data::<_, p256::ecdsa::DerSignature>(key, &credentials.chain, digest),
+    }
+}
+
+/// SHA-256 over the two signed spans described by a `/ByteRange`.
+pub fn byte_range_digest(bytes: &[u8], range: [usize; 4]) -> Result<Vec<u8>, String> {
+    let [start1, len1, start2, len2] = range;
+    let first = bytes.get(start1..start1 + len1);
+    let second = bytes.get(start2..start2 + len2);
+    match (first, second) {
+        (Some(first), Some(second)) => {
+            let mut hasher = Sha256::new();
+            hasher.update(first);
+            hasher.update(second);
+            Ok(hasher.finalize().to_vec())
+        }
+        _ => Err("ByteRange outside the file".into()),
+    }
+}
+
+fn find_from(haystack: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
+
+fn unique_field_name(update: &IncrementalUpdate, fields: &[Object]) -> String {
+    let taken: Vec<Vec<u8>> = fields
+        .iter()
+        .filter_map(|field| update.resolve(field).ok())
+        .filter_map(|field| match field {
+            Object::Dictionary(dict) => dict.get(b"T").ok().and_then(|t| t.as_str().ok()).map(|t| t.to_vec()),
+            _ => None,
+        })
+        .collect();
+    (1..)
+        .map(|idx| format!("Signature{idx}"))
+        .find(|name| !taken.iter().any(|t| t == name.as_bytes()))
+        .unwrap_or_else(|| "Signature".into())
+}
+
+/// Append `reference` to the array stored under `key` in `dict`,
+/// following an indirect array if there is one. Returns the dictionary
+/// to write back when the array lives inline.
+fn push_to_array(update: &mut IncrementalUpdate, dict: &mut lopdf::Dictionary, key: &[u8], reference: ObjectId) -> Result<(), String> {
+    match dict.get(key).ok().cloned() {
+        Some(Object::Reference(array_id)) => {
+            let mut items = match update.get(array_id)? {
+                Object::Array(items) => items,
+                _ => return Err(format!("object {}: array expected", array_id.0)),
+            };
+            items.push(Object::Reference(reference));
+            update.set(array_id, Object::Array(items));
+        }
+        Some(Object::Array(mut items)) => {
+            items.push(Object::Reference(reference));
+            dict.set(key, Object::Array(items));
+        }
+        _ => dict.set(key, Object::Array(vec![Object::Reference(reference)])),
+    }
+    Ok(())
+}
+
+fn array_items(update: &IncrementalUpdate, dict: &lopdf::Dictionary, key: &[u8]) -> Vec<Object> {
+    dict.get(key)
+        .ok()
+        .and_then(|value| update.resolve(value).ok())
+        .and_then(|value| match value {
+            Object::Array(items) => Some(items),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Build the incremental update carrying an empty signature field and
+/// return the serialized bytes with placeholders still in place.
+fn prepare(pdf: &[u8], placement: Option<SignaturePlacement>, signer_name: Option<String>) -> Result<Vec<u8>, String> {
+    let mut update = IncrementalUpdate::new(pdf)?;
+    let pages = update.pages()?;
+    let page_number = placement.map(|p| p.page).unwrap_or(1);
+    let page_id = *pages
+        .get(&page_number)
+        .ok_or_else(|| format!("page {page_number} not found"))?;
+    let catalog_id = update
+        .trailer()
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|_| "invalid pdf: catalog not found".to_string())?;
+
+    let mut sig = format!(
+        "<</Type /Sig /Filter /Adobe.PPKLite /SubFilter /ETSI.CAdES.detached /M ({}) /ByteRange {BYTE_RANGE_PLACEHOLDER} /Contents <{}>",
+        pdfio::pdf_date_now(),
+        "0".repeat(SIGNATURE_SIZE * 2)
+    )
+    .into_bytes();
+    if let Some(name) = signer_name {
+        sig.extend_from_slice(b" /Name ");
+        pdfio::write_object(&mut sig, &Object::String(name.into_bytes(), StringFormat::Literal));
+    }
+    sig.extend_from_slice(b">>");
+    let sig_id = update.add_raw(sig);
+
+    // The visible signature is already part of the flattened page
+    // content ; the widget only needs an empty appearance so viewers
+    // draw their validity badge over the same rect.
+    let rect = placement.map(|p| p.rect).unwrap_or(PdfRect { x: 0.0, y: 0.0, w: 0.0, h: 0.0 });
+    let appearance_id = update.add(Object::Stream(lopdf::Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => PdfRect { x: 0.0, y: 0.0, w: rect.w, h: rect.h }.to_array(),
+        },
+        Vec::new(),
+    )));
+
+    let catalog = update.get_dictionary(catalog_id)?;
+    let (acroform_id, mut acroform) = match catalog.get(b"AcroForm").ok().cloned() {
+        Some(Object::Reference(id)) => (Some(id), update.get_dictionary(id)?),
+        Some(Object::Dictionary(dict)) => (None, dict),
+        _ => (None, lopdf::Dictionary::new()),
+    };
+    let existing_fields = array_items(&update, &acroform, b"Fields");
+    let field_name = unique_field_name(&update, &existing_fields);
+
+    let widget_id = update.add(Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "FT" => "Sig",
+        "T" => Object::string_literal(field_name),
+        "V" => sig_id,
+        // Print | Locked : always printed, never movable by the viewer.
+        "F" => Object::Integer(132),
+        "Rect" => rect.to_array(),
+        "P" => page_id,
+        "AP" => dictionary! { "N" => appearance_id },
+    }));
+
+    let mut page = update.get_dictionary(page_id)?;
+    push_to_array(&mut update, &mut page, b"Annots", widget_id)?;
+    update.set(page_id, Object::Dictionary(page));
+
+    push_to_array(&mut update, &mut acroform, b"Fields", widget_id)?;
+    // SignaturesExist | AppendOnly
+    acroform.set("SigFlags", Object::Integer(3));
+    match acroform_id {
+        Some(id) => update.set(id, Object::Dictionary(acroform)),
+        None => {
+            let mut catalog = catalog;
+            catalog.set("AcroForm", Object::Dictionary(acroform));
+            update.set(catalog_id, Object::Dictionary(catalog));
+        }
+    }
+
+    update.to_bytes()
+}
+
+/// Byte range placeholder and `/Contents` hex span of the signature
+/// dictionary appended after `original_len`.
+struct Placeholders {
+    byte_range: usize,
+    contents_start: usize,
+    contents_end: usize,
+}
+
+fn locate_placeholders(bytes: &[u8], original_len: usize) -> Result<Placeholders, String> {
+    let byte_range = find_from(bytes, original_len, BYTE_RANGE_PLACEHOLDER.as_bytes())
+        .ok_or_else(|| "ByteRange not found".to_string())?;
+    let contents_key = find_from(bytes, byte_range, b"/Contents <").ok_or_else(|| "Contents not found".to_string())?;
+    let contents_start = contents_key + b"/Contents ".len();
+    let contents_end = contents_start + SIGNATURE_SIZE * 2 + 2;
+    if bytes.get(contents_end - 1) != Some(&b'>') {
+        return Err("malformed Contents".into());
+    }
+    Ok(Placeholders { byte_range, contents_start, contents_end })
+}
+
+/// Fill `/ByteRange`, hash the covered bytes and hand the digest to
+/// `sign`, whose DER output is written into `/Contents`.
+pub fn finish_with(
+    mut bytes: Vec<u8>,
+    original_len: usize,
+    sign: impl FnOnce(&[u8]) -> error::Result<Vec<u8>>,
+) -> error::Result<Vec<u8>> {
+    let spots = locate_placeholders(&bytes, original_len).map_err(Error::wrap(ErrorKind::InvalidPdf, "sign_failed"))?;
+    let range = [0, spots.contents_start, spots.contents_end, bytes.len() - spots.contents_end];
+    let filled = format!("[0 {:010} {:010} {:010}]", range[1], range[2], range[3]);
+    bytes[spots.byte_range..spots.byte_range + filled.len()].copy_from_slice(filled.as_bytes());
+
+    let digest = byte_range_digest(&bytes, range).map_err(Error::wrap(ErrorKind::InvalidPdf, "sign_failed"))?;
+    let der = sign(&digest)?;
+    if der.len() > SIGNATURE_SIZE {
+        return Err(Error::new(ErrorKind::Certificate, "signature_too_large")
+            .with_details(format!("{} bytes", der.len())));
+    }
+    let encoded = pdfio::hex(&der);
+    bytes[spots.contents_start + 1..spots.contents_start + 1 + encoded.len()].copy_from_slice(encoded.as_bytes());
+    Ok(bytes)
+}
+
+/// The DER value at the start of `bytes`, without the zero padding
+/// that fills the rest of a `/Contents` placeholder.
+pub fn der_prefix(bytes: &[u8]) -> Result<&[u8], String> {
+    use der::Reader;
+
+    let reader = der::SliceReader::new(bytes).map_err(|e| format!("invalid cms: {e}"))?;
+    let header = reader.peek_header().map_err(|e| format!("invalid cms: {e}"))?;
+    let len = (header.encoded_len().map_err(|e| format!("invalid cms: {e}"))? + header.length)
+        .and_then(usize::try_from)
+        .map_err(|e| format!("invalid cms: {e}"))?;
+    bytes.get(..len).ok_or_else(|| "truncated cms".to_string())
+}
+
+fn common_name(cert: &Certificate) -> Option<String> {
+    cert.tbs_certificate.subject.0.iter().flat_map(|rdn| rdn.0.iter()).find_map(|atv| {
+        if atv.oid != const_oid::db::rfc4519::CN {
+            return None;
+        }
+        der::asn1::Utf8StringRef::try_from(&atv.value)
+            .map(|s| s.as_str().to_string())
+            .or_else(|_| der::asn1::PrintableStringRef::try_from(&atv.value).map(|s| s.as_str().to_string()))
+            .ok()
+    })
+}
+
+/// Sign `pdf` with a PAdES signature. `placement` binds the field
+/// widget to a page rect ; without it the signature is invisible. With
+/// a `tsa_url`, the signature value is timestamped (PAdES-B-T) ;
+/// otherwise the result is PAdES-B-B.
+pub fn sign_pdf(
+    pdf: &[u8],
+    credentials: &Credentials,
+    placement: Option<SignaturePlacement>,
+    tsa_url: Option<&str>,
+) -> error::Result<Vec<u8>> {
+    let prepared = prepare(pdf, placement, common_name(credentials.leaf()))
+        .map_err(Error::wrap(ErrorKind::InvalidPdf, "sign_failed"))?;
+    finish_with(prepared, pdf.len(), |digest| {
+        let mut content_info =
+            sign_digest(credentials, digest).map_err(Error::wrap(ErrorKind::Certificate, "sign_failed"))?;
+        if let Some(url) = tsa_url {
+            content_info = crate::tsa::add_signature_timestamp(content_info, url)
+                .map_err(Error::wrap(ErrorKind::Network, "timestamp_failed"))?;
+        }
+        content_info
+            .to_der()
+            .map_err(|e| Error::new(ErrorKind::Certificate, "sign_failed").with_details(e))
+    })
+}
+
+/// Same contract as `save_pdf_to_path`, with the written file carrying a
+/// PAdES signature made with a PKCS#12 certificate. The certificate is
+/// chosen through the open dialog, never named by the renderer ;
+/// returns `None` when the user cancelled. `timestamp` requests a token
+/// from the TSA configured in the settings ; it runs off the main
+/// thread since that is a network round trip.
+#[tauri::command(async)]
+pub fn save_signed_pdf_to_path(
+    app: tauri::AppHandle,
+    bytes: Vec<u8>,
+    path: String,
+    password: String,
+    placement: Option<SignaturePlacement>,
+    timestamp: bool,
+) -> error::Result<Option<String>> {
+    let target = PathBuf::from(path);
+    if !crate::is_pdf_path(&target) {
+        return Err(Error::new(ErrorKind::InvalidInput, "not_a_pdf_target").with_path(&target));
+    }
+    let tsa_url = if timestamp {
+        let url = crate::tsa::load_settings(&app)?.url;
+        Some(url.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "tsa_not_configured"))?)
+    } else {
+        None
+    };
+    let Some(picked) = app.dialog().file().add_filter("PKCS#12", &["p12", "pfx"]).blocking_pick_file() else {
+        return Ok(None);
+    };
+    let certificate_path =
+        picked.into_path().map_err(|e| Error::new(ErrorKind::InvalidInput, "path_invalid").with_details(e))?;
+    let p12 = std::fs::read(&certificate_path).map_err(|e| Error::io("certificate_read_failed", &certificate_path, &e))?;
+    let credentials = Credentials::from_pkcs12(&p12, &password)
+        .map_err(|details| Error::new(ErrorKind::Certificate, "pkcs12_unreadable").with_path(&certificate_path).with_details(details))?;
+    let signed = sign_pdf(&bytes, &credentials, placement, tsa_url.as_deref())?;
+    std::fs::write(&target, signed).map_err(|e| Error::io("pdf_write_failed", &target, &e))?;
+    Ok(Some(target.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::pdfio::tests::sample_pdf;
+    use cms::signed_data::SignedData;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::time::Validity;
+
+    pub fn test_name(common_name: &str) -> Name {
+        Name::from_str(&format!("CN={common_name},O=Cerfini Tests")).unwrap()
+    }
+
+    /// A P-256 certificate of `key` for `common_name`, signed with
+    /// `issuer_key` (`key` itself for `Profile::Root`).
+    pub fn certificate(
+        common_name: &str,
+        profile: Profile,
+        key: &p256::ecdsa::SigningKey,
+        issuer_key: &p256::ecdsa::SigningKey,
+    ) -> Certificate {
+        let spki = spki::SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+        CertificateBuilder::new(
+            profile,
+            SerialNumber::from(42u32),
+            Validity::from_now(Duration::from_secs(3600)).unwrap(),
+            test_name(common_name),
+            spki,
+            issuer_key,
+        )
+        .unwrap()
+        .build::<p256::ecdsa::DerSignature>()
+        .unwrap()
+    }
+
+    pub fn random_key() -> p256::ecdsa::SigningKey {
+        p256::ecdsa::SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng)
+    }
+
+    /// Credentials for `key` and its chain, leaf first.
+    pub fn credentials(key: &p256::ecdsa::SigningKey, chain: Vec<Certificate>) -> Credentials {
+        use p256::pkcs8::EncodePrivateKey;
+
+        Credentials::from_pkcs8(key.to_pkcs8_der().unwrap().as_bytes(), chain).unwrap()
+    }
+
+    /// Self-signed P-256 certificate and matching credentials, built in
+    /// memory so the tests need no fixture files.
+    pub fn self_signed(common_name: &str) -> Credentials {
+        let key = random_key();
+        let cert = certificate(common_name, Profile::Root, &key, &key);
+        credentials(&key, vec![cert])
+    }
+
+    fn signature_dictionary(doc: &lopdf::Document) -> lopdf::Dictionary {
+        doc.objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .find(|dict| dict.get(b"Type").and_then(Object::as_name).ok() == Some(&b"Sig"[..]))
+            .cloned()
+            .expect("signature dictionary")
+    }
+
+    #[test]
+    fn signs_with_byte_range_covering_everything_but_contents() {
+        let original = sample_pdf(2, "Contrat");
+        let credentials = self_signed("Alice");
+        let placement = SignaturePlacement { page: 2, rect: PdfRect { x: 100.0, y: 100.0, w: 150.0, h: 50.0 } };
+        let signed = sign_pdf(&original, &credentials, Some(placement), None).unwrap();
+
+        assert!(signed.starts_with(&original));
+        let doc = lopdf::Document::load_mem(&signed).unwrap();
+        let sig = signature_dictionary(&doc);
+
+        let range: Vec<usize> = sig
+            .get(b"ByteRange")
+            .and_then(Object::as_array)
+            .unwrap()
+            .iter()
+            .map(|v| v.as_i64().unwrap() as usize)
+            .collect();
+        assert_eq!(range[0], 0);
+        assert_eq!(range[2] + range[3], signed.len());
+        assert_eq!(signed[range[1]], b'<');
+        assert_eq!(signed[range[2] - 1], b'>');
+
+        let contents = sig.get(b"Contents").and_then(Object::as_str).unwrap();
+        let info = ContentInfo::from_der(der_prefix(contents).unwrap()).unwrap();
+        let signed_data: SignedData = info.content.decode_as().unwrap();
+        assert_eq!(signed_data.signer_infos.0.len(), 1);
+        assert!(signed_data.encap_content_info.econtent.is_none());
+
+        let signer = signed_data.signer_infos.0.get(0).unwrap();
+        let attrs = signer.signed_attrs.as_ref().unwrap();
+        let digest = byte_range_digest(&signed, [range[0], range[1], range[2], range[3]]).unwrap();
+        let message_digest = attrs
+            .iter()
+            .find(|attr| attr.oid == const_oid::db::rfc5911::ID_MESSAGE_DIGEST)
+            .unwrap();
+        let value = message_digest.values.get(0).unwrap().decode_as::<OctetString>().unwrap();
+        assert_eq!(value.as_bytes(), digest.as_slice());
+        assert!(attrs.iter().any(|attr| attr.oid == ID_AA_SIGNING_CERTIFICATE_V2));
+    }
+
+    #[test]
+    fn widget_lands_on_requested_page_and_rect() {
+        let original = sample_pdf(3, "Annexe");
+        let credentials = self_signed("Bob");
+        let rect = PdfRect { x: 10.0, y: 20.0, w: 30.0, h: 40.0 };
+        let signed = sign_pdf(&original, &credentials, Some(SignaturePlacement { page: 3, rect }), None).unwrap();
+
+        let doc = lopdf::Document::load_mem(&signed).unwrap();
+        let page_id = doc.get_pages()[&3];
+        let page = doc.get_dictionary(page_id).unwrap();
+        let annots = page.get(b"Annots").and_then(Object::as_array).unwrap();
+        assert_eq!(annots.len(), 1);
+        let widget = doc.get_dictionary(annots[0].as_reference().unwrap()).unwrap();
+        assert_eq!(widget.get(b"FT").and_then(Object::as_name).unwrap(), b"Sig");
+        let coords: Vec<f32> = widget
+            .get(b"Rect")
+            .and_then(Object::as_array)
+            .unwrap()
+            .iter()
+            .map(|v| v.as_float().unwrap())
+            .collect();
+        assert_eq!(coords, vec![10.0, 20.0, 40.0, 60.0]);
+    }
+
+    #[test]
+    fn second_signature_gets_a_distinct_field_name() {
+        let original = sample_pdf(1, "Double");
+        let once = sign_pdf(&original, &self_signed("Alice"), None, None).unwrap();
+        let twice = sign_pdf(&once, &self_signed("Bob"), None, None).unwrap();
+
+        assert!(twice.starts_with(&once));
+        let doc = lopdf::Document::load_mem(&twice).unwrap();
+        let names: Vec<Vec<u8>> = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .filter(|dict| dict.get(b"FT").and_then(Object::as_name).ok() == Some(&b"Sig"[..]))
+            .map(|dict| dict.get(b"T").and_then(Object::as_str).unwrap().to_vec())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert_ne!(names[0], names[1]);
+    }
+
+    #[test]
+    fn timestamped_signature_embeds_a_valid_token() {
+        let url = crate::tsa::tests::spawn_local_tsa(1);
+        let signed = sign_pdf(&sample_pdf(1, "Horodaté"), &self_signed("Alice"), None, Some(&url)).unwrap();
+
+        let doc = lopdf::Document::load_mem(&signed).unwrap();
+        let contents = signature_dictionary(&doc).get(b"Contents").and_then(Object::as_str).unwrap().to_vec();
+        let signed_data: SignedData = ContentInfo::from_der(der_prefix(&contents).unwrap())
+            .unwrap()
+            .content
+            .decode_as()
+            .unwrap();
+        let signer = signed_data.signer_infos.0.get(0).unwrap();
+        let token_attr = signer
+            .unsigned_attrs
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|attr| attr.oid == crate::tsa::ID_AA_SIGNATURE_TIME_STAMP_TOKEN)
+            .unwrap();
+        let token: ContentInfo = token_attr.values.get(0).unwrap().decode_as().unwrap();
+        let tst_info = crate::tsa::validate_token(&token).unwrap().tst_info;
+        assert_eq!(
+            tst_info.message_imprint.hashed_message.as_bytes(),
+            Sha256::digest(signer.signature.as_bytes()).as_slice()
+        );
+    }
+}
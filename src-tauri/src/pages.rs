@@ -0,0 +1,505 @@
+//! Page-level edits : merge documents, extract page ranges, reorder,
+//! rotate by quarter turns and delete pages.
+//!
+//! Unlike the export, these cannot be incremental updates : the page
+//! tree is rebuilt, so the result is a new document returned to the
+//! renderer, which reloads it like any other. Existing digital
+//! signatures do not survive. Each command also returns `remap`, the
+//! new number of every page of the edited (first) document, so items
+//! follow their page ; rotation only changes `/Rotate`, items keep
+//! their user-space rects and the paraph stays on every page.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::export::{decode_text, text_string};
+
+/// Attributes a page may inherit from the nodes above it ; they are
+/// copied onto the page since the old tree is dropped.
+const INHERITED: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+/// Bound on `/Parent` and `/Kids` chains, which may loop in broken files.
+const MAX_DEPTH: usize = 64;
+/// Catalog entries kept from the first document ; the others (outline,
+/// page labels, structure tree…) refer to pages by position or object
+/// and would be wrong after the edit.
+const KEPT_CATALOG_KEYS: [&[u8]; 3] = [b"Lang", b"ViewerPreferences", b"PageLayout"];
+
+/// A document as sent by the renderer : the path it was opened from, or
+/// its bytes when it only lives in memory.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PdfSource {
+    Path(String),
+    Bytes(Vec<u8>),
+}
+
+/// Inclusive, 1-indexed.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct PageRange {
+    pub first: u32,
+    pub last: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PageEdit {
+    pub bytes: Vec<u8>,
+    pub page_count: u32,
+    /// For page `n` of the edited document, its new number at index
+    /// `n - 1`, or `None` when it was left out.
+    pub remap: Vec<Option<u32>>,
+}
+
+/// One page of the result : page `page` of `sources[source]`, turned
+/// clockwise by `quarter_turns` more than it was.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pick {
+    source: usize,
+    page: u32,
+    quarter_turns: i32,
+}
+
+impl Pick {
+    fn new(page: u32) -> Self {
+        Pick { source: 0, page, quarter_turns: 0 }
+    }
+}
+
+fn load(bytes: &[u8]) -> Result<Document> {
+    Document::load_mem(bytes).map_err(|e| Error::new(ErrorKind::InvalidPdf, "pdf_invalid").with_details(e))
+}
+
+fn page_count(doc: &Document) -> u32 {
+    doc.get_pages().len() as u32
+}
+
+/// Every page exists and is named once.
+fn check_pages(pages: &[u32], count: u32) -> Result<()> {
+    let mut seen = HashSet::new();
+    for &page in pages {
+        if page == 0 || page > count {
+            return Err(Error::new(ErrorKind::InvalidInput, "page_out_of_range").with_details(format!("{page} / {count}")));
+        }
+        if !seen.insert(page) {
+            return Err(Error::new(ErrorKind::InvalidInput, "page_repeated").with_details(page));
+        }
+    }
+    Ok(())
+}
+
+fn merge_picks(counts: &[u32]) -> Vec<Pick> {
+    counts
+        .iter()
+        .enumerate()
+        .flat_map(|(source, &count)| (1..=count).map(move |page| Pick { source, page, quarter_turns: 0 }))
+        .collect()
+}
+
+fn extract_picks(count: u32, ranges: &[PageRange]) -> Result<Vec<Pick>> {
+    let mut pages = Vec::new();
+    for range in ranges {
+        if range.first > range.last {
+            return Err(Error::new(ErrorKind::InvalidInput, "page_range_invalid")
+                .with_details(format!("{}-{}", range.first, range.last)));
+        }
+        pages.extend(range.first..=range.last);
+    }
+    if pages.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "pages_empty"));
+    }
+    check_pages(&pages, count)?;
+    Ok(pages.into_iter().map(Pick::new).collect())
+}
+
+fn reorder_picks(count: u32, order: &[u32]) -> Result<Vec<Pick>> {
+    check_pages(order, count)?;
+    if order.len() != count as usize {
+        return Err(Error::new(ErrorKind::InvalidInput, "page_order_incomplete")
+            .with_details(format!("{} / {count}", order.len())));
+    }
+    Ok(order.iter().copied().map(Pick::new).collect())
+}
+
+fn rotate_picks(count: u32, pages: &[u32], quarter_turns: i32) -> Result<Vec<Pick>> {
+    check_pages(pages, count)?;
+    Ok((1..=count)
+        .map(|page| Pick { quarter_turns: if pages.contains(&page) { quarter_turns } else { 0 }, ..Pick::new(page) })
+        .collect())
+}
+
+fn delete_picks(count: u32, pages: &[u32]) -> Result<Vec<Pick>> {
+    check_pages(pages, count)?;
+    if pages.len() == count as usize {
+        return Err(Error::new(ErrorKind::InvalidInput, "pages_all_deleted"));
+    }
+    Ok((1..=count).filter(|page| !pages.contains(page)).map(Pick::new).collect())
+}
+
+/// `key` on the page or the nearest node above it.
+fn inherited(doc: &Document, page: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node = doc.get_dictionary(page).ok()?;
+    for _ in 0..MAX_DEPTH {
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = doc.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+fn resolved<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).map(|(_, object)| object).unwrap_or(object)
+}
+
+fn rotation(doc: &Document, page: &Dictionary) -> i64 {
+    page.get(b"Rotate").ok().and_then(|rotate| resolved(doc, rotate).as_i64().ok()).unwrap_or(0)
+}
+
+/// References of the annotations of `page`.
+fn annotations(doc: &Document, page: ObjectId) -> Vec<ObjectId> {
+    let Ok(annots) = doc.get_dictionary(page).and_then(|page| page.get(b"Annots")) else {
+        return Vec::new();
+    };
+    match resolved(doc, annots).as_array() {
+        Ok(annots) => annots.iter().filter_map(|annot| annot.as_reference().ok()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Whether the form field `id` has a widget among `kept`, itself or
+/// through its kids.
+fn field_kept(doc: &Document, id: ObjectId, kept: &HashSet<ObjectId>, depth: usize) -> bool {
+    if kept.contains(&id) {
+        return true;
+    }
+    if depth >= MAX_DEPTH {
+        return false;
+    }
+    let Ok(kids) = doc.get_dictionary(id).and_then(|field| field.get(b"Kids")) else {
+        return false;
+    };
+    resolved(doc, kids).as_array().is_ok_and(|kids| {
+        kids.iter()
+            .filter_map(|kid| kid.as_reference().ok())
+            .any(|kid| field_kept(doc, kid, kept, depth + 1))
+    })
+}
+
+/// Fields of different documents that share a name would become one
+/// field, every widget showing the same value : the later ones get a
+/// `-2`, `-3`… suffix. Renaming the top-level field renames the fully
+/// qualified names of all its kids.
+fn rename_duplicate_fields(doc: &mut Document, fields: &[Object]) {
+    let mut names = HashSet::new();
+    for field in fields {
+        let Ok(dict) = field.as_reference().and_then(|id| doc.get_dictionary_mut(id)) else {
+            continue;
+        };
+        let Some(name) = dict.get(b"T").and_then(Object::as_str).ok().map(decode_text) else {
+            continue;
+        };
+        if names.insert(name.clone()) {
+            continue;
+        }
+        let unique = (2..).map(|idx| format!("{name}-{idx}")).find(|candidate| !names.contains(candidate));
+        let unique = unique.unwrap_or_default();
+        dict.set("T", text_string(&unique));
+        names.insert(unique);
+    }
+}
+
+/// Build the document made of `picks`, in order. Objects of every
+/// source are renumbered into one document ; the page tree, the
+/// catalogs and the pages left out are dropped, along with whatever
+/// only they referenced. Form fields whose widgets all sat on dropped
+/// pages go too ; the others are gathered into one `/AcroForm`, see
+/// `rename_duplicate_fields`.
+fn assemble(mut sources: Vec<Document>, picks: &[Pick]) -> std::result::Result<Vec<u8>, String> {
+    let mut out = Document::with_version("1.7");
+    let pages_id = out.new_object_id();
+    let mut page_ids: Vec<BTreeMap<u32, ObjectId>> = Vec::new();
+    let mut catalogs: Vec<Dictionary> = Vec::new();
+    let mut info = None;
+
+    for (idx, doc) in sources.iter_mut().enumerate() {
+        doc.renumber_objects_with(out.max_id + 1);
+        let pages = doc.get_pages();
+        for &page in pages.values() {
+            let values: Vec<(&[u8], Object)> =
+                INHERITED.iter().filter_map(|key| inherited(doc, page, key).map(|value| (*key, value))).collect();
+            let dict = doc.get_dictionary_mut(page).map_err(|e| e.to_string())?;
+            for (key, value) in values {
+                dict.set(key, value);
+            }
+        }
+        catalogs.push(doc.catalog().map_err(|e| e.to_string())?.clone());
+        if idx == 0 {
+            info = doc.trailer.get(b"Info").ok().cloned();
+        }
+        out.objects.append(&mut doc.objects);
+        out.max_id = doc.max_id;
+        page_ids.push(pages);
+    }
+
+    let mut kids = Vec::with_capacity(picks.len());
+    for pick in picks {
+        let id = *page_ids
+            .get(pick.source)
+            .and_then(|pages| pages.get(&pick.page))
+            .ok_or_else(|| format!("page {} missing from document {}", pick.page, pick.source + 1))?;
+        let rotate = rotation(&out, out.get_dictionary(id).map_err(|e| e.to_string())?);
+        let page = out.get_dictionary_mut(id).map_err(|e| e.to_string())?;
+        page.set("Parent", pages_id);
+        let rotate = (rotate + 90 * i64::from(pick.quarter_turns)).rem_euclid(360);
+        if rotate == 0 {
+            page.remove(b"Rotate");
+        } else {
+            page.set("Rotate", rotate);
+        }
+        kids.push(Object::Reference(id));
+    }
+
+    // References left to the old tree or to dropped pages (links,
+    // widgets' `/P`) become null rather than keeping them alive.
+    let kept: HashSet<ObjectId> = kids.iter().filter_map(|kid| kid.as_reference().ok()).collect();
+    let dropped: Vec<ObjectId> = page_ids.iter().flat_map(|pages| pages.values()).filter(|id| !kept.contains(id)).copied().collect();
+    let tree_nodes: Vec<ObjectId> = out
+        .objects
+        .iter()
+        .filter(|(_, object)| object.type_name().is_ok_and(|name| name == "Pages"))
+        .map(|(id, _)| *id)
+        .collect();
+    let kept_annots: HashSet<ObjectId> = kept.iter().flat_map(|&page| annotations(&out, page)).collect();
+
+    let mut acroform: Option<Dictionary> = None;
+    let mut fields = Vec::new();
+    for catalog in &catalogs {
+        let Some(form) = catalog.get(b"AcroForm").ok().and_then(|form| resolved(&out, form).as_dict().ok()) else {
+            continue;
+        };
+        if let Some(list) = form.get(b"Fields").ok().and_then(|list| resolved(&out, list).as_array().ok()) {
+            fields.extend(
+                list.iter()
+                    .filter(|field| field.as_reference().is_ok_and(|id| field_kept(&out, id, &kept_annots, 0)))
+                    .cloned(),
+            );
+        }
+        acroform.get_or_insert_with(|| form.clone());
+    }
+
+    rename_duplicate_fields(&mut out, &fields);
+
+    for id in dropped.iter().chain(&tree_nodes) {
+        out.objects.remove(id);
+    }
+    out.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => picks.len() as i64,
+        }),
+    );
+
+    let mut catalog = dictionary! { "Type" => "Catalog", "Pages" => pages_id };
+    if let Some(first) = catalogs.first() {
+        for key in KEPT_CATALOG_KEYS {
+            if let Ok(value) = first.get(key) {
+                catalog.set(key, value.clone());
+            }
+        }
+    }
+    if let Some(mut form) = acroform {
+        form.set("Fields", fields);
+        catalog.set("AcroForm", form);
+    }
+    let catalog_id = out.add_object(catalog);
+    out.trailer.set("Root", catalog_id);
+    if let Some(info) = info {
+        out.trailer.set("Info", info);
+    }
+
+    out.prune_objects();
+    out.renumber_objects();
+    out.compress();
+    let mut bytes = Vec::new();
+    out.save_to(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+fn edit(sources: Vec<Document>, picks: Vec<Pick>) -> Result<PageEdit> {
+    let count = sources.first().map(page_count).unwrap_or(0);
+    let mut remap = vec![None; count as usize];
+    for (idx, pick) in picks.iter().enumerate() {
+        if pick.source == 0 {
+            remap[pick.page as usize - 1] = Some(idx as u32 + 1);
+        }
+    }
+    let bytes = assemble(sources, &picks).map_err(Error::wrap(ErrorKind::InvalidPdf, "pages_edit_failed"))?;
+    Ok(PageEdit { bytes, page_count: picks.len() as u32, remap })
+}
+
+pub(crate) fn read_source(app: &tauri::AppHandle, source: PdfSource) -> Result<Document> {
+    match source {
+        PdfSource::Bytes(bytes) => load(&bytes),
+        PdfSource::Path(path) => {
+            let path = crate::scope::readable_pdf(app, Path::new(&path))?;
+            let bytes = crate::crypt::read_clear(&app.state(), &path)?;
+            load(&bytes).map_err(|err| err.with_path(&path))
+        }
+    }
+}
+
+/// Append the pages of the other documents after those of the first,
+/// which is the one being edited.
+#[tauri::command(async)]
+pub fn merge_pdfs(app: tauri::AppHandle, documents: Vec<PdfSource>) -> Result<PageEdit> {
+    if documents.len() < 2 {
+        return Err(Error::new(ErrorKind::InvalidInput, "merge_needs_documents"));
+    }
+    let sources = documents.into_iter().map(|source| read_source(&app, source)).collect::<Result<Vec<_>>>()?;
+    let picks = merge_picks(&sources.iter().map(page_count).collect::<Vec<_>>());
+    edit(sources, picks)
+}
+
+/// A new document made of `ranges`, in the order given.
+#[tauri::command(async)]
+pub fn extract_pages(app: tauri::AppHandle, document: PdfSource, ranges: Vec<PageRange>) -> Result<PageEdit> {
+    let doc = read_source(&app, document)?;
+    let picks = extract_picks(page_count(&doc), &ranges)?;
+    edit(vec![doc], picks)
+}
+
+/// `order` lists every page once, in its new position.
+#[tauri::command(async)]
+pub fn reorder_pages(app: tauri::AppHandle, document: PdfSource, order: Vec<u32>) -> Result<PageEdit> {
+    let doc = read_source(&app, document)?;
+    let picks = reorder_picks(page_count(&doc), &order)?;
+    edit(vec![doc], picks)
+}
+
+/// Turn `pages` clockwise by `quarter_turns` × 90° (negative turns go
+/// counter-clockwise).
+#[tauri::command(async)]
+pub fn rotate_pages(app: tauri::AppHandle, document: PdfSource, pages: Vec<u32>, quarter_turns: i32) -> Result<PageEdit> {
+    let doc = read_source(&app, document)?;
+    let picks = rotate_picks(page_count(&doc), &pages, quarter_turns)?;
+    edit(vec![doc], picks)
+}
+
+#[tauri::command(async)]
+pub fn delete_pages(app: tauri::AppHandle, document: PdfSource, pages: Vec<u32>) -> Result<PageEdit> {
+    let doc = read_source(&app, document)?;
+    let picks = delete_picks(page_count(&doc), &pages)?;
+    edit(vec![doc], picks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdfio::tests::sample_pdf;
+
+    fn sample(pages: usize, text: &str) -> Document {
+        load(&sample_pdf(pages, text)).unwrap()
+    }
+
+    /// The text drawn on each page of `bytes`, in page order.
+    fn page_texts(bytes: &[u8]) -> Vec<String> {
+        let doc = Document::load_mem(bytes).unwrap();
+        doc.get_pages()
+            .values()
+            .map(|&page| {
+                let content = doc.get_and_decode_page_content(page).unwrap();
+                let tj = content.operations.iter().find(|op| op.operator == "Tj").unwrap();
+                String::from_utf8(tj.operands[0].as_str().unwrap().to_vec()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merged_pages_follow_the_edited_document() {
+        let edit = edit(vec![sample(2, "Contrat"), sample(1, "Annexe")], merge_picks(&[2, 1])).unwrap();
+        assert_eq!(page_texts(&edit.bytes), ["Contrat 1", "Contrat 2", "Annexe 1"]);
+        assert_eq!((edit.page_count, edit.remap), (3, vec![Some(1), Some(2)]));
+    }
+
+    #[test]
+    fn reorder_and_delete_report_where_pages_went() {
+        let reordered = edit(vec![sample(3, "Page")], reorder_picks(3, &[3, 1, 2]).unwrap()).unwrap();
+        assert_eq!(page_texts(&reordered.bytes), ["Page 3", "Page 1", "Page 2"]);
+        assert_eq!(reordered.remap, vec![Some(2), Some(3), Some(1)]);
+
+        let deleted = edit(vec![sample(3, "Page")], delete_picks(3, &[2]).unwrap()).unwrap();
+        assert_eq!(page_texts(&deleted.bytes), ["Page 1", "Page 3"]);
+        assert_eq!(deleted.remap, vec![Some(1), None, Some(2)]);
+        let doc = Document::load_mem(&deleted.bytes).unwrap();
+        assert_eq!(doc.objects.values().filter(|object| object.type_name().ok() == Some("Page")).count(), 2);
+    }
+
+    #[test]
+    fn extracted_ranges_keep_their_order() {
+        let ranges = [PageRange { first: 4, last: 5 }, PageRange { first: 1, last: 1 }];
+        let edit = edit(vec![sample(5, "Page")], extract_picks(5, &ranges).unwrap()).unwrap();
+        assert_eq!(page_texts(&edit.bytes), ["Page 4", "Page 5", "Page 1"]);
+        assert_eq!(edit.remap, vec![Some(3), None, None, Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn rotation_adds_to_the_existing_one() {
+        let edit = edit(vec![sample(2, "Page")], rotate_picks(2, &[2], -1).unwrap()).unwrap();
+        let doc = Document::load_mem(&edit.bytes).unwrap();
+        let rotations: Vec<i64> = doc.get_pages().values().map(|&page| rotation(&doc, doc.get_dictionary(page).unwrap())).collect();
+        assert_eq!(rotations, [0, 270]);
+        assert_eq!(edit.remap, vec![Some(1), Some(2)]);
+    }
+
+    /// One-page document whose form has a text field `name`.
+    fn with_field(name: &str) -> Document {
+        let mut doc = sample(1, "Formulaire");
+        let page = doc.get_pages()[&1];
+        let field = doc.add_object(dictionary! {
+            "FT" => "Tx", "T" => Object::string_literal(name), "Type" => "Annot", "Subtype" => "Widget",
+            "Rect" => vec![0.into(), 0.into(), 100.into(), 20.into()], "P" => page,
+        });
+        doc.get_dictionary_mut(page).unwrap().set("Annots", vec![Object::Reference(field)]);
+        let catalog = doc.trailer.get(b"Root").and_then(Object::as_reference).unwrap();
+        doc.get_dictionary_mut(catalog).unwrap().set("AcroForm", dictionary! { "Fields" => vec![Object::Reference(field)] });
+        doc
+    }
+
+    #[test]
+    fn merged_fields_with_the_same_name_are_renamed() {
+        let sources = vec![with_field("nom"), with_field("nom"), with_field("nom")];
+        let edit = edit(sources, merge_picks(&[1, 1, 1])).unwrap();
+        let doc = Document::load_mem(&edit.bytes).unwrap();
+        let form = doc.catalog().unwrap().get(b"AcroForm").and_then(Object::as_dict).unwrap();
+        let names: Vec<String> = form
+            .get(b"Fields")
+            .and_then(Object::as_array)
+            .unwrap()
+            .iter()
+            .map(|field| {
+                let field = doc.get_dictionary(field.as_reference().unwrap()).unwrap();
+                decode_text(field.get(b"T").and_then(Object::as_str).unwrap())
+            })
+            .collect();
+        assert_eq!(names, ["nom", "nom-2", "nom-3"]);
+    }
+
+    #[test]
+    fn page_lists_are_checked() {
+        let code = |result: Result<Vec<Pick>>| result.unwrap_err().code;
+        assert_eq!(code(reorder_picks(3, &[1, 2])), "page_order_incomplete");
+        assert_eq!(code(reorder_picks(3, &[1, 1, 2])), "page_repeated");
+        assert_eq!(code(delete_picks(2, &[1, 2])), "pages_all_deleted");
+        assert_eq!(code(rotate_picks(2, &[3], 1)), "page_out_of_range");
+        assert_eq!(code(extract_picks(2, &[PageRange { first: 2, last: 1 }])), "page_range_invalid");
+        assert_eq!(code(extract_picks(2, &[])), "pages_empty");
+    }
+}
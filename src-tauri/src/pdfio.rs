@@ -0,0 +1,571 @@
+//! Low-level PDF writing helpers shared by the Rust-side PDF features.
+//!
+//! Parsing goes through `lopdf` (object by object for incremental
+//! updates, see [`crate::pdfread`]) ; writing is done here so we control
+//! the exact bytes we append. That matters for incremental updates :
+//! the original file must be preserved byte for byte (any existing
+//! digital signature covers it) and the signing code needs to patch
+//! placeholders at known offsets after serialization.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Cursor, Read, Seek};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::pdfread::LazyPdf;
+
+/// Rectangle in PDF user space (origin bottom-left), same shape as the
+/// frontend's `PdfRect`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PdfRect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl PdfRect {
+    /// `[llx lly urx ury]` as expected by `/Rect` and `/BBox`.
+    pub fn to_array(self) -> Object {
+        Object::Array(vec![
+            real(self.x),
+            real(self.y),
+            real(self.x + self.w),
+            real(self.y + self.h),
+        ])
+    }
+}
+
+pub fn real(value: f64) -> Object {
+    Object::Real(value as f32)
+}
+
+fn is_regular(byte: u8) -> bool {
+    !matches!(
+        byte,
+        b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ' | b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+fn write_name(out: &mut Vec<u8>, name: &[u8]) {
+    out.push(b'/');
+    for &byte in name {
+        if byte == b'#' || !is_regular(byte) || !(b'!'..=b'~').contains(&byte) {
+            out.extend_from_slice(format!("#{byte:02X}").as_bytes());
+        } else {
+            out.push(byte);
+        }
+    }
+}
+
+fn write_real(out: &mut Vec<u8>, value: f32) {
+    // PDF reals have no exponent form ; 4 decimals is below a device
+    // pixel at any sane zoom.
+    let mut text = format!("{:.4}", value);
+    if text.contains('.') {
+        while text.ends_with('0') {
+            text.pop();
+        }
+        if text.ends_with('.') {
+            text.pop();
+        }
+    }
+    if text == "-0" {
+        text = "0".into();
+    }
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn write_dictionary(out: &mut Vec<u8>, dict: &Dictionary) {
+    out.extend_from_slice(b"<<");
+    for (key, value) in dict.iter() {
+        write_name(out, key);
+        out.push(b' ');
+        write_object(out, value);
+    }
+    out.extend_from_slice(b">>");
+}
+
+/// Serialize `object` in PDF syntax. Streams are written with their
+/// content as-is ; `/Length` is rewritten to match it.
+pub fn write_object(out: &mut Vec<u8>, object: &Object) {
+    match object {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Boolean(value) => out.extend_from_slice(if *value { b"true" } else { b"false" }),
+        Object::Integer(value) => out.extend_from_slice(value.to_string().as_bytes()),
+        Object::Real(value) => write_real(out, *value),
+        Object::Name(name) => write_name(out, name),
+        Object::String(bytes, StringFormat::Literal) => {
+            out.push(b'(');
+            for &byte in bytes {
+                match byte {
+                    b'(' | b')' | b'\\' => {
+                        out.push(b'\\');
+                        out.push(byte);
+                    }
+                    b'\r' => out.extend_from_slice(b"\\r"),
+                    b'\n' => out.extend_from_slice(b"\\n"),
+                    _ => out.push(byte),
+                }
+            }
+            out.push(b')');
+        }
+        Object::String(bytes, StringFormat::Hexadecimal) => {
+            out.push(b'<');
+            out.extend_from_slice(hex(bytes).as_bytes());
+            out.push(b'>');
+        }
+        Object::Array(items) => {
+            out.push(b'[');
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    out.push(b' ');
+                }
+                write_object(out, item);
+            }
+            out.push(b']');
+        }
+        Object::Dictionary(dict) => write_dictionary(out, dict),
+        Object::Stream(stream) => {
+            let mut dict = stream.dict.clone();
+            dict.set("Length", Object::Integer(stream.content.len() as i64));
+            write_dictionary(out, &dict);
+            out.extend_from_slice(b"\nstream\n");
+            out.extend_from_slice(&stream.content);
+            out.extend_from_slice(b"\nendstream");
+        }
+        Object::Reference((id, generation)) => {
+            out.extend_from_slice(format!("{id} {generation} R").as_bytes());
+        }
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+/// Current UTC time as a PDF date string (`D:YYYYMMDDHHmmSS+00'00'`).
+pub fn pdf_date_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!(
+        "D:{year:04}{month:02}{day:02}{:02}{:02}{:02}+00'00'",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Days since 1970-01-01 to a proleptic Gregorian date (H. Hinnant's
+/// `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Parse a PDF date (`D:YYYYMMDDHHmmSSOHH'mm'`, every field after the
+/// year optional) into Unix seconds.
+pub fn parse_pdf_date(raw: &str) -> Option<i64> {
+    let raw = raw.strip_prefix("D:").unwrap_or(raw);
+    let digits: String = raw.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if digits.len() < 4 {
+        return None;
+    }
+    let field = |start: usize, len: usize, default: u32| -> u32 {
+        digits.get(start..start + len).and_then(|v| v.parse().ok()).unwrap_or(default)
+    };
+    let year: i64 = digits[..4].parse().ok()?;
+    let (month, day) = (field(4, 2, 1), field(6, 2, 1));
+    let (hour, minute, second) = (field(8, 2, 0), field(10, 2, 0), field(12, 2, 0));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut secs = days_from_civil(year, month, day) * 86_400
+        + i64::from(hour) * 3600
+        + i64::from(minute) * 60
+        + i64::from(second);
+
+    let rest = &raw[digits.len()..];
+    if let Some(sign @ ('+' | '-')) = rest.chars().next() {
+        let offset: String = rest[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+        let hours: i64 = offset.get(..2).and_then(|v| v.parse().ok()).unwrap_or(0);
+        let minutes: i64 = offset.get(2..4).and_then(|v| v.parse().ok()).unwrap_or(0);
+        let offset_secs = hours * 3600 + minutes * 60;
+        secs += if sign == '+' { -offset_secs } else { offset_secs };
+    }
+    Some(secs)
+}
+
+/// Offset of the last `startxref` value, i.e. where the newest
+/// cross-reference section of `bytes` begins.
+pub fn last_startxref(bytes: &[u8]) -> Result<usize, String> {
+    let marker = b"startxref";
+    let pos = bytes
+        .windows(marker.len())
+        .rposition(|window| window == marker)
+        .ok_or_else(|| "invalid pdf: startxref not found".to_string())?;
+    let digits: String = bytes[pos + marker.len()..]
+        .iter()
+        .skip_while(|byte| byte.is_ascii_whitespace())
+        .take_while(|byte| byte.is_ascii_digit())
+        .map(|&byte| byte as char)
+        .collect();
+    digits
+        .parse()
+        .map_err(|_| "invalid pdf: unreadable startxref".to_string())
+}
+
+/// Body of an object queued in an [`IncrementalUpdate`].
+enum Pending {
+    Object(Object),
+    /// Pre-serialized body, for objects whose exact bytes matter (the
+    /// signature dictionary and its placeholders).
+    Raw(Vec<u8>),
+}
+
+/// An incremental update (ISO 32000-1 §7.5.6) appended to an existing
+/// file : new and replaced objects followed by a cross-reference
+/// section chained to the previous one through `/Prev`. The original
+/// bytes are left untouched, and only the objects the update reads are
+/// loaded from them (see [`crate::pdfread`]).
+pub struct IncrementalUpdate<'a> {
+    original: LazyPdf<'a>,
+    next_id: u32,
+    objects: BTreeMap<u32, Pending>,
+}
+
+impl<'a> IncrementalUpdate<'a> {
+    pub fn new(original: &'a [u8]) -> Result<Self, String> {
+        Self::from_reader(Cursor::new(original))
+    }
+
+    /// Update of a file read on demand, typically the `File` itself so
+    /// a large scan is never held in memory.
+    pub fn from_reader(original: impl Read + Seek + 'a) -> Result<Self, String> {
+        let original = LazyPdf::new(original)?;
+        if original.trailer().get(b"Encrypt").is_ok() {
+            return Err("encrypted pdf: incremental update not supported".into());
+        }
+        let next_id = original.max_id() + 1;
+        Ok(Self { original, next_id, objects: BTreeMap::new() })
+    }
+
+    /// Trailer of the original file.
+    pub fn trailer(&self) -> &Dictionary {
+        self.original.trailer()
+    }
+
+    /// Page objects by page number, from 1, in page tree order.
+    pub fn pages(&self) -> Result<BTreeMap<u32, ObjectId>, String> {
+        let catalog = self
+            .trailer()
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .map_err(|_| "invalid pdf: catalog not found".to_string())?;
+        let tree = self
+            .get_dictionary(catalog)?
+            .get(b"Pages")
+            .and_then(Object::as_reference)
+            .map_err(|_| "invalid pdf: page tree not found".to_string())?;
+        let mut pages = BTreeMap::new();
+        let mut seen = HashSet::new();
+        let mut pending = vec![tree];
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Ok(node) = self.get_dictionary(id) else {
+                continue;
+            };
+            match node.get(b"Kids").ok().map(|kids| self.resolve(kids)) {
+                Some(Ok(Object::Array(kids))) => {
+                    pending.extend(kids.iter().rev().filter_map(|kid| kid.as_reference().ok()));
+                }
+                _ => {
+                    pages.insert(pages.len() as u32 + 1, id);
+                }
+            }
+        }
+        Ok(pages)
+    }
+
+    /// The object as it will read after this update : a pending
+    /// replacement wins over the original.
+    pub fn get(&self, id: ObjectId) -> Result<Object, String> {
+        match self.objects.get(&id.0) {
+            Some(Pending::Object(object)) => Ok(object.clone()),
+            Some(Pending::Raw(_)) => Err("raw object cannot be read back".into()),
+            None => self.original.get_object(id),
+        }
+    }
+
+    pub fn get_dictionary(&self, id: ObjectId) -> Result<Dictionary, String> {
+        match self.get(id)? {
+            Object::Dictionary(dict) => Ok(dict),
+            _ => Err(format!("object {}: dictionary expected", id.0)),
+        }
+    }
+
+    fn reserve(&mut self) -> ObjectId {
+        let id = self.next_id;
+        self.next_id += 1;
+        (id, 0)
+    }
+
+    pub fn add(&mut self, object: Object) -> ObjectId {
+        let id = self.reserve();
+        self.objects.insert(id.0, Pending::Object(object));
+        id
+    }
+
+    pub fn add_raw(&mut self, body: Vec<u8>) -> ObjectId {
+        let id = self.reserve();
+        self.objects.insert(id.0, Pending::Raw(body));
+        id
+    }
+
+    /// Replace an existing object. Updated objects keep their number ;
+    /// generation numbers are never bumped since nothing is freed.
+    pub fn set(&mut self, id: ObjectId, object: Object) {
+        self.objects.insert(id.0, Pending::Object(object));
+    }
+
+    /// Resolve `object` if it is a reference, following pending
+    /// replacements.
+    pub fn resolve(&self, object: &Object) -> Result<Object, String> {
+        match object {
+            Object::Reference(id) => self.get(*id),
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// The numbers of an array such as a `/Rect`, references resolved ;
+    /// empty when `object` is not an array.
+    pub fn numbers(&self, object: &Object) -> Vec<f64> {
+        match self.resolve(object) {
+            Ok(Object::Array(values)) => values
+                .iter()
+                .map(|value| self.resolve(value).ok().as_ref().and_then(crate::text::number).unwrap_or(0.0))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Serialize the original file followed by the update.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut out = self.original.read_all()?;
+        out.extend_from_slice(&self.tail()?);
+        Ok(out)
+    }
+
+    /// Only the bytes to append after the original file, for callers
+    /// that copy the original on disk rather than through memory.
+    /// Empty when nothing was added or replaced.
+    pub fn tail(&self) -> Result<Vec<u8>, String> {
+        if self.objects.is_empty() {
+            return Ok(Vec::new());
+        }
+        let prev = self.original.startxref();
+        let xref_stream = self.original.uses_xref_stream();
+
+        let base = self.original.len() as usize;
+        let mut out = Vec::new();
+        if !self.original.ends_with_newline() {
+            out.push(b'\n');
+        }
+
+        let mut offsets = BTreeMap::new();
+        for (id, pending) in &self.objects {
+            offsets.insert(*id, base + out.len());
+            out.extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+            match pending {
+                Pending::Object(object) => write_object(&mut out, object),
+                Pending::Raw(body) => out.extend_from_slice(body),
+            }
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let mut trailer = Dictionary::new();
+        for key in [&b"Root"[..], b"Info", b"ID"] {
+            if let Ok(value) = self.original.trailer().get(key) {
+                trailer.set(key, value.clone());
+            }
+        }
+        trailer.set("Prev", Object::Integer(prev as i64));
+
+        if xref_stream {
+            // The original uses a cross-reference stream : keep the same
+            // kind so readers that only follow one flavour in a chain
+            // still find every section.
+            let xref_id = self.next_id;
+            let xref_offset = base + out.len();
+            offsets.insert(xref_id, xref_offset);
+            trailer.set("Size", Object::Integer(i64::from(xref_id) + 1));
+            trailer.set("Type", Object::Name(b"XRef".to_vec()));
+            trailer.set("W", Object::Array(vec![Object::Integer(1), Object::Integer(4), Object::Integer(2)]));
+
+            let mut index = Vec::new();
+            let mut data = Vec::new();
+            for (start, count) in subsections(&offsets) {
+                index.push(Object::Integer(i64::from(start)));
+                index.push(Object::Integer(count as i64));
+                for id in start..start + count as u32 {
+                    data.push(1u8);
+                    data.extend_from_slice(&(offsets[&id] as u32).to_be_bytes());
+                    data.extend_from_slice(&0u16.to_be_bytes());
+                }
+            }
+            trailer.set("Index", Object::Array(index));
+            out.extend_from_slice(format!("{xref_id} 0 obj\n").as_bytes());
+            write_object(&mut out, &Object::Stream(lopdf::Stream::new(trailer, data)));
+            out.extend_from_slice(b"\nendobj\n");
+            out.extend_from_slice(format!("startxref\n{xref_offset}\n%%EOF\n").as_bytes());
+        } else {
+            let xref_offset = base + out.len();
+            trailer.set("Size", Object::Integer(i64::from(self.next_id)));
+            out.extend_from_slice(b"xref\n");
+            for (start, count) in subsections(&offsets) {
+                out.extend_from_slice(format!("{start} {count}\n").as_bytes());
+                for id in start..start + count as u32 {
+                    out.extend_from_slice(format!("{:010} 00000 n \n", offsets[&id]).as_bytes());
+                }
+            }
+            out.extend_from_slice(b"trailer\n");
+            write_object(&mut out, &Object::Dictionary(trailer));
+            out.extend_from_slice(format!("\nstartxref\n{xref_offset}\n%%EOF\n").as_bytes());
+        }
+        Ok(out)
+    }
+}
+
+/// Group object numbers into runs of consecutive ids.
+fn subsections(offsets: &BTreeMap<u32, usize>) -> Vec<(u32, usize)> {
+    let mut runs: Vec<(u32, usize)> = Vec::new();
+    for &id in offsets.keys() {
+        match runs.last_mut() {
+            Some((start, count)) if *start + *count as u32 == id => *count += 1,
+            _ => runs.push((id, 1)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Stream};
+
+    /// Minimal `pages`-page document with a line of Helvetica text on
+    /// each page, shared by the PDF tests across modules.
+    pub fn sample_pdf(pages: usize, text: &str) -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let mut kids = Vec::new();
+        for idx in 0..pages {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec![Object::Name(b"F1".to_vec()), Object::Integer(12)]),
+                    Operation::new("Td", vec![Object::Integer(72), Object::Integer(700)]),
+                    Operation::new("Tj", vec![Object::string_literal(format!("{text} {}", idx + 1))]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![Object::Integer(0), Object::Integer(0), Object::Integer(595), Object::Integer(842)],
+                "Contents" => content_id,
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+            });
+            kids.push(page_id.into());
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => pages as i64,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let mut out = Vec::new();
+        doc.save_to(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn incremental_update_preserves_original_and_reparses() {
+        let original = sample_pdf(1, "Hello");
+        let mut update = IncrementalUpdate::new(&original).unwrap();
+        let added = update.add(Object::string_literal("ajout (1)"));
+
+        let out = update.to_bytes().unwrap();
+        assert!(out.starts_with(&original));
+
+        let doc = Document::load_mem(&out).unwrap();
+        let object = doc.get_object(added).unwrap();
+        assert_eq!(object.as_str().unwrap(), b"ajout (1)");
+    }
+
+    #[test]
+    fn names_and_reals_are_escaped() {
+        let mut out = Vec::new();
+        write_object(&mut out, &Object::Name(b"A B#".to_vec()));
+        out.push(b' ');
+        write_object(&mut out, &Object::Real(1.5));
+        out.push(b' ');
+        write_object(&mut out, &Object::Real(-0.00001));
+        assert_eq!(out, b"/A#20B#23 1.5 0");
+    }
+
+    #[test]
+    fn civil_dates_match_known_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+    }
+
+    #[test]
+    fn pdf_dates_parse_with_timezone() {
+        assert_eq!(parse_pdf_date("D:20240101000000Z"), Some(19_723 * 86_400));
+        assert_eq!(parse_pdf_date("D:20240101020000+02'00'"), Some(19_723 * 86_400));
+        assert_eq!(parse_pdf_date("D:2024"), Some(19_723 * 86_400));
+        assert_eq!(parse_pdf_date("hier"), None);
+    }
+}
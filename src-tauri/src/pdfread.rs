@@ -0,0 +1,784 @@
+//! Objects of an existing PDF read on demand, for the incremental
+//! updates of [`crate::pdfio`].
+//!
+//! `lopdf::Document` parses every object of a file up front, which for
+//! a 100 MB scan means holding every page image in memory to change a
+//! few dictionaries. Here only the cross-reference sections and the
+//! trailer are read when the file is opened ; an object is read (and
+//! kept) the first time it is asked for, by seeking to its offset or
+//! decoding the object stream that holds it.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Seek, SeekFrom};
+
+use lopdf::{Dictionary, Object, ObjectId, ObjectStream, Stream, StringFormat};
+
+/// Bytes read at once when the size of what follows is unknown ;
+/// doubled until the parse completes.
+const WINDOW: usize = 4096;
+
+/// How deep `/Length` references may nest before the file is deemed
+/// malformed.
+const MAX_DEPTH: usize = 8;
+
+/// Where an object lives, from the newest cross-reference section that
+/// lists it. Free entries are left out, as `lopdf` does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Slot {
+    Offset(u64),
+    Compressed { container: u32, index: u32 },
+}
+
+pub trait Input: Read + Seek {}
+
+impl<T: Read + Seek> Input for T {}
+
+pub struct LazyPdf<'a> {
+    input: RefCell<Box<dyn Input + 'a>>,
+    len: u64,
+    startxref: u64,
+    xref_stream: bool,
+    ends_with_newline: bool,
+    xref: BTreeMap<u32, Slot>,
+    trailer: Dictionary,
+    objects: RefCell<BTreeMap<u32, Object>>,
+    object_streams: RefCell<BTreeMap<u32, BTreeMap<ObjectId, Object>>>,
+}
+
+impl<'a> LazyPdf<'a> {
+    /// Read the cross-reference sections of `input`, newest first, and
+    /// the newest trailer.
+    pub fn new(input: impl Read + Seek + 'a) -> Result<Self, String> {
+        let mut input: Box<dyn Input + 'a> = Box::new(input);
+        let len = input.seek(SeekFrom::End(0)).map_err(|e| format!("invalid pdf: {e}"))?;
+        let mut pdf = Self {
+            input: RefCell::new(input),
+            len,
+            startxref: 0,
+            xref_stream: false,
+            ends_with_newline: false,
+            xref: BTreeMap::new(),
+            trailer: Dictionary::new(),
+            objects: RefCell::new(BTreeMap::new()),
+            object_streams: RefCell::new(BTreeMap::new()),
+        };
+        let head = pdf.read_at(0, 1024)?;
+        if !head.windows(5).any(|window| window == b"%PDF-") {
+            return Err("invalid pdf: header not found".into());
+        }
+        let tail_start = len.saturating_sub(1024);
+        let tail = pdf.read_at(tail_start, 1024)?;
+        pdf.ends_with_newline = tail.ends_with(b"\n");
+        pdf.startxref = crate::pdfio::last_startxref(&tail)? as u64;
+
+        let mut next = Some(pdf.startxref);
+        let mut seen = HashSet::new();
+        while let Some(offset) = next.filter(|offset| seen.insert(*offset)) {
+            let (is_stream, mut entries, trailer) = pdf.read_section(offset)?;
+            if offset == pdf.startxref {
+                pdf.xref_stream = is_stream;
+                pdf.trailer = trailer.clone();
+            }
+            // Hybrid files list the objects of their object streams in
+            // a separate stream, and as free entries in the table.
+            if let Ok(stream_offset) = trailer.get(b"XRefStm").and_then(Object::as_i64) {
+                let (_, stream_entries, _) = pdf.read_section(offset_value(stream_offset)?)?;
+                for (id, slot) in stream_entries {
+                    entries.entry(id).or_insert(slot);
+                }
+            }
+            for (id, slot) in entries {
+                pdf.xref.entry(id).or_insert(slot);
+            }
+            next = match trailer.get(b"Prev").and_then(Object::as_i64) {
+                Ok(prev) => Some(offset_value(prev)?),
+                Err(_) => None,
+            };
+        }
+        Ok(pdf)
+    }
+
+    /// Size of the file.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Offset of the newest cross-reference section.
+    pub fn startxref(&self) -> u64 {
+        self.startxref
+    }
+
+    /// Whether the newest cross-reference section is a stream rather
+    /// than a table.
+    pub fn uses_xref_stream(&self) -> bool {
+        self.xref_stream
+    }
+
+    pub fn ends_with_newline(&self) -> bool {
+        self.ends_with_newline
+    }
+
+    pub fn trailer(&self) -> &Dictionary {
+        &self.trailer
+    }
+
+    /// Highest object number in use.
+    pub fn max_id(&self) -> u32 {
+        self.xref.keys().next_back().copied().unwrap_or(0)
+    }
+
+    pub fn get_object(&self, id: ObjectId) -> Result<Object, String> {
+        self.object(id, 0)
+    }
+
+    /// The whole file, for the callers that need it in memory anyway.
+    pub fn read_all(&self) -> Result<Vec<u8>, String> {
+        self.read_at(0, self.len as usize)
+    }
+
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+        let len = len.min(self.len.saturating_sub(offset) as usize);
+        let mut bytes = vec![0; len];
+        let mut input = self.input.borrow_mut();
+        input
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| input.read_exact(&mut bytes))
+            .map_err(|e| format!("read failed at {offset}: {e}"))?;
+        Ok(bytes)
+    }
+
+    /// Run `parse` on the bytes from `offset`, reading more of them as
+    /// long as it runs past the end of what was read.
+    fn parse_at<T>(&self, offset: u64, parse: impl Fn(&mut Lexer) -> Parsed<T>) -> Result<(T, Vec<u8>, usize), String> {
+        if offset >= self.len {
+            return Err(format!("invalid pdf: offset {offset} past the end of the file"));
+        }
+        let mut size = WINDOW;
+        loop {
+            let bytes = self.read_at(offset, size)?;
+            let complete = offset + bytes.len() as u64 >= self.len;
+            let mut lexer = Lexer { bytes: &bytes, pos: 0, complete };
+            match parse(&mut lexer) {
+                Ok(value) => {
+                    let pos = lexer.pos;
+                    return Ok((value, bytes, pos));
+                }
+                Err(Parse::Incomplete) if !complete => size *= 2,
+                Err(Parse::Incomplete) => return Err(format!("invalid pdf: truncated at {offset}")),
+                Err(Parse::Invalid(details)) => return Err(format!("invalid pdf at {offset}: {details}")),
+            }
+        }
+    }
+
+    /// One cross-reference section : whether it is a stream, its
+    /// entries and its trailer.
+    fn read_section(&self, offset: u64) -> Result<(bool, BTreeMap<u32, Slot>, Dictionary), String> {
+        let (table, _, _) = self.parse_at(offset, |lexer| {
+            lexer.skip_space();
+            match lexer.peek() {
+                Some(_) => Ok(lexer.rest().starts_with(b"xref")),
+                None => Err(lexer.end()),
+            }
+        })?;
+        if table {
+            let ((entries, trailer), _, _) = self.parse_at(offset, |lexer| lexer.xref_table())?;
+            return Ok((false, entries, trailer));
+        }
+        let (_, object) = self.read_indirect(offset, 0)?;
+        let Object::Stream(stream) = object else {
+            return Err(format!("invalid pdf: no cross-reference section at {offset}"));
+        };
+        Ok((true, xref_stream_entries(&stream)?, stream.dict))
+    }
+
+    fn object(&self, id: ObjectId, depth: usize) -> Result<Object, String> {
+        if let Some(object) = self.objects.borrow().get(&id.0) {
+            return Ok(object.clone());
+        }
+        let object = match self.xref.get(&id.0) {
+            Some(Slot::Offset(offset)) => self.read_indirect(*offset, depth)?.1,
+            Some(Slot::Compressed { container, .. }) => self.compressed(id, *container, depth)?,
+            None => return Err(format!("object {} not found", id.0)),
+        };
+        self.objects.borrow_mut().insert(id.0, object.clone());
+        Ok(object)
+    }
+
+    fn compressed(&self, id: ObjectId, container: u32, depth: usize) -> Result<Object, String> {
+        if !self.object_streams.borrow().contains_key(&container) {
+            let Object::Stream(mut stream) = self.object((container, 0), depth + 1)? else {
+                return Err(format!("object {container}: object stream expected"));
+            };
+            let objects = ObjectStream::new(&mut stream)
+                .map_err(|e| format!("object stream {container}: {e}"))?
+                .objects;
+            self.object_streams.borrow_mut().insert(container, objects);
+        }
+        self.object_streams.borrow()[&container]
+            .get(&(id.0, 0))
+            .cloned()
+            .ok_or_else(|| format!("object {} not found in object stream {container}", id.0))
+    }
+
+    /// The object written at `offset`, with its stream content if any.
+    fn read_indirect(&self, offset: u64, depth: usize) -> Result<(ObjectId, Object), String> {
+        if depth > MAX_DEPTH {
+            return Err("invalid pdf: stream lengths nest too deep".into());
+        }
+        let ((id, object, data), bytes, _) = self.parse_at(offset, |lexer| lexer.indirect_object())?;
+        let Some(data) = data else {
+            return Ok((id, object));
+        };
+        let Object::Dictionary(dict) = object else {
+            return Err(format!("object {}: stream without a dictionary", id.0));
+        };
+        let length = match dict.get(b"Length") {
+            Ok(Object::Integer(length)) => Some(*length),
+            Ok(Object::Reference(length_id)) => self.object(*length_id, depth + 1)?.as_i64().ok(),
+            _ => None,
+        };
+        let start = offset + data as u64;
+        let content = match length.filter(|length| *length >= 0 && start + *length as u64 <= self.len) {
+            Some(length) if data + length as usize <= bytes.len() => bytes[data..data + length as usize].to_vec(),
+            Some(length) => self.read_at(start, length as usize)?,
+            // A missing or wrong `/Length` : the content ends before
+            // `endstream`, as `lopdf` reads it.
+            None => {
+                let (end, bytes, _) = self.parse_at(start, |lexer| lexer.find(b"endstream"))?;
+                let mut content = bytes[..end].to_vec();
+                if content.ends_with(b"\n") {
+                    content.pop();
+                }
+                if content.ends_with(b"\r") {
+                    content.pop();
+                }
+                content
+            }
+        };
+        Ok((id, Object::Stream(Stream::new(dict, content))))
+    }
+}
+
+fn offset_value(value: i64) -> Result<u64, String> {
+    u64::try_from(value).map_err(|_| format!("invalid pdf: offset {value}"))
+}
+
+/// Entries of a cross-reference stream (ISO 32000-1 §7.5.8).
+fn xref_stream_entries(stream: &Stream) -> Result<BTreeMap<u32, Slot>, String> {
+    let integers = |key: &[u8]| -> Option<Vec<i64>> {
+        match stream.dict.get(key).ok()? {
+            Object::Array(items) => items.iter().map(|item| item.as_i64().ok()).collect(),
+            _ => None,
+        }
+    };
+    let widths: Vec<usize> = integers(b"W")
+        .filter(|widths| widths.len() == 3 && widths.iter().all(|width| (0..=8).contains(width)))
+        .ok_or("invalid pdf: cross-reference stream without /W")?
+        .into_iter()
+        .map(|width| width as usize)
+        .collect();
+    let size = stream.dict.get(b"Size").and_then(Object::as_i64).map_err(|_| "invalid pdf: /Size missing")?;
+    let index = integers(b"Index").unwrap_or_else(|| vec![0, size]);
+    let data = if stream.dict.has(b"Filter") {
+        stream.decompressed_content().map_err(|e| format!("cross-reference stream: {e}"))?
+    } else {
+        stream.content.clone()
+    };
+
+    let row = widths.iter().sum::<usize>();
+    let mut rows = data.chunks_exact(row.max(1));
+    let mut entries = BTreeMap::new();
+    for pair in index.chunks_exact(2) {
+        for id in pair[0]..pair[0] + pair[1] {
+            let Some(bytes) = rows.next() else {
+                return Ok(entries);
+            };
+            let mut fields = [0u64; 3];
+            let mut at = 0;
+            for (field, width) in fields.iter_mut().zip(&widths) {
+                *field = bytes[at..at + width].iter().fold(0, |value, byte| value << 8 | u64::from(*byte));
+                at += width;
+            }
+            // A type field of width 0 defaults to 1.
+            let kind = if widths[0] == 0 { 1 } else { fields[0] };
+            let slot = match kind {
+                1 => Slot::Offset(fields[1]),
+                2 => Slot::Compressed { container: fields[1] as u32, index: fields[2] as u32 },
+                _ => continue,
+            };
+            entries.entry(id as u32).or_insert(slot);
+        }
+    }
+    Ok(entries)
+}
+
+enum Parse {
+    /// Ran past the end of the bytes read so far.
+    Incomplete,
+    Invalid(String),
+}
+
+type Parsed<T> = Result<T, Parse>;
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn is_regular(byte: u8) -> bool {
+    !is_space(byte) && !is_delimiter(byte)
+}
+
+/// PDF syntax over a window of the file. `complete` tells whether the
+/// window reaches the end of the file, i.e. whether running out of
+/// bytes is an error or a reason to read more.
+struct Lexer<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+    complete: bool,
+}
+
+impl<'b> Lexer<'b> {
+    fn end(&self) -> Parse {
+        if self.complete {
+            Parse::Invalid("unexpected end of file".into())
+        } else {
+            Parse::Incomplete
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Parsed<u8> {
+        let byte = self.peek().ok_or_else(|| self.end())?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn rest(&self) -> &'b [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(byte) = self.peek() {
+            if is_space(byte) {
+                self.pos += 1;
+            } else if byte == b'%' {
+                while self.peek().is_some_and(|byte| byte != b'\r' && byte != b'\n') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Offset of the next `needle`.
+    fn find(&mut self, needle: &[u8]) -> Parsed<usize> {
+        match self.rest().windows(needle.len()).position(|window| window == needle) {
+            Some(at) => Ok(self.pos + at),
+            None => Err(self.end()),
+        }
+    }
+
+    /// The run of regular characters at the cursor.
+    fn token(&mut self) -> Parsed<&'b [u8]> {
+        self.skip_space();
+        let start = self.pos;
+        while self.peek().is_some_and(is_regular) {
+            self.pos += 1;
+        }
+        if self.peek().is_none() && !self.complete {
+            return Err(Parse::Incomplete);
+        }
+        if start == self.pos {
+            return Err(Parse::Invalid(format!("token expected at {start}")));
+        }
+        Ok(&self.bytes[start..self.pos])
+    }
+
+    fn keyword(&mut self, word: &[u8]) -> Parsed<()> {
+        let token = self.token()?;
+        if token != word {
+            return Err(Parse::Invalid(format!("{} expected", String::from_utf8_lossy(word))));
+        }
+        Ok(())
+    }
+
+    fn integer(&mut self) -> Parsed<i64> {
+        let token = self.token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| Parse::Invalid(format!("integer expected, found {}", String::from_utf8_lossy(token))))
+    }
+
+    fn object(&mut self) -> Parsed<Object> {
+        self.skip_space();
+        match self.peek().ok_or_else(|| self.end())? {
+            b'/' => self.name().map(Object::Name),
+            b'(' => self.literal_string(),
+            b'<' => {
+                if self.bytes.get(self.pos + 1).ok_or_else(|| self.end())? == &b'<' {
+                    self.dictionary().map(Object::Dictionary)
+                } else {
+                    self.hex_string()
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_space();
+                    if self.peek().ok_or_else(|| self.end())? == b']' {
+                        self.pos += 1;
+                        return Ok(Object::Array(items));
+                    }
+                    items.push(self.object()?);
+                }
+            }
+            _ => {
+                let token = self.token()?;
+                match token {
+                    b"true" => Ok(Object::Boolean(true)),
+                    b"false" => Ok(Object::Boolean(false)),
+                    b"null" => Ok(Object::Null),
+                    _ => self.number(token),
+                }
+            }
+        }
+    }
+
+    fn number(&mut self, token: &[u8]) -> Parsed<Object> {
+        let invalid = || Parse::Invalid(format!("unexpected {}", String::from_utf8_lossy(token)));
+        let text = std::str::from_utf8(token).map_err(|_| invalid())?;
+        if text.contains('.') {
+            return text.parse().map(Object::Real).map_err(|_| invalid());
+        }
+        let value: i64 = text.parse().map_err(|_| invalid())?;
+        // `n g R` : look ahead for the generation and the operator.
+        let save = self.pos;
+        let reference = (|| -> Parsed<Option<u16>> {
+            let generation = match self.integer() {
+                Ok(generation) => generation,
+                Err(Parse::Incomplete) => return Err(Parse::Incomplete),
+                Err(Parse::Invalid(_)) => return Ok(None),
+            };
+            Ok((self.token()? == b"R").then_some(generation as u16))
+        })();
+        match reference {
+            Ok(Some(generation)) if value >= 0 => Ok(Object::Reference((value as u32, generation))),
+            Err(Parse::Incomplete) => Err(Parse::Incomplete),
+            _ => {
+                self.pos = save;
+                Ok(Object::Integer(value))
+            }
+        }
+    }
+
+    fn name(&mut self) -> Parsed<Vec<u8>> {
+        self.pos += 1;
+        let mut name = Vec::new();
+        while let Some(byte) = self.peek().filter(|byte| is_regular(*byte)) {
+            self.pos += 1;
+            if byte == b'#' {
+                let digits = self.bytes.get(self.pos..self.pos + 2).ok_or_else(|| self.end())?;
+                let decoded = std::str::from_utf8(digits).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(decoded) = decoded {
+                    name.push(decoded);
+                    self.pos += 2;
+                    continue;
+                }
+            }
+            name.push(byte);
+        }
+        if self.peek().is_none() && !self.complete {
+            return Err(Parse::Incomplete);
+        }
+        Ok(name)
+    }
+
+    fn literal_string(&mut self) -> Parsed<Object> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        let mut depth = 1;
+        loop {
+            match self.next()? {
+                b'\\' => match self.next()? {
+                    b'n' => out.push(b'\n'),
+                    b'r' => out.push(b'\r'),
+                    b't' => out.push(b'\t'),
+                    b'b' => out.push(8),
+                    b'f' => out.push(12),
+                    // A backslash before an end of line continues the
+                    // string on the next one.
+                    b'\r' => {
+                        if self.peek() == Some(b'\n') {
+                            self.pos += 1;
+                        }
+                    }
+                    b'\n' => {}
+                    digit @ b'0'..=b'7' => {
+                        let mut value = u32::from(digit - b'0');
+                        for _ in 0..2 {
+                            match self.peek() {
+                                Some(digit @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(digit - b'0');
+                                    self.pos += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(value as u8);
+                    }
+                    other => out.push(other),
+                },
+                b'(' => {
+                    depth += 1;
+                    out.push(b'(');
+                }
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Object::String(out, StringFormat::Literal));
+                    }
+                    out.push(b')');
+                }
+                byte => out.push(byte),
+            }
+        }
+    }
+
+    fn hex_string(&mut self) -> Parsed<Object> {
+        self.pos += 1;
+        let mut digits = Vec::new();
+        loop {
+            match self.next()? {
+                b'>' => break,
+                byte if is_space(byte) => {}
+                byte => digits.push(
+                    (byte as char)
+                        .to_digit(16)
+                        .ok_or_else(|| Parse::Invalid(format!("invalid hex digit {}", byte as char)))?
+                        as u8,
+                ),
+            }
+        }
+        let bytes = digits.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)).collect();
+        Ok(Object::String(bytes, StringFormat::Hexadecimal))
+    }
+
+    fn dictionary(&mut self) -> Parsed<Dictionary> {
+        self.pos += 2;
+        let mut dict = Dictionary::new();
+        loop {
+            self.skip_space();
+            match self.peek().ok_or_else(|| self.end())? {
+                b'>' => {
+                    if self.bytes.get(self.pos + 1).ok_or_else(|| self.end())? != &b'>' {
+                        return Err(Parse::Invalid("unbalanced dictionary".into()));
+                    }
+                    self.pos += 2;
+                    return Ok(dict);
+                }
+                b'/' => {
+                    let key = self.name()?;
+                    let value = self.object()?;
+                    dict.set(key, value);
+                }
+                _ => return Err(Parse::Invalid("dictionary key expected".into())),
+            }
+        }
+    }
+
+    /// `n g obj <object>`, followed by `stream` and its end of line for
+    /// a stream : the offset of its content is returned with it.
+    fn indirect_object(&mut self) -> Parsed<(ObjectId, Object, Option<usize>)> {
+        let id = self.integer()?;
+        let generation = self.integer()?;
+        self.keyword(b"obj")?;
+        let object = self.object()?;
+        let id = (id as u32, generation as u16);
+        let save = self.pos;
+        match self.token() {
+            Ok(b"stream") => {
+                if self.peek() == Some(b'\r') {
+                    self.pos += 1;
+                }
+                match self.peek() {
+                    Some(b'\n') => self.pos += 1,
+                    None if !self.complete => return Err(Parse::Incomplete),
+                    _ => {}
+                }
+                Ok((id, object, Some(self.pos)))
+            }
+            Err(Parse::Incomplete) => Err(Parse::Incomplete),
+            _ => {
+                self.pos = save;
+                Ok((id, object, None))
+            }
+        }
+    }
+
+    /// A `xref` table and the trailer after it.
+    fn xref_table(&mut self) -> Parsed<(BTreeMap<u32, Slot>, Dictionary)> {
+        self.keyword(b"xref")?;
+        let mut entries = BTreeMap::new();
+        loop {
+            let token = self.token()?;
+            if token == b"trailer" {
+                return match self.object()? {
+                    Object::Dictionary(trailer) => Ok((entries, trailer)),
+                    _ => Err(Parse::Invalid("trailer dictionary expected".into())),
+                };
+            }
+            self.pos -= token.len();
+            let start = self.integer()?;
+            let count = self.integer()?;
+            for id in start..start + count {
+                let offset = self.integer()?;
+                self.integer()?;
+                match self.token()? {
+                    b"n" => {
+                        entries.entry(id as u32).or_insert(Slot::Offset(offset as u64));
+                    }
+                    b"f" => {}
+                    _ => return Err(Parse::Invalid("xref entry type expected".into())),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdfio::tests::sample_pdf;
+    use std::io::Cursor;
+
+    /// Every object of `bytes` as `lopdf` reads them.
+    fn eager(bytes: &[u8]) -> lopdf::Document {
+        lopdf::Document::load_mem(bytes).unwrap()
+    }
+
+    /// `object` without what only says where `lopdf` read it from.
+    fn comparable(object: Object) -> Object {
+        match object {
+            Object::Stream(stream) => {
+                let content = Object::String(stream.content, StringFormat::Literal);
+                Object::Array(vec![Object::Dictionary(stream.dict), content])
+            }
+            other => other,
+        }
+    }
+
+    #[test]
+    fn objects_match_a_full_parse() {
+        let streamed = sample_pdf(3, "Bonjour (tout) le monde");
+        let mut doc = eager(&streamed);
+        doc.reference_table.cross_reference_type = lopdf::xref::XrefType::CrossReferenceTable;
+        let mut tabled = Vec::new();
+        doc.save_to(&mut tabled).unwrap();
+
+        for (bytes, xref_stream) in [(streamed, true), (tabled, false)] {
+            let doc = eager(&bytes);
+            let lazy = LazyPdf::new(Cursor::new(&bytes[..])).unwrap();
+            assert_eq!(lazy.trailer().get(b"Root").unwrap(), doc.trailer.get(b"Root").unwrap());
+            assert_eq!(lazy.max_id(), doc.max_id);
+            assert_eq!(lazy.uses_xref_stream(), xref_stream);
+            for (id, object) in &doc.objects {
+                assert_eq!(comparable(lazy.get_object(*id).unwrap()), comparable(object.clone()), "object {id:?}");
+            }
+        }
+    }
+
+    /// A document whose page lives in an object stream, indexed by a
+    /// cross-reference stream with a `/Length` given by reference.
+    fn compressed_pdf() -> Vec<u8> {
+        let mut out = b"%PDF-1.7\n".to_vec();
+        let mut offsets = BTreeMap::new();
+        let mut object = |out: &mut Vec<u8>, id: u32, body: &[u8]| {
+            offsets.insert(id, out.len());
+            out.extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        };
+        object(&mut out, 1, b"<< /Type /Catalog /Pages 2 0 R >>");
+        let pages = &b"<< /Type /Pages /Kids [3 0 R] /Count 1 >> "[..];
+        let page = &b"<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Contents 6 0 R /Note (a\\051b) >>"[..];
+        let members = format!("2 0 3 {} ", pages.len()).into_bytes();
+        let content = [&members[..], pages, page].concat();
+        let header = format!("<< /Type /ObjStm /N 2 /First {} /Length 5 0 R >>\nstream\n", members.len());
+        let mut stream = header.into_bytes();
+        stream.extend_from_slice(&content);
+        stream.extend_from_slice(b"\nendstream");
+        object(&mut out, 4, &stream);
+        object(&mut out, 5, content.len().to_string().as_bytes());
+        object(&mut out, 6, b"<< /Length 3 >>\nstream\nq Q\nendstream");
+
+        let xref_offset = out.len();
+        let mut rows = Vec::new();
+        let mut row = |kind: u8, field: usize, index: u16| {
+            rows.push(kind);
+            rows.extend_from_slice(&(field as u32).to_be_bytes());
+            rows.extend_from_slice(&index.to_be_bytes());
+        };
+        row(0, 0, 0xffff);
+        row(1, offsets[&1], 0);
+        row(2, 4, 0);
+        row(2, 4, 1);
+        row(1, offsets[&4], 0);
+        row(1, offsets[&5], 0);
+        row(1, offsets[&6], 0);
+        row(1, xref_offset, 0);
+        out.extend_from_slice(
+            format!("7 0 obj\n<< /Type /XRef /Size 8 /W [1 4 2] /Root 1 0 R /Length {} >>\nstream\n", rows.len())
+                .as_bytes(),
+        );
+        out.extend_from_slice(&rows);
+        out.extend_from_slice(format!("\nendstream\nendobj\nstartxref\n{xref_offset}\n%%EOF\n").as_bytes());
+        out
+    }
+
+    #[test]
+    fn object_and_cross_reference_streams_are_followed() {
+        let bytes = compressed_pdf();
+        let doc = eager(&bytes);
+        let lazy = LazyPdf::new(Cursor::new(&bytes[..])).unwrap();
+        assert!(lazy.uses_xref_stream());
+        assert_eq!(lazy.max_id(), doc.max_id);
+        let page = lazy.get_object((3, 0)).unwrap();
+        assert_eq!(page, doc.objects[&(3, 0)]);
+        assert_eq!(page.as_dict().unwrap().get(b"Note").unwrap().as_str().unwrap(), b"a)b");
+        assert_eq!(lazy.get_object((6, 0)).unwrap().as_stream().unwrap().content, b"q Q");
+    }
+
+    #[test]
+    fn newest_section_wins_after_an_incremental_update() {
+        let original = sample_pdf(1, "Avant");
+        let mut update = crate::pdfio::IncrementalUpdate::new(&original).unwrap();
+        let catalog = update.trailer().get(b"Root").unwrap().as_reference().unwrap();
+        let mut dict = update.get_dictionary(catalog).unwrap();
+        dict.set("Lang", Object::string_literal("fr"));
+        update.set(catalog, Object::Dictionary(dict));
+        let bytes = update.to_bytes().unwrap();
+
+        let lazy = LazyPdf::new(Cursor::new(&bytes[..])).unwrap();
+        let catalog = lazy.get_object(catalog).unwrap();
+        assert_eq!(catalog.as_dict().unwrap().get(b"Lang").unwrap().as_str().unwrap(), b"fr");
+        assert!(lazy.startxref() > original.len() as u64);
+    }
+
+    #[test]
+    fn truncated_files_are_refused() {
+        let bytes = sample_pdf(1, "Coupé");
+        assert!(LazyPdf::new(Cursor::new(&bytes[..bytes.len() / 2])).is_err());
+        assert!(LazyPdf::new(Cursor::new(&b"pas un pdf"[..])).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+//! Recently opened and exported PDFs (`recent.json` in `app_data_dir`),
+//! surfaced in File > Open Recent.
+//!
+//! Only paths Rust can vouch for are recorded : files opened through
+//! [`crate::scope`], exports whose target came from the native save
+//! dialog or was chosen here. A path the renderer sends is never added,
+//! so the menu cannot be made to offer a file the user never handled.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::error::Result;
+use crate::store;
+
+const MAX_RECENT: usize = 10;
+/// Menu ids of recent entries are this prefix followed by the path.
+pub const MENU_PREFIX: &str = "recent:";
+pub const MENU_CLEAR: &str = "clear_recent";
+
+/// Serializes read-modify-write cycles on `recent.json` : opening and
+/// exporting can run concurrently.
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentFile {
+    pub path: String,
+    /// Unix seconds of the last open or export.
+    pub last_used: u64,
+}
+
+fn recent_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+    Ok(crate::app_data_dir(app)?.join("recent.json"))
+}
+
+/// Move `path` to the front, dropping duplicates and the oldest
+/// entries past the cap.
+fn push(mut entries: Vec<RecentFile>, path: &str, now: u64) -> Vec<RecentFile> {
+    entries.retain(|entry| entry.path != path);
+    entries.insert(0, RecentFile { path: path.to_string(), last_used: now });
+    entries.truncate(MAX_RECENT);
+    entries
+}
+
+fn prune(entries: &mut Vec<RecentFile>) -> bool {
+    let before = entries.len();
+    entries.retain(|entry| Path::new(&entry.path).exists());
+    entries.len() != before
+}
+
+/// The list with vanished files pruned (and the pruning persisted).
+pub fn load(app: &tauri::AppHandle) -> Result<Vec<RecentFile>> {
+    let _guard = LOCK.lock().unwrap();
+    let path = recent_path(app)?;
+    let mut entries: Vec<RecentFile> = store::load_json(&path)?.unwrap_or_default();
+    if prune(&mut entries) {
+        store::save_json(&path, &entries)?;
+    }
+    Ok(entries)
+}
+
+fn changed(app: &tauri::AppHandle, entries: &[RecentFile]) {
+    if let Err(err) = crate::build_app_menu(app, entries).and_then(|menu| app.set_menu(menu).map(|_| ())) {
+        eprintln!("rebuild menu failed: {err}");
+    }
+    if let Err(err) = app.emit("recent-files", entries) {
+        eprintln!("emit recent-files failed: {err}");
+    }
+}
+
+/// Record an opened or exported file. Best effort : a failure is
+/// logged and never fails the operation that triggered it.
+pub fn record(app: &tauri::AppHandle, path: &Path) {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let Some(path) = path.to_str() else {
+        return;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let result = (|| {
+        let _guard = LOCK.lock().unwrap();
+        let store_path = recent_path(app)?;
+        let entries: Vec<RecentFile> = store::load_json(&store_path)?.unwrap_or_default();
+        let entries = push(entries, path, now);
+        store::save_json(&store_path, &entries)?;
+        Ok::<_, crate::error::Error>(entries)
+    })();
+    match result {
+        Ok(entries) => changed(app, &entries),
+        Err(err) => eprintln!("record recent file failed: {err}"),
+    }
+}
+
+#[tauri::command]
+pub fn list_recent_files(app: tauri::AppHandle) -> Result<Vec<RecentFile>> {
+    load(&app)
+}
+
+#[tauri::command]
+pub fn clear_recent_files(app: tauri::AppHandle) -> Result<()> {
+    {
+        let _guard = LOCK.lock().unwrap();
+        store::save_json(&recent_path(&app)?, &Vec::<RecentFile>::new())?;
+    }
+    changed(&app, &[]);
+    Ok(())
+}
+
+/// Label for a menu entry : the file name, with `&` doubled so Windows
+/// does not read it as a mnemonic.
+pub fn menu_label(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+        .replace('&', "&&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, last_used: u64) -> RecentFile {
+        RecentFile { path: path.into(), last_used }
+    }
+
+    #[test]
+    fn push_deduplicates_and_caps() {
+        let entries: Vec<RecentFile> = (0..MAX_RECENT as u64).map(|idx| entry(&format!("/f{idx}.pdf"), idx)).collect();
+        let entries = push(entries, "/f3.pdf", 100);
+        assert_eq!(entries.len(), MAX_RECENT);
+        assert_eq!(entries[0], entry("/f3.pdf", 100));
+        assert_eq!(entries.iter().filter(|e| e.path == "/f3.pdf").count(), 1);
+
+        let entries = push(entries, "/new.pdf", 101);
+        assert_eq!(entries.len(), MAX_RECENT);
+        assert_eq!(entries[0].path, "/new.pdf");
+        assert!(!entries.iter().any(|e| e.path == "/f9.pdf"));
+    }
+
+    #[test]
+    fn prune_drops_missing_files() {
+        let existing = std::env::temp_dir().join(format!("cerfini-recent-{}.pdf", std::process::id()));
+        std::fs::write(&existing, b"%PDF-").unwrap();
+        let mut entries = vec![entry(existing.to_str().unwrap(), 1), entry("/definitely/missing.pdf", 2)];
+        assert!(prune(&mut entries));
+        assert_eq!(entries.len(), 1);
+        assert!(!prune(&mut entries));
+        let _ = std::fs::remove_file(&existing);
+    }
+
+    #[test]
+    fn menu_label_escapes_mnemonics() {
+        assert_eq!(menu_label("/docs/R&D plan.pdf"), "R&&D plan.pdf");
+    }
+}
@@ -0,0 +1,901 @@
+//! Irreversible redaction : the text and image pixels under the given
+//! boxes are taken out of the page content, not merely covered, and an
+//! opaque box is painted in their place. Annotations over the boxes go
+//! too, and the removed text is scrubbed from the strings of the rest
+//! of the document (document info, XMP metadata, bookmarks, form
+//! values, alternate texts of tagged PDFs…).
+//!
+//! Like page edits, the result is a rewritten document : an incremental
+//! update would leave the original content in the file. Existing
+//! digital signatures do not survive.
+
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::pages::PdfSource;
+use crate::pdfio::{real, PdfRect};
+use crate::text::{
+    self, dictionary, numbers, page_content, page_resources, resolved, stream_content, Font, Fonts, GraphicsState,
+    Matrix, Segment, TextCursor, IDENTITY,
+};
+
+/// Bound on nested form XObjects and on `/Parent` and `/Kids` chains.
+const MAX_DEPTH: usize = 16;
+/// Removed text shorter than this (in characters) is not scrubbed from
+/// the rest of the document : short fragments match everywhere.
+const MIN_NEEDLE: usize = 4;
+/// Images larger than this are dropped rather than decoded.
+const MAX_PIXELS: usize = 1 << 26;
+
+/// A box to redact, in the user space of `page` (1-indexed).
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Redaction {
+    pub page: u32,
+    pub rect: PdfRect,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Redacted {
+    pub bytes: Vec<u8>,
+    /// Glyphs taken out of the text.
+    pub glyphs: usize,
+    /// Images scrubbed or, when their encoding cannot be rewritten,
+    /// removed.
+    pub images: usize,
+    pub annotations: usize,
+}
+
+fn encode(segments: Vec<Segment>) -> std::result::Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Ops(operations) => out.extend(Content { operations }.encode().map_err(|e| e.to_string())?),
+            Segment::Inline(raw) => out.extend(raw),
+        }
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+/// `[llx lly urx ury]` as a rectangle.
+fn rect_of(doc: &Document, object: &Object) -> Option<PdfRect> {
+    match numbers(doc, object)[..] {
+        [x0, y0, x1, y1] => Some(text::normalized(PdfRect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })),
+        _ => None,
+    }
+}
+
+/// What became of a `Do`.
+enum Placed {
+    Kept,
+    Dropped,
+    /// Now draws a scrubbed copy, under a new resource name.
+    Replaced(Vec<u8>, ObjectId),
+}
+
+/// A content stream after redaction.
+struct Rewrite {
+    segments: Vec<Segment>,
+    changed: bool,
+    /// XObjects added for the rewritten `Do` operators.
+    xobjects: Vec<(Vec<u8>, ObjectId)>,
+    /// Names no `Do` draws any more, and those still drawn ; the
+    /// former leave the resources so the originals get pruned.
+    retired: HashSet<Vec<u8>>,
+    drawn: HashSet<Vec<u8>>,
+}
+
+impl Rewrite {
+    /// `resources` as the rewritten content needs them.
+    fn resources(&self, doc: &Document, resources: Option<&Dictionary>) -> Dictionary {
+        let mut resources = resources.cloned().unwrap_or_default();
+        if self.xobjects.is_empty() && self.retired.is_empty() {
+            return resources;
+        }
+        let mut xobjects = dictionary(doc, &resources, b"XObject").cloned().unwrap_or_default();
+        for name in self.retired.difference(&self.drawn) {
+            xobjects.remove(name);
+        }
+        for (name, id) in &self.xobjects {
+            xobjects.set(name.clone(), *id);
+        }
+        resources.set("XObject", xobjects);
+        resources
+    }
+}
+
+struct Redactor<'a> {
+    doc: &'a Document,
+    next_id: u32,
+    added: Vec<(ObjectId, Object)>,
+    /// Runs of consecutive removed glyphs, as text.
+    removed: Vec<String>,
+    glyphs: usize,
+    images: usize,
+}
+
+impl<'a> Redactor<'a> {
+    fn add(&mut self, object: Object) -> ObjectId {
+        let id = (self.next_id, 0);
+        self.next_id += 1;
+        self.added.push((id, object));
+        id
+    }
+
+    fn replacement(&mut self, object: Object) -> Placed {
+        let id = self.add(object);
+        Placed::Replaced(format!("Rd{}", id.0).into_bytes(), id)
+    }
+
+    fn end_run(&mut self, run: &mut String) {
+        let run = std::mem::take(run);
+        if !run.trim().is_empty() {
+            self.removed.push(run);
+        }
+    }
+
+    fn content(
+        &mut self,
+        raw: &[u8],
+        resources: Option<&'a Dictionary>,
+        ctm: Matrix,
+        areas: &[PdfRect],
+        depth: usize,
+    ) -> std::result::Result<Rewrite, String> {
+        let mut fonts = Fonts::new(self.doc, resources);
+        let mut state = GraphicsState::new(ctm);
+        let mut saved = Vec::new();
+        let mut cursor = TextCursor::default();
+        let mut rewrite = Rewrite {
+            segments: Vec::new(),
+            changed: false,
+            xobjects: Vec::new(),
+            retired: HashSet::new(),
+            drawn: HashSet::new(),
+        };
+        for segment in text::segments(raw)? {
+            let ops = match segment {
+                Segment::Ops(ops) => ops,
+                Segment::Inline(raw) => {
+                    let placed = text::bounds(&state.ctm, 0.0, 0.0, 1.0, 1.0);
+                    if areas.iter().any(|area| text::overlaps(&placed, area)) {
+                        self.images += 1;
+                        rewrite.changed = true;
+                    } else {
+                        rewrite.segments.push(Segment::Inline(raw));
+                    }
+                    continue;
+                }
+            };
+            let mut kept = Vec::with_capacity(ops.len());
+            for op in ops {
+                state.apply(&op);
+                cursor.apply(&op, &state);
+                match op.operator.as_str() {
+                    "q" => saved.push(state.clone()),
+                    "Q" => {
+                        if let Some(previous) = saved.pop() {
+                            state = previous;
+                        }
+                    }
+                    "Tj" | "TJ" | "'" | "\"" => {
+                        let font = fonts.get(state.font.as_deref());
+                        if let Some(replacement) = self.text(&op, &state, &mut cursor, font, areas) {
+                            rewrite.changed = true;
+                            kept.extend(replacement);
+                            continue;
+                        }
+                    }
+                    "Do" => {
+                        let name =
+                            op.operands.first().and_then(|name| name.as_name().ok()).unwrap_or_default().to_vec();
+                        match self.xobject(&name, &state, resources, areas, depth)? {
+                            Placed::Kept => {
+                                rewrite.drawn.insert(name);
+                            }
+                            Placed::Dropped => {
+                                rewrite.changed = true;
+                                rewrite.retired.insert(name);
+                                continue;
+                            }
+                            Placed::Replaced(new_name, id) => {
+                                rewrite.changed = true;
+                                rewrite.retired.insert(name);
+                                kept.push(Operation::new("Do", vec![Object::Name(new_name.clone())]));
+                                rewrite.xobjects.push((new_name, id));
+                                continue;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                kept.push(op);
+            }
+            rewrite.segments.push(Segment::Ops(kept));
+        }
+        Ok(rewrite)
+    }
+
+    /// The operators replacing the text operator `op` when some of its
+    /// glyphs fall in `areas` : a `TJ` where each removed glyph becomes
+    /// an adjustment of its advance, so the remaining ones stay put.
+    fn text(
+        &mut self,
+        op: &Operation,
+        state: &GraphicsState,
+        cursor: &mut TextCursor,
+        font: &Font,
+        areas: &[PdfRect],
+    ) -> Option<Vec<Operation>> {
+        let size = state.font_size;
+        let mut elements = Vec::new();
+        let mut run = String::new();
+        let mut hit = false;
+        for element in text::shown(op) {
+            if let Object::String(bytes, _) = element {
+                for glyph in cursor.show(state, font, bytes) {
+                    if areas.iter().any(|area| text::overlaps(&glyph.rect, area)) {
+                        hit = true;
+                        self.glyphs += 1;
+                        run.push_str(&glyph.text);
+                        if size.abs() > f64::EPSILON {
+                            push_adjustment(&mut elements, -glyph.advance * 1000.0 / size);
+                        }
+                    } else {
+                        self.end_run(&mut run);
+                        push_code(&mut elements, &glyph.code);
+                    }
+                }
+            } else if let Some(amount) = text::number(element) {
+                cursor.adjust(state, amount);
+                push_adjustment(&mut elements, amount);
+            }
+        }
+        self.end_run(&mut run);
+        if !hit {
+            return None;
+        }
+        let operand = |index: usize| op.operands.get(index).cloned().unwrap_or(Object::Integer(0));
+        let mut ops = match op.operator.as_str() {
+            "'" => vec![Operation::new("T*", vec![])],
+            "\"" => vec![
+                Operation::new("Tw", vec![operand(0)]),
+                Operation::new("Tc", vec![operand(1)]),
+                Operation::new("T*", vec![]),
+            ],
+            _ => Vec::new(),
+        };
+        ops.push(Operation::new("TJ", vec![Object::Array(elements)]));
+        Some(ops)
+    }
+
+    fn xobject(
+        &mut self,
+        name: &[u8],
+        state: &GraphicsState,
+        resources: Option<&'a Dictionary>,
+        areas: &[PdfRect],
+        depth: usize,
+    ) -> std::result::Result<Placed, String> {
+        let doc = self.doc;
+        let Some(stream) = text::xobject(doc, resources, name) else {
+            return Ok(Placed::Kept);
+        };
+        match stream.dict.get(b"Subtype").and_then(Object::as_name_str) {
+            Ok("Image") => {
+                let placed = text::bounds(&state.ctm, 0.0, 0.0, 1.0, 1.0);
+                let hits: Vec<PdfRect> = areas.iter().filter(|area| text::overlaps(&placed, area)).copied().collect();
+                if hits.is_empty() {
+                    return Ok(Placed::Kept);
+                }
+                self.images += 1;
+                Ok(match self.scrub_image(stream, &state.ctm, &hits) {
+                    Some(image) => self.replacement(Object::Stream(image)),
+                    None => Placed::Dropped,
+                })
+            }
+            Ok("Form") => {
+                let ctm = text::multiply(&text::form_matrix(doc, stream), &state.ctm);
+                let bbox = stream.dict.get(b"BBox").ok().and_then(|bbox| rect_of(doc, bbox));
+                if let Some(bbox) = bbox {
+                    let placed = text::bounds(&ctm, bbox.x, bbox.y, bbox.x + bbox.w, bbox.y + bbox.h);
+                    if !areas.iter().any(|area| text::overlaps(&placed, area)) {
+                        return Ok(Placed::Kept);
+                    }
+                }
+                if depth >= MAX_DEPTH {
+                    return Ok(Placed::Dropped);
+                }
+                let form_resources = dictionary(doc, &stream.dict, b"Resources").or(resources);
+                let inner = self.content(&stream_content(stream), form_resources, ctm, areas, depth + 1)?;
+                if !inner.changed {
+                    return Ok(Placed::Kept);
+                }
+                let mut dict = stream.dict.clone();
+                dict.remove(b"Filter");
+                dict.remove(b"DecodeParms");
+                dict.set("Resources", inner.resources(doc, form_resources));
+                let form = Stream::new(dict, encode(inner.segments)?);
+                Ok(self.replacement(Object::Stream(form)))
+            }
+            _ => Ok(Placed::Kept),
+        }
+    }
+
+    /// `image` with the pixels under `areas` zeroed, or `None` when its
+    /// encoding cannot be rewritten and it has to go entirely.
+    fn scrub_image(&mut self, image: &Stream, ctm: &Matrix, areas: &[PdfRect]) -> Option<Stream> {
+        let inverse = text::invert(ctm)?;
+        let doc = self.doc;
+        let dimension = |key: &[u8]| {
+            let value = image.dict.get(key).ok().map(|value| resolved(doc, value))?.as_i64().ok()?;
+            usize::try_from(value).ok().filter(|value| *value > 0)
+        };
+        let (width, height) = (dimension(b"Width")?, dimension(b"Height")?);
+        if width.checked_mul(height)? > MAX_PIXELS {
+            return None;
+        }
+        let (mut samples, components, bits) = self.samples(image, width, height)?;
+        let stride = (width * components * bits + 7) / 8;
+        if samples.len() < stride * height {
+            return None;
+        }
+        for area in areas {
+            // The image fills the unit square, its first row at the top.
+            let unit = text::bounds(&inverse, area.x, area.y, area.x + area.w, area.y + area.h);
+            let columns = span(unit.x, unit.x + unit.w, width);
+            for row in span(1.0 - (unit.y + unit.h), 1.0 - unit.y, height) {
+                let line = &mut samples[row * stride..(row + 1) * stride];
+                clear_bits(line, columns.start * components * bits, columns.end * components * bits);
+            }
+        }
+        let mut dict = image.dict.clone();
+        dict.remove(b"Filter");
+        dict.remove(b"DecodeParms");
+        dict.set("BitsPerComponent", bits as i64);
+        if let Ok(mask) = image.dict.get(b"SMask").and_then(Object::as_reference) {
+            let mask = doc.get_object(mask).and_then(Object::as_stream).ok()?;
+            let mask = self.scrub_image(mask, ctm, areas)?;
+            dict.set("SMask", self.add(Object::Stream(mask)));
+        }
+        Some(Stream::new(dict, samples))
+    }
+
+    /// Decoded samples of `image`, with its components per pixel and
+    /// bits per component.
+    fn samples(&self, image: &Stream, width: usize, height: usize) -> Option<(Vec<u8>, usize, usize)> {
+        let dict = &image.dict;
+        let filters: Vec<&str> = match dict.get(b"Filter").map(|filter| resolved(self.doc, filter)) {
+            Ok(Object::Name(name)) => vec![std::str::from_utf8(name).ok()?],
+            Ok(Object::Array(names)) => names.iter().filter_map(|name| name.as_name_str().ok()).collect(),
+            _ => Vec::new(),
+        };
+        if filters.iter().any(|filter| matches!(*filter, "DCTDecode" | "DCT")) {
+            if filters.len() != 1 {
+                return None;
+            }
+            let mut decoder = jpeg_decoder::Decoder::new(image.content.as_slice());
+            let pixels = decoder.decode().ok()?;
+            let info = decoder.info()?;
+            let components = match info.pixel_format {
+                jpeg_decoder::PixelFormat::L8 => 1,
+                jpeg_decoder::PixelFormat::RGB24 => 3,
+                // Adobe CMYK JPEGs are often stored inverted ; not worth
+                // the guesswork, the image is dropped instead.
+                _ => return None,
+            };
+            if usize::from(info.width) != width || usize::from(info.height) != height {
+                return None;
+            }
+            return Some((pixels, components, 8));
+        }
+        let samples = if filters.is_empty() {
+            image.content.clone()
+        } else {
+            // lopdf refuses to decode image streams ; they only differ
+            // from the others by their subtype.
+            let mut plain = image.clone();
+            plain.dict.remove(b"Subtype");
+            plain.decompressed_content().ok()?
+        };
+        if dict.get(b"ImageMask").and_then(Object::as_bool).unwrap_or(false) {
+            return Some((samples, 1, 1));
+        }
+        let components = self.components(dict.get(b"ColorSpace").ok()?)?;
+        let bits = dict.get(b"BitsPerComponent").ok().and_then(|bits| resolved(self.doc, bits).as_i64().ok())?;
+        matches!(bits, 1 | 2 | 4 | 8 | 16).then_some((samples, components, bits as usize))
+    }
+
+    fn components(&self, space: &Object) -> Option<usize> {
+        let family = |name: &[u8]| match name {
+            b"DeviceGray" | b"G" | b"CalGray" | b"Indexed" | b"I" | b"Separation" => Some(1),
+            b"DeviceRGB" | b"RGB" | b"CalRGB" | b"Lab" => Some(3),
+            b"DeviceCMYK" | b"CMYK" => Some(4),
+            _ => None,
+        };
+        match resolved(self.doc, space) {
+            Object::Name(name) => family(name),
+            Object::Array(parts) => match parts.first()?.as_name().ok()? {
+                b"ICCBased" => {
+                    let profile = resolved(self.doc, parts.get(1)?).as_stream().ok()?;
+                    usize::try_from(profile.dict.get(b"N").ok()?.as_i64().ok()?).ok()
+                }
+                b"DeviceN" => Some(resolved(self.doc, parts.get(1)?).as_array().ok()?.len()),
+                name => family(name),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Indices `0..count` covering `from..to` of the unit interval.
+fn span(from: f64, to: f64, count: usize) -> Range<usize> {
+    let start = (from.max(0.0) * count as f64).floor() as usize;
+    let end = (to.min(1.0) * count as f64).ceil().max(0.0) as usize;
+    start.min(count)..end.min(count)
+}
+
+fn clear_bits(line: &mut [u8], from: usize, to: usize) {
+    for bit in from..to {
+        line[bit / 8] &= !(0x80 >> (bit % 8));
+    }
+}
+
+fn push_code(elements: &mut Vec<Object>, code: &[u8]) {
+    match elements.last_mut() {
+        Some(Object::String(bytes, _)) => bytes.extend_from_slice(code),
+        _ => elements.push(Object::String(code.to_vec(), StringFormat::Literal)),
+    }
+}
+
+fn push_adjustment(elements: &mut Vec<Object>, amount: f64) {
+    match elements.last_mut() {
+        Some(last @ (Object::Integer(_) | Object::Real(_))) => *last = real(text::number(last).unwrap_or(0.0) + amount),
+        _ => elements.push(real(amount)),
+    }
+}
+
+/// The opaque boxes, drawn last so nothing paints over them.
+fn boxes(areas: &[PdfRect]) -> std::result::Result<Vec<u8>, String> {
+    let mut operations = vec![Operation::new("q", vec![]), Operation::new("g", vec![Object::Integer(0)])];
+    for area in areas {
+        operations.push(Operation::new("re", vec![real(area.x), real(area.y), real(area.w), real(area.h)]));
+    }
+    operations.push(Operation::new("f", vec![]));
+    operations.push(Operation::new("Q", vec![]));
+    Content { operations }.encode().map_err(|e| e.to_string())
+}
+
+/// Remove the annotations of `page` whose rectangle meets `areas`, with
+/// the pop-ups attached to them. Returns how many went and the objects
+/// among them.
+fn remove_annotations(doc: &mut Document, page: ObjectId, areas: &[PdfRect]) -> (usize, HashSet<ObjectId>) {
+    let annots = doc
+        .get_dictionary(page)
+        .and_then(|page| page.get(b"Annots"))
+        .ok()
+        .and_then(|annots| resolved(doc, annots).as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+    let mut doomed: Vec<bool> = annots
+        .iter()
+        .map(|annot| {
+            let rect = resolved(doc, annot).as_dict().ok().and_then(|annot| rect_of(doc, annot.get(b"Rect").ok()?));
+            rect.is_some_and(|rect| areas.iter().any(|area| text::overlaps(&rect, area)))
+        })
+        .collect();
+    let removed: HashSet<ObjectId> = annots
+        .iter()
+        .zip(&doomed)
+        .filter(|(_, doomed)| **doomed)
+        .filter_map(|(annot, _)| annot.as_reference().ok())
+        .collect();
+    for (annot, doomed) in annots.iter().zip(doomed.iter_mut()) {
+        let parent = resolved(doc, annot)
+            .as_dict()
+            .ok()
+            .and_then(|annot| annot.get(b"Parent").and_then(Object::as_reference).ok());
+        if parent.is_some_and(|parent| removed.contains(&parent)) {
+            *doomed = true;
+        }
+    }
+    let count = doomed.iter().filter(|doomed| **doomed).count();
+    if count == 0 {
+        return (0, HashSet::new());
+    }
+    let removed = annots
+        .iter()
+        .zip(&doomed)
+        .filter(|(_, doomed)| **doomed)
+        .filter_map(|(annot, _)| annot.as_reference().ok())
+        .collect();
+    let kept: Vec<Object> =
+        annots.into_iter().zip(doomed).filter(|(_, doomed)| !doomed).map(|(annot, _)| annot).collect();
+    if let Ok(page) = doc.get_dictionary_mut(page) {
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", kept);
+        }
+    }
+    (count, removed)
+}
+
+/// Take the removed widgets out of the form and clear the value of the
+/// fields they showed.
+fn drop_fields(doc: &mut Document, widgets: &HashSet<ObjectId>) {
+    if widgets.is_empty() {
+        return;
+    }
+    let parents: Vec<ObjectId> = widgets
+        .iter()
+        .filter_map(|&widget| doc.get_dictionary(widget).ok()?.get(b"Parent").ok()?.as_reference().ok())
+        .collect();
+    for parent in parents {
+        if let Ok(field) = doc.get_dictionary_mut(parent) {
+            field.remove(b"V");
+            field.remove(b"DV");
+        }
+    }
+    let Some(form) = doc.catalog().ok().and_then(|catalog| catalog.get(b"AcroForm").ok()).cloned() else {
+        return;
+    };
+    let fields = match &form {
+        Object::Reference(id) => doc.get_dictionary(*id).ok(),
+        Object::Dictionary(form) => Some(form),
+        _ => None,
+    }
+    .and_then(|form| form.get(b"Fields").ok())
+    .and_then(|fields| resolved(doc, fields).as_array().ok())
+    .cloned()
+    .unwrap_or_default();
+    let fields = prune_fields(doc, fields, widgets, 0);
+    let form = match form {
+        Object::Reference(id) => doc.get_dictionary_mut(id).ok(),
+        _ => doc
+            .catalog_mut()
+            .ok()
+            .and_then(|catalog| catalog.get_mut(b"AcroForm").ok())
+            .and_then(|form| form.as_dict_mut().ok()),
+    };
+    if let Some(form) = form {
+        form.set("Fields", fields);
+    }
+}
+
+/// `fields` without `gone`, nor the fields left without any kid.
+fn prune_fields(doc: &mut Document, fields: Vec<Object>, gone: &HashSet<ObjectId>, depth: usize) -> Vec<Object> {
+    let mut kept = Vec::new();
+    for field in fields {
+        let Ok(id) = field.as_reference() else {
+            kept.push(field);
+            continue;
+        };
+        if gone.contains(&id) {
+            continue;
+        }
+        let kids = doc
+            .get_dictionary(id)
+            .and_then(|field| field.get(b"Kids"))
+            .ok()
+            .and_then(|kids| resolved(doc, kids).as_array().ok())
+            .cloned();
+        if let (Some(kids), true) = (kids, depth < MAX_DEPTH) {
+            let count = kids.len();
+            let remaining = prune_fields(doc, kids, gone, depth + 1);
+            if remaining.is_empty() && count > 0 {
+                continue;
+            }
+            if remaining.len() != count {
+                if let Ok(field) = doc.get_dictionary_mut(id) {
+                    field.set("Kids", remaining);
+                }
+            }
+        }
+        kept.push(field);
+    }
+    kept
+}
+
+/// What to look for in the rest of the document : the removed runs and
+/// their words, longest first so a run goes before its words.
+fn needles(removed: &[String]) -> Vec<String> {
+    let long_enough = |text: &&str| text.chars().count() >= MIN_NEEDLE;
+    let mut needles: Vec<String> = removed
+        .iter()
+        .map(|run| run.trim())
+        .chain(removed.iter().flat_map(|run| run.split_whitespace()))
+        .filter(long_enough)
+        .map(str::to_owned)
+        .collect();
+    needles.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    needles.dedup();
+    needles
+}
+
+fn without(text: &str, needles: &[String]) -> String {
+    needles.iter().fold(text.to_owned(), |text, needle| text.replace(needle.as_str(), ""))
+}
+
+fn scrub(object: &mut Object, needles: &[String]) {
+    match object {
+        Object::String(..) => {
+            if let Ok(text) = lopdf::decode_text_string(object) {
+                let cleaned = without(&text, needles);
+                if cleaned != text {
+                    *object = lopdf::text_string(&cleaned);
+                }
+            }
+        }
+        Object::Array(items) => items.iter_mut().for_each(|item| scrub(item, needles)),
+        Object::Dictionary(dict) => scrub_dictionary(dict, needles),
+        Object::Stream(stream) => {
+            scrub_dictionary(&mut stream.dict, needles);
+            if stream.dict.type_is(b"Metadata") {
+                scrub_xmp(stream, needles);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn scrub_dictionary(dict: &mut Dictionary, needles: &[String]) {
+    // The /Contents of a signature is binary, and signed.
+    let signature = dict.has(b"ByteRange");
+    for (key, value) in dict.iter_mut() {
+        if !(signature && key == b"Contents") {
+            scrub(value, needles);
+        }
+    }
+}
+
+fn scrub_xmp(stream: &mut Stream, needles: &[String]) {
+    let Ok(xml) = String::from_utf8(stream_content(stream)) else {
+        return;
+    };
+    let escaped: Vec<String> =
+        needles.iter().map(|needle| needle.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")).collect();
+    let cleaned = without(&without(&xml, needles), &escaped);
+    if cleaned != xml {
+        stream.dict.remove(b"Filter");
+        stream.dict.remove(b"DecodeParms");
+        stream.set_content(cleaned.into_bytes());
+    }
+}
+
+pub fn redact(mut doc: Document, areas: &[Redaction]) -> Result<Redacted> {
+    if doc.is_encrypted() {
+        return Err(Error::new(ErrorKind::InvalidPdf, "pdf_encrypted"));
+    }
+    if areas.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "redaction_empty"));
+    }
+    let pages = doc.get_pages();
+    let mut by_page: BTreeMap<u32, Vec<PdfRect>> = BTreeMap::new();
+    for area in areas {
+        if !pages.contains_key(&area.page) {
+            return Err(Error::new(ErrorKind::InvalidInput, "page_out_of_range").with_details(format!(
+                "{} / {}",
+                area.page,
+                pages.len()
+            )));
+        }
+        let rect = text::normalized(area.rect);
+        if !(rect.w > 0.0 && rect.h > 0.0) {
+            return Err(Error::new(ErrorKind::InvalidInput, "redaction_area_empty").with_details(area.page));
+        }
+        by_page.entry(area.page).or_default().push(rect);
+    }
+    let failed = |details: String| Error::new(ErrorKind::InvalidPdf, "redaction_failed").with_details(details);
+
+    let mut redactor =
+        Redactor { doc: &doc, next_id: doc.max_id + 1, added: Vec::new(), removed: Vec::new(), glyphs: 0, images: 0 };
+    let mut rewritten = Vec::new();
+    for (page, rects) in by_page {
+        let page_id = pages[&page];
+        let resources = page_resources(&doc, page_id);
+        let rewrite = redactor.content(&page_content(&doc, page_id), resources, IDENTITY, &rects, 0).map_err(failed)?;
+        let resources = rewrite.resources(&doc, resources);
+        rewritten.push((page_id, rects, rewrite.segments, resources));
+    }
+    let Redactor { next_id, added, removed, glyphs, images, .. } = redactor;
+    doc.objects.extend(added);
+    doc.max_id = doc.max_id.max(next_id - 1);
+
+    let needles = needles(&removed);
+    let mut annotations = 0;
+    let mut widgets = HashSet::new();
+    for (page_id, rects, mut segments, resources) in rewritten {
+        // Marked content may repeat the text as /ActualText.
+        for segment in &mut segments {
+            if let Segment::Ops(ops) = segment {
+                for op in ops.iter_mut().filter(|op| matches!(op.operator.as_str(), "BDC" | "DP")) {
+                    op.operands.iter_mut().for_each(|operand| scrub(operand, &needles));
+                }
+            }
+        }
+        let mut content = b"q\n".to_vec();
+        content.extend(encode(segments).map_err(failed)?);
+        content.extend_from_slice(b"Q\n");
+        content.extend(boxes(&rects).map_err(failed)?);
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content));
+
+        let (count, removed) = remove_annotations(&mut doc, page_id, &rects);
+        annotations += count;
+        widgets.extend(removed);
+        let page = doc.get_dictionary_mut(page_id).map_err(|e| failed(e.to_string()))?;
+        page.set("Contents", content_id);
+        page.set("Resources", resources);
+        page.remove(b"Thumb");
+    }
+    drop_fields(&mut doc, &widgets);
+    if !needles.is_empty() {
+        doc.objects.values_mut().for_each(|object| scrub(object, &needles));
+        scrub_dictionary(&mut doc.trailer, &needles);
+    }
+
+    doc.prune_objects();
+    doc.compress();
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes).map_err(|e| Error::new(ErrorKind::Io, "redaction_failed").with_details(e))?;
+    Ok(Redacted { bytes, glyphs, images, annotations })
+}
+
+/// Redact `areas` and return the rewritten document, which the
+/// renderer reloads ; nothing is written to disk.
+#[tauri::command(async)]
+pub fn redact_pdf(app: tauri::AppHandle, document: PdfSource, areas: Vec<Redaction>) -> Result<Redacted> {
+    let doc = crate::pages::read_source(&app, document)?;
+    redact(doc, &areas)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::pdfio::tests::sample_pdf;
+    use lopdf::dictionary;
+
+    /// The one-page sample document (font `/F1` is Helvetica) drawing
+    /// `content` instead.
+    pub fn with_content(content: &[u8]) -> (Document, ObjectId) {
+        let mut doc = Document::load_mem(&sample_pdf(1, "")).unwrap();
+        let page = doc.page_iter().next().unwrap();
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content.to_vec()));
+        doc.get_dictionary_mut(page).unwrap().set("Contents", content_id);
+        (doc, page)
+    }
+
+    fn area(x: f64, y: f64, w: f64, h: f64) -> Redaction {
+        Redaction { page: 1, rect: PdfRect { x, y, w, h } }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    /// Text and left edge of every glyph shown on the first page.
+    fn glyphs(doc: &Document) -> Vec<(String, f64)> {
+        let page = doc.page_iter().next().unwrap();
+        text::page_glyphs(doc, page).into_iter().map(|glyph| (glyph.text, glyph.rect.x)).collect()
+    }
+
+    #[test]
+    fn removes_the_text_under_the_box_from_the_whole_file() {
+        let (mut doc, page) = with_content(b"BT /F1 12 Tf 72 700 Td (IBAN FR7630001007941234567890185) Tj ET");
+        let info = doc.add_object(dictionary! { "Title" => lopdf::text_string("Relevé FR7630001007941234567890185") });
+        doc.trailer.set("Info", info);
+        let over = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Text",
+            "Rect" => PdfRect { x: 150.0, y: 690.0, w: 20.0, h: 20.0 }.to_array(),
+            "Contents" => Object::string_literal("à vérifier"),
+        });
+        let elsewhere = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Text",
+            "Rect" => PdfRect { x: 100.0, y: 100.0, w: 20.0, h: 20.0 }.to_array(),
+            "Contents" => Object::string_literal("Virement vers FR7630001007941234567890185"),
+        });
+        doc.get_dictionary_mut(page)
+            .unwrap()
+            .set("Annots", vec![Object::Reference(over), Object::Reference(elsewhere)]);
+
+        let redacted = redact(doc, &[area(101.0, 690.0, 400.0, 25.0)]).unwrap();
+        // The space after "IBAN" spans x = 100.008 to 103.344 and goes too.
+        assert_eq!((redacted.glyphs, redacted.images, redacted.annotations), (28, 0, 1));
+        let doc = Document::load_mem(&redacted.bytes).unwrap();
+        let text = doc.extract_text(&[1]).unwrap();
+        assert!(text.contains("IBAN") && !text.contains("FR76") && !text.contains("0185"), "{text}");
+        // Neither the old content stream nor any string keeps it.
+        for object in doc.objects.values() {
+            if let Ok(stream) = object.as_stream() {
+                assert!(!contains(&stream_content(stream), b"FR76"));
+            }
+        }
+        let strings: Vec<String> = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .flat_map(|dict| dict.iter().filter_map(|(_, value)| lopdf::decode_text_string(value).ok()))
+            .collect();
+        assert!(strings.iter().any(|string| string == "Relevé "), "{strings:?}");
+        assert!(strings.iter().any(|string| string == "Virement vers "), "{strings:?}");
+        assert!(!strings.iter().any(|string| string.contains("FR76") || string.contains("vérifier")));
+    }
+
+    #[test]
+    fn remaining_glyphs_keep_their_place() {
+        let (doc, _) = with_content(b"BT /F1 12 Tf 72 700 Td 2 Tc (Nom SECRET) Tj [(Du) -250 (pont)] TJ ET");
+        let before = glyphs(&doc);
+        // From inside "S" to just past the box of "T", short of the "D"
+        // that follows the character spacing.
+        let (left, right) = (before[4].1 + 0.5, before[10].1 - 1.0);
+        let redacted = redact(doc, &[area(left, 690.0, right - left, 25.0)]).unwrap();
+        assert_eq!(redacted.glyphs, 6);
+
+        let after = glyphs(&Document::load_mem(&redacted.bytes).unwrap());
+        let expected: Vec<&(String, f64)> =
+            before.iter().enumerate().filter(|(index, _)| !(4..10).contains(index)).map(|(_, glyph)| glyph).collect();
+        assert_eq!(after.iter().map(|(text, _)| text.as_str()).collect::<String>(), "Nom Dupont");
+        for ((text, x), (_, expected_x)) in after.iter().zip(expected) {
+            assert!((x - expected_x).abs() < 0.01, "{text} at {x}, expected {expected_x}");
+        }
+    }
+
+    #[test]
+    fn zeroes_image_pixels_and_drops_inline_images() {
+        let content = b"q 100 0 0 100 100 400 cm /Im1 Do Q\n\
+            q 40 0 0 40 300 420 cm BI /W 2 /H 2 /CS /G /BPC 8 ID \xff\xff\xff\xff EI Q\n\
+            BT /F1 12 Tf 72 700 Td (Reste) Tj ET";
+        let (mut doc, page) = with_content(content);
+        let image = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 4,
+                "Height" => 4,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0xFF; 16],
+        ));
+        let resources = doc.get_dictionary_mut(page).unwrap().get_mut(b"Resources").unwrap();
+        resources.as_dict_mut().unwrap().set("XObject", dictionary! { "Im1" => image });
+
+        // The right half of the image, all of the inline one.
+        let redacted = redact(doc, &[area(150.0, 380.0, 200.0, 140.0)]).unwrap();
+        assert_eq!((redacted.glyphs, redacted.images), (0, 2));
+        let doc = Document::load_mem(&redacted.bytes).unwrap();
+        assert!(doc.extract_text(&[1]).unwrap().contains("Reste"));
+        let page = doc.page_iter().next().unwrap();
+        assert!(!contains(&page_content(&doc, page), b"BI"));
+
+        let xobjects = dictionary(&doc, page_resources(&doc, page).unwrap(), b"XObject").unwrap();
+        let names: Vec<&[u8]> = xobjects.iter().map(|(name, _)| name.as_slice()).collect();
+        assert!(names.len() == 1 && names[0].starts_with(b"Rd"), "{names:?}");
+        let mut scrubbed = doc
+            .get_object(xobjects.get(names[0]).unwrap().as_reference().unwrap())
+            .unwrap()
+            .as_stream()
+            .unwrap()
+            .clone();
+        scrubbed.dict.remove(b"Subtype");
+        let samples = scrubbed.decompressed_content().unwrap_or(scrubbed.content);
+        assert_eq!(samples, [0xFF, 0xFF, 0, 0].repeat(4));
+    }
+
+    #[test]
+    fn refuses_areas_outside_the_document() {
+        let doc = || Document::load_mem(&sample_pdf(1, "Page")).unwrap();
+        let code = |areas: &[Redaction]| redact(doc(), areas).unwrap_err().code;
+        assert_eq!(code(&[]), "redaction_empty");
+        assert_eq!(code(&[Redaction { page: 2, ..area(0.0, 0.0, 10.0, 10.0) }]), "page_out_of_range");
+        assert_eq!(code(&[area(10.0, 10.0, 0.0, 5.0)]), "redaction_area_empty");
+    }
+}
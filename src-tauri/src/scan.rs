@@ -0,0 +1,283 @@
+//! Cleanup of photographed or scanned signatures : the paper becomes
+//! transparent, the image is cropped to the ink and the ink can be
+//! recoloured.
+//!
+//! Photos are lit unevenly, so the paper is not one grey : its
+//! brightness is estimated per block of the image, and a pixel counts
+//! as ink by how much darker it is than the paper around it. The
+//! threshold is picked with Otsu's method unless the user sets one.
+//! The import dialog calls this on demand and shows the result ; the
+//! asset is only saved once the user accepts it.
+
+use serde::Deserialize;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::{images, StoredSignature};
+
+/// Side of the blocks the paper brightness is estimated over.
+const BLOCK: usize = 32;
+/// Share of a block's pixels assumed to be paper.
+const PAPER_PERCENTILE: usize = 90;
+/// Auto thresholds stay in this range : below, paper grain becomes
+/// ink ; above, light strokes vanish.
+const AUTO_THRESHOLD: (f32, f32) = (0.08, 0.5);
+/// Alpha under which a pixel does not extend the crop.
+const CROP_ALPHA: u8 = 32;
+/// Cleanup works on at most this many pixels per side (600 DPI over a
+/// 4-inch signature) ; bigger photos are downscaled first.
+const MAX_SIDE: u32 = 2400;
+const BLUE_INK: [u8; 3] = [0x1a, 0x2b, 0x8c];
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InkColor {
+    /// Keep the colour of each ink pixel.
+    #[default]
+    Original,
+    Black,
+    Blue,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupOptions {
+    /// How much darker than the paper a pixel must be to count as ink,
+    /// from 0 to 1 (0.1 keeps faint pencil, 0.4 only bold strokes).
+    /// `None` picks it from the image.
+    #[serde(default)]
+    pub threshold: Option<f32>,
+    #[serde(default)]
+    pub ink: InkColor,
+}
+
+fn luminance(px: &[u8]) -> u8 {
+    let lum = (299 * u32::from(px[0]) + 587 * u32::from(px[1]) + 114 * u32::from(px[2])) / 1000;
+    // Transparent areas read as white paper.
+    (255 - (255 - lum) * u32::from(px[3]) / 255) as u8
+}
+
+/// Paper brightness behind every pixel : a high percentile per block,
+/// widened to the neighbouring blocks so a block full of ink borrows
+/// the paper around it, then interpolated between block centres.
+fn paper(lum: &[u8], width: usize, height: usize) -> Vec<f32> {
+    let (cols, rows) = ((width + BLOCK - 1) / BLOCK, (height + BLOCK - 1) / BLOCK);
+    let mut blocks = vec![0u8; cols * rows];
+    for by in 0..rows {
+        for bx in 0..cols {
+            let mut histogram = [0usize; 256];
+            let mut count = 0;
+            for y in by * BLOCK..((by + 1) * BLOCK).min(height) {
+                for &value in &lum[y * width + bx * BLOCK..y * width + ((bx + 1) * BLOCK).min(width)] {
+                    histogram[usize::from(value)] += 1;
+                    count += 1;
+                }
+            }
+            let wanted = count * PAPER_PERCENTILE / 100;
+            let mut seen = 0;
+            blocks[by * cols + bx] = (0..=255u8)
+                .find(|&value| {
+                    seen += histogram[usize::from(value)];
+                    seen > wanted
+                })
+                .unwrap_or(255);
+        }
+    }
+    let widened: Vec<f32> = (0..rows * cols)
+        .map(|idx| {
+            let (bx, by) = (idx % cols, idx / cols);
+            let mut best = 0;
+            for y in by.saturating_sub(1)..(by + 2).min(rows) {
+                for x in bx.saturating_sub(1)..(bx + 2).min(cols) {
+                    best = best.max(blocks[y * cols + x]);
+                }
+            }
+            f32::from(best.max(1))
+        })
+        .collect();
+
+    // Position of pixel `p` between block centres `i` and `i + 1`.
+    let locate = |p: usize, count: usize| {
+        let centre = (p as f32 + 0.5) / BLOCK as f32 - 0.5;
+        let i = (centre.max(0.0).floor() as usize).min(count - 1);
+        let next = (i + 1).min(count - 1);
+        (i, next, (centre - i as f32).clamp(0.0, 1.0))
+    };
+    let mut out = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let (y0, y1, fy) = locate(y, rows);
+        for x in 0..width {
+            let (x0, x1, fx) = locate(x, cols);
+            let top = widened[y0 * cols + x0] * (1.0 - fx) + widened[y0 * cols + x1] * fx;
+            let bottom = widened[y1 * cols + x0] * (1.0 - fx) + widened[y1 * cols + x1] * fx;
+            out.push(top * (1.0 - fy) + bottom * fy);
+        }
+    }
+    out
+}
+
+/// Otsu's threshold over the darkness values, in 0..1. When several
+/// thresholds separate the classes equally well (the empty gap between
+/// paper and ink), the middle of them is taken.
+fn otsu(darkness: &[f32]) -> f32 {
+    let mut histogram = [0f64; 256];
+    for &value in darkness {
+        histogram[(value * 255.0).round() as usize] += 1.0;
+    }
+    let total = darkness.len() as f64;
+    let sum: f64 = histogram.iter().enumerate().map(|(idx, count)| idx as f64 * count).sum();
+    let (mut weight_low, mut sum_low) = (0.0, 0.0);
+    let (mut first, mut last, mut best_variance) = (0, 0, 0.0);
+    for (idx, count) in histogram.iter().enumerate() {
+        weight_low += count;
+        sum_low += idx as f64 * count;
+        let weight_high = total - weight_low;
+        if weight_low == 0.0 || weight_high == 0.0 {
+            continue;
+        }
+        let mean_low = sum_low / weight_low;
+        let mean_high = (sum - sum_low) / weight_high;
+        let variance = weight_low * weight_high * (mean_low - mean_high).powi(2);
+        if variance > best_variance {
+            (first, last, best_variance) = (idx, idx, variance);
+        } else if variance == best_variance {
+            last = idx;
+        }
+    }
+    (first + last) as f32 / 2.0 / 255.0
+}
+
+/// Transparent paper, soft-edged ink, cropped RGBA pixels.
+fn clean_pixels(rgba: &[u8], width: u32, height: u32, options: &CleanupOptions) -> Result<(Vec<u8>, u32, u32)> {
+    let (w, h) = (width as usize, height as usize);
+    let lum: Vec<u8> = rgba.chunks_exact(4).map(luminance).collect();
+    let paper = paper(&lum, w, h);
+    let darkness: Vec<f32> = lum
+        .iter()
+        .zip(&paper)
+        .map(|(&value, &paper)| (1.0 - f32::from(value) / paper).clamp(0.0, 1.0))
+        .collect();
+    let threshold = match options.threshold {
+        Some(threshold) if (0.0..=1.0).contains(&threshold) => threshold.max(0.01),
+        Some(threshold) => {
+            return Err(Error::new(ErrorKind::InvalidInput, "scan_threshold_invalid").with_details(threshold))
+        }
+        None => otsu(&darkness).clamp(AUTO_THRESHOLD.0, AUTO_THRESHOLD.1),
+    };
+    // Alpha ramps up around the threshold, which keeps stroke edges
+    // anti-aliased instead of jagged.
+    let (low, high) = (threshold * 0.5, (threshold * 1.5).min(1.0));
+
+    let mut out = Vec::with_capacity(rgba.len());
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (w, h, 0, 0);
+    for (idx, px) in rgba.chunks_exact(4).enumerate() {
+        let alpha = (((darkness[idx] - low) / (high - low)).clamp(0.0, 1.0) * 255.0).round() as u8;
+        let color = match options.ink {
+            InkColor::Original => [px[0], px[1], px[2]],
+            InkColor::Black => [0, 0, 0],
+            InkColor::Blue => BLUE_INK,
+        };
+        out.extend_from_slice(&[color[0], color[1], color[2], alpha]);
+        if alpha >= CROP_ALPHA {
+            let (x, y) = (idx % w, idx / w);
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+        }
+    }
+    if min_x > max_x {
+        return Err(Error::new(ErrorKind::InvalidInput, "scan_no_ink"));
+    }
+
+    let margin = (w.max(h) / 50).max(2);
+    let (left, top) = (min_x.saturating_sub(margin), min_y.saturating_sub(margin));
+    let (right, bottom) = ((max_x + margin).min(w - 1), (max_y + margin).min(h - 1));
+    let cropped: Vec<u8> = (top..=bottom)
+        .flat_map(|y| out[(y * w + left) * 4..(y * w + right + 1) * 4].iter().copied())
+        .collect();
+    Ok((cropped, (right - left + 1) as u32, (bottom - top + 1) as u32))
+}
+
+/// The cleaned version of `asset`, as a PNG with alpha. The id and
+/// name are kept.
+pub fn clean(asset: StoredSignature, max_side: Option<u32>, options: &CleanupOptions) -> Result<StoredSignature> {
+    let max_side = max_side.unwrap_or(MAX_SIDE).min(MAX_SIDE);
+    let asset = images::normalize(asset, Some(max_side))?;
+    let (rgba, width, height) = images::decode_rgba(&asset)?;
+    let (pixels, natural_w, natural_h) = clean_pixels(&rgba, width, height, options)?;
+    Ok(StoredSignature {
+        mime: "image/png".into(),
+        bytes: images::encode_png(&pixels, natural_w, natural_h, png::ColorType::Rgba)?,
+        natural_w,
+        natural_h,
+        ink: None,
+        ..asset
+    })
+}
+
+/// Preview of the cleaned scan ; nothing is saved.
+#[tauri::command(async)]
+pub fn clean_signature_scan(
+    app: tauri::AppHandle,
+    asset: StoredSignature,
+    options: CleanupOptions,
+) -> Result<StoredSignature> {
+    let settings = images::load_settings(&crate::app_data_dir(&app)?)?;
+    clean(asset, images::max_side(&settings), &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 200x100 photo : paper darkening from 230 to 170 left to right,
+    /// with a dark horizontal stroke from (50, 40) to (149, 49).
+    fn photo() -> Vec<u8> {
+        (0..100usize)
+            .flat_map(|y| {
+                (0..200usize).flat_map(move |x| {
+                    let paper = (230 - x * 60 / 200) as u8;
+                    let ink = (50..150).contains(&x) && (40..50).contains(&y);
+                    let value = if ink { 40 } else { paper };
+                    [value, value, value, 255]
+                })
+            })
+            .collect()
+    }
+
+    fn alpha_at(pixels: &[u8], width: u32, x: usize, y: usize) -> u8 {
+        pixels[(y * width as usize + x) * 4 + 3]
+    }
+
+    #[test]
+    fn uneven_paper_becomes_transparent_and_ink_is_cropped() {
+        let (pixels, width, height) = clean_pixels(&photo(), 200, 100, &CleanupOptions::default()).unwrap();
+        // 100x10 stroke plus a 4-pixel margin on each side.
+        assert_eq!((width, height), (108, 18));
+        assert_eq!(alpha_at(&pixels, width, 0, 0), 0);
+        assert_eq!(alpha_at(&pixels, width, width as usize - 1, height as usize - 1), 0);
+        assert_eq!(alpha_at(&pixels, width, 54, 9), 255);
+    }
+
+    #[test]
+    fn ink_can_be_recoloured() {
+        let options = CleanupOptions { threshold: Some(0.3), ink: InkColor::Blue };
+        let (pixels, width, _) = clean_pixels(&photo(), 200, 100, &options).unwrap();
+        let centre = (9 * width as usize + 54) * 4;
+        assert_eq!(&pixels[centre..centre + 4], &[BLUE_INK[0], BLUE_INK[1], BLUE_INK[2], 255]);
+    }
+
+    #[test]
+    fn blank_paper_is_refused() {
+        let blank: Vec<u8> = [200, 200, 200, 255].repeat(64 * 64);
+        assert_eq!(clean_pixels(&blank, 64, 64, &CleanupOptions::default()).err().unwrap().code, "scan_no_ink");
+        let options = CleanupOptions { threshold: Some(2.0), ..CleanupOptions::default() };
+        assert_eq!(clean_pixels(&photo(), 200, 100, &options).err().unwrap().code, "scan_threshold_invalid");
+    }
+
+    #[test]
+    fn cleaned_asset_is_a_png_with_alpha() {
+        let photo = images::encode_png(&photo(), 200, 100, png::ColorType::Rgba).unwrap();
+        let asset = crate::export::tests::asset("scan", "image/png", photo);
+        let cleaned = clean(asset, None, &CleanupOptions::default()).unwrap();
+        assert_eq!((cleaned.id.as_str(), cleaned.mime.as_str()), ("scan", "image/png"));
+        assert_eq!((cleaned.natural_w, cleaned.natural_h), (108, 18));
+    }
+}
@@ -0,0 +1,292 @@
+//! Versioned JSON format of the user stores (`signatures.json`,
+//! `paraphs.json`, `templates.json`, `snippets.json`). The stores now
+//! live in the database (see [`crate::db`]) ; this format remains the
+//! one of the files imported into it, of the history snapshots and of
+//! the templates in a bundle, and the template rows keep its versions.
+//!
+//! Each store is written as `{ "version": n, "data": … }`. The files
+//! written before versioning are bare arrays and count as version 0.
+//! On load, `data` is walked through the store's migrations up to the
+//! current version ; a file from a newer build is refused on load and
+//! never overwritten, so downgrading the app cannot destroy it.
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::store;
+
+/// Upgrades `data` from version `n` to `n + 1`.
+type Migration = fn(Value) -> Result<Value>;
+
+pub struct Schema {
+    /// `migrations[n]` takes version `n` to `n + 1` ; the current
+    /// version is the number of migrations.
+    migrations: &'static [Migration],
+}
+
+impl Schema {
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+}
+
+/// Signature and paraph galleries (`StoredSignature` lists). Version 2
+/// adds the optional `ink` strokes ; older builds would drop them, so
+/// they refuse it.
+pub const IMAGE_ASSETS: Schema = Schema { migrations: &[unchanged, unchanged] };
+pub const TEMPLATES: Schema = Schema { migrations: &[templates_v1] };
+pub const SNIPPETS: Schema = Schema { migrations: &[unchanged] };
+
+/// Schema of the user store saved as `file_name` in `app_data_dir`.
+pub fn for_store(file_name: &str) -> Option<&'static Schema> {
+    match file_name {
+        crate::SIGNATURES_FILE | crate::PARAPHS_FILE => Some(&IMAGE_ASSETS),
+        crate::TEMPLATES_FILE => Some(&TEMPLATES),
+        crate::SNIPPETS_FILE => Some(&SNIPPETS),
+        _ => None,
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, T: ?Sized> {
+    version: u32,
+    data: &'a T,
+}
+
+fn unrecognized() -> Error {
+    Error::new(ErrorKind::CorruptData, "store_format_unknown")
+}
+
+/// Version and payload of a decoded file.
+fn split(value: Value) -> Result<(u32, Value)> {
+    match value {
+        Value::Array(_) => Ok((0, value)),
+        Value::Object(mut map) => {
+            let version = map
+                .get("version")
+                .and_then(Value::as_u64)
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or_else(unrecognized)?;
+            let data = map.remove("data").ok_or_else(unrecognized)?;
+            Ok((version, data))
+        }
+        _ => Err(unrecognized()),
+    }
+}
+
+fn newer(schema: &Schema, version: u32) -> Error {
+    Error::new(ErrorKind::NewerVersion, "store_version_newer")
+        .with_details(format!("version {version}, at most {} expected", schema.version()))
+}
+
+/// Bring a decoded file (envelope or legacy array) to the current
+/// version and return its payload.
+pub fn upgrade(schema: &Schema, value: Value) -> Result<Value> {
+    let (version, mut data) = split(value)?;
+    if version > schema.version() {
+        return Err(newer(schema, version));
+    }
+    for migrate in &schema.migrations[version as usize..] {
+        data = migrate(data)?;
+    }
+    Ok(data)
+}
+
+pub fn decode<T: DeserializeOwned>(schema: &Schema, value: Value) -> Result<T> {
+    serde_json::from_value(upgrade(schema, value)?)
+        .map_err(|e| Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e))
+}
+
+/// The value to persist for `data` : wrapped in the current envelope.
+pub fn envelope<T: Serialize + ?Sized>(schema: &Schema, data: &T) -> Result<Value> {
+    serde_json::to_value(Envelope { version: schema.version(), data })
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))
+}
+
+/// Refuse to replace `existing` (the decoded current file) when a newer
+/// build wrote it. Undecodable files are left to the `.bak` logic of
+/// [`store::save_json`].
+pub fn ensure_writable(schema: &Schema, existing: Value) -> Result<()> {
+    match split(existing) {
+        Ok((version, _)) if version > schema.version() => Err(newer(schema, version)),
+        _ => Ok(()),
+    }
+}
+
+pub fn load<T: DeserializeOwned>(schema: &Schema, path: &Path) -> Result<Option<T>> {
+    store::load_with(path, |bytes| {
+        let value = serde_json::from_slice(bytes)
+            .map_err(|e| Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e))?;
+        decode(schema, value)
+    })
+}
+
+fn unchanged(data: Value) -> Result<Value> {
+    Ok(data)
+}
+
+fn set_default(item: &mut Map<String, Value>, key: &str, value: Value) {
+    item.entry(key).or_insert(value);
+}
+
+fn number(item: &Map<String, Value>, key: &str) -> f64 {
+    item.get(key).and_then(Value::as_f64).unwrap_or(0.0)
+}
+
+/// Templates saved before versioning relied on the frontend's
+/// fallbacks : text items without font family or styles, lines and
+/// arrows without endpoints, templates without `paraph` or `updatedAt`.
+/// Version 1 spells every field out.
+fn templates_v1(data: Value) -> Result<Value> {
+    let Value::Array(templates) = data else {
+        return Err(unrecognized());
+    };
+    let templates = templates
+        .into_iter()
+        .map(|mut template| {
+            let Some(fields) = template.as_object_mut() else {
+                return template;
+            };
+            set_default(fields, "paraph", Value::Null);
+            set_default(fields, "updatedAt", json!("1970-01-01T00:00:00.000Z"));
+            if let Some(Value::Array(items)) = fields.get_mut("items") {
+                for item in items.iter_mut().filter_map(Value::as_object_mut) {
+                    match item.get("type").and_then(Value::as_str) {
+                        Some("text") => {
+                            set_default(item, "value", json!(""));
+                            set_default(item, "fontFamily", json!("sans"));
+                            set_default(item, "bold", json!(false));
+                            set_default(item, "underline", json!(false));
+                            set_default(item, "strike", json!(false));
+                        }
+                        Some("check") => set_default(item, "value", json!("")),
+                        Some("line" | "arrow") => {
+                            // Same endpoints as `LineItem::endpoints`.
+                            let rect = item.get("rect").and_then(Value::as_object).cloned().unwrap_or_default();
+                            let (x, y, w, h) = (number(&rect, "x"), number(&rect, "y"), number(&rect, "w"), number(&rect, "h"));
+                            set_default(item, "start", json!({ "x": x, "y": y + h / 2.0 }));
+                            set_default(item, "end", json!({ "x": x + w, "y": y + h / 2.0 }));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            template
+        })
+        .collect();
+    Ok(Value::Array(templates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::scratch_dir;
+    use crate::items::Item;
+    use crate::StoredSignature;
+
+    /// One fixture per store and per historical version. Adding a
+    /// migration means adding the fixture of the version it leaves.
+    const FIXTURES: &[(&str, u32, &str)] = &[
+        ("signatures", 0, include_str!("../fixtures/stores/signatures.v0.json")),
+        ("signatures", 1, include_str!("../fixtures/stores/signatures.v1.json")),
+        ("signatures", 2, include_str!("../fixtures/stores/signatures.v2.json")),
+        ("paraphs", 0, include_str!("../fixtures/stores/paraphs.v0.json")),
+        ("paraphs", 1, include_str!("../fixtures/stores/paraphs.v1.json")),
+        ("paraphs", 2, include_str!("../fixtures/stores/paraphs.v2.json")),
+        ("templates", 0, include_str!("../fixtures/stores/templates.v0.json")),
+        ("templates", 1, include_str!("../fixtures/stores/templates.v1.json")),
+        ("snippets", 0, include_str!("../fixtures/stores/snippets.v0.json")),
+        ("snippets", 1, include_str!("../fixtures/stores/snippets.v1.json")),
+    ];
+
+    fn schema_of(store: &str) -> &'static Schema {
+        match store {
+            "signatures" | "paraphs" => &IMAGE_ASSETS,
+            "templates" => &TEMPLATES,
+            "snippets" => &SNIPPETS,
+            other => panic!("unknown store {other}"),
+        }
+    }
+
+    fn fixture(store: &str, version: u32) -> Value {
+        let (_, _, raw) = FIXTURES
+            .iter()
+            .find(|(name, v, _)| *name == store && *v == version)
+            .unwrap_or_else(|| panic!("no fixture for {store} v{version}"));
+        serde_json::from_str(raw).unwrap()
+    }
+
+    #[test]
+    fn every_version_of_every_store_has_a_fixture() {
+        for store in ["signatures", "paraphs", "templates", "snippets"] {
+            for version in 0..=schema_of(store).version() {
+                fixture(store, version);
+            }
+        }
+    }
+
+    #[test]
+    fn every_fixture_loads_into_the_current_types() {
+        for (store, version, _) in FIXTURES {
+            let schema = schema_of(store);
+            let value = fixture(store, *version);
+            let context = format!("{store} v{version}");
+            match *store {
+                "signatures" | "paraphs" => {
+                    let assets: Vec<StoredSignature> = decode(schema, value).expect(&context);
+                    assert!(!assets.is_empty(), "{context}");
+                }
+                "templates" => {
+                    let templates: Vec<Value> = decode(schema, value).expect(&context);
+                    for template in templates {
+                        for key in ["id", "name", "updatedAt", "items", "paraph"] {
+                            assert!(template.get(key).is_some(), "{context}: {key} missing");
+                        }
+                        let items: Vec<Item> = serde_json::from_value(template["items"].clone()).expect(&context);
+                        assert!(!items.is_empty(), "{context}");
+                    }
+                }
+                _ => {
+                    let snippets: Vec<String> = decode(schema, value).expect(&context);
+                    assert!(!snippets.is_empty(), "{context}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_templates_get_explicit_fields() {
+        let templates = upgrade(&TEMPLATES, fixture("templates", 0)).unwrap();
+        let upgraded = upgrade(&TEMPLATES, fixture("templates", 1)).unwrap();
+        assert_eq!(templates, upgraded);
+    }
+
+    #[test]
+    fn newer_file_is_refused_and_left_untouched() {
+        let dir = scratch_dir("newer");
+        let path = dir.join("snippets.json");
+        let future = br#"{"version":99,"data":{"snippets":["later"]}}"#;
+        std::fs::write(&path, future).unwrap();
+        std::fs::write(store::backup_path(&path), b"[\"old\"]").unwrap();
+
+        let err = load::<Vec<String>>(&SNIPPETS, &path).unwrap_err();
+        assert_eq!((err.kind, err.code), (ErrorKind::NewerVersion, "store_version_newer"));
+        let err = ensure_writable(&SNIPPETS, serde_json::from_slice(future).unwrap()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NewerVersion);
+        assert_eq!(std::fs::read(&path).unwrap(), future);
+    }
+
+    #[test]
+    fn legacy_file_is_reencoded_as_an_envelope() {
+        let dir = scratch_dir("rewrite");
+        let path = dir.join("snippets.json");
+        std::fs::write(&path, b"[\"a\"]").unwrap();
+
+        let loaded: Vec<String> = load(&SNIPPETS, &path).unwrap().unwrap();
+        assert_eq!(envelope(&SNIPPETS, &loaded).unwrap(), json!({ "version": SNIPPETS.version(), "data": ["a"] }));
+    }
+}
@@ -0,0 +1,289 @@
+//! The text of a document, page by page : runs placed in PDF user space
+//! for the renderer, and search over them so the UI can jump to a
+//! phrase and place a signature next to it.
+//!
+//! Runs are rebuilt from the glyph boxes rather than from the strings
+//! of the content stream : producers split words, or draw a line in
+//! several pieces, as they please.
+
+use lopdf::{Document, ObjectId};
+use serde::Serialize;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::pages::PdfSource;
+use crate::pdfio::PdfRect;
+use crate::text::{self, Glyph};
+
+/// Gap between two glyphs, in font sizes, from which a space is assumed
+/// (word spacing is around a quarter of the size, kerning far less).
+const SPACE_GAP: f64 = 0.15;
+/// Gap from which two glyphs of the same baseline belong to different
+/// runs, such as the columns of a table.
+const RUN_GAP: f64 = 1.5;
+
+/// Text on one line, in one size.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TextRun {
+    pub text: String,
+    pub font_size: f64,
+    pub rect: PdfRect,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PageText {
+    pub page: u32,
+    pub runs: Vec<TextRun>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub page: u32,
+    /// One rect per run the match spans.
+    pub rects: Vec<PdfRect>,
+    /// The text of those runs, for the list of results.
+    pub context: String,
+}
+
+/// A run with the box of each of its characters.
+pub struct Line {
+    pub run: TextRun,
+    boxes: Vec<PdfRect>,
+}
+
+impl Line {
+    fn new(glyph: &Glyph) -> Self {
+        let font_size = (glyph.size * 100.0).round() / 100.0;
+        Line { run: TextRun { text: String::new(), font_size, rect: glyph.rect }, boxes: Vec::new() }
+    }
+
+    fn push(&mut self, text: &str, rect: PdfRect) {
+        for ch in text.chars() {
+            self.run.text.push(ch);
+            self.boxes.push(rect);
+        }
+        self.run.rect = union(&self.run.rect, &rect);
+    }
+
+    /// Horizontal gap before `rect` when it continues this line.
+    fn gap_before(&self, rect: &PdfRect, size: f64) -> Option<f64> {
+        let last = self.boxes.last()?;
+        let gap = rect.x - (last.x + last.w);
+        let aligned = (self.run.font_size - size).abs() <= size * 0.2 && (last.y - rect.y).abs() <= size * 0.3;
+        (aligned && gap >= -size * 0.5 && gap <= size * RUN_GAP).then_some(gap)
+    }
+}
+
+fn union(a: &PdfRect, b: &PdfRect) -> PdfRect {
+    let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+    PdfRect { x, y, w: (a.x + a.w).max(b.x + b.w) - x, h: (a.y + a.h).max(b.y + b.h) - y }
+}
+
+/// Gather glyphs into runs. Space glyphs only mark a word break : the
+/// space is added, with a box over the gap, when the next glyph
+/// continues the run.
+fn lines(glyphs: Vec<Glyph>) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut space = false;
+    for glyph in glyphs {
+        if glyph.text.trim().is_empty() {
+            space |= !glyph.text.is_empty();
+            continue;
+        }
+        let gap = lines.last().and_then(|line| line.gap_before(&glyph.rect, glyph.size));
+        match (lines.last_mut(), gap) {
+            (Some(line), Some(gap)) => {
+                if space || gap > glyph.size * SPACE_GAP {
+                    let last = line.boxes[line.boxes.len() - 1];
+                    let x = last.x + last.w;
+                    line.push(" ", PdfRect { x, y: glyph.rect.y, w: gap.max(0.0), h: glyph.rect.h });
+                }
+                line.push(&glyph.text, glyph.rect);
+            }
+            _ => {
+                let mut line = Line::new(&glyph);
+                line.push(&glyph.text, glyph.rect);
+                lines.push(line);
+            }
+        }
+        space = false;
+    }
+    lines
+}
+
+pub fn page_lines(doc: &Document, page: ObjectId) -> Vec<Line> {
+    lines(text::page_glyphs(doc, page))
+}
+
+/// Lowercase and without accents, typographic apostrophes and
+/// non-breaking spaces made plain : "lu et approuve" finds "Lu et
+/// approuvé".
+fn fold(ch: char) -> char {
+    match ch.to_lowercase().next().unwrap_or(ch) {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        '\u{2018}' | '\u{2019}' => '\'',
+        '\u{a0}' | '\u{202f}' => ' ',
+        other => other,
+    }
+}
+
+/// `query` folded, with its whitespace collapsed to single spaces.
+fn folded(query: &str) -> Vec<char> {
+    let mut out: Vec<char> = Vec::new();
+    for ch in query.chars().map(fold) {
+        if !ch.is_whitespace() {
+            out.push(ch);
+        } else if out.last().is_some_and(|last| *last != ' ') {
+            out.push(' ');
+        }
+    }
+    if out.last() == Some(&' ') {
+        out.pop();
+    }
+    out
+}
+
+/// Matches of `query` (already folded) on one page ; runs are joined by
+/// a space, so a phrase may go on over the next line.
+fn page_hits(page: u32, lines: &[Line], query: &[char]) -> Vec<SearchHit> {
+    // The page as one folded string, each character pointing back to
+    // its run and box.
+    let mut chars = Vec::new();
+    let mut origins = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        for (position, ch) in line.run.text.chars().map(fold).enumerate() {
+            if ch.is_whitespace() && chars.last().map_or(true, |last| *last == ' ') {
+                continue;
+            }
+            chars.push(if ch.is_whitespace() { ' ' } else { ch });
+            origins.push(Some((index, position)));
+        }
+        if chars.last().is_some_and(|last| *last != ' ') {
+            chars.push(' ');
+            origins.push(None);
+        }
+    }
+
+    let mut hits = Vec::new();
+    let mut start = 0;
+    while start + query.len() <= chars.len() {
+        if chars[start..start + query.len()] != *query {
+            start += 1;
+            continue;
+        }
+        let mut rects: Vec<(usize, PdfRect)> = Vec::new();
+        for &(index, position) in origins[start..start + query.len()].iter().flatten() {
+            let rect = lines[index].boxes[position];
+            match rects.last_mut() {
+                Some((line, united)) if *line == index => *united = union(united, &rect),
+                _ => rects.push((index, rect)),
+            }
+        }
+        let context = rects.iter().map(|(index, _)| lines[*index].run.text.as_str()).collect::<Vec<_>>().join(" ");
+        hits.push(SearchHit { page, rects: rects.into_iter().map(|(_, rect)| rect).collect(), context });
+        start += query.len();
+    }
+    hits
+}
+
+/// Every match of `query`, ignoring case, accents and how whitespace
+/// is laid out, in page order.
+pub fn search(doc: &Document, query: &str) -> Result<Vec<SearchHit>> {
+    let query = folded(query);
+    if query.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "search_query_empty"));
+    }
+    Ok(doc.get_pages().into_iter().flat_map(|(page, id)| page_hits(page, &page_lines(doc, id), &query)).collect())
+}
+
+/// A path to an encrypted document is read in clear with the password
+/// it was opened with ; one still encrypted here is refused.
+pub(crate) fn read_text_source(app: &tauri::AppHandle, document: PdfSource) -> Result<Document> {
+    let doc = crate::pages::read_source(app, document)?;
+    if doc.is_encrypted() {
+        return Err(Error::new(ErrorKind::InvalidPdf, "pdf_encrypted"));
+    }
+    Ok(doc)
+}
+
+#[tauri::command(async)]
+pub fn extract_pdf_text(app: tauri::AppHandle, document: PdfSource) -> Result<Vec<PageText>> {
+    let doc = read_text_source(&app, document)?;
+    Ok(doc
+        .get_pages()
+        .into_iter()
+        .map(|(page, id)| PageText { page, runs: page_lines(&doc, id).into_iter().map(|line| line.run).collect() })
+        .collect())
+}
+
+#[tauri::command(async)]
+pub fn search_pdf(app: tauri::AppHandle, document: PdfSource, query: String) -> Result<Vec<SearchHit>> {
+    let doc = read_text_source(&app, document)?;
+    search(&doc, &query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdfio::tests::sample_pdf;
+    use crate::redact::tests::with_content;
+
+    fn runs(doc: &Document) -> Vec<TextRun> {
+        page_lines(doc, doc.page_iter().next().unwrap()).into_iter().map(|line| line.run).collect()
+    }
+
+    #[test]
+    fn rebuilds_runs_from_split_strings() {
+        let (doc, _) = with_content(
+            b"BT /F1 12 Tf 72 700 Td (Signa) Tj (ture du) Tj ( client) Tj ET\n\
+              BT /F1 10 Tf 72 650 Td [(Lu) -300 (et appr) 20 (ouv\\351)] TJ ET\n\
+              BT /F1 10 Tf 400 650 Td (Date) Tj ET",
+        );
+        let runs = runs(&doc);
+        let texts: Vec<&str> = runs.iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(texts, ["Signature du client", "Lu et approuvé", "Date"]);
+        assert_eq!((runs[0].font_size, runs[1].font_size), (12.0, 10.0));
+        // Helvetica : descent -0.25 and ascent 0.9 of the size.
+        let first = runs[0].rect;
+        assert!((first.x - 72.0).abs() < 1e-9 && (first.y - 697.0).abs() < 1e-9 && (first.h - 13.8).abs() < 1e-9);
+        let width = crate::fonts::StandardFont::Helvetica.width_of(b"Signature du client", 12.0);
+        assert!((first.w - width).abs() < 1e-9);
+    }
+
+    #[test]
+    fn finds_phrases_regardless_of_case_accents_and_lines() {
+        let (doc, _) = with_content(
+            b"BT /F1 12 Tf 72 700 Td (Signature du client) Tj ET\n\
+              BT /F1 12 Tf 72 680 Td (Lu et approuv\\351,  le client) Tj ET",
+        );
+        let hits = search(&doc, "  LU ET APPROUVE ").unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].page, hits[0].context.as_str()), (1, "Lu et approuvé, le client"));
+        assert!((hits[0].rects[0].x - 72.0).abs() < 1e-9 && (hits[0].rects[0].y - 677.0).abs() < 1e-9);
+
+        assert_eq!(search(&doc, "client").unwrap().len(), 2);
+        // The end of a line runs on to the next one.
+        let across = search(&doc, "du client lu").unwrap();
+        assert_eq!(across.len(), 1);
+        assert_eq!(across[0].rects.len(), 2);
+
+        assert_eq!(search(&doc, " ").unwrap_err().code, "search_query_empty");
+    }
+
+    #[test]
+    fn reports_the_page_of_each_hit() {
+        let doc = Document::load_mem(&sample_pdf(3, "Page")).unwrap();
+        let hits = search(&doc, "page 2").unwrap();
+        assert_eq!(hits.iter().map(|hit| hit.page).collect::<Vec<_>>(), [2]);
+        assert_eq!(search(&doc, "page").unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,248 @@
+//! Crash-safe persistence for the JSON stores kept in `app_data_dir`
+//! (`signatures.json`, `paraphs.json`, `templates.json`, `snippets.json`).
+//!
+//! Every write goes to a sibling temp file which is fsynced and then
+//! renamed over the target, so a crash or power loss leaves either the
+//! old or the new content on disk — never a truncated file. The
+//! previous generation is kept as `<name>.bak` ; when the primary file
+//! is missing or unreadable, loading falls back to that backup.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{Error, ErrorKind, Result};
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| OsString::from("store"));
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Path of the previous generation of `path` (`signatures.json.bak`).
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".bak")
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".tmp")
+}
+
+/// Flush the directory entry itself so the rename survives a power
+/// loss. Directories cannot be opened for syncing on Windows, where
+/// `MoveFileEx` is already durable enough for our needs.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Atomically replace `path` with `bytes`. When `keep_backup` is set,
+/// the current content of `path` becomes `<path>.bak` first.
+pub fn write_atomic(path: &Path, bytes: &[u8], keep_backup: bool) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+
+    if keep_backup && path.exists() {
+        // Between these two renames the primary briefly does not
+        // exist ; `load_json` covers that window by reading the backup,
+        // which then holds the latest committed generation.
+        fs::rename(path, backup_path(path))?;
+    }
+    fs::rename(&tmp, path)?;
+
+    if let Some(parent) = path.parent() {
+        sync_dir(parent)?;
+    }
+    Ok(())
+}
+
+fn read_decoded<T>(path: &Path, decode: &impl Fn(&[u8]) -> Result<T>) -> Result<T> {
+    let bytes = fs::read(path).map_err(|e| Error::io("store_read_failed", path, &e))?;
+    decode(&bytes).map_err(|err| err.with_path(path))
+}
+
+fn decode_json<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    serde_json::from_slice(bytes).map_err(|e| Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e))
+}
+
+/// Load the store at `path`, falling back to its `.bak` when the primary
+/// is missing or corrupt. Returns `Ok(None)` when neither file exists
+/// (first launch) ; the primary's error is reported when both fail.
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    load_with(path, decode_json)
+}
+
+/// Same fallback rules as [`load_json`], with a caller-supplied decoder
+/// for stores whose on-disk bytes are not the plain JSON value (e.g.
+/// the sealed envelopes written by the vault).
+pub fn load_with<T>(path: &Path, decode: impl Fn(&[u8]) -> Result<T>) -> Result<Option<T>> {
+    let backup = backup_path(path);
+
+    if !path.exists() {
+        if !backup.exists() {
+            return Ok(None);
+        }
+        return read_decoded(&backup, &decode).map(Some);
+    }
+
+    match read_decoded(path, &decode) {
+        Ok(value) => Ok(Some(value)),
+        // A newer build's file is intact : serving the older backup
+        // instead would hide it and invite overwriting it.
+        Err(primary_err) if primary_err.kind == ErrorKind::NewerVersion => Err(primary_err),
+        Err(primary_err) => match read_decoded(&backup, &decode) {
+            Ok(value) => {
+                eprintln!("{} unreadable ({primary_err}), restoring from {}", path.display(), backup.display());
+                Ok(Some(value))
+            }
+            Err(_) => Err(primary_err),
+        },
+    }
+}
+
+/// Serialize `value` and write it to `path` with [`write_atomic`].
+///
+/// The current primary is only rotated into `.bak` when it still parses
+/// as JSON : after a crash left it corrupt, overwriting the good backup
+/// with it would throw away the one generation we could recover.
+pub fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    let bytes = serde_json::to_vec(value)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))?;
+    save_bytes(path, &bytes)
+}
+
+/// [`save_json`] for content that is already serialized JSON.
+pub fn save_bytes(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io("create_dir_failed", parent, &e))?;
+    }
+
+    let keep_backup = fs::read(path)
+        .map(|current| serde_json::from_slice::<serde_json::Value>(&current).is_ok())
+        .unwrap_or(false);
+    write_atomic(path, bytes, keep_backup).map_err(|e| Error::io("store_write_failed", path, &e))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Empty directory of a test, removed with everything in it when
+    /// dropped. Derefs to its path.
+    pub struct ScratchDir(PathBuf);
+
+    impl std::ops::Deref for ScratchDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for ScratchDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for ScratchDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A fresh [`ScratchDir`] ; `label` only makes it easier to spot.
+    /// Tests run in parallel, so every call gets its own directory.
+    pub fn scratch_dir(label: &str) -> ScratchDir {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let idx = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("cerfini-{label}-{}-{idx}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        ScratchDir(dir)
+    }
+
+    #[test]
+    fn missing_store_loads_as_none() {
+        let dir = scratch_dir("missing");
+        let loaded: Option<Vec<String>> = load_json(&dir.join("snippets.json")).unwrap();
+        assert!(loaded.is_none());
+    }
+
+    #[test]
+    fn save_keeps_previous_generation_as_backup() {
+        let dir = scratch_dir("rotate");
+        let path = dir.join("snippets.json");
+        save_json(&path, &vec!["a"]).unwrap();
+        save_json(&path, &vec!["b"]).unwrap();
+
+        let primary: Vec<String> = read_decoded(&path, &decode_json).unwrap();
+        let backup: Vec<String> = read_decoded(&backup_path(&path), &decode_json).unwrap();
+        assert_eq!(primary, vec!["b"]);
+        assert_eq!(backup, vec!["a"]);
+        assert!(!temp_path(&path).exists());
+    }
+
+    #[test]
+    fn truncated_primary_falls_back_to_backup() {
+        let dir = scratch_dir("truncated");
+        let path = dir.join("snippets.json");
+        save_json(&path, &vec!["first"]).unwrap();
+        save_json(&path, &vec!["second"]).unwrap();
+        fs::write(&path, b"[\"sec").unwrap();
+
+        let loaded: Option<Vec<String>> = load_json(&path).unwrap();
+        assert_eq!(loaded, Some(vec!["first".to_string()]));
+    }
+
+    #[test]
+    fn missing_primary_after_interrupted_rotation_reads_backup() {
+        let dir = scratch_dir("rotation");
+        let path = dir.join("snippets.json");
+        fs::write(backup_path(&path), b"[\"kept\"]").unwrap();
+
+        let loaded: Option<Vec<String>> = load_json(&path).unwrap();
+        assert_eq!(loaded, Some(vec!["kept".to_string()]));
+    }
+
+    #[test]
+    fn corrupt_primary_does_not_overwrite_good_backup() {
+        let dir = scratch_dir("preserve");
+        let path = dir.join("snippets.json");
+        fs::write(backup_path(&path), b"[\"good\"]").unwrap();
+        fs::write(&path, b"[tru").unwrap();
+
+        save_json(&path, &vec!["new"]).unwrap();
+        let backup: Vec<String> = read_decoded(&backup_path(&path), &decode_json).unwrap();
+        assert_eq!(backup, vec!["good"]);
+    }
+
+    #[test]
+    fn corrupt_primary_and_backup_report_primary_error() {
+        let dir = scratch_dir("both");
+        let path = dir.join("snippets.json");
+        fs::write(&path, b"{").unwrap();
+        fs::write(backup_path(&path), b"}").unwrap();
+
+        let err = load_json::<Vec<String>>(&path).unwrap_err();
+        assert_eq!((err.kind, err.code), (ErrorKind::CorruptData, "json_invalid"));
+        assert_eq!(err.path.as_deref(), path.to_str());
+    }
+}
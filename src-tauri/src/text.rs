@@ -0,0 +1,684 @@
+//! Where text sits on a page : the graphics and text state of content
+//! streams, font metrics and the box of every glyph shown.
+//!
+//! Boxes come from the advance widths and the font's ascent and
+//! descent, not from the glyph outlines ; that is what viewers use for
+//! selection, and what the redaction needs to decide which glyphs a
+//! box covers.
+
+use std::collections::{BTreeMap, HashMap};
+
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Encoding, Object, ObjectId, Stream};
+
+use crate::fonts::StandardFont;
+use crate::pdfio::PdfRect;
+
+/// `[a b c d e f]`, applied to row vectors as in the PDF specification.
+pub type Matrix = [f64; 6];
+
+pub const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Bound on nested form XObjects and on `/Parent` chains.
+const MAX_DEPTH: usize = 16;
+/// Ascent and descent (in em) of fonts whose descriptor has none.
+const ASCENT: f64 = 0.9;
+const DESCENT: f64 = -0.25;
+
+/// `m` then `n`.
+pub fn multiply(m: &Matrix, n: &Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+pub fn transform(m: &Matrix, x: f64, y: f64) -> (f64, f64) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+/// `None` for degenerate matrices, which draw nothing visible.
+pub fn invert(m: &Matrix) -> Option<Matrix> {
+    let det = m[0] * m[3] - m[1] * m[2];
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let (a, b, c, d) = (m[3] / det, -m[1] / det, -m[2] / det, m[0] / det);
+    Some([a, b, c, d, -(m[4] * a + m[5] * c), -(m[4] * b + m[5] * d)])
+}
+
+/// Bounding box of the rectangle `(x0, y0)-(x1, y1)` once mapped by `m`.
+pub fn bounds(m: &Matrix, x0: f64, y0: f64, x1: f64, y1: f64) -> PdfRect {
+    let corners = [transform(m, x0, y0), transform(m, x1, y0), transform(m, x0, y1), transform(m, x1, y1)];
+    let (mut left, mut bottom, mut right, mut top) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for (x, y) in corners {
+        left = left.min(x);
+        bottom = bottom.min(y);
+        right = right.max(x);
+        top = top.max(y);
+    }
+    PdfRect { x: left, y: bottom, w: right - left, h: top - bottom }
+}
+
+/// Same rectangle with a non-negative width and height.
+pub fn normalized(rect: PdfRect) -> PdfRect {
+    PdfRect { x: rect.x.min(rect.x + rect.w), y: rect.y.min(rect.y + rect.h), w: rect.w.abs(), h: rect.h.abs() }
+}
+
+/// The two (normalized) rectangles share some area ; touching edges do
+/// not count.
+pub fn overlaps(a: &PdfRect, b: &PdfRect) -> bool {
+    a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h
+}
+
+pub fn number(object: &Object) -> Option<f64> {
+    match object {
+        Object::Integer(value) => Some(*value as f64),
+        Object::Real(value) => Some(f64::from(*value)),
+        _ => None,
+    }
+}
+
+/// Six numbers, as the operands of `cm` and `Tm` or a `/Matrix`.
+pub fn matrix(values: &[Object]) -> Option<Matrix> {
+    let numbers = values.iter().map(number).collect::<Option<Vec<_>>>()?;
+    numbers.try_into().ok()
+}
+
+pub fn resolved<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).map(|(_, object)| object).unwrap_or(object)
+}
+
+pub fn dictionary<'a>(doc: &'a Document, dict: &'a Dictionary, key: &[u8]) -> Option<&'a Dictionary> {
+    dict.get(key).ok().and_then(|object| resolved(doc, object).as_dict().ok())
+}
+
+pub fn numbers(doc: &Document, object: &Object) -> Vec<f64> {
+    match resolved(doc, object) {
+        Object::Array(values) => values.iter().map(|value| number(resolved(doc, value)).unwrap_or(0.0)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn stream_content(stream: &Stream) -> Vec<u8> {
+    stream.decompressed_content().unwrap_or_else(|_| stream.content.clone())
+}
+
+/// The page's content streams, decoded and joined.
+pub fn page_content(doc: &Document, page: ObjectId) -> Vec<u8> {
+    let mut content = Vec::new();
+    for id in doc.get_page_contents(page) {
+        if let Ok(stream) = doc.get_object(id).and_then(Object::as_stream) {
+            content.extend(stream_content(stream));
+            // Streams may split operators anywhere but between tokens.
+            content.push(b'\n');
+        }
+    }
+    content
+}
+
+/// `/Resources` of the page or the nearest node above it.
+pub fn page_resources(doc: &Document, page: ObjectId) -> Option<&Dictionary> {
+    let mut node = doc.get_dictionary(page).ok()?;
+    for _ in 0..MAX_DEPTH {
+        if let Some(resources) = dictionary(doc, node, b"Resources") {
+            return Some(resources);
+        }
+        node = doc.get_dictionary(node.get(b"Parent").and_then(Object::as_reference).ok()?).ok()?;
+    }
+    None
+}
+
+fn font_resource<'a>(doc: &'a Document, resources: Option<&'a Dictionary>, name: &[u8]) -> Option<&'a Dictionary> {
+    let fonts = dictionary(doc, resources?, b"Font")?;
+    dictionary(doc, fonts, name)
+}
+
+/// The stream of the XObject resource `name`.
+pub fn xobject<'a>(doc: &'a Document, resources: Option<&'a Dictionary>, name: &[u8]) -> Option<&'a Stream> {
+    let xobjects = dictionary(doc, resources?, b"XObject")?;
+    resolved(doc, xobjects.get(name).ok()?).as_stream().ok()
+}
+
+/// `/Matrix` of a form XObject.
+pub fn form_matrix(doc: &Document, form: &Stream) -> Matrix {
+    form.dict.get(b"Matrix").ok().and_then(|m| matrix(resolved(doc, m).as_array().ok()?)).unwrap_or(IDENTITY)
+}
+
+/// A stretch of content : operators parsed by lopdf, or an inline image
+/// (`BI … ID … EI`) kept as raw bytes, since lopdf's parser silently
+/// stops at the first one.
+pub enum Segment {
+    Ops(Vec<Operation>),
+    Inline(Vec<u8>),
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    byte.is_ascii_whitespace() || byte == 0 || b"()<>[]{}/%".contains(&byte)
+}
+
+pub fn segments(content: &[u8]) -> Result<Vec<Segment>, String> {
+    let mut out = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    while i < content.len() {
+        match content[i] {
+            b'%' => {
+                while i < content.len() && !matches!(content[i], b'\r' | b'\n') {
+                    i += 1;
+                }
+            }
+            b'(' => i = skip_string(content, i),
+            b'<' if content.get(i + 1) == Some(&b'<') => i += 2,
+            b'<' => i = content[i..].iter().position(|&byte| byte == b'>').map_or(content.len(), |end| i + end + 1),
+            byte if is_delimiter(byte) => i += 1,
+            _ => {
+                let end = content[i..].iter().position(|&byte| is_delimiter(byte)).map_or(content.len(), |end| i + end);
+                if &content[i..end] == b"BI" {
+                    let image_end = inline_image_end(content, end).ok_or("inline image without EI")?;
+                    push_ops(&mut out, &content[start..i])?;
+                    out.push(Segment::Inline(content[i..image_end].to_vec()));
+                    start = image_end;
+                    i = image_end;
+                } else {
+                    i = end;
+                }
+            }
+        }
+    }
+    push_ops(&mut out, &content[start..])?;
+    Ok(out)
+}
+
+/// Index past the `)` closing the literal string opened at `i`.
+fn skip_string(content: &[u8], mut i: usize) -> usize {
+    let mut depth = 0;
+    while i < content.len() {
+        match content[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Index past the `EI` of the inline image whose dictionary starts at
+/// `from`. The data is binary : `EI` only counts between whitespace.
+fn inline_image_end(content: &[u8], from: usize) -> Option<usize> {
+    let is_token = |at: usize, token: &[u8]| {
+        content[at..].starts_with(token)
+            && content.get(at.wrapping_sub(1)).map_or(true, |byte| byte.is_ascii_whitespace())
+            && content.get(at + token.len()).map_or(true, |&byte| is_delimiter(byte))
+    };
+    let data = (from..content.len()).find(|&at| is_token(at, b"ID"))? + 3;
+    (data..content.len()).find(|&at| is_token(at, b"EI")).map(|at| at + 2)
+}
+
+fn push_ops(out: &mut Vec<Segment>, content: &[u8]) -> Result<(), String> {
+    if content.iter().all(u8::is_ascii_whitespace) {
+        return Ok(());
+    }
+    let content = Content::decode(content).map_err(|e| format!("unreadable content: {e}"))?;
+    out.push(Segment::Ops(content.operations));
+    Ok(())
+}
+
+/// The strings and `TJ` adjustments shown by a text operator.
+pub fn shown(op: &Operation) -> &[Object] {
+    match (op.operator.as_str(), op.operands.last()) {
+        ("TJ", Some(Object::Array(elements))) => elements,
+        ("Tj" | "'" | "\"", Some(string)) => std::slice::from_ref(string),
+        _ => &[],
+    }
+}
+
+/// Metrics and text encoding of one font resource.
+pub struct Font<'a> {
+    /// Type0 fonts, read as two-byte codes : the Identity CMaps are by
+    /// far the most common.
+    two_byte: bool,
+    first_char: u32,
+    widths: Vec<f64>,
+    /// `/W` of the descendant font of a Type0 font, by CID.
+    cid_widths: BTreeMap<u32, f64>,
+    default_width: f64,
+    standard: Option<StandardFont>,
+    /// Glyph space to text space : 1/1000, or `/FontMatrix` for Type3.
+    scale: f64,
+    ascent: f64,
+    descent: f64,
+    encoding: Option<Encoding<'a>>,
+}
+
+/// One glyph shown by a text operator.
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    /// Its code in the shown string.
+    pub code: Vec<u8>,
+    /// The text it stands for ; empty when the font has no usable
+    /// encoding.
+    pub text: String,
+    /// Its box in the user space of the page.
+    pub rect: PdfRect,
+    /// Horizontal displacement it causes, in unscaled text space units
+    /// (glyph width plus character and word spacing), as a `TJ`
+    /// adjustment would counter it.
+    pub advance: f64,
+    /// Font size as it appears on the page, text and current
+    /// transformation matrices applied.
+    pub size: f64,
+}
+
+impl<'a> Font<'a> {
+    pub fn load(doc: &'a Document, dict: &'a Dictionary) -> Self {
+        let subtype = dict.get(b"Subtype").and_then(Object::as_name_str).unwrap_or("");
+        let base_font = dict.get(b"BaseFont").and_then(Object::as_name_str).unwrap_or("");
+        // lopdf asserts the type in debug builds ; some producers leave it out.
+        let encoding = if dict.type_is(b"Font") { dict.get_font_encoding(doc).ok() } else { None };
+        let mut font = Font {
+            two_byte: subtype == "Type0",
+            first_char: 0,
+            widths: Vec::new(),
+            cid_widths: BTreeMap::new(),
+            default_width: 500.0,
+            standard: StandardFont::from_base_font(base_font),
+            scale: 0.001,
+            ascent: ASCENT,
+            descent: DESCENT,
+            encoding,
+        };
+        let descriptor_owner = if font.two_byte {
+            let descendant = dict
+                .get(b"DescendantFonts")
+                .ok()
+                .and_then(|fonts| resolved(doc, fonts).as_array().ok())
+                .and_then(|fonts| fonts.first())
+                .and_then(|descendant| resolved(doc, descendant).as_dict().ok());
+            if let Some(descendant) = descendant {
+                font.default_width = descendant.get(b"DW").ok().and_then(number).unwrap_or(1000.0);
+                if let Ok(widths) = descendant.get(b"W") {
+                    font.cid_widths = cid_widths(doc, widths);
+                }
+            } else {
+                font.default_width = 1000.0;
+            }
+            descendant
+        } else {
+            font.first_char = dict.get(b"FirstChar").ok().and_then(number).unwrap_or(0.0) as u32;
+            if let Ok(widths) = dict.get(b"Widths") {
+                font.widths = numbers(doc, widths);
+            }
+            if subtype == "Type3" {
+                font.scale = dict
+                    .get(b"FontMatrix")
+                    .ok()
+                    .map(|matrix| numbers(doc, matrix))
+                    .and_then(|m| m.first().copied())
+                    .unwrap_or(0.001);
+            }
+            Some(dict)
+        };
+        if let Some(descriptor) = descriptor_owner.and_then(|owner| dictionary(doc, owner, b"FontDescriptor")) {
+            let metric = |key: &[u8]| descriptor.get(key).ok().map(|value| resolved(doc, value)).and_then(number);
+            if let Some(missing) = metric(b"MissingWidth").filter(|_| !font.two_byte) {
+                font.default_width = missing;
+            }
+            // Some producers write 0 for both ; keep the defaults then.
+            if let (Some(ascent), Some(descent)) = (metric(b"Ascent"), metric(b"Descent")) {
+                if ascent > 0.0 {
+                    font.ascent = ascent / 1000.0;
+                    font.descent = (descent / 1000.0).min(0.0);
+                }
+            }
+        }
+        font
+    }
+
+    /// Stand-in for a `Tf` naming a font missing from the resources.
+    pub fn fallback() -> Font<'static> {
+        Font {
+            two_byte: false,
+            first_char: 0,
+            widths: Vec::new(),
+            cid_widths: BTreeMap::new(),
+            default_width: 500.0,
+            standard: None,
+            scale: 0.001,
+            ascent: ASCENT,
+            descent: DESCENT,
+            encoding: None,
+        }
+    }
+
+    /// Split a shown string into the codes of its glyphs.
+    fn codes<'b>(&self, bytes: &'b [u8]) -> Vec<&'b [u8]> {
+        if self.two_byte {
+            bytes.chunks(2).collect()
+        } else {
+            bytes.chunks(1).collect()
+        }
+    }
+
+    /// Advance of `code` in em.
+    fn width(&self, code: &[u8]) -> f64 {
+        let value = code.iter().fold(0u32, |value, &byte| (value << 8) | u32::from(byte));
+        let units = if self.two_byte {
+            self.cid_widths.get(&value).copied().unwrap_or(self.default_width)
+        } else if let Some(width) = value.checked_sub(self.first_char).and_then(|index| self.widths.get(index as usize))
+        {
+            *width
+        } else if let (true, Some(standard)) = (self.widths.is_empty(), self.standard) {
+            standard.width_of(code, 1000.0)
+        } else {
+            self.default_width
+        };
+        units * self.scale
+    }
+
+    pub fn decode(&self, code: &[u8]) -> String {
+        self.encoding.as_ref().and_then(|encoding| encoding.bytes_to_string(code).ok()).unwrap_or_default()
+    }
+}
+
+/// The fonts of a resource dictionary, loaded on first use.
+pub struct Fonts<'a> {
+    doc: &'a Document,
+    resources: Option<&'a Dictionary>,
+    loaded: HashMap<Vec<u8>, Font<'a>>,
+    fallback: Font<'a>,
+}
+
+impl<'a> Fonts<'a> {
+    pub fn new(doc: &'a Document, resources: Option<&'a Dictionary>) -> Self {
+        Fonts { doc, resources, loaded: HashMap::new(), fallback: Font::fallback() }
+    }
+
+    /// The font selected by `Tf`, or a stand-in when it is missing.
+    pub fn get(&mut self, name: Option<&[u8]>) -> &Font<'a> {
+        let Some(name) = name else {
+            return &self.fallback;
+        };
+        if !self.loaded.contains_key(name) {
+            if let Some(dict) = font_resource(self.doc, self.resources, name) {
+                self.loaded.insert(name.to_vec(), Font::load(self.doc, dict));
+            }
+        }
+        self.loaded.get(name).unwrap_or(&self.fallback)
+    }
+}
+
+/// `/W` arrays mix `c [w1 w2 …]` and `c_first c_last w`.
+fn cid_widths(doc: &Document, widths: &Object) -> BTreeMap<u32, f64> {
+    let mut out = BTreeMap::new();
+    let Ok(entries) = resolved(doc, widths).as_array() else {
+        return out;
+    };
+    let mut index = 0;
+    while index < entries.len() {
+        let Some(first) = number(resolved(doc, &entries[index])) else {
+            break;
+        };
+        let first = first as u32;
+        match entries.get(index + 1).map(|entry| resolved(doc, entry)) {
+            Some(Object::Array(list)) => {
+                for (offset, width) in list.iter().enumerate() {
+                    out.insert(first + offset as u32, number(resolved(doc, width)).unwrap_or(0.0));
+                }
+                index += 2;
+            }
+            Some(last) => {
+                let (Some(last), Some(width)) =
+                    (number(last), entries.get(index + 2).and_then(|width| number(resolved(doc, width))))
+                else {
+                    break;
+                };
+                // Bounded : a broken range must not allocate millions of entries.
+                for cid in first..=(last as u32).min(first + 0xFFFF) {
+                    out.insert(cid, width);
+                }
+                index += 3;
+            }
+            None => break,
+        }
+    }
+    out
+}
+
+/// The part of the graphics state that places text, saved by `q`.
+#[derive(Clone, Debug)]
+pub struct GraphicsState {
+    pub ctm: Matrix,
+    /// Resource name of the current font.
+    pub font: Option<Vec<u8>>,
+    pub font_size: f64,
+    pub char_spacing: f64,
+    pub word_spacing: f64,
+    /// `Tz` / 100.
+    pub horizontal_scaling: f64,
+    pub leading: f64,
+    pub rise: f64,
+}
+
+impl GraphicsState {
+    pub fn new(ctm: Matrix) -> Self {
+        GraphicsState {
+            ctm,
+            font: None,
+            font_size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        }
+    }
+
+    /// Apply `op` when it sets part of this state.
+    pub fn apply(&mut self, op: &Operation) {
+        let operand = |index: usize| op.operands.get(index).and_then(number);
+        match op.operator.as_str() {
+            "cm" => {
+                if let Some(m) = matrix(&op.operands) {
+                    self.ctm = multiply(&m, &self.ctm);
+                }
+            }
+            "Tf" => {
+                self.font = op.operands.first().and_then(|name| name.as_name().ok()).map(<[u8]>::to_vec);
+                self.font_size = operand(1).unwrap_or(self.font_size);
+            }
+            "Tc" => self.char_spacing = operand(0).unwrap_or(self.char_spacing),
+            "Tw" => self.word_spacing = operand(0).unwrap_or(self.word_spacing),
+            "Tz" => self.horizontal_scaling = operand(0).map_or(self.horizontal_scaling, |tz| tz / 100.0),
+            "TL" => self.leading = operand(0).unwrap_or(self.leading),
+            "Ts" => self.rise = operand(0).unwrap_or(self.rise),
+            "TD" => self.leading = operand(1).map_or(self.leading, |ty| -ty),
+            "\"" => {
+                self.word_spacing = operand(0).unwrap_or(self.word_spacing);
+                self.char_spacing = operand(1).unwrap_or(self.char_spacing);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Text matrix and text line matrix, reset by `BT`.
+#[derive(Clone, Copy, Debug)]
+pub struct TextCursor {
+    pub tm: Matrix,
+    pub tlm: Matrix,
+}
+
+impl Default for TextCursor {
+    fn default() -> Self {
+        TextCursor { tm: IDENTITY, tlm: IDENTITY }
+    }
+}
+
+impl TextCursor {
+    /// Apply `op` when it moves the cursor ; `'` and `"` move to the
+    /// next line before showing their string. Call after
+    /// [`GraphicsState::apply`], which takes the leading set by `TD`.
+    pub fn apply(&mut self, op: &Operation, state: &GraphicsState) {
+        let operand = |index: usize| op.operands.get(index).and_then(number);
+        match op.operator.as_str() {
+            "BT" => *self = TextCursor::default(),
+            "Td" | "TD" => {
+                let (tx, ty) = (operand(0).unwrap_or(0.0), operand(1).unwrap_or(0.0));
+                self.move_line(tx, ty);
+            }
+            "Tm" => {
+                if let Some(m) = matrix(&op.operands) {
+                    self.tm = m;
+                    self.tlm = m;
+                }
+            }
+            "T*" | "'" | "\"" => self.move_line(0.0, -state.leading),
+            _ => {}
+        }
+    }
+
+    fn move_line(&mut self, tx: f64, ty: f64) {
+        self.tlm = multiply(&[1.0, 0.0, 0.0, 1.0, tx, ty], &self.tlm);
+        self.tm = self.tlm;
+    }
+
+    /// The glyphs of `bytes`, advancing the cursor past them.
+    pub fn show(&mut self, state: &GraphicsState, font: &Font, bytes: &[u8]) -> Vec<Glyph> {
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+        let mut glyphs = Vec::new();
+        for code in font.codes(bytes) {
+            let width = font.width(code) * size;
+            let mut advance = width + state.char_spacing;
+            // Word spacing applies to the single-byte code 32 only.
+            if code == b" " {
+                advance += state.word_spacing;
+            }
+            let to_user = multiply(&self.tm, &state.ctm);
+            let rect = bounds(
+                &to_user,
+                0.0,
+                state.rise + font.descent * size,
+                width * scaling,
+                state.rise + font.ascent * size,
+            );
+            let size = size * to_user[2].hypot(to_user[3]);
+            glyphs.push(Glyph { code: code.to_vec(), text: font.decode(code), rect, advance, size });
+            self.tm = multiply(&[1.0, 0.0, 0.0, 1.0, advance * scaling, 0.0], &self.tm);
+        }
+        glyphs
+    }
+
+    /// A number of a `TJ` array, in thousandths of the font size.
+    pub fn adjust(&mut self, state: &GraphicsState, amount: f64) {
+        let tx = -amount / 1000.0 * state.font_size * state.horizontal_scaling;
+        self.tm = multiply(&[1.0, 0.0, 0.0, 1.0, tx, 0.0], &self.tm);
+    }
+}
+
+/// Every glyph shown on `page`, in content order, those of form
+/// XObjects included.
+pub fn page_glyphs(doc: &Document, page: ObjectId) -> Vec<Glyph> {
+    let mut glyphs = Vec::new();
+    collect_glyphs(doc, &page_content(doc, page), page_resources(doc, page), IDENTITY, 0, &mut glyphs);
+    glyphs
+}
+
+fn collect_glyphs<'a>(
+    doc: &'a Document,
+    content: &[u8],
+    resources: Option<&'a Dictionary>,
+    ctm: Matrix,
+    depth: usize,
+    glyphs: &mut Vec<Glyph>,
+) {
+    // Content lopdf cannot parse has no text we could place anyway.
+    let Ok(segments) = segments(content) else {
+        return;
+    };
+    let mut fonts = Fonts::new(doc, resources);
+    let mut state = GraphicsState::new(ctm);
+    let mut saved = Vec::new();
+    let mut cursor = TextCursor::default();
+    let ops = segments.into_iter().flat_map(|segment| match segment {
+        Segment::Ops(ops) => ops,
+        Segment::Inline(_) => Vec::new(),
+    });
+    for op in ops {
+        state.apply(&op);
+        cursor.apply(&op, &state);
+        match op.operator.as_str() {
+            "q" => saved.push(state.clone()),
+            "Q" => {
+                if let Some(previous) = saved.pop() {
+                    state = previous;
+                }
+            }
+            "Tj" | "TJ" | "'" | "\"" => {
+                let font = fonts.get(state.font.as_deref());
+                for element in shown(&op) {
+                    match element {
+                        Object::String(bytes, _) => glyphs.extend(cursor.show(&state, font, bytes)),
+                        other => cursor.adjust(&state, number(other).unwrap_or(0.0)),
+                    }
+                }
+            }
+            "Do" if depth < MAX_DEPTH => {
+                let name = op.operands.first().and_then(|name| name.as_name().ok()).unwrap_or_default();
+                let Some(form) = xobject(doc, resources, name)
+                    .filter(|xobject| xobject.dict.get(b"Subtype").and_then(Object::as_name_str).ok() == Some("Form"))
+                else {
+                    continue;
+                };
+                let form_resources = dictionary(doc, &form.dict, b"Resources").or(resources);
+                let form_ctm = multiply(&form_matrix(doc, form), &state.ctm);
+                collect_glyphs(doc, &stream_content(form), form_resources, form_ctm, depth + 1, glyphs);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = [2.0, 0.5, -1.0, 3.0, 10.0, 20.0];
+        let inverse = invert(&m).unwrap();
+        let (x, y) = transform(&m, 7.0, -4.0);
+        let (x, y) = transform(&inverse, x, y);
+        assert!((x - 7.0).abs() < 1e-9 && (y + 4.0).abs() < 1e-9);
+        assert!(invert(&[1.0, 2.0, 2.0, 4.0, 0.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn places_glyphs_with_standard_metrics() {
+        let doc = Document::with_version("1.7");
+        let dict = lopdf::dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" };
+        let font = Font::load(&doc, &dict);
+        let mut state = GraphicsState::new([2.0, 0.0, 0.0, 2.0, 0.0, 0.0]);
+        state.font_size = 10.0;
+        state.word_spacing = 5.0;
+        let mut cursor = TextCursor::default();
+        cursor.apply(&Operation::new("Td", vec![Object::Integer(50), Object::Integer(100)]), &state);
+        let glyphs = cursor.show(&state, &font, b"H i");
+        // "H" is 722 units wide, the space 278 plus the word spacing.
+        assert!((glyphs[0].rect.x - 100.0).abs() < 1e-9 && (glyphs[0].rect.w - 14.44).abs() < 1e-9);
+        assert!((glyphs[1].advance - 7.78).abs() < 1e-9);
+        assert!((glyphs[2].rect.x - 2.0 * (50.0 + 7.22 + 7.78)).abs() < 1e-9);
+        assert!((glyphs[0].rect.y - 2.0 * 97.5).abs() < 1e-9 && (glyphs[0].rect.h - 2.0 * 11.5).abs() < 1e-9);
+    }
+}
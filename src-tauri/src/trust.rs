@@ -0,0 +1,96 @@
+//! User-managed trust store (`trusted_certificates.json` in
+//! `app_data_dir`) : the root or intermediate certificates against
+//! which signatures found in opened PDFs are validated.
+
+use std::path::PathBuf;
+
+use der::{Decode, DecodePem, Encode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri_plugin_dialog::DialogExt;
+use x509_cert::Certificate;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::store;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedCertificate {
+    /// Hex SHA-256 of the DER encoding, used as the identifier.
+    pub fingerprint: String,
+    pub subject: String,
+    pub bytes: Vec<u8>,
+}
+
+fn trust_store_path(app: &tauri::AppHandle) -> Result<PathBuf> {
+    Ok(crate::app_data_dir(app)?.join("trusted_certificates.json"))
+}
+
+pub fn fingerprint(der: &[u8]) -> String {
+    crate::pdfio::hex(&Sha256::digest(der)).to_lowercase()
+}
+
+/// Parse a certificate file, accepting both PEM and raw DER.
+pub fn parse_certificate(bytes: &[u8]) -> Result<Certificate> {
+    let invalid = |e: der::Error| Error::new(ErrorKind::Certificate, "certificate_invalid").with_details(e);
+    if bytes.starts_with(b"-----BEGIN") {
+        Certificate::from_pem(bytes).map_err(invalid)
+    } else {
+        Certificate::from_der(bytes).map_err(invalid)
+    }
+}
+
+/// Load the anchors as parsed certificates ; entries that no longer
+/// parse are skipped rather than failing every verification.
+pub fn load_anchors(app: &tauri::AppHandle) -> Result<Vec<Certificate>> {
+    let entries: Vec<TrustedCertificate> = store::load_json(&trust_store_path(app)?)?.unwrap_or_default();
+    Ok(entries
+        .iter()
+        .filter_map(|entry| Certificate::from_der(&entry.bytes).ok())
+        .collect())
+}
+
+#[tauri::command]
+pub fn list_trusted_certificates(app: tauri::AppHandle) -> Result<Vec<TrustedCertificate>> {
+    Ok(store::load_json(&trust_store_path(&app)?)?.unwrap_or_default())
+}
+
+/// Add a certificate (`.pem`, `.crt`, `.cer`, `.der`) chosen through
+/// the open dialog ; the renderer never names the file. Adding a
+/// certificate already present, or cancelling, is a no-op.
+#[tauri::command(async)]
+pub fn add_trusted_certificate(app: tauri::AppHandle) -> Result<Vec<TrustedCertificate>> {
+    let Some(picked) =
+        app.dialog().file().add_filter("Certificate", &["pem", "crt", "cer", "der"]).blocking_pick_file()
+    else {
+        return list_trusted_certificates(app);
+    };
+    let path = &picked.into_path().map_err(|e| Error::new(ErrorKind::InvalidInput, "path_invalid").with_details(e))?;
+    let bytes = std::fs::read(path).map_err(|e| Error::io("certificate_read_failed", path, &e))?;
+    let cert = parse_certificate(&bytes).map_err(|err| err.with_path(path))?;
+    let der = cert
+        .to_der()
+        .map_err(|e| Error::new(ErrorKind::Certificate, "certificate_invalid").with_path(path).with_details(e))?;
+
+    let store_path = trust_store_path(&app)?;
+    let mut entries: Vec<TrustedCertificate> = store::load_json(&store_path)?.unwrap_or_default();
+    let fingerprint = fingerprint(&der);
+    if !entries.iter().any(|entry| entry.fingerprint == fingerprint) {
+        entries.push(TrustedCertificate {
+            fingerprint,
+            subject: cert.tbs_certificate.subject.to_string(),
+            bytes: der,
+        });
+        store::save_json(&store_path, &entries)?;
+    }
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn remove_trusted_certificate(app: tauri::AppHandle, fingerprint: String) -> Result<Vec<TrustedCertificate>> {
+    let store_path = trust_store_path(&app)?;
+    let mut entries: Vec<TrustedCertificate> = store::load_json(&store_path)?.unwrap_or_default();
+    entries.retain(|entry| entry.fingerprint != fingerprint);
+    store::save_json(&store_path, &entries)?;
+    Ok(entries)
+}
@@ -0,0 +1,452 @@
+//! RFC 3161 timestamp client, used to lift exported signatures from
+//! PAdES-B-B to PAdES-B-T.
+//!
+//! The token is requested over the signer's signature value, validated
+//! (status, message imprint, nonce, TSA signature and key usage) and
+//! then embedded as the `signature-time-stamp` unsigned attribute of
+//! the CMS signer info. The TSA URL is a user setting kept in
+//! `tsa.json` in `app_data_dir`.
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cms::content_info::ContentInfo;
+use cms::signed_data::{SignedData, SignerInfos};
+use const_oid::db::{rfc5911, rfc5912};
+use const_oid::ObjectIdentifier;
+use der::asn1::{GeneralizedTime, OctetString, SetOfVec, Uint};
+use der::{Any, Decode, Encode, Sequence};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spki::AlgorithmIdentifierOwned;
+use x509_cert::attr::Attribute;
+use x509_cert::ext::pkix::ExtendedKeyUsage;
+use x509_cert::Certificate;
+
+use crate::error::{self, Error, ErrorKind};
+use crate::store;
+use crate::verify;
+
+/// `id-ct-TSTInfo` (RFC 3161).
+pub const ID_CT_TST_INFO: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.4");
+/// `id-aa-signatureTimeStampToken` (RFC 3161 appendix A).
+pub const ID_AA_SIGNATURE_TIME_STAMP_TOKEN: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.2.14");
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Timestamp replies are a few kilobytes ; anything bigger is refused.
+const MAX_RESPONSE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Sequence, Clone, Debug, PartialEq, Eq)]
+pub struct MessageImprint {
+    pub hash_algorithm: AlgorithmIdentifierOwned,
+    pub hashed_message: OctetString,
+}
+
+#[derive(Sequence, Debug)]
+pub struct TimeStampReq {
+    pub version: u8,
+    pub message_imprint: MessageImprint,
+    #[asn1(optional = "true")]
+    pub req_policy: Option<ObjectIdentifier>,
+    #[asn1(optional = "true")]
+    pub nonce: Option<Uint>,
+    #[asn1(default = "Default::default")]
+    pub cert_req: bool,
+}
+
+#[derive(Sequence, Debug)]
+pub struct PkiStatusInfo {
+    pub status: u8,
+    #[asn1(optional = "true")]
+    pub status_string: Option<Vec<String>>,
+    #[asn1(optional = "true")]
+    pub fail_info: Option<der::asn1::BitString>,
+}
+
+#[derive(Sequence, Debug)]
+pub struct TimeStampResp {
+    pub status: PkiStatusInfo,
+    #[asn1(optional = "true")]
+    pub time_stamp_token: Option<ContentInfo>,
+}
+
+#[derive(Sequence, Debug)]
+pub struct Accuracy {
+    #[asn1(optional = "true")]
+    pub seconds: Option<u64>,
+    #[asn1(context_specific = "0", tag_mode = "IMPLICIT", optional = "true")]
+    pub millis: Option<u16>,
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    pub micros: Option<u16>,
+}
+
+#[derive(Sequence, Debug)]
+pub struct TstInfo {
+    pub version: u8,
+    pub policy: ObjectIdentifier,
+    pub message_imprint: MessageImprint,
+    pub serial_number: Uint,
+    pub gen_time: GeneralizedTime,
+    #[asn1(optional = "true")]
+    pub accuracy: Option<Accuracy>,
+    #[asn1(default = "Default::default")]
+    pub ordering: bool,
+    #[asn1(optional = "true")]
+    pub nonce: Option<Uint>,
+    #[asn1(context_specific = "0", optional = "true")]
+    pub tsa: Option<Any>,
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    pub extensions: Option<x509_cert::ext::Extensions>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TsaSettings {
+    /// e.g. `http://timestamp.digicert.com`. `None` disables timestamping.
+    pub url: Option<String>,
+}
+
+fn settings_path(app: &tauri::AppHandle) -> error::Result<PathBuf> {
+    Ok(crate::app_data_dir(app)?.join("tsa.json"))
+}
+
+pub fn load_settings(app: &tauri::AppHandle) -> error::Result<TsaSettings> {
+    Ok(store::load_json(&settings_path(app)?)?.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn get_tsa_settings(app: tauri::AppHandle) -> error::Result<TsaSettings> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub fn set_tsa_settings(app: tauri::AppHandle, settings: TsaSettings) -> error::Result<()> {
+    if let Some(url) = settings.url.as_deref() {
+        let invalid = || Error::new(ErrorKind::InvalidInput, "tsa_url_invalid");
+        let parsed = url::Url::parse(url).map_err(|e| invalid().with_details(e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(invalid().with_details("http or https expected"));
+        }
+    }
+    store::save_json(&settings_path(&app)?, &settings)
+}
+
+fn sha256_imprint(data: &[u8]) -> Result<MessageImprint, String> {
+    Ok(MessageImprint {
+        hash_algorithm: AlgorithmIdentifierOwned { oid: rfc5912::ID_SHA_256, parameters: None },
+        hashed_message: OctetString::new(Sha256::digest(data).to_vec()).map_err(|e| e.to_string())?,
+    })
+}
+
+fn random_nonce() -> Result<Uint, String> {
+    use p256::elliptic_curve::rand_core::{OsRng, RngCore};
+
+    let mut nonce = [0u8; 8];
+    OsRng.fill_bytes(&mut nonce);
+    // Positive INTEGER : clear the top bit so no leading 0x00 is needed.
+    nonce[0] &= 0x7f;
+    Uint::new(&nonce).map_err(|e| e.to_string())
+}
+
+/// Build the request for a token over `data` (the signature value).
+pub fn build_request(data: &[u8]) -> Result<TimeStampReq, String> {
+    Ok(TimeStampReq {
+        version: 1,
+        message_imprint: sha256_imprint(data)?,
+        req_policy: None,
+        nonce: Some(random_nonce()?),
+        cert_req: true,
+    })
+}
+
+fn post(url: &str, body: &[u8]) -> Result<Vec<u8>, String> {
+    let response = ureq::post(url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/timestamp-query")
+        .send_bytes(body)
+        .map_err(|e| format!("timestamping failed: {e}"))?;
+    let mut out = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_RESPONSE_SIZE)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("timestamping failed: {e}"))?;
+    Ok(out)
+}
+
+fn has_time_stamping_usage(cert: &Certificate) -> bool {
+    cert.tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .filter(|ext| ext.extn_id == rfc5912::ID_CE_EXT_KEY_USAGE)
+        .filter_map(|ext| ExtendedKeyUsage::from_der(ext.extn_value.as_bytes()).ok())
+        .any(|usage| usage.0.contains(&rfc5912::ID_KP_TIME_STAMPING))
+}
+
+/// Validate a `TimeStampResp` against the request it answers and return
+/// the token plus its decoded `TSTInfo`.
+pub fn validate_response(der: &[u8], request: &TimeStampReq) -> Result<(ContentInfo, TstInfo), String> {
+    let response = TimeStampResp::from_der(der).map_err(|e| format!("invalid timestamp response: {e}"))?;
+    if response.status.status > 1 {
+        let detail = response.status.status_string.unwrap_or_default().join(" ");
+        return Err(format!("timestamp refused (status {}): {detail}", response.status.status));
+    }
+    let token = response
+        .time_stamp_token
+        .ok_or_else(|| "timestamp response without a token".to_string())?;
+    let tst_info = validate_token(&token)?.tst_info;
+
+    if tst_info.message_imprint != request.message_imprint {
+        return Err("timestamp token: digest differs from the request".into());
+    }
+    if tst_info.nonce != request.nonce {
+        return Err("timestamp token: nonce differs from the request".into());
+    }
+    Ok((token, tst_info))
+}
+
+/// A token whose signature checked out. Whether its authority is one
+/// the user trusts is left to the caller.
+pub struct ValidToken {
+    pub tst_info: TstInfo,
+    /// The certificate that signed the token.
+    pub authority: Certificate,
+    /// Every certificate shipped in the token, to chain `authority` up.
+    pub certificates: Vec<Certificate>,
+}
+
+/// Check a token on its own : a `SignedData` over a `TSTInfo`, signed
+/// by a certificate carrying the time-stamping extended key usage.
+pub fn validate_token(token: &ContentInfo) -> Result<ValidToken, String> {
+    if token.content_type != rfc5911::ID_SIGNED_DATA {
+        return Err("invalid timestamp token: SignedData expected".into());
+    }
+    let signed_data: SignedData = token
+        .content
+        .decode_as()
+        .map_err(|e| format!("invalid timestamp token: {e}"))?;
+    if signed_data.encap_content_info.econtent_type != ID_CT_TST_INFO {
+        return Err("invalid timestamp token: TSTInfo expected".into());
+    }
+    let econtent = signed_data
+        .encap_content_info
+        .econtent
+        .as_ref()
+        .ok_or_else(|| "invalid timestamp token: missing content".to_string())?
+        .decode_as::<OctetString>()
+        .map_err(|e| format!("invalid timestamp token: {e}"))?;
+    let tst_info = TstInfo::from_der(econtent.as_bytes()).map_err(|e| format!("invalid TSTInfo: {e}"))?;
+
+    let certs = verify::embedded_certificates(&signed_data);
+    let signer = signed_data
+        .signer_infos
+        .0
+        .get(0)
+        .ok_or_else(|| "timestamp token without a signer".to_string())?;
+    let cert = verify::signer_certificate(signer, &certs)
+        .ok_or_else(|| "timestamp token: authority certificate missing".to_string())?;
+    if !has_time_stamping_usage(cert) {
+        return Err("timestamp token: certificate without the timeStamping usage".into());
+    }
+    if !verify::check_integrity(signer, cert, &[econtent.as_bytes()])? {
+        return Err("timestamp token: invalid signature".into());
+    }
+    let authority = cert.clone();
+    Ok(ValidToken { tst_info, authority, certificates: certs })
+}
+
+/// Request, validate and return a token over `data`.
+pub fn fetch_token(url: &str, data: &[u8]) -> Result<ContentInfo, String> {
+    let request = build_request(data)?;
+    let body = request.to_der().map_err(|e| e.to_string())?;
+    let response = post(url, &body)?;
+    validate_response(&response, &request).map(|(token, _)| token)
+}
+
+/// Timestamp the first signer of a CMS `SignedData` : its signature
+/// value is sent to the TSA and the token stored as an unsigned
+/// attribute. Returns the re-encoded `ContentInfo`.
+pub fn add_signature_timestamp(content_info: ContentInfo, url: &str) -> Result<ContentInfo, String> {
+    let mut signed_data: SignedData = content_info.content.decode_as().map_err(|e| format!("invalid cms: {e}"))?;
+    let mut signer_infos = signed_data.signer_infos.0.into_vec();
+    let signer = signer_infos
+        .first_mut()
+        .ok_or_else(|| "cms without a signer".to_string())?;
+
+    let token = fetch_token(url, signer.signature.as_bytes())?;
+    let attribute = Attribute {
+        oid: ID_AA_SIGNATURE_TIME_STAMP_TOKEN,
+        values: SetOfVec::try_from(vec![Any::encode_from(&token).map_err(|e| e.to_string())?])
+            .map_err(|e| e.to_string())?,
+    };
+    let mut unsigned = signer.unsigned_attrs.take().map(|attrs| attrs.into_vec()).unwrap_or_default();
+    unsigned.push(attribute);
+    signer.unsigned_attrs = Some(SetOfVec::try_from(unsigned).map_err(|e| e.to_string())?);
+
+    signed_data.signer_infos = SignerInfos(SetOfVec::try_from(signer_infos).map_err(|e| e.to_string())?);
+    Ok(ContentInfo {
+        content_type: rfc5911::ID_SIGNED_DATA,
+        content: Any::encode_from(&signed_data).map_err(|e| e.to_string())?,
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use cms::builder::{SignedDataBuilder, SignerInfoBuilder};
+    use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+    use cms::signed_data::{EncapsulatedContentInfo, SignerIdentifier};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::name::Name;
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::time::Validity;
+
+    pub fn tsa_identity() -> (p256::ecdsa::SigningKey, Certificate) {
+        let key = p256::ecdsa::SigningKey::random(&mut p256::elliptic_curve::rand_core::OsRng);
+        let spki = spki::SubjectPublicKeyInfoOwned::from_key(*key.verifying_key()).unwrap();
+        let mut builder = CertificateBuilder::new(
+            Profile::Root,
+            SerialNumber::from(7u32),
+            Validity::from_now(Duration::from_secs(3600)).unwrap(),
+            Name::from_str("CN=Local TSA,O=Cerfini Tests").unwrap(),
+            spki,
+            &key,
+        )
+        .unwrap();
+        builder
+            .add_extension(&ExtendedKeyUsage(vec![rfc5912::ID_KP_TIME_STAMPING]))
+            .unwrap();
+        let cert = builder.build::<p256::ecdsa::DerSignature>().unwrap();
+        (key, cert)
+    }
+
+    fn sign_tst_info(key: &p256::ecdsa::SigningKey, cert: &Certificate, tst_info: &TstInfo) -> ContentInfo {
+        let tst_der = tst_info.to_der().unwrap();
+        let content = EncapsulatedContentInfo {
+            econtent_type: ID_CT_TST_INFO,
+            econtent: Some(Any::encode_from(&OctetString::new(tst_der).unwrap()).unwrap()),
+        };
+        let sid = SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+            issuer: cert.tbs_certificate.issuer.clone(),
+            serial_number: cert.tbs_certificate.serial_number.clone(),
+        });
+        let digest = AlgorithmIdentifierOwned { oid: rfc5912::ID_SHA_256, parameters: None };
+        let signer = SignerInfoBuilder::new(key, sid, digest.clone(), &content, None).unwrap();
+        SignedDataBuilder::new(&content)
+            .add_digest_algorithm(digest)
+            .unwrap()
+            .add_certificate(CertificateChoices::Certificate(cert.clone()))
+            .unwrap()
+            .add_signer_info::<_, p256::ecdsa::DerSignature>(signer)
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    /// Answer `request` the way a real TSA would.
+    fn respond(key: &p256::ecdsa::SigningKey, cert: &Certificate, request: &TimeStampReq) -> Vec<u8> {
+        let tst_info = TstInfo {
+            version: 1,
+            policy: ObjectIdentifier::new_unwrap("1.2.3.4.1"),
+            message_imprint: request.message_imprint.clone(),
+            serial_number: Uint::new(&[1]).unwrap(),
+            gen_time: GeneralizedTime::from_system_time(std::time::SystemTime::now()).unwrap(),
+            accuracy: None,
+            ordering: false,
+            nonce: request.nonce.clone(),
+            tsa: None,
+            extensions: None,
+        };
+        TimeStampResp {
+            status: PkiStatusInfo { status: 0, status_string: None, fail_info: None },
+            time_stamp_token: Some(sign_tst_info(key, cert, &tst_info)),
+        }
+        .to_der()
+        .unwrap()
+    }
+
+    /// Tiny offline TSA : a one-thread HTTP server on an ephemeral
+    /// loopback port, answering `requests` timestamp queries. Returns
+    /// its URL.
+    pub fn spawn_local_tsa(requests: usize) -> String {
+        let (key, cert) = tsa_identity();
+        spawn_tsa_as(requests, key, cert)
+    }
+
+    /// Same as `spawn_local_tsa`, signing with the given identity.
+    pub fn spawn_tsa_as(requests: usize, key: p256::ecdsa::SigningKey, cert: Certificate) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/tsa", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let request = TimeStampReq::from_der(&body).unwrap();
+                let reply = respond(&key, &cert, &request);
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/timestamp-reply\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    reply.len()
+                )
+                .unwrap();
+                stream.write_all(&reply).unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn local_tsa_token_validates() {
+        let url = spawn_local_tsa(1);
+        let request = build_request(b"signature value").unwrap();
+        let response = post(&url, &request.to_der().unwrap()).unwrap();
+        let (_, tst_info) = validate_response(&response, &request).unwrap();
+        assert_eq!(tst_info.message_imprint, sha256_imprint(b"signature value").unwrap());
+    }
+
+    #[test]
+    fn token_for_another_request_is_rejected() {
+        let (key, cert) = tsa_identity();
+        let asked = build_request(b"mine").unwrap();
+        let other = build_request(b"theirs").unwrap();
+        let response = respond(&key, &cert, &other);
+        assert!(validate_response(&response, &asked).is_err());
+    }
+
+    #[test]
+    fn rejected_status_is_an_error() {
+        let request = build_request(b"x").unwrap();
+        let response = TimeStampResp {
+            status: PkiStatusInfo { status: 2, status_string: Some(vec!["bad alg".into()]), fail_info: None },
+            time_stamp_token: None,
+        }
+        .to_der()
+        .unwrap();
+        let err = validate_response(&response, &request).unwrap_err();
+        assert!(err.contains("bad alg"));
+    }
+}
@@ -0,0 +1,612 @@
+//! Encryption at rest for the signature and paraph images.
+//!
+//! The vault is opt-in : until the user sets a passphrase through
+//! `unlock_vault`, the images stay in plain and behave exactly as
+//! before. The first unlock creates `vault.json` (Argon2id salt and
+//! parameters plus a sealed check value) and seals every existing
+//! image with XChaCha20-Poly1305 : each database row on its own (see
+//! [`seal_blob`]), and the legacy JSON stores and gallery snapshots as
+//! whole envelopes. From then on the images can only be read or
+//! written while the vault is unlocked ;
+//! the key lives in memory only and is dropped after an idle timeout,
+//! or kept in the OS keyring when the user asks to be remembered.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use zeroize::Zeroizing;
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::schema::{self, Schema};
+use crate::{history, store};
+
+pub const VAULT_FILE: &str = "vault.json";
+const CIPHER: &str = "xchacha20poly1305";
+const CHECK_PLAINTEXT: &[u8] = b"cerfini-vault";
+const KEYRING_SERVICE: &str = "com.coubiac.cerfini";
+const KEYRING_USER: &str = "vault-key";
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 15 * 60;
+const IDLE_POLL: Duration = Duration::from_secs(30);
+
+/// Store files sealed by the vault. Templates and snippets hold no
+/// biometric-like data and stay readable while locked.
+const SEALED_STORES: &[&str] = &["signatures.json", "paraphs.json"];
+
+pub type Key = Zeroizing<[u8; 32]>;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct KdfParams {
+    algorithm: String,
+    salt: String,
+    mem_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+/// On-disk form of an encrypted payload. The nonce and ciphertext are
+/// base64 so the sealed store is still a JSON document and keeps
+/// benefiting from the `.bak` rotation in [`store`].
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    /// `CHECK_PLAINTEXT` sealed with the derived key, used to reject a
+    /// wrong passphrase before touching any store.
+    check: Sealed,
+    idle_timeout_secs: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    initialized: bool,
+    unlocked: bool,
+    idle_timeout_secs: u64,
+}
+
+struct Unlocked {
+    key: Key,
+    last_used: Instant,
+    idle_timeout: Duration,
+}
+
+#[derive(Default)]
+pub struct Vault(Mutex<Option<Unlocked>>);
+
+impl Vault {
+    /// Current key, refreshing the idle timer. Fails when locked or when
+    /// the idle timeout elapsed since the last access.
+    pub fn key(&self) -> Result<Key> {
+        let mut guard = self.0.lock().unwrap();
+        match guard.as_mut() {
+            Some(unlocked) if unlocked.last_used.elapsed() < unlocked.idle_timeout => {
+                unlocked.last_used = Instant::now();
+                Ok(unlocked.key.clone())
+            }
+            _ => {
+                *guard = None;
+                Err(Error::new(ErrorKind::VaultLocked, "vault_locked"))
+            }
+        }
+    }
+
+    /// Drop the key ; the next store access requires an unlock.
+    pub fn lock(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    fn is_unlocked(&self) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|unlocked| unlocked.last_used.elapsed() < unlocked.idle_timeout)
+            .unwrap_or(false)
+    }
+
+    /// Drop the key if it has been idle for too long. Returns true when
+    /// this call is the one that locked the vault.
+    fn expire_idle(&self) -> bool {
+        let mut guard = self.0.lock().unwrap();
+        let expired = guard
+            .as_ref()
+            .map(|unlocked| unlocked.last_used.elapsed() >= unlocked.idle_timeout)
+            .unwrap_or(false);
+        if expired {
+            *guard = None;
+        }
+        expired
+    }
+}
+
+fn vault_path(dir: &Path) -> PathBuf {
+    dir.join(VAULT_FILE)
+}
+
+/// Whether the user set up a vault : the galleries are then sealed.
+pub fn is_initialized(dir: &Path) -> bool {
+    vault_path(dir).exists()
+}
+
+fn read_vault_file(dir: &Path) -> Result<Option<VaultFile>> {
+    store::load_json(&vault_path(dir))
+}
+
+fn corrupt() -> Error {
+    Error::new(ErrorKind::CorruptData, "vault_corrupt")
+}
+
+fn derive_key(passphrase: &str, kdf: &KdfParams) -> Result<Key> {
+    if kdf.algorithm != "argon2id" {
+        return Err(corrupt().with_details(format!("unsupported kdf: {}", kdf.algorithm)));
+    }
+    let salt = BASE64
+        .decode(&kdf.salt)
+        .map_err(|e| corrupt().with_details(format!("invalid salt: {e}")))?;
+    let params = Params::new(kdf.mem_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| corrupt().with_details(format!("invalid kdf parameters: {e}")))?;
+    let mut key: Key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key[..])
+        .map_err(|e| corrupt().with_details(format!("key derivation failed: {e}")))?;
+    Ok(key)
+}
+
+/// `aad` binds the ciphertext to its store name so one sealed file
+/// cannot be swapped for another (paraphs served as signatures).
+fn seal(key: &Key, aad: &str, plaintext: &[u8]) -> Result<Sealed> {
+    let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key[..]));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: aad.as_bytes() })
+        .map_err(|_| Error::new(ErrorKind::Io, "vault_seal_failed"))?;
+    Ok(Sealed {
+        cipher: CIPHER.into(),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn open(key: &Key, aad: &str, sealed: &Sealed) -> Result<Zeroizing<Vec<u8>>> {
+    if sealed.cipher != CIPHER {
+        return Err(corrupt().with_details(format!("unsupported algorithm: {}", sealed.cipher)));
+    }
+    let nonce = BASE64
+        .decode(&sealed.nonce)
+        .map_err(|e| corrupt().with_details(format!("invalid nonce: {e}")))?;
+    if nonce.len() != 24 {
+        return Err(corrupt().with_details("invalid nonce"));
+    }
+    let ciphertext = BASE64
+        .decode(&sealed.ciphertext)
+        .map_err(|e| corrupt().with_details(format!("invalid ciphertext: {e}")))?;
+    let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key[..]));
+    cipher
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: aad.as_bytes() })
+        .map(Zeroizing::new)
+        .map_err(|_| corrupt().with_details("decryption failed"))
+}
+
+/// Seal one database blob as `nonce || ciphertext`. Like [`seal`],
+/// `aad` names the row so a blob cannot be moved to another one.
+pub fn seal_blob(key: &Key, aad: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key[..]));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad: aad.as_bytes() })
+        .map_err(|_| Error::new(ErrorKind::Io, "vault_seal_failed"))?;
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+pub fn open_blob(key: &Key, aad: &str, blob: &[u8]) -> Result<Vec<u8>> {
+    if blob.len() < 24 {
+        return Err(corrupt().with_details("truncated sealed blob"));
+    }
+    let (nonce, ciphertext) = blob.split_at(24);
+    let cipher = XChaCha20Poly1305::new(chacha20poly1305::Key::from_slice(&key[..]));
+    cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: aad.as_bytes() })
+        .map_err(|_| corrupt().with_details("decryption failed"))
+}
+
+fn store_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Either a legacy plaintext store or a sealed envelope. Plaintext is
+/// still accepted once the vault exists so a `.bak` written before the
+/// migration remains a usable fallback.
+fn decode_store<T: DeserializeOwned>(key: &Key, aad: &str, bytes: &[u8]) -> Result<T> {
+    let invalid = |e: serde_json::Error| Error::new(ErrorKind::CorruptData, "json_invalid").with_details(e);
+    let value: serde_json::Value = serde_json::from_slice(bytes).map_err(invalid)?;
+    if value.get("cipher").is_some() {
+        let sealed: Sealed = serde_json::from_value(value).map_err(invalid)?;
+        let plaintext = open(key, aad, &sealed)?;
+        serde_json::from_slice(&plaintext).map_err(invalid)
+    } else {
+        serde_json::from_value(value).map_err(invalid)
+    }
+}
+
+/// Load a versioned store that may be sealed. Falls through to plain
+/// [`schema::load`] while no vault has been set up.
+pub fn load_store<T: DeserializeOwned>(vault: &Vault, dir: &Path, path: &Path, schema: &Schema) -> Result<Option<T>> {
+    if !is_initialized(dir) {
+        return schema::load(schema, path);
+    }
+    let key = vault.key()?;
+    let aad = store_name(path);
+    store::load_with(path, |bytes| schema::decode(schema, decode_store(&key, &aad, bytes)?))
+}
+
+/// The bytes [`load_store`] reads back for `value` in the store file
+/// `name` : the versioned envelope, sealed when a vault exists. The
+/// history snapshots of the galleries are written in this form.
+pub fn encode_store<T: Serialize + ?Sized>(vault: &Vault, dir: &Path, name: &str, schema: &Schema, value: &T) -> Result<Vec<u8>> {
+    let unserializable = |e: serde_json::Error| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e);
+    let plaintext = serde_json::to_vec(&schema::envelope(schema, value)?).map_err(unserializable)?;
+    if !is_initialized(dir) {
+        return Ok(plaintext);
+    }
+    let plaintext = Zeroizing::new(plaintext);
+    let sealed = seal(&vault.key()?, name, &plaintext)?;
+    serde_json::to_vec(&sealed).map_err(unserializable)
+}
+
+/// Re-write one plaintext file (primary or `.bak`) as a sealed
+/// envelope in place. Already-sealed and unreadable files are left
+/// untouched.
+fn seal_file_in_place(key: &Key, aad: &str, path: &Path) -> Result<()> {
+    let Ok(bytes) = std::fs::read(path) else {
+        return Ok(());
+    };
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Ok(());
+    };
+    if value.get("cipher").is_some() {
+        return Ok(());
+    }
+    let sealed = seal(key, aad, &bytes)?;
+    let out = serde_json::to_vec(&sealed)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, "json_unserializable").with_details(e))?;
+    store::write_atomic(path, &out, false).map_err(|e| Error::io("store_write_failed", path, &e))
+}
+
+/// Seal the gallery files written in plain before the vault existed :
+/// the legacy stores, their `.bak`, the copies an earlier release left
+/// once imported into the database, and the history snapshots.
+fn migrate_plaintext_stores(key: &Key, dir: &Path) -> Result<()> {
+    for name in SEALED_STORES {
+        let path = dir.join(name);
+        for file in [path.clone(), store::backup_path(&path)] {
+            seal_file_in_place(key, name, &file)?;
+            seal_file_in_place(key, name, &crate::db::imported_path(&file))?;
+        }
+        for snapshot in history::snapshot_files(dir, name) {
+            seal_file_in_place(key, name, &snapshot)?;
+        }
+    }
+    Ok(())
+}
+
+fn create_vault_file(dir: &Path, passphrase: &str) -> Result<(VaultFile, Key)> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams {
+        algorithm: "argon2id".into(),
+        salt: BASE64.encode(salt),
+        mem_kib: 19 * 1024,
+        iterations: 2,
+        parallelism: 1,
+    };
+    let key = derive_key(passphrase, &kdf)?;
+    let vault = VaultFile {
+        version: 1,
+        kdf,
+        check: seal(&key, VAULT_FILE, CHECK_PLAINTEXT)?,
+        idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+    };
+    store::save_json(&vault_path(dir), &vault)?;
+    Ok((vault, key))
+}
+
+fn verify_key(vault: &VaultFile, key: &Key) -> Result<()> {
+    match open(key, VAULT_FILE, &vault.check) {
+        Ok(check) if check.as_slice() == CHECK_PLAINTEXT => Ok(()),
+        _ => Err(Error::new(ErrorKind::AuthenticationFailed, "vault_wrong_passphrase")),
+    }
+}
+
+fn keyring_unavailable(err: keyring::Error) -> Error {
+    Error::new(ErrorKind::Unavailable, "keyring_unavailable").with_details(err)
+}
+
+fn keyring_entry() -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(keyring_unavailable)
+}
+
+fn key_from_keyring() -> Result<Key> {
+    let secret = Zeroizing::new(keyring_entry()?.get_secret().map_err(|e| match e {
+        keyring::Error::NoEntry => Error::new(ErrorKind::AuthenticationFailed, "keyring_no_key"),
+        e => keyring_unavailable(e),
+    })?);
+    let bytes: [u8; 32] = secret
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::new(ErrorKind::CorruptData, "keyring_key_invalid"))?;
+    Ok(Zeroizing::new(bytes))
+}
+
+fn status(vault: &Vault, dir: &Path) -> Result<VaultStatus> {
+    let file = read_vault_file(dir)?;
+    Ok(VaultStatus {
+        initialized: file.is_some(),
+        unlocked: vault.is_unlocked(),
+        idle_timeout_secs: file
+            .map(|file| file.idle_timeout_secs)
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS),
+    })
+}
+
+fn install(vault: &Vault, key: Key, file: &VaultFile) {
+    *vault.0.lock().unwrap() = Some(Unlocked {
+        key,
+        last_used: Instant::now(),
+        idle_timeout: Duration::from_secs(file.idle_timeout_secs),
+    });
+}
+
+/// Unlock for a run without a window (the command-line mode) : no
+/// vault means plaintext stores and nothing to do ; otherwise the key
+/// comes from `passphrase` or the keyring. Never creates a vault nor
+/// migrates stores.
+pub fn unlock_headless(vault: &Vault, dir: &Path, passphrase: Option<&str>) -> Result<()> {
+    let Some(file) = read_vault_file(dir)? else {
+        return Ok(());
+    };
+    let key = match passphrase {
+        Some(passphrase) => derive_key(passphrase, &file.kdf)?,
+        None => key_from_keyring()?,
+    };
+    verify_key(&file, &key)?;
+    install(vault, key, &file);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn vault_status(app: tauri::AppHandle, vault: tauri::State<Vault>) -> Result<VaultStatus> {
+    status(&vault, &crate::app_data_dir(&app)?)
+}
+
+/// Unlock the vault, creating it on first use.
+///
+/// With a passphrase, the key is derived with Argon2id and checked
+/// against `vault.json` ; `remember` additionally stores it in the OS
+/// keyring. Without one, the key is fetched from the keyring, which
+/// fails when it was never remembered or the platform has no keyring —
+/// the frontend then prompts for the passphrase.
+#[tauri::command]
+pub fn unlock_vault(
+    app: tauri::AppHandle,
+    vault: tauri::State<Vault>,
+    passphrase: Option<String>,
+    remember: bool,
+) -> Result<VaultStatus> {
+    let dir = crate::app_data_dir(&app)?;
+    let passphrase = passphrase.map(Zeroizing::new);
+
+    let (file, key) = match (read_vault_file(&dir)?, passphrase.as_deref()) {
+        (Some(file), Some(passphrase)) => {
+            let key = derive_key(passphrase, &file.kdf)?;
+            (file, key)
+        }
+        (Some(file), None) => (file, key_from_keyring()?),
+        (None, Some(passphrase)) => {
+            if passphrase.is_empty() {
+                return Err(Error::new(ErrorKind::InvalidInput, "passphrase_empty"));
+            }
+            std::fs::create_dir_all(&dir).map_err(|e| Error::io("create_dir_failed", &dir, &e))?;
+            create_vault_file(&dir, passphrase)?
+        }
+        (None, None) => return Err(Error::new(ErrorKind::InvalidInput, "passphrase_required")),
+    };
+    verify_key(&file, &key)?;
+    migrate_plaintext_stores(&key, &dir)?;
+    crate::db::seal_assets(&dir, &key)?;
+
+    if remember && passphrase.is_some() {
+        if let Err(err) = keyring_entry().and_then(|entry| entry.set_secret(&key[..]).map_err(keyring_unavailable)) {
+            eprintln!("vault keyring store failed: {err}");
+        }
+    }
+
+    install(&vault, key, &file);
+    if let Err(err) = app.emit("vault-unlocked", ()) {
+        eprintln!("emit vault-unlocked failed: {err}");
+    }
+    status(&vault, &dir)
+}
+
+#[tauri::command]
+pub fn lock_vault(app: tauri::AppHandle, vault: tauri::State<Vault>) -> Result<()> {
+    vault.lock();
+    if let Err(err) = app.emit("vault-locked", ()) {
+        eprintln!("emit vault-locked failed: {err}");
+    }
+    Ok(())
+}
+
+/// Forget the key remembered in the OS keyring ; the next unlock will
+/// require the passphrase again.
+#[tauri::command]
+pub fn forget_vault_keyring() -> Result<()> {
+    match keyring_entry()?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(keyring_unavailable(err)),
+    }
+}
+
+#[tauri::command]
+pub fn set_vault_idle_timeout(
+    app: tauri::AppHandle,
+    vault: tauri::State<Vault>,
+    secs: u64,
+) -> Result<VaultStatus> {
+    if secs < 60 {
+        return Err(Error::new(ErrorKind::InvalidInput, "idle_timeout_too_short").with_details("60 s minimum"));
+    }
+    let dir = crate::app_data_dir(&app)?;
+    let mut file = read_vault_file(&dir)?.ok_or_else(|| Error::new(ErrorKind::VaultLocked, "vault_not_initialized"))?;
+    // Changing the policy is a privileged operation : require the vault
+    // to be unlocked so a locked session cannot extend its own timeout.
+    vault.key()?;
+    file.idle_timeout_secs = secs;
+    store::save_json(&vault_path(&dir), &file)?;
+    if let Some(unlocked) = vault.0.lock().unwrap().as_mut() {
+        unlocked.idle_timeout = Duration::from_secs(secs);
+    }
+    status(&vault, &dir)
+}
+
+/// Background thread that locks the vault once the idle timeout
+/// elapses, so the key does not linger in memory while the app sits
+/// unattended. Emits `"vault-locked"` so the frontend can drop its
+/// decoded copies of the images.
+pub fn spawn_idle_watch(app: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(IDLE_POLL);
+        if app.state::<Vault>().expire_idle() {
+            if let Err(err) = app.emit("vault-locked", ()) {
+                eprintln!("emit vault-locked failed: {err}");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A vault set up in `dir` and unlocked.
+    pub fn unlocked(dir: &Path) -> Vault {
+        let (file, key) = create_vault_file(dir, "correct horse").unwrap();
+        let vault = Vault::default();
+        install(&vault, key, &file);
+        vault
+    }
+
+    fn test_key() -> Key {
+        Zeroizing::new([7u8; 32])
+    }
+
+    #[test]
+    fn sealed_payload_round_trips() {
+        let key = test_key();
+        let sealed = seal(&key, "signatures.json", b"[1,2,3]").unwrap();
+        let opened = open(&key, "signatures.json", &sealed).unwrap();
+        assert_eq!(opened.as_slice(), b"[1,2,3]");
+    }
+
+    #[test]
+    fn sealed_payload_is_bound_to_its_store() {
+        let key = test_key();
+        let sealed = seal(&key, "paraphs.json", b"[]").unwrap();
+        assert!(open(&key, "signatures.json", &sealed).is_err());
+    }
+
+    #[test]
+    fn wrong_passphrase_fails_the_check_value() {
+        let dir = crate::store::tests::scratch_dir("vault");
+        let (file, key) = create_vault_file(&dir, "correct horse").unwrap();
+        assert!(verify_key(&file, &key).is_ok());
+
+        let wrong = derive_key("battery staple", &file.kdf).unwrap();
+        assert_eq!(verify_key(&file, &wrong).unwrap_err().kind, ErrorKind::AuthenticationFailed);
+    }
+
+    /// Contents of every file below `root`.
+    fn files_under(root: &Path) -> Vec<Vec<u8>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for path in std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()) {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    files.push(std::fs::read(path).unwrap());
+                }
+            }
+        }
+        files
+    }
+
+    #[test]
+    fn gallery_snapshots_taken_before_the_vault_are_sealed() {
+        let dir = crate::store::tests::scratch_dir("vault-history");
+        let gallery = crate::db::Gallery::Paraphs;
+        let paraph = crate::export::tests::asset("p", "image/png", crate::export::tests::tiny_png());
+        let stored = crate::db::add_asset(&Vault::default(), &dir, gallery, paraph).unwrap().bytes;
+        crate::db::rename_asset(&Vault::default(), &dir, gallery, "p", "Paraphe").unwrap();
+        // The images appear in the snapshots as JSON arrays of numbers.
+        let needle = serde_json::to_string(&stored).unwrap();
+        let needle = needle.trim_start_matches('[').trim_end_matches(']').as_bytes();
+        let leaks = || {
+            files_under(&dir.join("history"))
+                .iter()
+                .filter(|bytes| bytes.windows(needle.len()).any(|window| window == needle))
+                .count()
+        };
+        assert_eq!(leaks(), 1);
+
+        let vault = unlocked(&dir);
+        migrate_plaintext_stores(&vault.key().unwrap(), &dir).unwrap();
+        assert_eq!(leaks(), 0);
+        assert_eq!(history::snapshot_files(&dir, crate::PARAPHS_FILE).len(), 1);
+    }
+
+    #[test]
+    fn galleries_left_imported_are_sealed() {
+        let dir = crate::store::tests::scratch_dir("vault-imported");
+        let legacy = include_bytes!("../fixtures/stores/paraphs.v0.json");
+        let imported = crate::db::imported_path(&dir.join(crate::PARAPHS_FILE));
+        std::fs::write(&imported, legacy).unwrap();
+
+        let vault = unlocked(&dir);
+        migrate_plaintext_stores(&vault.key().unwrap(), &dir).unwrap();
+        let sealed = std::fs::read(&imported).unwrap();
+        assert_ne!(sealed, legacy);
+        let value: serde_json::Value = decode_store(&vault.key().unwrap(), crate::PARAPHS_FILE, &sealed).unwrap();
+        assert_eq!(value, serde_json::from_slice::<serde_json::Value>(legacy).unwrap());
+    }
+
+    #[test]
+    fn legacy_plaintext_still_decodes_once_vault_exists() {
+        let key = test_key();
+        let decoded: Vec<String> = decode_store(&key, "snippets.json", b"[\"a\"]").unwrap();
+        assert_eq!(decoded, vec!["a"]);
+    }
+}
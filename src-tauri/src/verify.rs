@@ -0,0 +1,1033 @@
+//! Verification of the digital signatures already present in a PDF.
+//!
+//! Each signature field is checked on four axes, reported separately
+//! so the UI can explain *why* a signature is not green :
+//!   - the `/ByteRange` is well formed and excludes exactly `/Contents` ;
+//!   - the CMS blob is intact (message digest and signer signature) ;
+//!   - the signer certificate chains up to the user's trust store ;
+//!   - nothing but signature plumbing was changed in later revisions.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use cms::content_info::ContentInfo;
+use cms::signed_data::{SignedData, SignerIdentifier, SignerInfo};
+use const_oid::db::{rfc5911, rfc5912};
+use const_oid::ObjectIdentifier;
+use der::asn1::OctetString;
+use der::{Decode, Encode};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use signature::hazmat::PrehashVerifier;
+use signature::Verifier;
+use spki::DecodePublicKey;
+use x509_cert::ext::pkix::{BasicConstraints, KeyUsage, KeyUsages, SubjectKeyIdentifier};
+use x509_cert::Certificate;
+
+use crate::error::{self, Error, ErrorKind};
+use crate::pades;
+use crate::pdfio;
+use crate::trust;
+use crate::tsa;
+
+/// Maximum depth of the AcroForm field tree and of a certificate
+/// chain ; both guard against cycles in hostile files.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Integrity {
+    Valid,
+    Invalid,
+    /// Well formed, but uses an algorithm we cannot check (SHA-1,
+    /// P-384, legacy `adbe.x509.rsa_sha1`…).
+    Unsupported,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateSummary {
+    pub subject: String,
+    pub issuer: String,
+    pub serial: String,
+    pub fingerprint: String,
+    /// Unix seconds.
+    pub not_before: u64,
+    pub not_after: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureReport {
+    pub field_name: Option<String>,
+    pub sub_filter: Option<String>,
+    /// Raw `/M` entry, as claimed by the signer.
+    pub signing_time: Option<String>,
+    /// Time certified by an embedded RFC 3161 token (Unix seconds),
+    /// when the signature carries a valid one from a trusted authority.
+    pub timestamp: Option<u64>,
+    pub byte_range_valid: bool,
+    pub covers_whole_document: bool,
+    pub integrity: Integrity,
+    pub signer: Option<CertificateSummary>,
+    /// Signer first, then each issuer found up to the anchor.
+    pub chain: Vec<CertificateSummary>,
+    pub trusted: bool,
+    /// Changes made in revisions appended after this signature, other
+    /// than adding further signatures.
+    pub modifications: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub signatures: Vec<SignatureReport>,
+    /// Exporting rewrites the whole file through pdf-lib, so any
+    /// overlay breaks every existing signature. Surfaced so the UI can
+    /// warn before the first edit.
+    pub editing_invalidates_signatures: bool,
+}
+
+pub fn summarize(cert: &Certificate) -> CertificateSummary {
+    let tbs = &cert.tbs_certificate;
+    CertificateSummary {
+        subject: tbs.subject.to_string(),
+        issuer: tbs.issuer.to_string(),
+        serial: pdfio::hex(tbs.serial_number.as_bytes()),
+        fingerprint: cert.to_der().map(|der| trust::fingerprint(&der)).unwrap_or_default(),
+        not_before: tbs.validity.not_before.to_unix_duration().as_secs(),
+        not_after: tbs.validity.not_after.to_unix_duration().as_secs(),
+    }
+}
+
+struct FoundSignature {
+    field_name: Option<String>,
+    dict: Dictionary,
+}
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object {
+        Object::Reference(id) => doc.get_object(*id).ok(),
+        other => Some(other),
+    }
+}
+
+fn text(object: &Object) -> Option<String> {
+    object.as_str().ok().map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn walk_fields(
+    doc: &Document,
+    fields: &[Object],
+    parent_name: Option<&str>,
+    inherited_ft: Option<&[u8]>,
+    depth: usize,
+    out: &mut Vec<FoundSignature>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    for field in fields {
+        let Some(Object::Dictionary(dict)) = resolve(doc, field) else {
+            continue;
+        };
+        let partial = dict.get(b"T").ok().and_then(text);
+        let name = match (parent_name, partial) {
+            (Some(parent), Some(partial)) => Some(format!("{parent}.{partial}")),
+            (None, partial) => partial,
+            (Some(parent), None) => Some(parent.to_string()),
+        };
+        let ft = dict.get(b"FT").and_then(Object::as_name).ok().or(inherited_ft);
+
+        if ft == Some(&b"Sig"[..]) {
+            if let Some(Object::Dictionary(sig)) = dict.get(b"V").ok().and_then(|v| resolve(doc, v)) {
+                out.push(FoundSignature { field_name: name.clone(), dict: sig.clone() });
+                continue;
+            }
+        }
+        if let Some(Object::Array(kids)) = dict.get(b"Kids").ok().and_then(|k| resolve(doc, k)) {
+            walk_fields(doc, kids, name.as_deref(), ft, depth + 1, out);
+        }
+    }
+}
+
+fn find_signatures(doc: &Document) -> Vec<FoundSignature> {
+    let mut found = Vec::new();
+    let fields = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"AcroForm").ok())
+        .and_then(|acroform| resolve(doc, acroform))
+        .and_then(|acroform| acroform.as_dict().ok())
+        .and_then(|acroform| acroform.get(b"Fields").ok())
+        .and_then(|fields| resolve(doc, fields))
+        .and_then(|fields| fields.as_array().ok());
+    if let Some(fields) = fields {
+        walk_fields(doc, fields, None, None, 0, &mut found);
+    }
+    found
+}
+
+fn parse_byte_range(dict: &Dictionary) -> Option<[usize; 4]> {
+    let values = dict.get(b"ByteRange").and_then(Object::as_array).ok()?;
+    if values.len() != 4 {
+        return None;
+    }
+    let mut range = [0usize; 4];
+    for (slot, value) in range.iter_mut().zip(values) {
+        *slot = usize::try_from(value.as_i64().ok()?).ok()?;
+    }
+    Some(range)
+}
+
+/// The two spans must start at 0, leave a gap holding exactly the hex
+/// `/Contents` string, and stay inside the file.
+fn byte_range_is_well_formed(bytes: &[u8], range: [usize; 4]) -> bool {
+    let [start1, len1, start2, len2] = range;
+    start1 == 0
+        && len1 < start2
+        && start2.checked_add(len2).map(|end| end <= bytes.len()).unwrap_or(false)
+        && bytes.get(len1) == Some(&b'<')
+        && bytes.get(start2 - 1) == Some(&b'>')
+        && bytes[len1 + 1..start2 - 1].iter().all(|b| b.is_ascii_hexdigit() || b.is_ascii_whitespace())
+}
+
+#[derive(Clone, Copy)]
+enum Hash {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Hash {
+    fn from_digest_oid(oid: ObjectIdentifier) -> Option<Self> {
+        if oid == rfc5912::ID_SHA_256 {
+            Some(Hash::Sha256)
+        } else if oid == rfc5912::ID_SHA_384 {
+            Some(Hash::Sha384)
+        } else if oid == rfc5912::ID_SHA_512 {
+            Some(Hash::Sha512)
+        } else {
+            None
+        }
+    }
+
+    /// Hash implied by a combined signature algorithm, if it is one.
+    fn from_signature_oid(oid: ObjectIdentifier) -> Option<Self> {
+        if oid == rfc5912::SHA_256_WITH_RSA_ENCRYPTION || oid == rfc5912::ECDSA_WITH_SHA_256 {
+            Some(Hash::Sha256)
+        } else if oid == rfc5912::SHA_384_WITH_RSA_ENCRYPTION || oid == rfc5912::ECDSA_WITH_SHA_384 {
+            Some(Hash::Sha384)
+        } else if oid == rfc5912::SHA_512_WITH_RSA_ENCRYPTION || oid == rfc5912::ECDSA_WITH_SHA_512 {
+            Some(Hash::Sha512)
+        } else {
+            None
+        }
+    }
+
+    fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        fn run<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            Hash::Sha256 => run::<Sha256>(parts),
+            Hash::Sha384 => run::<Sha384>(parts),
+            Hash::Sha512 => run::<Sha512>(parts),
+        }
+    }
+}
+
+fn verify_rsa<D>(key: rsa::RsaPublicKey, message: &[u8], signature: &[u8]) -> bool
+where
+    D: Digest + const_oid::AssociatedOid,
+{
+    let Ok(signature) = rsa::pkcs1v15::Signature::try_from(signature) else {
+        return false;
+    };
+    rsa::pkcs1v15::VerifyingKey::<D>::new(key).verify(message, &signature).is_ok()
+}
+
+/// Check `signature` over `message` with the public key in `spki_der`.
+/// `Ok(false)` means a definite mismatch, `Err` an unsupported setup.
+pub fn verify_signature(
+    spki_der: &[u8],
+    signature_oid: ObjectIdentifier,
+    digest_oid: Option<ObjectIdentifier>,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool, String> {
+    let hash = Hash::from_signature_oid(signature_oid)
+        .or_else(|| digest_oid.and_then(Hash::from_digest_oid))
+        .ok_or_else(|| format!("unsupported algorithm: {signature_oid}"))?;
+
+    if let Ok(key) = rsa::RsaPublicKey::from_public_key_der(spki_der) {
+        return Ok(match hash {
+            Hash::Sha256 => verify_rsa::<Sha256>(key, message, signature),
+            Hash::Sha384 => verify_rsa::<Sha384>(key, message, signature),
+            Hash::Sha512 => verify_rsa::<Sha512>(key, message, signature),
+        });
+    }
+    if let Ok(key) = p256::ecdsa::VerifyingKey::from_public_key_der(spki_der) {
+        let Ok(signature) = p256::ecdsa::Signature::from_der(signature) else {
+            return Ok(false);
+        };
+        let prehash = hash.digest(&[message]);
+        return Ok(key.verify_prehash(&prehash, &signature).is_ok());
+    }
+    Err("unsupported key type".into())
+}
+
+fn spki_der(cert: &Certificate) -> Result<Vec<u8>, String> {
+    cert.tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|e| format!("invalid certificate: {e}"))
+}
+
+/// True when `issuer` signed `cert`.
+fn issued_by(cert: &Certificate, issuer: &Certificate) -> bool {
+    if cert.tbs_certificate.issuer != issuer.tbs_certificate.subject {
+        return false;
+    }
+    let (Ok(tbs), Ok(key)) = (cert.tbs_certificate.to_der(), spki_der(issuer)) else {
+        return false;
+    };
+    let Some(signature) = cert.signature.as_bytes() else {
+        return false;
+    };
+    verify_signature(&key, cert.signature_algorithm.oid, None, &tbs, signature).unwrap_or(false)
+}
+
+/// The extension `oid` of `cert`, decoded ; `None` when it is absent
+/// or malformed.
+fn extension<'a, T: Decode<'a>>(cert: &'a Certificate, oid: ObjectIdentifier) -> Option<T> {
+    cert.tbs_certificate
+        .extensions
+        .iter()
+        .flatten()
+        .find(|ext| ext.extn_id == oid)
+        .and_then(|ext| T::from_der(ext.extn_value.as_bytes()).ok())
+}
+
+/// Whether `issuer` may sign the next certificate of a chain that
+/// already holds `below` certificates, the leaf included : it must be a
+/// CA (basicConstraints cA), allowed to sign certificates (keyUsage
+/// keyCertSign, which RFC 5280 makes mandatory for CAs), and its
+/// pathLenConstraint must leave room for the intermediates under it.
+fn can_issue(issuer: &Certificate, below: usize) -> bool {
+    let Some(constraints) =
+        extension::<BasicConstraints>(issuer, rfc5912::ID_CE_BASIC_CONSTRAINTS).filter(|constraints| constraints.ca)
+    else {
+        return false;
+    };
+    let signs_certificates = extension::<KeyUsage>(issuer, rfc5912::ID_CE_KEY_USAGE)
+        .is_some_and(|usage| usage.0.contains(KeyUsages::KeyCertSign));
+    let intermediates = below.saturating_sub(1);
+    signs_certificates && constraints.path_len_constraint.map_or(true, |max| intermediates <= usize::from(max))
+}
+
+fn same_certificate(a: &Certificate, b: &Certificate) -> bool {
+    matches!((a.to_der(), b.to_der()), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Walk issuer links from `leaf` through `pool` (certificates shipped
+/// in the CMS blob) until a trust anchor is reached. Only CA
+/// certificates are followed, anchors included : an end-entity
+/// certificate that signed another one does not make it trusted.
+pub fn build_chain(leaf: &Certificate, pool: &[Certificate], anchors: &[Certificate]) -> (Vec<Certificate>, bool) {
+    let mut chain = vec![leaf.clone()];
+    for _ in 0..MAX_DEPTH {
+        let current = chain.last().unwrap();
+        if anchors.iter().any(|anchor| same_certificate(anchor, current)) {
+            return (chain, true);
+        }
+        if let Some(anchor) = anchors.iter().find(|anchor| issued_by(current, anchor) && can_issue(anchor, chain.len()))
+        {
+            chain.push(anchor.clone());
+            return (chain, true);
+        }
+        let next = pool.iter().find(|candidate| {
+            !chain.iter().any(|c| same_certificate(c, candidate))
+                && issued_by(current, candidate)
+                && can_issue(candidate, chain.len())
+        });
+        match next {
+            Some(next) => chain.push(next.clone()),
+            None => break,
+        }
+    }
+    (chain, false)
+}
+
+pub fn signer_certificate<'a>(signer: &SignerInfo, certs: &'a [Certificate]) -> Option<&'a Certificate> {
+    match &signer.sid {
+        SignerIdentifier::IssuerAndSerialNumber(id) => certs.iter().find(|cert| {
+            cert.tbs_certificate.issuer == id.issuer && cert.tbs_certificate.serial_number == id.serial_number
+        }),
+        SignerIdentifier::SubjectKeyIdentifier(ski) => certs.iter().find(|cert| {
+            cert.tbs_certificate
+                .extensions
+                .iter()
+                .flatten()
+                .filter(|ext| ext.extn_id == rfc5912::ID_CE_SUBJECT_KEY_IDENTIFIER)
+                .filter_map(|ext| SubjectKeyIdentifier::from_der(ext.extn_value.as_bytes()).ok())
+                .any(|found| found.0 == ski.0)
+        }),
+    }
+}
+
+pub fn decode_signed_data(contents: &[u8]) -> Result<SignedData, String> {
+    let info = ContentInfo::from_der(pades::der_prefix(contents)?).map_err(|e| format!("invalid cms: {e}"))?;
+    if info.content_type != rfc5911::ID_SIGNED_DATA {
+        return Err("invalid cms: SignedData expected".into());
+    }
+    info.content.decode_as().map_err(|e| format!("invalid cms: {e}"))
+}
+
+pub fn embedded_certificates(signed_data: &SignedData) -> Vec<Certificate> {
+    signed_data
+        .certificates
+        .iter()
+        .flat_map(|set| set.0.iter())
+        .filter_map(|choice| match choice {
+            cms::cert::CertificateChoices::Certificate(cert) => Some(cert.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Check the message digest attribute against the signed content
+/// (the byte range spans, or a timestamp's `TSTInfo`) and the signer's
+/// signature over the signed attributes.
+pub fn check_integrity(signer: &SignerInfo, cert: &Certificate, spans: &[&[u8]]) -> Result<bool, String> {
+    let hash = Hash::from_digest_oid(signer.digest_alg.oid)
+        .ok_or_else(|| format!("unsupported algorithm: {}", signer.digest_alg.oid))?;
+    let key = spki_der(cert)?;
+    let signature = signer.signature.as_bytes();
+
+    match &signer.signed_attrs {
+        Some(attrs) => {
+            let digest = hash.digest(spans);
+            let claimed = attrs
+                .iter()
+                .find(|attr| attr.oid == rfc5911::ID_MESSAGE_DIGEST)
+                .and_then(|attr| attr.values.get(0))
+                .and_then(|value| value.decode_as::<OctetString>().ok())
+                .ok_or_else(|| "message-digest attribute missing".to_string())?;
+            if claimed.as_bytes() != digest.as_slice() {
+                return Ok(false);
+            }
+            let signed = attrs.to_der().map_err(|e| format!("invalid cms: {e}"))?;
+            verify_signature(&key, signer.signature_algorithm.oid, Some(signer.digest_alg.oid), &signed, signature)
+        }
+        None => {
+            let message = spans.concat();
+            verify_signature(&key, signer.signature_algorithm.oid, Some(signer.digest_alg.oid), &message, signature)
+        }
+    }
+}
+
+fn serialize(object: &Object) -> Vec<u8> {
+    let mut out = Vec::new();
+    pdfio::write_object(&mut out, object);
+    out
+}
+
+fn dictionary_of(object: &Object) -> Option<&Dictionary> {
+    match object {
+        Object::Dictionary(dict) => Some(dict),
+        Object::Stream(stream) => Some(&stream.dict),
+        _ => None,
+    }
+}
+
+fn name_of<'a>(dict: &'a Dictionary, key: &[u8]) -> Option<&'a [u8]> {
+    dict.get(key).and_then(Object::as_name).ok()
+}
+
+fn reference_in(dict: &Dictionary, key: &[u8]) -> Option<ObjectId> {
+    dict.get(key).and_then(Object::as_reference).ok()
+}
+
+/// A signature field, or the widget of one.
+fn is_signature_field(doc: &Document, dict: &Dictionary) -> bool {
+    name_of(dict, b"FT") == Some(b"Sig")
+        || reference_in(dict, b"Parent")
+            .and_then(|parent| doc.get_dictionary(parent).ok())
+            .is_some_and(|parent| name_of(parent, b"FT") == Some(b"Sig"))
+}
+
+/// Whether `new` and `old` differ at most under `keys`.
+fn differ_only_in(new: &Dictionary, old: &Dictionary, keys: &[&[u8]]) -> bool {
+    new.iter()
+        .chain(old.iter())
+        .map(|(key, _)| key.as_slice())
+        .filter(|key| !keys.contains(key))
+        .all(|key| new.get(key).ok().map(serialize) == old.get(key).ok().map(serialize))
+}
+
+/// The objects of the final revision the signing workflow is allowed
+/// to touch, each with the changes it may receive.
+#[derive(Clone, Copy)]
+enum Role {
+    /// Only `/AcroForm`, `/DSS` and `/Perms` may change.
+    Catalog,
+    /// Only `/Fields` (which may only grow) and `/SigFlags`.
+    AcroForm,
+    /// Only `/Annots`, which may only grow.
+    Page,
+    /// A page's `/Annots` or the AcroForm's `/Fields` stored on its own.
+    Array,
+    /// Validation data, free to change.
+    Dss,
+}
+
+fn roles(doc: &Document) -> HashMap<ObjectId, Role> {
+    let mut roles = HashMap::new();
+    for page in doc.get_pages().into_values() {
+        roles.insert(page, Role::Page);
+        if let Some(annots) = doc.get_dictionary(page).ok().and_then(|page| reference_in(page, b"Annots")) {
+            roles.insert(annots, Role::Array);
+        }
+    }
+    let Some(catalog_id) = reference_in(&doc.trailer, b"Root") else {
+        return roles;
+    };
+    roles.insert(catalog_id, Role::Catalog);
+    let Ok(catalog) = doc.get_dictionary(catalog_id) else {
+        return roles;
+    };
+    if let Some(dss) = reference_in(catalog, b"DSS") {
+        roles.insert(dss, Role::Dss);
+    }
+    let acroform = match catalog.get(b"AcroForm") {
+        Ok(Object::Reference(id)) => {
+            roles.insert(*id, Role::AcroForm);
+            doc.get_dictionary(*id).ok()
+        }
+        Ok(Object::Dictionary(dict)) => Some(dict),
+        _ => None,
+    };
+    if let Some(fields) = acroform.and_then(|acroform| reference_in(acroform, b"Fields")) {
+        roles.insert(fields, Role::Array);
+    }
+    roles
+}
+
+/// What changed between a signed revision and the final one, sorted
+/// into signature plumbing (further signatures, timestamps, validation
+/// data) and edits of the signed content.
+struct Revisions<'a> {
+    full: &'a Document,
+    signed: &'a Document,
+    roles: HashMap<ObjectId, Role>,
+    /// New objects that belong to the signing workflow.
+    appended: HashSet<ObjectId>,
+}
+
+impl<'a> Revisions<'a> {
+    fn new(full: &'a Document, signed: &'a Document) -> Self {
+        let mut revisions = Revisions { full, signed, roles: roles(full), appended: HashSet::new() };
+        revisions.appended = revisions.appended_plumbing();
+        revisions
+    }
+
+    fn is_new(&self, id: ObjectId) -> bool {
+        !self.signed.objects.contains_key(&id)
+    }
+
+    /// New signature and timestamp dictionaries, signature fields and
+    /// their widgets, and DSS objects, with the new objects their
+    /// appearances and validation data refer to.
+    fn appended_plumbing(&self) -> HashSet<ObjectId> {
+        let dss = self.roles.iter().find(|(_, role)| matches!(role, Role::Dss)).map(|(id, _)| *id);
+        let mut appended = HashSet::new();
+        let mut pending: Vec<&Object> = Vec::new();
+        for (id, object) in &self.full.objects {
+            let Some(dict) = dictionary_of(object) else {
+                continue;
+            };
+            let root = match name_of(dict, b"Type") {
+                Some(b"Sig" | b"DocTimeStamp" | b"XRef" | b"ObjStm") => true,
+                Some(b"DSS" | b"VRI") => {
+                    pending.push(object);
+                    true
+                }
+                _ if Some(*id) == dss => {
+                    pending.push(object);
+                    true
+                }
+                _ if is_signature_field(self.full, dict) => {
+                    // A field signed after the fact gets a new appearance.
+                    pending.extend(dict.get(b"AP").ok());
+                    true
+                }
+                _ => false,
+            };
+            if root && self.is_new(*id) {
+                appended.insert(*id);
+            }
+        }
+        while let Some(object) = pending.pop() {
+            match object {
+                Object::Reference(id) if self.is_new(*id) && appended.insert(*id) => {
+                    pending.extend(self.full.objects.get(id))
+                }
+                Object::Array(items) => pending.extend(items),
+                Object::Dictionary(dict) => pending.extend(dict.iter().map(|(_, value)| value)),
+                Object::Stream(stream) => pending.extend(stream.dict.iter().map(|(_, value)| value)),
+                _ => {}
+            }
+        }
+        appended
+    }
+
+    /// Whether an array went from `old` to `new` only by gaining
+    /// references to new signature plumbing. A reference to a new array
+    /// is accepted here and checked on its own.
+    fn grew(&self, new: Option<&Object>, old: Option<&Object>) -> bool {
+        let old_items = match old {
+            None => &[][..],
+            Some(Object::Array(items)) => items.as_slice(),
+            Some(old) => return new.map(serialize) == Some(serialize(old)),
+        };
+        match new {
+            None => old_items.is_empty(),
+            Some(Object::Reference(id)) => self.is_new(*id),
+            Some(Object::Array(items)) => {
+                let kept = |item: &Object| old_items.iter().any(|old| serialize(old) == serialize(item));
+                old_items.iter().all(|old| items.iter().any(|item| serialize(item) == serialize(old)))
+                    && items
+                        .iter()
+                        .filter(|item| !kept(item))
+                        .all(|item| matches!(item, Object::Reference(id) if self.appended.contains(id)))
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn acroform_is_plumbing(&self, new: &Dictionary, old: &Dictionary) -> bool {
+        differ_only_in(new, old, &[b"Fields", b"SigFlags"])
+            && self.grew(new.get(b"Fields").ok(), old.get(b"Fields").ok())
+    }
+
+    fn catalog_is_plumbing(&self, new: &Dictionary, old: &Dictionary) -> bool {
+        if !differ_only_in(new, old, &[b"AcroForm", b"DSS", b"Perms"]) {
+            return false;
+        }
+        match (new.get(b"AcroForm").ok(), old.get(b"AcroForm").ok()) {
+            (Some(Object::Dictionary(new)), Some(Object::Dictionary(old))) => self.acroform_is_plumbing(new, old),
+            (Some(Object::Dictionary(new)), None) => self.acroform_is_plumbing(new, &Dictionary::new()),
+            (Some(Object::Reference(id)), old) => {
+                old.is_some_and(|old| serialize(old) == serialize(&Object::Reference(*id))) || self.is_new(*id)
+            }
+            (new, old) => new.map(serialize) == old.map(serialize),
+        }
+    }
+
+    fn is_plumbing(&self, id: ObjectId, new: &Object, old: Option<&Object>) -> bool {
+        if old.is_none() && self.appended.contains(&id) {
+            return true;
+        }
+        let (Some(dict), old_dict) = (dictionary_of(new), old.and_then(dictionary_of)) else {
+            return matches!(self.roles.get(&id), Some(Role::Array)) && self.grew(Some(new), old);
+        };
+        match self.roles.get(&id) {
+            Some(Role::Catalog) => old_dict.is_some_and(|old| self.catalog_is_plumbing(dict, old)),
+            Some(Role::AcroForm) => self.acroform_is_plumbing(dict, old_dict.unwrap_or(&Dictionary::new())),
+            Some(Role::Page) => old_dict.is_some_and(|old| {
+                differ_only_in(dict, old, &[b"Annots"]) && self.grew(dict.get(b"Annots").ok(), old.get(b"Annots").ok())
+            }),
+            Some(Role::Dss) => true,
+            Some(Role::Array) => false,
+            // Signing a field that was left empty.
+            None => old_dict.is_some_and(|old| {
+                is_signature_field(self.full, dict) && differ_only_in(dict, old, &[b"V", b"AP"])
+            }),
+        }
+    }
+}
+
+/// Describe objects changed or added after the revision ending at
+/// `revision_end`. Only signature plumbing is let through : a new
+/// signature, timestamp or validation data, the widget and appearance
+/// that go with it, and their entries in the page, AcroForm and catalog.
+fn later_modifications(full: &Document, bytes: &[u8], revision_end: usize) -> Vec<String> {
+    let Ok(signed) = Document::load_mem(&bytes[..revision_end]) else {
+        return vec!["unreadable signed revision".into()];
+    };
+    let revisions = Revisions::new(full, &signed);
+    let mut changes = Vec::new();
+    for (id, object) in &full.objects {
+        let old = signed.objects.get(id);
+        if old.map(|old| serialize(old) == serialize(object)).unwrap_or(false) {
+            continue;
+        }
+        if revisions.is_plumbing(*id, object, old) {
+            continue;
+        }
+        let kind = dictionary_of(object)
+            .and_then(|dict| dict.get(b"Type").and_then(Object::as_name).ok())
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .unwrap_or_else(|| "object".into());
+        let verb = if old.is_some() { "modified" } else { "added" };
+        changes.push(format!("{kind} {} {verb}", id.0));
+    }
+    changes
+}
+
+/// Time certified by the signature's RFC 3161 token, if it carries one
+/// that matches the signature and comes from a trusted authority.
+fn signature_timestamp(signer: &SignerInfo, anchors: &[Certificate], problems: &mut Vec<String>) -> Option<u64> {
+    let token = signer
+        .unsigned_attrs
+        .iter()
+        .flat_map(|attrs| attrs.iter())
+        .find(|attr| attr.oid == tsa::ID_AA_SIGNATURE_TIME_STAMP_TOKEN)?
+        .values
+        .get(0)?
+        .decode_as::<ContentInfo>()
+        .ok()?;
+    match tsa::validate_token(&token) {
+        Ok(token) => {
+            let imprint = &token.tst_info.message_imprint;
+            let expected = Hash::from_digest_oid(imprint.hash_algorithm.oid)
+                .map(|hash| hash.digest(&[signer.signature.as_bytes()]));
+            if expected.as_deref() != Some(imprint.hashed_message.as_bytes()) {
+                problems.push("timestamp token unrelated to the signature".into());
+                return None;
+            }
+            // Anyone can mint a token : its date only counts when the
+            // authority chains up to the trust store.
+            if !build_chain(&token.authority, &token.certificates, anchors).1 {
+                problems.push(format!(
+                    "untrusted timestamp authority, token date ignored: {}",
+                    token.authority.tbs_certificate.subject
+                ));
+                return None;
+            }
+            Some(token.tst_info.gen_time.to_unix_duration().as_secs())
+        }
+        Err(err) => {
+            problems.push(err);
+            None
+        }
+    }
+}
+
+fn verify_one(full: &Document, bytes: &[u8], found: FoundSignature, anchors: &[Certificate]) -> SignatureReport {
+    let dict = &found.dict;
+    let mut report = SignatureReport {
+        field_name: found.field_name,
+        sub_filter: dict
+            .get(b"SubFilter")
+            .and_then(Object::as_name)
+            .ok()
+            .map(|name| String::from_utf8_lossy(name).into_owned()),
+        signing_time: dict.get(b"M").ok().and_then(text),
+        timestamp: None,
+        byte_range_valid: false,
+        covers_whole_document: false,
+        integrity: Integrity::Invalid,
+        signer: None,
+        chain: Vec::new(),
+        trusted: false,
+        modifications: Vec::new(),
+        problems: Vec::new(),
+    };
+
+    let Some(range) = parse_byte_range(dict).filter(|range| byte_range_is_well_formed(bytes, *range)) else {
+        report.problems.push("ByteRange missing or invalid".into());
+        return report;
+    };
+    report.byte_range_valid = true;
+    let revision_end = range[2] + range[3];
+    report.covers_whole_document = bytes[revision_end..].iter().all(u8::is_ascii_whitespace);
+    if !report.covers_whole_document {
+        report.modifications = later_modifications(full, bytes, revision_end);
+    }
+
+    let Some(contents) = dict.get(b"Contents").and_then(Object::as_str).ok() else {
+        report.problems.push("Contents missing".into());
+        return report;
+    };
+    if matches!(report.sub_filter.as_deref(), Some("adbe.x509.rsa_sha1")) {
+        report.integrity = Integrity::Unsupported;
+        report.problems.push("unsupported adbe.x509.rsa_sha1 format".into());
+        return report;
+    }
+    let signed_data = match decode_signed_data(contents) {
+        Ok(signed_data) => signed_data,
+        Err(err) => {
+            report.problems.push(err);
+            return report;
+        }
+    };
+    let certs = embedded_certificates(&signed_data);
+    let Some(signer) = signed_data.signer_infos.0.get(0) else {
+        report.problems.push("no signer".into());
+        return report;
+    };
+    let Some(cert) = signer_certificate(signer, &certs) else {
+        report.problems.push("signer certificate missing".into());
+        return report;
+    };
+    report.signer = Some(summarize(cert));
+
+    let spans = [&bytes[range[0]..range[0] + range[1]], &bytes[range[2]..revision_end]];
+    report.integrity = match check_integrity(signer, cert, &spans) {
+        Ok(true) => Integrity::Valid,
+        Ok(false) => Integrity::Invalid,
+        Err(err) => {
+            report.problems.push(err);
+            Integrity::Unsupported
+        }
+    };
+
+    let (chain, trusted) = build_chain(cert, &certs, anchors);
+    report.trusted = trusted;
+    if !trusted {
+        report.problems.push("untrusted certificate chain".into());
+    }
+    report.timestamp = signature_timestamp(signer, anchors, &mut report.problems);
+    // A certified time beats the signer's own claim.
+    let at = report.timestamp.or_else(|| {
+        report
+            .signing_time
+            .as_deref()
+            .and_then(pdfio::parse_pdf_date)
+            .and_then(|secs| u64::try_from(secs).ok())
+    });
+    report.chain = chain.iter().map(summarize).collect();
+    if let Some(at) = at {
+        for cert in &report.chain {
+            if at < cert.not_before || at > cert.not_after {
+                report.problems.push(format!("certificate not valid at signing time: {}", cert.subject));
+            }
+        }
+    }
+    report
+}
+
+/// Verify every signature of `bytes`. Returns `None` for unsigned
+/// documents so callers can skip the report entirely.
+pub fn verify_pdf(bytes: &[u8], anchors: &[Certificate]) -> Result<Option<VerificationReport>, String> {
+    // Cheap pre-check : parsing is wasted work on the common unsigned case.
+    if !bytes.windows(b"/ByteRange".len()).any(|w| w == b"/ByteRange") {
+        return Ok(None);
+    }
+    let doc = Document::load_mem(bytes).map_err(|e| format!("invalid pdf: {e}"))?;
+    let found = find_signatures(&doc);
+    if found.is_empty() {
+        return Ok(None);
+    }
+    let signatures = found
+        .into_iter()
+        .map(|found| verify_one(&doc, bytes, found, anchors))
+        .collect();
+    Ok(Some(VerificationReport { signatures, editing_invalidates_signatures: true }))
+}
+
+/// Re-run verification on a file, e.g. after the trust store changed.
+#[tauri::command]
+pub fn verify_pdf_signatures(app: tauri::AppHandle, path: String) -> error::Result<Option<VerificationReport>> {
+    let source = crate::scope::readable_pdf(&app, &PathBuf::from(&path))?;
+    let bytes = std::fs::read(&source).map_err(|e| Error::io("pdf_read_failed", &source, &e))?;
+    verify_pdf(&bytes, &trust::load_anchors(&app)?)
+        .map_err(|details| Error::new(ErrorKind::InvalidPdf, "pdf_invalid").with_path(&source).with_details(details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pades::tests::{certificate, credentials, random_key, self_signed, test_name};
+    use crate::pades::{sign_pdf, SignaturePlacement};
+    use crate::pdfio::tests::sample_pdf;
+    use crate::pdfio::PdfRect;
+    use x509_cert::builder::Profile;
+
+    fn placement() -> Option<SignaturePlacement> {
+        Some(SignaturePlacement { page: 1, rect: PdfRect { x: 50.0, y: 50.0, w: 100.0, h: 40.0 } })
+    }
+
+    #[test]
+    fn unsigned_pdf_has_no_report() {
+        assert!(verify_pdf(&sample_pdf(1, "Vide"), &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn fresh_signature_is_intact_and_trusted_when_anchor_known() {
+        let credentials = self_signed("Alice");
+        let signed = sign_pdf(&sample_pdf(1, "Contrat"), &credentials, placement(), None).unwrap();
+
+        let report = verify_pdf(&signed, &[credentials.leaf().clone()]).unwrap().unwrap();
+        assert_eq!(report.signatures.len(), 1);
+        let sig = &report.signatures[0];
+        assert!(sig.byte_range_valid);
+        assert!(sig.covers_whole_document);
+        assert_eq!(sig.integrity, Integrity::Valid);
+        assert!(sig.trusted);
+        assert!(sig.modifications.is_empty());
+        assert!(sig.signer.as_ref().unwrap().subject.contains("Alice"));
+    }
+
+    #[test]
+    fn unknown_signer_is_intact_but_untrusted() {
+        let signed = sign_pdf(&sample_pdf(1, "Contrat"), &self_signed("Mallory"), placement(), None).unwrap();
+        let report = verify_pdf(&signed, &[self_signed("Alice").leaf().clone()]).unwrap().unwrap();
+        assert_eq!(report.signatures[0].integrity, Integrity::Valid);
+        assert!(!report.signatures[0].trusted);
+    }
+
+    fn leaf_profile(issuer: &str) -> Profile {
+        Profile::Leaf { issuer: test_name(issuer), enable_key_agreement: false, enable_key_encipherment: false }
+    }
+
+    #[test]
+    fn leaf_signed_by_an_end_entity_certificate_is_untrusted() {
+        let (root_key, bob_key, mallory_key) = (random_key(), random_key(), random_key());
+        let root = certificate("Racine", Profile::Root, &root_key, &root_key);
+        let bob = certificate("Bob", leaf_profile("Racine"), &bob_key, &root_key);
+        let mallory = certificate("Mallory", leaf_profile("Bob"), &mallory_key, &bob_key);
+
+        // Bob is trusted through the root, but may not vouch for anyone.
+        let signed = sign_pdf(&sample_pdf(1, "Contrat"), &credentials(&bob_key, vec![bob.clone()]), placement(), None);
+        let report = verify_pdf(&signed.unwrap(), std::slice::from_ref(&root)).unwrap().unwrap();
+        assert!(report.signatures[0].trusted);
+
+        let credentials = credentials(&mallory_key, vec![mallory, bob]);
+        let signed = sign_pdf(&sample_pdf(1, "Contrat"), &credentials, placement(), None).unwrap();
+        let report = verify_pdf(&signed, &[root]).unwrap().unwrap();
+        assert_eq!(report.signatures[0].integrity, Integrity::Valid);
+        assert!(!report.signatures[0].trusted);
+    }
+
+    #[test]
+    fn path_length_constraints_are_enforced() {
+        let (root_key, first_key, second_key, leaf_key) = (random_key(), random_key(), random_key(), random_key());
+        let root = certificate("Racine", Profile::Root, &root_key, &root_key);
+        let no_sub_ca = Profile::SubCA { issuer: test_name("Racine"), path_len_constraint: Some(0) };
+        let first = certificate("AC 1", no_sub_ca, &first_key, &root_key);
+        let nested = Profile::SubCA { issuer: test_name("AC 1"), path_len_constraint: None };
+        let second = certificate("AC 2", nested, &second_key, &first_key);
+
+        let direct = certificate("Alice", leaf_profile("AC 1"), &leaf_key, &first_key);
+        let (chain, trusted) = build_chain(&direct, std::slice::from_ref(&first), std::slice::from_ref(&root));
+        assert!(trusted);
+        assert_eq!(chain.len(), 3);
+
+        let too_deep = certificate("Alice", leaf_profile("AC 2"), &leaf_key, &second_key);
+        assert!(!build_chain(&too_deep, &[first, second], &[root]).1);
+    }
+
+    #[test]
+    fn timestamp_only_counts_from_a_trusted_authority() {
+        let (tsa_key, tsa_cert) = crate::tsa::tests::tsa_identity();
+        let url = crate::tsa::tests::spawn_tsa_as(1, tsa_key, tsa_cert.clone());
+        let credentials = self_signed("Alice");
+        let signed = sign_pdf(&sample_pdf(1, "Horodaté"), &credentials, placement(), Some(&url)).unwrap();
+
+        let report = verify_pdf(&signed, &[credentials.leaf().clone()]).unwrap().unwrap();
+        let sig = &report.signatures[0];
+        assert!(sig.trusted);
+        assert_eq!(sig.timestamp, None);
+        assert!(sig.problems.iter().any(|problem| problem.contains("Local TSA")), "{:?}", sig.problems);
+
+        let report = verify_pdf(&signed, &[credentials.leaf().clone(), tsa_cert]).unwrap().unwrap();
+        assert!(report.signatures[0].timestamp.is_some());
+        assert!(report.signatures[0].problems.is_empty(), "{:?}", report.signatures[0].problems);
+    }
+
+    #[test]
+    fn tampered_bytes_break_integrity() {
+        let signed = sign_pdf(&sample_pdf(1, "Montant 100"), &self_signed("Alice"), placement(), None).unwrap();
+        let pos = signed.windows(3).position(|w| w == b"100").unwrap();
+        let mut tampered = signed.clone();
+        tampered[pos] = b'9';
+
+        let report = verify_pdf(&tampered, &[]).unwrap().unwrap();
+        assert_eq!(report.signatures[0].integrity, Integrity::Invalid);
+    }
+
+    #[test]
+    fn countersignature_is_not_reported_as_modification() {
+        let once = sign_pdf(&sample_pdf(1, "Contrat"), &self_signed("Alice"), placement(), None).unwrap();
+        let twice = sign_pdf(&once, &self_signed("Bob"), None, None).unwrap();
+
+        let report = verify_pdf(&twice, &[]).unwrap().unwrap();
+        assert_eq!(report.signatures.len(), 2);
+        let first = report
+            .signatures
+            .iter()
+            .find(|sig| sig.signer.as_ref().unwrap().subject.contains("Alice"))
+            .unwrap();
+        assert!(!first.covers_whole_document);
+        assert_eq!(first.integrity, Integrity::Valid);
+        assert!(first.modifications.is_empty(), "{:?}", first.modifications);
+    }
+
+    #[test]
+    fn content_change_after_signing_is_reported() {
+        let signed = sign_pdf(&sample_pdf(1, "Contrat"), &self_signed("Alice"), placement(), None).unwrap();
+        let mut update = pdfio::IncrementalUpdate::new(&signed).unwrap();
+        let page_id = update.pages().unwrap()[&1];
+        let content = update.get_dictionary(page_id).unwrap().get(b"Contents").unwrap().as_reference().unwrap();
+        update.set(content, Object::Stream(lopdf::Stream::new(Dictionary::new(), b"BT ET".to_vec())));
+        let edited = update.to_bytes().unwrap();
+
+        let report = verify_pdf(&edited, &[]).unwrap().unwrap();
+        let sig = &report.signatures[0];
+        assert_eq!(sig.integrity, Integrity::Valid);
+        assert!(!sig.modifications.is_empty());
+    }
+
+    fn catalog_id(update: &pdfio::IncrementalUpdate) -> lopdf::ObjectId {
+        update.trailer().get(b"Root").unwrap().as_reference().unwrap()
+    }
+
+    #[test]
+    fn changed_open_action_is_reported() {
+        let signed = sign_pdf(&sample_pdf(1, "Contrat"), &self_signed("Alice"), placement(), None).unwrap();
+        let mut update = pdfio::IncrementalUpdate::new(&signed).unwrap();
+        let catalog_id = catalog_id(&update);
+        let mut catalog = update.get_dictionary(catalog_id).unwrap();
+        let script = lopdf::dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(1)") };
+        catalog.set("OpenAction", Object::Dictionary(script));
+        update.set(catalog_id, Object::Dictionary(catalog));
+
+        let report = verify_pdf(&update.to_bytes().unwrap(), &[]).unwrap().unwrap();
+        assert_eq!(report.signatures[0].modifications, [format!("Catalog {} modified", catalog_id.0)]);
+    }
+
+    #[test]
+    fn grown_contents_array_is_reported() {
+        // The page content stored as an indirect array, which may not
+        // grow the way `/Annots` does.
+        let original = sample_pdf(1, "Contrat");
+        let mut update = pdfio::IncrementalUpdate::new(&original).unwrap();
+        let page_id = update.pages().unwrap()[&1];
+        let mut page = update.get_dictionary(page_id).unwrap();
+        let contents = update.add(Object::Array(vec![page.get(b"Contents").unwrap().clone()]));
+        page.set("Contents", contents);
+        update.set(page_id, Object::Dictionary(page));
+        let unsigned = update.to_bytes().unwrap();
+
+        let signed = sign_pdf(&unsigned, &self_signed("Alice"), placement(), None).unwrap();
+        let mut update = pdfio::IncrementalUpdate::new(&signed).unwrap();
+        let overlay = update.add(Object::Stream(lopdf::Stream::new(Dictionary::new(), b"BT ET".to_vec())));
+        let Object::Array(mut items) = update.get(contents).unwrap() else { panic!("array expected") };
+        items.push(Object::Reference(overlay));
+        update.set(contents, Object::Array(items));
+
+        let report = verify_pdf(&update.to_bytes().unwrap(), &[]).unwrap().unwrap();
+        let modifications = &report.signatures[0].modifications;
+        assert!(modifications.contains(&format!("object {} modified", contents.0)), "{modifications:?}");
+        assert!(modifications.contains(&format!("object {} added", overlay.0)), "{modifications:?}");
+    }
+}
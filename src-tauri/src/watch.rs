@@ -0,0 +1,511 @@
+//! Hot folder : PDFs dropped into a watched folder are picked up as
+//! they arrive. By default each one is queued in the window through
+//! [`crate::emit_open_pdf`] ; in unattended mode a saved template is
+//! applied, the result flattened and written to an output folder, like
+//! `cerfini apply` does.
+//!
+//! Scanners and file shares write a file in several steps, so a file
+//! is only handled once its size and modification time have stayed the
+//! same for [`SETTLE`]. In unattended mode the handled file is moved to
+//! `processed/` or `failed/` inside the watched folder, which also
+//! keeps it from being handled again. Every outcome is appended to
+//! `watch_log.json` and emitted as `"watch-log"`.
+//!
+//! The folders come from the folder dialog, never from the renderer,
+//! and the configuration stays on this machine : it is not part of a
+//! profile backup.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::cli::{self, DateLocale, Job};
+use crate::error::{Error, ErrorKind, Result};
+use crate::store;
+use crate::vault::{self, Vault};
+
+const CONFIG_FILE: &str = "watch_folder.json";
+const LOG_FILE: &str = "watch_log.json";
+const MAX_LOG: usize = 500;
+/// How long a file must stay unchanged before it is handled.
+const SETTLE: Duration = Duration::from_secs(2);
+const TICK: Duration = Duration::from_millis(500);
+const PROCESSED_DIR: &str = "processed";
+const FAILED_DIR: &str = "failed";
+
+/// Serializes read-modify-write cycles on `watch_log.json`.
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Open each new PDF in the window.
+    #[default]
+    Queue,
+    /// Apply `template` and write the result to `output_dir`.
+    Unattended,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchConfig {
+    pub enabled: bool,
+    pub input_dir: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub mode: WatchMode,
+    /// Template name or id, as for `cerfini apply`.
+    pub template: Option<String>,
+    /// Locale of the date written into date items.
+    pub locale: DateLocale,
+}
+
+/// What the renderer may change ; the folders go through
+/// [`pick_watch_folder`].
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchOptions {
+    enabled: bool,
+    mode: WatchMode,
+    template: Option<String>,
+    #[serde(default)]
+    locale: DateLocale,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum FolderRole {
+    Input,
+    Output,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchStatus {
+    config: WatchConfig,
+    running: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Queued,
+    Processed,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// RFC 3339.
+    pub at: String,
+    pub input: String,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// The serialized [`Error`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<serde_json::Value>,
+}
+
+impl LogEntry {
+    fn new(input: &Path, outcome: Outcome) -> Self {
+        LogEntry {
+            at: chrono::Utc::now().to_rfc3339(),
+            input: input.to_string_lossy().to_string(),
+            outcome,
+            output: None,
+            error: None,
+        }
+    }
+}
+
+/// The running watcher ; dropping it closes the channel, which stops
+/// the worker thread.
+#[derive(Default)]
+pub struct HotFolder(Mutex<Option<RecommendedWatcher>>);
+
+fn load_config(dir: &Path) -> Result<WatchConfig> {
+    Ok(store::load_json(&dir.join(CONFIG_FILE))?.unwrap_or_default())
+}
+
+fn check_config(config: &WatchConfig) -> Result<()> {
+    if !config.enabled {
+        return Ok(());
+    }
+    let Some(input) = &config.input_dir else {
+        return Err(Error::new(ErrorKind::InvalidInput, "watch_input_missing"));
+    };
+    if config.mode == WatchMode::Unattended {
+        let Some(output) = &config.output_dir else {
+            return Err(Error::new(ErrorKind::InvalidInput, "watch_output_missing"));
+        };
+        // The outputs would be picked up again.
+        if output == input {
+            return Err(Error::new(ErrorKind::InvalidInput, "watch_output_is_input").with_path(output));
+        }
+        if config.template.as_deref().map_or(true, |template| template.trim().is_empty()) {
+            return Err(Error::new(ErrorKind::InvalidInput, "watch_template_missing"));
+        }
+    }
+    Ok(())
+}
+
+pub fn load_log(dir: &Path) -> Result<Vec<LogEntry>> {
+    Ok(store::load_json(&dir.join(LOG_FILE))?.unwrap_or_default())
+}
+
+/// Append `entry` to the log, dropping the oldest entries past the cap.
+fn append_log(dir: &Path, entry: &LogEntry) -> Result<()> {
+    let _guard = LOG_LOCK.lock().unwrap();
+    let mut entries = load_log(dir)?;
+    entries.push(entry.clone());
+    let excess = entries.len().saturating_sub(MAX_LOG);
+    entries.drain(..excess);
+    store::save_json(&dir.join(LOG_FILE), &entries)
+}
+
+/// Files seen in the watched folder and not handled yet.
+#[derive(Default)]
+struct Pending(HashMap<PathBuf, Seen>);
+
+struct Seen {
+    size: u64,
+    modified: Option<SystemTime>,
+    since: Instant,
+}
+
+impl Pending {
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        self.0.entry(path).or_insert(Seen { size: u64::MAX, modified: None, since: now });
+    }
+
+    /// Files unchanged for [`SETTLE`], removed from the set. Vanished
+    /// files are forgotten.
+    fn settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.0.retain(|path, seen| {
+            let Ok(meta) = std::fs::metadata(path) else {
+                return false;
+            };
+            let modified = meta.modified().ok();
+            if meta.len() != seen.size || modified != seen.modified {
+                *seen = Seen { size: meta.len(), modified, since: now };
+                return true;
+            }
+            if now.duration_since(seen.since) < SETTLE {
+                return true;
+            }
+            ready.push(path.clone());
+            false
+        });
+        ready.sort();
+        ready
+    }
+}
+
+/// PDFs directly inside the watched folder ; hidden files are partial
+/// copies from some tools.
+fn is_candidate(input_dir: &Path, path: &Path) -> bool {
+    path.parent() == Some(input_dir)
+        && crate::is_pdf_path(path)
+        && !path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'))
+}
+
+/// Move a handled file into `sub` of its folder, renamed on collision.
+fn move_aside(input: &Path, sub: &str) -> Result<PathBuf> {
+    let dir = input.parent().unwrap_or(Path::new(".")).join(sub);
+    std::fs::create_dir_all(&dir).map_err(|e| Error::io("create_dir_failed", &dir, &e))?;
+    let name = input.file_name().and_then(|name| name.to_str()).unwrap_or("document.pdf");
+    let target = crate::next_available_path(dir, name);
+    std::fs::rename(input, &target).map_err(|e| Error::io("watch_move_failed", input, &e))?;
+    Ok(target)
+}
+
+/// The job for `config`, with the window's vault or, when it is
+/// locked, the key remembered in the keyring.
+fn prepare(vault: &Vault, dir: &Path, config: &WatchConfig) -> Result<Job> {
+    let template = config.template.as_deref().unwrap_or_default();
+    match Job::prepare(vault, dir, template, config.locale) {
+        Err(err) if err.kind == ErrorKind::VaultLocked => {
+            let headless = Vault::default();
+            vault::unlock_headless(&headless, dir, None)?;
+            Job::prepare(&headless, dir, template, config.locale)
+        }
+        other => other,
+    }
+}
+
+/// Unattended handling of `input` : flatten it into the output folder,
+/// then move it aside.
+fn process_file(vault: &Vault, dir: &Path, config: &WatchConfig, input: &Path) -> LogEntry {
+    let written = config
+        .output_dir
+        .as_deref()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "watch_output_missing"))
+        .and_then(|out_dir| {
+            let job = prepare(vault, dir, config)?;
+            std::fs::create_dir_all(out_dir).map_err(|e| Error::io("create_dir_failed", out_dir, &e))?;
+            cli::process(input, out_dir, &job)
+        });
+    let (mut entry, sub) = match written {
+        Ok(output) => {
+            let mut entry = LogEntry::new(input, Outcome::Processed);
+            entry.output = Some(output.to_string_lossy().to_string());
+            (entry, PROCESSED_DIR)
+        }
+        Err(err) => {
+            let mut entry = LogEntry::new(input, Outcome::Failed);
+            entry.error = serde_json::to_value(&err).ok();
+            (entry, FAILED_DIR)
+        }
+    };
+    if let Err(err) = move_aside(input, sub) {
+        eprintln!("hot folder: {err}");
+        if entry.error.is_none() {
+            entry.error = serde_json::to_value(&err).ok();
+        }
+    }
+    entry
+}
+
+fn record(app: &tauri::AppHandle, dir: &Path, entry: LogEntry) {
+    if let Err(err) = append_log(dir, &entry) {
+        eprintln!("hot folder log failed: {err}");
+    }
+    if let Err(err) = app.emit("watch-log", entry) {
+        eprintln!("emit watch-log failed: {err}");
+    }
+}
+
+fn run(app: tauri::AppHandle, dir: PathBuf, config: WatchConfig, input_dir: PathBuf, events: Receiver<PathBuf>) {
+    let mut pending = Pending::default();
+    // Files already queued in the window, with the modification time
+    // they had, so an unchanged file is not opened twice.
+    let mut queued: HashMap<PathBuf, Option<SystemTime>> = HashMap::new();
+    // Unattended mode moves what it handles, so whatever is still in
+    // the folder was left over by a previous run.
+    if config.mode == WatchMode::Unattended {
+        if let Ok(entries) = std::fs::read_dir(&input_dir) {
+            for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+                if is_candidate(&input_dir, &path) {
+                    pending.touch(path, Instant::now());
+                }
+            }
+        }
+    }
+    loop {
+        match events.recv_timeout(TICK) {
+            Ok(path) if is_candidate(&input_dir, &path) => pending.touch(path, Instant::now()),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        for path in pending.settled(Instant::now()) {
+            match config.mode {
+                WatchMode::Queue => {
+                    let modified = std::fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+                    if queued.get(&path) == Some(&modified) {
+                        continue;
+                    }
+                    queued.insert(path.clone(), modified);
+                    crate::emit_open_pdf(&app, path.clone());
+                    record(&app, &dir, LogEntry::new(&path, Outcome::Queued));
+                }
+                WatchMode::Unattended => {
+                    let entry = process_file(&app.state::<Vault>(), &dir, &config, &path);
+                    record(&app, &dir, entry);
+                }
+            }
+        }
+    }
+}
+
+/// Stop the current watcher and start one for the saved configuration,
+/// when it is enabled.
+pub fn restart(app: &tauri::AppHandle) -> Result<()> {
+    let dir = crate::app_data_dir(app)?;
+    let config = load_config(&dir)?;
+    let state = app.state::<HotFolder>();
+    let mut running = state.0.lock().unwrap();
+    *running = None;
+    if !config.enabled {
+        return Ok(());
+    }
+    check_config(&config)?;
+    let input_dir = config.input_dir.clone().unwrap_or_default();
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+        Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+            for path in event.paths {
+                let _ = sender.send(path);
+            }
+        }
+        Ok(_) => {}
+        Err(err) => eprintln!("hot folder watch failed: {err}"),
+    })
+    .map_err(|e| Error::new(ErrorKind::Unavailable, "watch_failed").with_details(e))?;
+    watcher
+        .watch(&input_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| Error::new(ErrorKind::Io, "watch_failed").with_path(&input_dir).with_details(e))?;
+
+    let app = app.clone();
+    std::thread::spawn(move || run(app, dir, config, input_dir, events));
+    *running = Some(watcher);
+    Ok(())
+}
+
+fn status(app: &tauri::AppHandle) -> Result<WatchStatus> {
+    let config = load_config(&crate::app_data_dir(app)?)?;
+    let running = app.state::<HotFolder>().0.lock().unwrap().is_some();
+    Ok(WatchStatus { config, running })
+}
+
+fn save_and_restart(app: &tauri::AppHandle, config: &WatchConfig) -> Result<WatchStatus> {
+    check_config(config)?;
+    store::save_json(&crate::app_data_dir(app)?.join(CONFIG_FILE), config)?;
+    restart(app)?;
+    status(app)
+}
+
+#[tauri::command]
+pub fn get_watch_folder(app: tauri::AppHandle) -> Result<WatchStatus> {
+    status(&app)
+}
+
+/// Choose the watched or the output folder through the folder dialog.
+#[tauri::command(async)]
+pub fn pick_watch_folder(app: tauri::AppHandle, role: FolderRole) -> Result<WatchStatus> {
+    let Some(picked) = app.dialog().file().blocking_pick_folder() else {
+        return status(&app);
+    };
+    let folder = picked.into_path().map_err(|e| Error::new(ErrorKind::InvalidInput, "path_invalid").with_details(e))?;
+    let folder = std::fs::canonicalize(&folder).map_err(|e| Error::io("folder_read_failed", &folder, &e))?;
+    let mut config = load_config(&crate::app_data_dir(&app)?)?;
+    match role {
+        FolderRole::Input => config.input_dir = Some(folder),
+        FolderRole::Output => config.output_dir = Some(folder),
+    }
+    save_and_restart(&app, &config)
+}
+
+#[tauri::command]
+pub fn set_watch_folder(app: tauri::AppHandle, options: WatchOptions) -> Result<WatchStatus> {
+    let mut config = load_config(&crate::app_data_dir(&app)?)?;
+    config.enabled = options.enabled;
+    config.mode = options.mode;
+    config.template = options.template.filter(|template| !template.trim().is_empty());
+    config.locale = options.locale;
+    save_and_restart(&app, &config)
+}
+
+/// Outcomes of the handled files, oldest first.
+#[tauri::command]
+pub fn watch_folder_log(app: tauri::AppHandle) -> Result<Vec<LogEntry>> {
+    load_log(&crate::app_data_dir(&app)?)
+}
+
+#[tauri::command]
+pub fn clear_watch_folder_log(app: tauri::AppHandle) -> Result<()> {
+    let _guard = LOG_LOCK.lock().unwrap();
+    store::save_json(&crate::app_data_dir(&app)?.join(LOG_FILE), &Vec::<LogEntry>::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::scratch_dir;
+    use crate::pdfio::tests::sample_pdf;
+
+    #[test]
+    fn files_are_handled_once_they_stop_growing() {
+        let dir = scratch_dir("settle");
+        let file = dir.join("scan.pdf");
+        std::fs::write(&file, b"%PDF-1.7\n").unwrap();
+        let start = Instant::now();
+        let mut pending = Pending::default();
+        pending.touch(file.clone(), start);
+
+        assert!(pending.settled(start).is_empty());
+        std::fs::write(&file, b"%PDF-1.7\n% still copying\n").unwrap();
+        assert!(pending.settled(start + SETTLE).is_empty(), "a growing file waits");
+        assert!(pending.settled(start + SETTLE + TICK).is_empty());
+        assert_eq!(pending.settled(start + SETTLE * 2), vec![file.clone()]);
+        assert!(pending.0.is_empty());
+
+        pending.touch(dir.join("gone.pdf"), start);
+        assert!(pending.settled(start + SETTLE * 3).is_empty());
+        assert!(pending.0.is_empty(), "vanished files are forgotten");
+    }
+
+    #[test]
+    fn only_pdfs_directly_inside_the_folder_are_candidates() {
+        let dir = Path::new("/watched");
+        assert!(is_candidate(dir, &dir.join("Contrat.PDF")));
+        assert!(!is_candidate(dir, &dir.join(".Contrat.pdf")));
+        assert!(!is_candidate(dir, &dir.join("notes.txt")));
+        assert!(!is_candidate(dir, &dir.join(PROCESSED_DIR).join("Contrat.pdf")));
+    }
+
+    #[test]
+    fn unattended_config_needs_a_distinct_output_and_a_template() {
+        let input = PathBuf::from("/in");
+        let config = WatchConfig {
+            enabled: true,
+            input_dir: Some(input.clone()),
+            output_dir: Some(input.clone()),
+            mode: WatchMode::Unattended,
+            template: Some("Contrat".into()),
+            locale: DateLocale::Fr,
+        };
+        assert_eq!(check_config(&config).unwrap_err().code, "watch_output_is_input");
+        let config = WatchConfig { output_dir: Some("/out".into()), template: None, ..config };
+        assert_eq!(check_config(&config).unwrap_err().code, "watch_template_missing");
+        assert!(check_config(&WatchConfig { mode: WatchMode::Queue, ..config }).is_ok());
+    }
+
+    #[test]
+    fn unattended_files_are_written_moved_aside_and_logged() {
+        let dir = scratch_dir("unattended");
+        let (data, input_dir, output_dir) = (dir.join("data"), dir.join("in"), dir.join("out"));
+        std::fs::create_dir_all(&input_dir).unwrap();
+        crate::db::save_templates(
+            &data,
+            &[serde_json::json!({ "id": "t", "name": "Contrat", "updatedAt": "2026-01-01T00:00:00Z", "items": [], "paraph": null })],
+        )
+        .unwrap();
+        let config = WatchConfig {
+            enabled: true,
+            input_dir: Some(input_dir.clone()),
+            output_dir: Some(output_dir.clone()),
+            mode: WatchMode::Unattended,
+            template: Some("Contrat".into()),
+            locale: DateLocale::Fr,
+        };
+        let good = input_dir.join("contrat.pdf");
+        std::fs::write(&good, sample_pdf(1, "Contrat")).unwrap();
+        let bad = input_dir.join("broken.pdf");
+        std::fs::write(&bad, b"not a pdf").unwrap();
+
+        let vault = Vault::default();
+        let processed = process_file(&vault, &data, &config, &good);
+        assert_eq!(processed.outcome, Outcome::Processed);
+        assert_eq!(processed.output, Some(output_dir.join("contrat-signed.pdf").to_string_lossy().to_string()));
+        assert!(input_dir.join(PROCESSED_DIR).join("contrat.pdf").exists() && !good.exists());
+
+        let failed = process_file(&vault, &data, &config, &bad);
+        assert_eq!(failed.outcome, Outcome::Failed);
+        assert_eq!(failed.error.as_ref().and_then(|err| err["kind"].as_str()), Some("invalid_pdf"));
+        assert!(input_dir.join(FAILED_DIR).join("broken.pdf").exists());
+
+        append_log(&data, &processed).unwrap();
+        append_log(&data, &failed).unwrap();
+        assert_eq!(load_log(&data).unwrap(), vec![processed, failed]);
+    }
+}
@@ -1,6 +1,17 @@
 import { useEffect, useMemo, useRef, useState } from "react";
 import type { MouseEvent as ReactMouseEvent, PointerEvent as ReactPointerEvent } from "react";
-import type { Item, Paraph, PdfRect, SignatureAsset, Tool, TextItem } from "./types";
+import type {
+  InkDrawing,
+  InkPoint,
+  Item,
+  Paraph,
+  PdfProtection,
+  PdfRect,
+  SignatureAsset,
+  Tool,
+  TextItem,
+  VerificationReport
+} from "./types";
 import { exportFlattenedPdf } from "./pdf/exportPdf";
 import { pxDeltaToPdfDelta, pxSizeToPdfSize } from "./pdf/coords";
 import {
@@ -9,6 +20,7 @@ import {
   HISTORY_LIMIT,
   MIN_RESIZE_PDF,
   OBJECT_URL_REVOKE_MS,
+  PAD_PEN,
   PATH_REOPEN_DEBOUNCE_MS,
   SIGNATURE_DEFAULTS,
   TEXT_DEFAULTS,
@@ -16,15 +28,20 @@ import {
 } from "./constants";
 import { bytesToDataUrl, fileToBytes, getImageNaturalSize } from "./utils/file";
 import { uid } from "./utils/uid";
+import { describeCommandError, isCommandError } from "./utils/commandError";
 import { ItemOverlay } from "./components/items/ItemOverlay";
 import { ParaphOverlay } from "./components/items/ParaphOverlay";
 import { useSnippets } from "./hooks/useSnippets";
 import { useSignatures } from "./hooks/useSignatures";
 import { useParaphAssets } from "./hooks/useParaphAssets";
+import { normalizeImageAsset } from "./hooks/useImageAssets";
+import { ScanCleanupModal } from "./components/ScanCleanupModal";
+import { SignatureReportModal } from "./components/SignatureReportModal";
 import { useTemplates } from "./hooks/useTemplates";
 import { TemplatesModal } from "./components/TemplatesModal";
 import { applyTemplate } from "./templates/applyTemplate";
 import type { Template } from "./templates/types";
+import { pickAnchor, withAnchors, type PickedAnchor, type SearchHit } from "./templates/anchors";
 import { useDragMachine } from "./hooks/useDragMachine";
 import { usePdfDocument } from "./hooks/usePdfDocument";
 import { useTextStyle } from "./hooks/useTextStyle";
@@ -47,6 +64,7 @@ import {
   FilePdf,
   FloppyDisk,
   Highlighter,
+  LockSimple,
   Minus,
   Moon,
   Plus,
@@ -74,6 +92,15 @@ export default function App() {
   const [pdfBytes, setPdfBytes] = useState<Uint8Array | null>(null);
   const [scale, setScale] = useState<number>(ZOOM.default);
   const [fileName, setFileName] = useState<string>("document.pdf");
+  /** Password the export is protected with, or null for a plain copy.
+   *  Preset to the opening password when a protected file is loaded, so
+   *  saving it again does not silently drop the protection. */
+  const [exportPassword, setExportPassword] = useState<string | null>(null);
+  /** Digital signatures found in the open file, if it has any. */
+  const [signatureReport, setSignatureReport] = useState<VerificationReport | null>(null);
+  const [showSignatureReport, setShowSignatureReport] = useState(false);
+  /** The user agreed to edit the signed document ; asked once per file. */
+  const signedEditAccepted = useRef(false);
 
   const canvasRefs = useRef(new Map<number, HTMLCanvasElement>());
 
@@ -106,7 +133,14 @@ export default function App() {
 
   const [showAboutModal, setShowAboutModal] = useState(false);
   const [showTemplatesModal, setShowTemplatesModal] = useState(false);
+  /** Imported image waiting for the scan cleanup dialog, and the
+   *  gallery it goes to. */
+  const [pendingScan, setPendingScan] = useState<{ asset: SignatureAsset; gallery: "signatures" | "paraphs" } | null>(null);
   const [templates, setTemplates] = useTemplates();
+  /** Phrases items are tied to, by item id, for the next template saved
+   *  from this document. */
+  const [itemAnchors, setItemAnchors] = useState<Record<string, PickedAnchor>>({});
+  const [anchorQuery, setAnchorQuery] = useState("");
   /** App version read from `tauri.conf.json` once the Tauri shell is
    *  ready ; falls back to "dev" in the plain-browser web preview. */
   const [appVersion, setAppVersion] = useState<string>("dev");
@@ -135,6 +169,9 @@ export default function App() {
   const [padMode, setPadMode] = useState<"signature" | "paraph" | null>(null);
   const [isDrawingSignature, setIsDrawingSignature] = useState(false);
   const lastSignaturePoint = useRef<{ x: number; y: number } | null>(null);
+  /** Strokes drawn on the pad, kept next to the PNG so the native
+   *  export can draw the signature as vectors. */
+  const padStrokes = useRef<{ start: number; strokes: InkPoint[][] }>({ start: 0, strokes: [] });
   const [editingSignatureId, setEditingSignatureId] = useState<string | null>(null);
   const [editingSignatureName, setEditingSignatureName] = useState("");
   const [themeChoice, setThemeChoice] = useState<"light" | "dark">(() => {
@@ -300,9 +337,10 @@ export default function App() {
     ctx.scale(dpr, dpr);
     ctx.lineCap = "round";
     ctx.lineJoin = "round";
-    ctx.strokeStyle = "#111111";
-    ctx.lineWidth = 2;
+    ctx.strokeStyle = PAD_PEN.color;
+    ctx.lineWidth = PAD_PEN.width;
     ctx.clearRect(0, 0, rect.width, rect.height);
+    padStrokes.current = { start: 0, strokes: [] };
   }, [padMode]);
 
   useEffect(() => {
@@ -362,10 +400,26 @@ export default function App() {
     });
   }
 
+  /** Whether the document may be edited : exporting a signed one breaks
+   *  its signatures, so the first edit asks for confirmation. */
+  function confirmEditingSigned(): boolean {
+    if (!signatureReport?.editingInvalidatesSignatures || signedEditAccepted.current) return true;
+    if (!window.confirm(t("signed_edit_confirm"))) {
+      // Drop whatever gesture asked, so a drag does not ask again on
+      // every move.
+      resetDrag();
+      setTool("pan");
+      return false;
+    }
+    signedEditAccepted.current = true;
+    return true;
+  }
+
   function updateItems(
     updater: Item[] | ((prev: Item[]) => Item[]),
     options: { record?: boolean } = {}
   ) {
+    if (!confirmEditingSigned()) return;
     const { record = true } = options;
     setItems(prev => {
       if (record) pushHistory(prev);
@@ -453,6 +507,11 @@ export default function App() {
     // the source view ; keep a defensive copy for the export pipeline.
     setPdfBytes(bytes.slice(0));
     setFileName(name || "document.pdf");
+    setExportPassword(null);
+    setSignatureReport(null);
+    setShowSignatureReport(false);
+    setItemAnchors({});
+    signedEditAccepted.current = false;
     setItems([]);
     setHistory([]);
     setEditingId(null);
@@ -468,12 +527,48 @@ export default function App() {
     openPdfBytes(bytes, file.name || "document.pdf");
   }
 
-  type LoadedPdfPayload = { bytes: number[]; name: string };
+  type LoadedPdfPayload = {
+    bytes: number[];
+    name: string;
+    signatureReport: VerificationReport | null;
+    encrypted: boolean;
+  };
 
+  /** Open `path`, asking for its password as long as the file is
+   *  protected and the user does not cancel. */
   async function openPdfFromPath(path: string) {
-    const payload = await invoke<LoadedPdfPayload>("load_pdf_from_path", { path });
-    const bytes = new Uint8Array(payload.bytes);
-    openPdfBytes(bytes, payload.name);
+    let password: string | undefined;
+    for (;;) {
+      let payload: LoadedPdfPayload;
+      try {
+        payload = await invoke<LoadedPdfPayload>("load_pdf_from_path", { path, password });
+      } catch (err) {
+        // `pdf_password_required`, or `pdf_password_incorrect` on a retry.
+        if (!isCommandError(err) || !err.code.startsWith("pdf_password_")) throw err;
+        const name = path.split(/[\\/]/).pop() ?? path;
+        const key = err.code === "pdf_password_required" ? "pdf_password_prompt" : "pdf_password_retry";
+        const answer = window.prompt(t(key).replace("{name}", name));
+        if (answer === null) return;
+        password = answer;
+        continue;
+      }
+      openPdfBytes(new Uint8Array(payload.bytes), payload.name);
+      if (payload.encrypted) setExportPassword(password ?? "");
+      if (payload.signatureReport) {
+        setSignatureReport(payload.signatureReport);
+        setShowSignatureReport(true);
+      }
+      return;
+    }
+  }
+
+  function toggleExportProtection() {
+    if (exportPassword !== null) {
+      setExportPassword(null);
+      return;
+    }
+    const password = window.prompt(t("export_protect_prompt"));
+    if (password) setExportPassword(password);
   }
 
   function openPdfPathOnce(path: string) {
@@ -484,6 +579,7 @@ export default function App() {
     window.setTimeout(() => openedPdfPaths.current.delete(key), PATH_REOPEN_DEBOUNCE_MS);
     void openPdfFromPath(path).catch((err) => {
       console.error("Open PDF from path failed:", err);
+      window.alert(describeCommandError(t, err));
     });
   }
 
@@ -650,6 +746,7 @@ export default function App() {
       const ratio = asset.naturalH > 0 ? asset.naturalW / asset.naturalH : 3;
       const targetHPx = Math.max(SIGNATURE_DEFAULTS.minHeightPx, Math.round(targetWPx / Math.max(1, ratio)));
       const { wPdf, hPdf } = pxSizeToPdfSize(targetWPx, targetHPx, viewport);
+      if (!confirmEditingSigned()) return;
 
       setParaph({
         assetId: selectedParaphId,
@@ -781,41 +878,41 @@ export default function App() {
     handleDragPointerUp();
   }
 
+  /**
+   * Build a gallery asset from imported or drawn bytes, validated and
+   * normalized on the Rust side. Returns null (after telling the user)
+   * when the image is refused.
+   */
+  async function buildImageAsset(bytes: Uint8Array, mime: "image/png" | "image/jpeg", name: string, ink?: InkDrawing) {
+    try {
+      const dataUrl = await bytesToDataUrl(bytes, mime);
+      const { w, h } = await getImageNaturalSize(dataUrl);
+      return await normalizeImageAsset({ id: uid(), name, mime, bytes, dataUrl, naturalW: w, naturalH: h, ink });
+    } catch (err) {
+      console.error("Image import failed:", err);
+      window.alert(describeCommandError(t, err));
+      return null;
+    }
+  }
+
   async function importSignature(file: File) {
     const bytes = await fileToBytes(file);
     const mime = (file.type === "image/png" ? "image/png" : "image/jpeg") as "image/png" | "image/jpeg";
-    const dataUrl = await bytesToDataUrl(bytes, mime);
-    const { w, h } = await getImageNaturalSize(dataUrl);
+    const asset = await buildImageAsset(bytes, mime, file.name || "signature");
+    if (!asset) return;
 
-    const asset: SignatureAsset = {
-      id: uid(),
-      name: file.name || "signature",
-      mime,
-      bytes,
-      dataUrl,
-      naturalW: w,
-      naturalH: h
-    };
-
-    setSignatures(prev => [asset, ...prev]);
-    setSelectedSignatureId(asset.id);
+    // Imported files are often photos of paper : offer the cleanup.
+    if (isTauri()) setPendingScan({ asset, gallery: "signatures" });
+    else addSignature(asset);
   }
 
-  async function addSignatureFromBytes(bytes: Uint8Array, name: string) {
-    const mime = "image/png" as const;
-    const dataUrl = await bytesToDataUrl(bytes, mime);
-    const { w, h } = await getImageNaturalSize(dataUrl);
-
-    const asset: SignatureAsset = {
-      id: uid(),
-      name,
-      mime,
-      bytes,
-      dataUrl,
-      naturalW: w,
-      naturalH: h
-    };
+  async function addSignatureFromBytes(bytes: Uint8Array, name: string, ink?: InkDrawing) {
+    const asset = await buildImageAsset(bytes, "image/png", name, ink);
+    if (!asset) return;
+    addSignature(asset);
+  }
 
+  function addSignature(asset: SignatureAsset) {
     setSignatures(prev => [asset, ...prev]);
     setSelectedSignatureId(asset.id);
   }
@@ -835,41 +932,23 @@ export default function App() {
     setEditingSignatureName("");
   }
 
-  async function addParaphFromBytes(bytes: Uint8Array, name: string) {
-    const mime = "image/png" as const;
-    const dataUrl = await bytesToDataUrl(bytes, mime);
-    const { w, h } = await getImageNaturalSize(dataUrl);
-
-    const asset: SignatureAsset = {
-      id: uid(),
-      name,
-      mime,
-      bytes,
-      dataUrl,
-      naturalW: w,
-      naturalH: h
-    };
-
-    setParaphs(prev => [asset, ...prev]);
-    setSelectedParaphId(asset.id);
+  async function addParaphFromBytes(bytes: Uint8Array, name: string, ink?: InkDrawing) {
+    const asset = await buildImageAsset(bytes, "image/png", name, ink);
+    if (!asset) return;
+    addParaph(asset);
   }
 
   async function importParaph(file: File) {
     const bytes = await fileToBytes(file);
     const mime = (file.type === "image/png" ? "image/png" : "image/jpeg") as "image/png" | "image/jpeg";
-    const dataUrl = await bytesToDataUrl(bytes, mime);
-    const { w, h } = await getImageNaturalSize(dataUrl);
+    const asset = await buildImageAsset(bytes, mime, file.name || "paraph");
+    if (!asset) return;
 
-    const asset: SignatureAsset = {
-      id: uid(),
-      name: file.name || "paraph",
-      mime,
-      bytes,
-      dataUrl,
-      naturalW: w,
-      naturalH: h
-    };
+    if (isTauri()) setPendingScan({ asset, gallery: "paraphs" });
+    else addParaph(asset);
+  }
 
+  function addParaph(asset: SignatureAsset) {
     setParaphs(prev => [asset, ...prev]);
     setSelectedParaphId(asset.id);
   }
@@ -896,14 +975,64 @@ export default function App() {
       updatedAt: new Date().toISOString(),
       // Deep-clone via JSON so subsequent mutations to items / paraph
       // in the live document don't bleed into the saved template.
-      items: JSON.parse(JSON.stringify(items)),
+      items: JSON.parse(JSON.stringify(withAnchors(items, itemAnchors))),
       paraph: paraph ? JSON.parse(JSON.stringify(paraph)) : null
     };
     setTemplates(prev => [template, ...prev]);
   }
 
-  function applyTemplateById(template: Template) {
-    const result = applyTemplate({ template, locale: lang });
+  /** Tie the selected item to the match of `anchorQuery` closest to it,
+   *  so templates saved from here find their place by that phrase. */
+  async function anchorSelectedItem() {
+    const query = anchorQuery.trim();
+    if (!selectedItem || !pdfBytes || !query) return;
+    try {
+      const hits = await invoke<SearchHit[]>("search_pdf", { document: { bytes: Array.from(pdfBytes) }, query });
+      const picked = pickAnchor(query, hits, selectedItem);
+      if (!picked) {
+        window.alert(t("anchor_not_on_page").replace("{text}", query));
+        return;
+      }
+      setItemAnchors(prev => ({ ...prev, [selectedItem.id]: picked }));
+      setAnchorQuery("");
+    } catch (err) {
+      console.error("Anchor search failed:", err);
+      window.alert(describeCommandError(t, err));
+    }
+  }
+
+  function detachSelectedItem() {
+    if (!selectedId) return;
+    setItemAnchors(prev => {
+      const next = { ...prev };
+      delete next[selectedId];
+      return next;
+    });
+  }
+
+  async function applyTemplateById(template: Template) {
+    if (!confirmEditingSigned()) return;
+    // Anchored items are placed on the open document by the Rust side ;
+    // those whose phrase is missing keep the template's position.
+    let placed = template;
+    if (pdfBytes && isTauri() && template.items.some(it => it.anchor)) {
+      try {
+        const resolved = await invoke<{ items: Item[]; missing: { itemId: string; text: string }[] }>(
+          "resolve_template_anchors",
+          { document: { bytes: Array.from(pdfBytes) }, items: template.items }
+        );
+        if (resolved.missing.length > 0) {
+          const phrases = [...new Set(resolved.missing.map(m => m.text))].join("\n");
+          window.alert(t("templates_anchor_missing").replace("{phrases}", phrases));
+        }
+        placed = { ...template, items: resolved.items };
+      } catch (err) {
+        console.error("Anchor resolution failed:", err);
+        window.alert(describeCommandError(t, err));
+        return;
+      }
+    }
+    const result = applyTemplate({ template: placed, locale: lang });
     // Append : preserve any overlays the user already placed. Records
     // a history entry so the apply is undoable in one shot.
     updateItems(prev => [...prev, ...result.items], { record: true });
@@ -929,6 +1058,21 @@ export default function App() {
     const ctx = canvas.getContext("2d");
     if (!ctx) return;
     ctx.clearRect(0, 0, canvas.width, canvas.height);
+    padStrokes.current = { start: 0, strokes: [] };
+  }
+
+  /** Record a pad sample ; mice report no pressure, stored as 0.5. */
+  function recordPadPoint(e: ReactPointerEvent<HTMLCanvasElement>, x: number, y: number, newStroke: boolean) {
+    const pad = padStrokes.current;
+    if (pad.strokes.length === 0) pad.start = e.timeStamp;
+    const point: InkPoint = {
+      x,
+      y,
+      pressure: e.pointerType === "mouse" || e.pressure === 0 ? 0.5 : e.pressure,
+      t: Math.max(0, e.timeStamp - pad.start)
+    };
+    if (newStroke || pad.strokes.length === 0) pad.strokes.push([point]);
+    else pad.strokes[pad.strokes.length - 1].push(point);
   }
 
   function isSignatureBlank(): boolean {
@@ -953,6 +1097,7 @@ export default function App() {
     const x = e.clientX - rect.left;
     const y = e.clientY - rect.top;
     lastSignaturePoint.current = { x, y };
+    recordPadPoint(e, x, y, true);
     setIsDrawingSignature(true);
     ctx.beginPath();
     ctx.moveTo(x, y);
@@ -973,6 +1118,7 @@ export default function App() {
     if (!last) return;
     ctx.lineTo(x, y);
     ctx.stroke();
+    recordPadPoint(e, x, y, false);
     lastSignaturePoint.current = { x, y };
   }
 
@@ -997,11 +1143,19 @@ export default function App() {
     if (!blob) return;
     const buf = await blob.arrayBuffer();
     const bytes = new Uint8Array(buf);
+    const rect = canvas.getBoundingClientRect();
+    const ink: InkDrawing = {
+      width: rect.width,
+      height: rect.height,
+      penWidth: PAD_PEN.width,
+      color: PAD_PEN.color,
+      strokes: padStrokes.current.strokes
+    };
     const datePart = new Date().toISOString().slice(0, 10);
     if (padMode === "signature") {
-      await addSignatureFromBytes(bytes, `signature-drawn-${datePart}.png`);
+      await addSignatureFromBytes(bytes, `signature-drawn-${datePart}.png`, ink);
     } else {
-      await addParaphFromBytes(bytes, `paraph-drawn-${datePart}.png`);
+      await addParaphFromBytes(bytes, `paraph-drawn-${datePart}.png`, ink);
     }
     setPadMode(null);
   }
@@ -1050,15 +1204,22 @@ export default function App() {
         });
         if (!pickedPath) return;
 
+        const protection: PdfProtection | null = exportPassword === null ? null : { userPassword: exportPassword };
         const savedPath = await invoke<string>("save_pdf_to_path", {
           bytes: Array.from(out),
-          path: pickedPath
+          path: pickedPath,
+          protection
         });
         window.alert(t("export_success").replace("{path}", savedPath));
         return;
       } catch (err) {
         console.error("Export PDF (Tauri) failed:", err);
-        window.alert(t("export_tauri_failed"));
+        // The direct download cannot be protected.
+        if (exportPassword !== null) {
+          window.alert(describeCommandError(t, err));
+          return;
+        }
+        window.alert(`${describeCommandError(t, err)}\n\n${t("export_tauri_failed")}`);
       }
     }
 
@@ -1355,6 +1516,18 @@ export default function App() {
           <button className="btn icon-btn" disabled={!canEdit} onClick={printPdf} title={t("print_pdf")} aria-label={t("print_pdf")}>
             <Printer size={18} weight="regular" />
           </button>
+          {isTauri() && (
+            <button
+              className={"btn icon-btn " + (exportPassword !== null ? "active" : "")}
+              disabled={!canEdit}
+              onClick={toggleExportProtection}
+              title={exportPassword !== null ? t("export_unprotect") : t("export_protect")}
+              aria-label={exportPassword !== null ? t("export_unprotect") : t("export_protect")}
+              aria-pressed={exportPassword !== null}
+            >
+              <LockSimple size={18} weight={exportPassword !== null ? "fill" : "regular"} />
+            </button>
+          )}
           <button className="btn icon-btn primary" disabled={!canEdit} onClick={exportPdf} title={t("export_pdf")} aria-label={t("export_pdf")}>
             <FloppyDisk size={18} weight="regular" />
           </button>
@@ -1591,6 +1764,47 @@ export default function App() {
               </div>
             )}
           </div>
+          {selectedItem && isTauri() && (
+            <div className="panel snippets">
+              <div className="snippets-head">
+                <h4>{t("anchor_title")}</h4>
+              </div>
+              {itemAnchors[selectedItem.id] ? (
+                <div className="snippets-item">
+                  <div className="snippets-use">
+                    <span>{itemAnchors[selectedItem.id].text}</span>
+                  </div>
+                  <button
+                    className="snippets-remove"
+                    onClick={detachSelectedItem}
+                    title={t("anchor_remove")}
+                    aria-label={t("anchor_remove")}
+                  >
+                    ×
+                  </button>
+                </div>
+              ) : (
+                <p className="hint">{t("anchor_hint")}</p>
+              )}
+              <div className="snippets-input">
+                <input
+                  type="text"
+                  value={anchorQuery}
+                  placeholder={t("anchor_placeholder")}
+                  onChange={(e) => setAnchorQuery(e.target.value)}
+                  onKeyDown={(e) => {
+                    if (e.key === "Enter") {
+                      e.preventDefault();
+                      void anchorSelectedItem();
+                    }
+                  }}
+                />
+                <button className="btn" disabled={!anchorQuery.trim()} onClick={() => void anchorSelectedItem()}>
+                  {t("anchor_set")}
+                </button>
+              </div>
+            </div>
+          )}
         </aside>
 
         <section className="viewer">
@@ -1649,7 +1863,9 @@ export default function App() {
                           placement={placement}
                           viewport={viewport}
                           values={formValues}
-                          onChange={setFormValue}
+                          onChange={(name, value) => {
+                            if (confirmEditingSigned()) setFormValue(name, value);
+                          }}
                           onButtonAction={(b) => { if (b.isReset) resetFormValues(); }}
                         />
                       ))}
@@ -1695,6 +1911,11 @@ export default function App() {
       <footer className="status">
         <span>
           {pdfDoc ? t("file_label").replace("{name}", fileName) : t("no_pdf")}
+          {signatureReport && (
+            <button className="btn link-btn" onClick={() => setShowSignatureReport(true)}>
+              {t("signature_report_view").replace("{count}", String(signatureReport.signatures.length))}
+            </button>
+          )}
         </span>
         <span className="right">
           {pdfDoc ? t("export_note") : ""}
@@ -1715,6 +1936,28 @@ export default function App() {
         />
       )}
 
+      {pendingScan && (
+        <ScanCleanupModal
+          asset={pendingScan.asset}
+          onUse={(asset) => {
+            if (pendingScan.gallery === "signatures") addSignature(asset);
+            else addParaph(asset);
+            setPendingScan(null);
+          }}
+          onClose={() => setPendingScan(null)}
+          t={t}
+        />
+      )}
+
+      {showSignatureReport && signatureReport && (
+        <SignatureReportModal
+          report={signatureReport}
+          onClose={() => setShowSignatureReport(false)}
+          t={t}
+          locale={lang}
+        />
+      )}
+
       {showAboutModal && (
         <div className="modal-backdrop" onClick={() => setShowAboutModal(false)}>
           <div className="modal-card about-card" onClick={(e) => e.stopPropagation()}>
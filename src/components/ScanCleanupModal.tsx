@@ -0,0 +1,121 @@
+import { useEffect, useState } from "react";
+import type { SignatureAsset } from "../types";
+import type { TranslationKey } from "../i18n/types";
+import { cleanSignatureScan, type ScanCleanupOptions } from "../hooks/useImageAssets";
+import { describeCommandError } from "../utils/commandError";
+
+type Props = {
+  /** The imported image, already normalized. */
+  asset: SignatureAsset;
+  /** Called with the asset to add : the cleaned one or the original. */
+  onUse: (asset: SignatureAsset) => void;
+  onClose: () => void;
+  t: (key: TranslationKey) => string;
+};
+
+const DEFAULT_THRESHOLD = 0.25;
+
+/**
+ * Offered when a signature or paraph image is imported : shows the
+ * original next to a cleaned copy (transparent paper, cropped to the
+ * ink, optionally recoloured) and lets the user pick which one to add.
+ * The preview is recomputed on the Rust side whenever an option moves.
+ */
+export function ScanCleanupModal({ asset, onUse, onClose, t }: Props) {
+  const [auto, setAuto] = useState(true);
+  const [threshold, setThreshold] = useState(DEFAULT_THRESHOLD);
+  const [ink, setInk] = useState<ScanCleanupOptions["ink"]>("original");
+  const [cleaned, setCleaned] = useState<SignatureAsset | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    let cancelled = false;
+    // Let the slider settle before asking for a new preview.
+    const timer = window.setTimeout(() => {
+      cleanSignatureScan(asset, { threshold: auto ? null : threshold, ink })
+        .then((result) => {
+          if (cancelled) return;
+          setCleaned(result);
+          setError(null);
+        })
+        .catch((err) => {
+          if (cancelled) return;
+          setCleaned(null);
+          setError(describeCommandError(t, err));
+        });
+    }, 150);
+    return () => {
+      cancelled = true;
+      window.clearTimeout(timer);
+    };
+  }, [asset, auto, threshold, ink, t]);
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal-card scan-card" onClick={(e) => e.stopPropagation()}>
+        <div className="modal-header">
+          <h3>{t("scan_cleanup_title")}</h3>
+          <button className="btn icon-btn" onClick={onClose} aria-label={t("signature_cancel")}>
+            ×
+          </button>
+        </div>
+
+        <div className="scan-previews">
+          <figure>
+            <img src={asset.dataUrl} alt={t("scan_cleanup_original")} />
+            <figcaption>{t("scan_cleanup_original")}</figcaption>
+          </figure>
+          <figure>
+            {cleaned ? (
+              <img src={cleaned.dataUrl} alt={t("scan_cleanup_cleaned")} />
+            ) : (
+              <p className="hint">{error ?? "…"}</p>
+            )}
+            <figcaption>{t("scan_cleanup_cleaned")}</figcaption>
+          </figure>
+        </div>
+
+        <div className="scan-options">
+          <label>
+            <input type="checkbox" checked={auto} onChange={(e) => setAuto(e.target.checked)} />
+            {t("scan_cleanup_auto")}
+          </label>
+          <label>
+            {t("scan_cleanup_threshold")}
+            <input
+              type="range"
+              min={0.05}
+              max={0.6}
+              step={0.01}
+              value={threshold}
+              disabled={auto}
+              onChange={(e) => setThreshold(Number(e.target.value))}
+            />
+          </label>
+          <label>
+            {t("scan_cleanup_ink")}
+            <select value={ink} onChange={(e) => setInk(e.target.value as ScanCleanupOptions["ink"])}>
+              <option value="original">{t("scan_cleanup_ink_original")}</option>
+              <option value="black">{t("scan_cleanup_ink_black")}</option>
+              <option value="blue">{t("scan_cleanup_ink_blue")}</option>
+            </select>
+          </label>
+        </div>
+
+        <div className="modal-actions">
+          <button className="btn" onClick={onClose}>
+            {t("signature_cancel")}
+          </button>
+          <div className="modal-actions-right">
+            <button className="btn" onClick={() => onUse(asset)}>
+              {t("scan_cleanup_keep")}
+            </button>
+            <button className="btn primary" disabled={!cleaned} onClick={() => cleaned && onUse(cleaned)}>
+              {t("scan_cleanup_use")}
+            </button>
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,72 @@
+import type { SignatureReport, VerificationReport } from "../types";
+import type { TranslationKey } from "../i18n/types";
+
+type Props = {
+  report: VerificationReport;
+  onClose: () => void;
+  t: (key: TranslationKey) => string;
+  locale: string;
+};
+
+/** One verdict per signature, worst first : a broken signature says
+ *  nothing about its signer, a modified document nothing about trust. */
+function statusKey(signature: SignatureReport): TranslationKey {
+  if (!signature.byteRangeValid || signature.integrity === "invalid") return "signature_status_invalid";
+  if (signature.integrity === "unsupported") return "signature_status_unsupported";
+  if (signature.modifications.length > 0) return "signature_status_modified";
+  if (!signature.trusted) return "signature_status_untrusted";
+  return "signature_status_valid";
+}
+
+/**
+ * The digital signatures found when the document was opened, as
+ * checked on the Rust side (`verify.rs`) : integrity, trust in the
+ * signer, and changes made after each signature.
+ */
+export function SignatureReportModal({ report, onClose, t, locale }: Props) {
+  const formatDate = (unixSeconds: number) => new Date(unixSeconds * 1000).toLocaleString(locale);
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal-card signature-report-card" onClick={(e) => e.stopPropagation()}>
+        <div className="modal-header">
+          <h3>{t("signature_report_title")}</h3>
+          <button className="btn icon-btn" onClick={onClose} aria-label={t("signature_cancel")}>
+            ×
+          </button>
+        </div>
+
+        <ul className="signature-report-list">
+          {report.signatures.map((signature, index) => {
+            const status = statusKey(signature);
+            return (
+              <li key={signature.fieldName ?? index} className={status === "signature_status_valid" ? "ok" : "warn"}>
+                <strong>{signature.signer?.subject ?? t("signature_report_signer_unknown")}</strong>
+                <span>{t(status)}</span>
+                {signature.timestamp !== null ? (
+                  <span className="hint">
+                    {t("signature_report_timestamped_at").replace("{date}", formatDate(signature.timestamp))}
+                  </span>
+                ) : signature.signingTime ? (
+                  <span className="hint">{t("signature_report_signed_at").replace("{date}", signature.signingTime)}</span>
+                ) : null}
+                {!signature.coversWholeDocument && <span className="hint">{t("signature_report_partial")}</span>}
+                {[...signature.modifications, ...signature.problems].map((line) => (
+                  <code key={line}>{line}</code>
+                ))}
+              </li>
+            );
+          })}
+        </ul>
+
+        {report.editingInvalidatesSignatures && <p className="hint">{t("signature_report_edit_warning")}</p>}
+
+        <div className="modal-actions">
+          <div className="modal-actions-right">
+            <button className="btn primary" onClick={onClose}>OK</button>
+          </div>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -29,6 +29,13 @@ export const SIGNATURE_DEFAULTS = {
   minHeightPx: 50
 } as const;
 
+// Pen of the "draw with the trackpad" pad, in CSS pixels. Also sent
+// with the recorded strokes so the vector export matches the canvas.
+export const PAD_PEN = {
+  width: 2,
+  color: "#111111"
+} as const;
+
 // Lower bound (in PDF units) when resizing an item via the handle.
 export const MIN_RESIZE_PDF = {
   width: 10,
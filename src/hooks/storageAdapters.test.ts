@@ -0,0 +1,62 @@
+import { beforeEach, describe, expect, it, vi } from "vitest";
+import { invoke } from "@tauri-apps/api/core";
+import { tauriRecordsAdapter } from "./storageAdapters";
+
+vi.mock("@tauri-apps/api/core", () => ({
+  isTauri: () => true,
+  invoke: vi.fn()
+}));
+
+type Record = { id: string; name: string };
+
+const invokeMock = vi.mocked(invoke);
+
+function makeAdapter() {
+  return tauriRecordsAdapter<Record>({
+    loadCommand: "load_signatures",
+    addCommand: "add_signature",
+    renameCommand: "rename_signature",
+    deleteCommand: "delete_signature",
+    recordArgName: "signature"
+  });
+}
+
+describe("tauriRecordsAdapter", () => {
+  beforeEach(() => {
+    invokeMock.mockReset();
+  });
+
+  it("sends only the records that changed since the load", async () => {
+    const adapter = makeAdapter();
+    invokeMock.mockResolvedValueOnce([{ id: "a", name: "A" }, { id: "b", name: "B" }]);
+    await adapter.load();
+    invokeMock.mockClear();
+    invokeMock.mockResolvedValue(undefined);
+
+    await adapter.save([{ id: "a", name: "A bis" }, { id: "c", name: "C" }]);
+
+    expect(invokeMock.mock.calls).toEqual([
+      ["delete_signature", { id: "b" }],
+      ["rename_signature", { id: "a", name: "A bis" }],
+      ["add_signature", { signature: { id: "c", name: "C" } }]
+    ]);
+
+    invokeMock.mockClear();
+    await adapter.save([{ id: "a", name: "A bis" }, { id: "c", name: "C" }]);
+    expect(invokeMock).not.toHaveBeenCalled();
+  });
+
+  it("retries a record whose command failed on the next save", async () => {
+    const adapter = makeAdapter();
+    invokeMock.mockResolvedValueOnce(null);
+    await adapter.load();
+    invokeMock.mockClear();
+
+    invokeMock.mockRejectedValueOnce(new Error("disk full"));
+    await expect(adapter.save([{ id: "a", name: "A" }])).rejects.toThrow("disk full");
+
+    invokeMock.mockResolvedValue(undefined);
+    await adapter.save([{ id: "a", name: "A" }]);
+    expect(invokeMock).toHaveBeenLastCalledWith("add_signature", { signature: { id: "a", name: "A" } });
+  });
+});
@@ -79,3 +79,68 @@ export function tauriOnlyAdapter<Stored>(opts: TauriAdapterOptions): StorageAdap
   }
   return tauriAdapter<Stored>(opts);
 }
+
+type RecordsAdapterOptions = {
+  loadCommand: string;
+  /** Commands acting on a single record : `add` takes the record under
+   *  `recordArgName`, `rename` an `id` and a `name`, `delete` an `id`. */
+  addCommand: string;
+  renameCommand: string;
+  deleteCommand: string;
+  recordArgName: string;
+};
+
+/**
+ * Adapter for a list of named records that the backend stores one by
+ * one. A save sends only what changed since the last one (records
+ * added, renamed or removed) instead of rewriting the whole list, so
+ * editing one entry cannot clobber the others. Saves run one after the
+ * other ; a record whose command failed is retried on the next save.
+ * No-ops outside Tauri, like `tauriOnlyAdapter`.
+ */
+export function tauriRecordsAdapter<Stored extends { id: string; name: string }>(
+  opts: RecordsAdapterOptions
+): StorageAdapter<Stored[]> {
+  if (!isTauri()) {
+    return {
+      async load() { return null; },
+      async save() { /* no-op */ }
+    };
+  }
+
+  /** What the backend holds, as far as this adapter knows. */
+  const persisted = new Map<string, Stored>();
+  let queue: Promise<void> = Promise.resolve();
+
+  async function sync(value: Stored[]) {
+    const next = new Map(value.map((record) => [record.id, record]));
+    for (const id of [...persisted.keys()]) {
+      if (next.has(id)) continue;
+      await invoke(opts.deleteCommand, { id });
+      persisted.delete(id);
+    }
+    for (const record of value) {
+      const previous = persisted.get(record.id);
+      if (!previous) {
+        await invoke(opts.addCommand, { [opts.recordArgName]: record });
+      } else if (previous.name !== record.name) {
+        await invoke(opts.renameCommand, { id: record.id, name: record.name });
+      }
+      persisted.set(record.id, record);
+    }
+  }
+
+  return {
+    async load() {
+      const value = await invoke<Stored[]>(opts.loadCommand);
+      persisted.clear();
+      for (const record of value ?? []) persisted.set(record.id, record);
+      return value ?? null;
+    },
+    save(value) {
+      const run = queue.then(() => sync(value));
+      queue = run.catch(() => undefined);
+      return run;
+    }
+  };
+}
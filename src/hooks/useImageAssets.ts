@@ -1,8 +1,9 @@
 import { useMemo } from "react";
-import type { SignatureAsset } from "../types";
+import { invoke, isTauri } from "@tauri-apps/api/core";
+import type { InkDrawing, SignatureAsset } from "../types";
 import { bytesToDataUrl } from "../utils/file";
 import { usePersistentState } from "./usePersistentState";
-import { tauriOnlyAdapter } from "./storageAdapters";
+import { tauriRecordsAdapter } from "./storageAdapters";
 
 /**
  * On-disk shape of an image asset (signatures, paraphs, …). Bytes are
@@ -16,58 +17,112 @@ export type StoredImageAsset = {
   bytes: number[];
   naturalW: number;
   naturalH: number;
+  ink?: InkDrawing;
 };
 
 type Options = {
-  /** Tauri command pair that loads / saves the asset list. */
+  /** Tauri command that loads the asset list. */
   loadCommand: string;
-  saveCommand: string;
-  /** Argument name expected by the Tauri save command. */
-  saveArgName: string;
+  /** Tauri commands that add, rename and delete a single asset. */
+  addCommand: string;
+  renameCommand: string;
+  deleteCommand: string;
+  /** Argument name expected by the Tauri add command. */
+  recordArgName: string;
   /** Debug label routed to the persistent-state hook. */
   label: string;
   /** Called once after first load completes, with the hydrated list. */
   onInitialLoad?: (assets: SignatureAsset[]) => void;
 };
 
+async function hydrateAsset(asset: StoredImageAsset): Promise<SignatureAsset> {
+  const bytes = new Uint8Array(asset.bytes);
+  const dataUrl = await bytesToDataUrl(bytes, asset.mime);
+  return {
+    id: asset.id,
+    name: asset.name,
+    mime: asset.mime,
+    bytes,
+    dataUrl,
+    naturalW: asset.naturalW,
+    naturalH: asset.naturalH,
+    ink: asset.ink
+  };
+}
+
+function dehydrateAsset(asset: SignatureAsset): StoredImageAsset {
+  return {
+    id: asset.id,
+    name: asset.name,
+    mime: asset.mime,
+    bytes: Array.from(asset.bytes),
+    naturalW: asset.naturalW,
+    naturalH: asset.naturalH,
+    ink: asset.ink
+  };
+}
+
+/**
+ * Run a freshly imported image through the Rust-side validation
+ * (decoded size, metadata stripped, optional downscale) so the preview
+ * matches what gets stored. Throws the command error when the image is
+ * refused ; outside Tauri the asset is returned unchanged.
+ */
+export async function normalizeImageAsset(asset: SignatureAsset): Promise<SignatureAsset> {
+  if (!isTauri()) return asset;
+  const stored = await invoke<StoredImageAsset>("normalize_image_asset", { asset: dehydrateAsset(asset) });
+  return hydrateAsset(stored);
+}
+
+/** Options of the scan cleanup, see `scan.rs`. */
+export type ScanCleanupOptions = {
+  /** Darkness (0–1) above which a pixel counts as ink ; null picks it
+   *  from the image. */
+  threshold: number | null;
+  ink: "original" | "black" | "blue";
+};
+
+/**
+ * Preview of a photographed or scanned signature with the paper made
+ * transparent, cropped to the ink. Nothing is stored ; throws the
+ * command error when no ink is found.
+ */
+export async function cleanSignatureScan(asset: SignatureAsset, options: ScanCleanupOptions): Promise<SignatureAsset> {
+  const stored = await invoke<StoredImageAsset>("clean_signature_scan", { asset: dehydrateAsset(asset), options });
+  return hydrateAsset(stored);
+}
+
 /**
  * Persisted list of image assets, available only inside the Tauri
  * desktop shell (the byte payloads are too large to keep in
  * localStorage). Hydration regenerates the data URL preview from the
  * stored bytes so callers can keep treating the records as immutable
- * `SignatureAsset` values.
+ * `SignatureAsset` values. Each change is saved record by record (see
+ * `tauriRecordsAdapter`) ; the bulk save commands are left to import
+ * and restore.
  */
-export function useImageAssets({ loadCommand, saveCommand, saveArgName, label, onInitialLoad }: Options) {
-  const adapter = useMemo(() => tauriOnlyAdapter<StoredImageAsset[]>({
+export function useImageAssets({
+  loadCommand,
+  addCommand,
+  renameCommand,
+  deleteCommand,
+  recordArgName,
+  label,
+  onInitialLoad
+}: Options) {
+  const adapter = useMemo(() => tauriRecordsAdapter<StoredImageAsset>({
     loadCommand,
-    saveCommand,
-    saveArgName
-  }), [loadCommand, saveCommand, saveArgName]);
+    addCommand,
+    renameCommand,
+    deleteCommand,
+    recordArgName
+  }), [loadCommand, addCommand, renameCommand, deleteCommand, recordArgName]);
 
   return usePersistentState<SignatureAsset[], StoredImageAsset[]>({
     adapter,
     defaultValue: [],
-    hydrate: async (stored) => Promise.all(stored.map(async (asset) => {
-      const bytes = new Uint8Array(asset.bytes);
-      const dataUrl = await bytesToDataUrl(bytes, asset.mime);
-      return {
-        id: asset.id,
-        name: asset.name,
-        mime: asset.mime,
-        bytes,
-        dataUrl,
-        naturalW: asset.naturalW,
-        naturalH: asset.naturalH
-      } satisfies SignatureAsset;
-    })),
-    dehydrate: (assets) => assets.map((asset) => ({
-      id: asset.id,
-      name: asset.name,
-      mime: asset.mime,
-      bytes: Array.from(asset.bytes),
-      naturalW: asset.naturalW,
-      naturalH: asset.naturalH
-    })),
+    hydrate: async (stored) => Promise.all(stored.map(hydrateAsset)),
+    dehydrate: (assets) => assets.map(dehydrateAsset),
     onLoaded: onInitialLoad,
     label
   });
@@ -13,8 +13,10 @@ type Options = {
 export function useParaphAssets({ onInitialLoad }: Options = {}) {
   return useImageAssets({
     loadCommand: "load_paraphs",
-    saveCommand: "save_paraphs",
-    saveArgName: "paraphs",
+    addCommand: "add_paraph",
+    renameCommand: "rename_paraph",
+    deleteCommand: "delete_paraph",
+    recordArgName: "paraph",
     label: "paraphs",
     onInitialLoad
   });
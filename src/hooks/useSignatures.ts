@@ -15,8 +15,10 @@ type Options = {
 export function useSignatures({ onInitialLoad }: Options = {}) {
   return useImageAssets({
     loadCommand: "load_signatures",
-    saveCommand: "save_signatures",
-    saveArgName: "signatures",
+    addCommand: "add_signature",
+    renameCommand: "rename_signature",
+    deleteCommand: "delete_signature",
+    recordArgName: "signature",
     label: "signatures",
     onInitialLoad
   });
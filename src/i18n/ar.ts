@@ -65,6 +65,13 @@ export const ar: Translations = {
   templates_apply: "تطبيق",
   templates_delete: "حذف القالب",
   templates_rename_hint: "انقر مرتين لإعادة التسمية",
+  templates_anchor_missing: "تعذر العثور على بعض عبارات الربط في هذا المستند. تبقى العناصر المرتبطة بها في موضعها من القالب:\n{phrases}",
+  anchor_title: "عبارة الربط",
+  anchor_hint: "اربط العنصر المحدد بعبارة من المستند: القوالب المحفوظة بعد ذلك تضعه بجوار تلك العبارة أينما كانت.",
+  anchor_placeholder: "عبارة، مثل توقيع العميل",
+  anchor_set: "ربط",
+  anchor_remove: "إزالة الربط",
+  anchor_not_on_page: "«{text}» غير موجودة في صفحة العنصر المحدد.",
   signatures: "التواقيع",
   remove_signature: "حذف التوقيع",
   signatures_hint: "استيراد صورة PNG (مفضل) أو JPG.",
@@ -85,7 +92,50 @@ export const ar: Translations = {
   export_success: "تم تصدير PDF إلى:\n{path}",
   export_failed: "فشل التصدير أثناء إنشاء PDF. راجع وحدة التحكم للتفاصيل.",
   export_tauri_failed: "فشل تصدير Tauri. سيتم محاولة تنزيل مباشر.",
+  export_protect: "حماية الملف المُصدَّر بكلمة مرور",
+  export_unprotect: "التصدير بدون كلمة مرور",
+  export_protect_prompt: "كلمة المرور اللازمة لفتح ملف PDF المُصدَّر:",
+  pdf_password_prompt: "«{name}» محمي بكلمة مرور. كلمة المرور:",
+  pdf_password_retry: "كلمة المرور غير صحيحة لـ «{name}». حاول مرة أخرى:",
+  signature_report_title: "التوقيعات الرقمية",
+  signature_report_view: "{count} توقيع رقمي — التفاصيل",
+  signature_report_signer_unknown: "موقّع غير معروف",
+  signature_status_valid: "صالح، والموقّع موثوق",
+  signature_status_untrusted: "سليم، لكن الموقّع ليس ضمن شهاداتك الموثوقة",
+  signature_status_modified: "سليم، لكن المستند عُدّل بعد التوقيع",
+  signature_status_invalid: "تالف: تم تغيير المحتوى الموقّع أو التوقيع معطوب",
+  signature_status_unsupported: "يتعذر التحقق: خوارزمية غير مدعومة",
+  signature_report_signed_at: "وُقّع في {date} (حسب الموقّع)",
+  signature_report_timestamped_at: "خُتم زمنيًا في {date}",
+  signature_report_partial: "يشمل مراجعة سابقة من المستند",
+  signature_report_edit_warning: "تعديل هذا المستند ثم تصديره سيُبطل هذه التوقيعات.",
+  signed_edit_confirm: "هذا المستند موقّع رقميًا. تصديره بأي تعديل سيُبطل توقيعاته. هل تريد تعديله رغم ذلك؟",
   no_pdf: "لا يوجد PDF محمّل",
   file_label: "الملف: {name}",
-  export_note: "التصدير = PDF مسطح (نص + صورة مدمجة)"
+  export_note: "التصدير = PDF مسطح (نص + صورة مدمجة)",
+  scan_cleanup_title: "تنظيف المسح الضوئي",
+  scan_cleanup_original: "الأصل",
+  scan_cleanup_cleaned: "بعد التنظيف",
+  scan_cleanup_auto: "عتبة تلقائية",
+  scan_cleanup_threshold: "العتبة",
+  scan_cleanup_ink: "الحبر",
+  scan_cleanup_ink_original: "اللون الأصلي",
+  scan_cleanup_ink_black: "أسود",
+  scan_cleanup_ink_blue: "أزرق",
+  scan_cleanup_use: "استخدام الصورة المنظفة",
+  scan_cleanup_keep: "الاحتفاظ بالأصل",
+  error_not_found: "تعذّر العثور على الملف.",
+  error_permission_denied: "تم رفض الوصول إلى الملف.",
+  error_io: "فشلت عملية على الملف.",
+  error_corrupt_data: "البيانات المحفوظة تالفة ولا يمكن قراءتها.",
+  error_invalid_input: "هذه القيمة غير مقبولة.",
+  error_out_of_scope: "لم يُفتح هذا الملف عبر Cerfini. افتحه من قائمة ملف أو وافق على مجلده أولاً.",
+  error_invalid_pdf: "هذا الملف ليس ملف PDF يمكن لـ Cerfini معالجته.",
+  error_certificate: "تعذّر استخدام الشهادة.",
+  error_authentication_failed: "عبارة المرور أو كلمة المرور غير صحيحة.",
+  error_vault_locked: "افتح قفل توقيعاتك أولاً.",
+  error_network: "تعذّر الوصول إلى خادم الطابع الزمني.",
+  error_unavailable: "هذه الميزة غير متاحة على هذا النظام.",
+  error_newer_version: "حُفظت هذه البيانات بإصدار أحدث من Cerfini. حدّث التطبيق لاستخدامها.",
+  error_unknown: "حدث خطأ غير متوقع."
 };
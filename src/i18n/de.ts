@@ -65,6 +65,13 @@ export const de: Translations = {
   templates_apply: "Anwenden",
   templates_delete: "Vorlage löschen",
   templates_rename_hint: "Doppelklick zum Umbenennen",
+  templates_anchor_missing: "Einige Ankertexte wurden in diesem Dokument nicht gefunden. Die zugehörigen Elemente bleiben an der Position aus der Vorlage:\n{phrases}",
+  anchor_title: "Ankertext",
+  anchor_hint: "Verknüpfen Sie das ausgewählte Element mit einem Satz des Dokuments: Danach gespeicherte Vorlagen platzieren es neben diesem Satz, wo auch immer er steht.",
+  anchor_placeholder: "Satz, z. B. Unterschrift des Kunden",
+  anchor_set: "Verankern",
+  anchor_remove: "Anker entfernen",
+  anchor_not_on_page: "„{text}“ kommt auf der Seite des ausgewählten Elements nicht vor.",
   signatures: "Signaturen",
   remove_signature: "Signatur entfernen",
   signatures_hint: "PNG (ideal) oder JPG importieren.",
@@ -85,7 +92,50 @@ export const de: Translations = {
   export_success: "PDF exportiert nach:\n{path}",
   export_failed: "Export fehlgeschlagen beim Erstellen des PDFs. Siehe Konsole für Details.",
   export_tauri_failed: "Tauri-Export fehlgeschlagen. Direkter Download wird versucht.",
+  export_protect: "Export mit einem Passwort schützen",
+  export_unprotect: "Ohne Passwort exportieren",
+  export_protect_prompt: "Passwort zum Öffnen der exportierten PDF:",
+  pdf_password_prompt: "„{name}“ ist passwortgeschützt. Passwort:",
+  pdf_password_retry: "Falsches Passwort für „{name}“. Erneut versuchen:",
+  signature_report_title: "Digitale Signaturen",
+  signature_report_view: "{count} digitale Signatur(en) — Details",
+  signature_report_signer_unknown: "Unbekannter Unterzeichner",
+  signature_status_valid: "Gültig, Unterzeichner vertrauenswürdig",
+  signature_status_untrusted: "Unversehrt, aber der Unterzeichner gehört nicht zu Ihren vertrauenswürdigen Zertifikaten",
+  signature_status_modified: "Unversehrt, aber das Dokument wurde nach der Signatur geändert",
+  signature_status_invalid: "Ungültig: Der signierte Inhalt wurde verändert oder die Signatur ist beschädigt",
+  signature_status_unsupported: "Prüfung nicht möglich: Algorithmus nicht unterstützt",
+  signature_report_signed_at: "Signiert am {date} (laut Unterzeichner)",
+  signature_report_timestamped_at: "Zeitgestempelt am {date}",
+  signature_report_partial: "Betrifft eine frühere Revision des Dokuments",
+  signature_report_edit_warning: "Wenn Sie dieses Dokument bearbeiten und exportieren, werden diese Signaturen ungültig.",
+  signed_edit_confirm: "Dieses Dokument ist digital signiert. Ein Export mit jeder Änderung macht seine Signaturen ungültig. Trotzdem bearbeiten?",
   no_pdf: "Kein PDF geladen",
   file_label: "Datei: {name}",
-  export_note: "Export = abgeflachtes PDF (Text + Bild eingebettet)"
+  export_note: "Export = abgeflachtes PDF (Text + Bild eingebettet)",
+  scan_cleanup_title: "Scan bereinigen",
+  scan_cleanup_original: "Original",
+  scan_cleanup_cleaned: "Bereinigt",
+  scan_cleanup_auto: "Automatischer Schwellenwert",
+  scan_cleanup_threshold: "Schwellenwert",
+  scan_cleanup_ink: "Tinte",
+  scan_cleanup_ink_original: "Original",
+  scan_cleanup_ink_black: "Schwarz",
+  scan_cleanup_ink_blue: "Blau",
+  scan_cleanup_use: "Bereinigtes Bild verwenden",
+  scan_cleanup_keep: "Original behalten",
+  error_not_found: "Die Datei wurde nicht gefunden.",
+  error_permission_denied: "Der Zugriff auf die Datei wurde verweigert.",
+  error_io: "Ein Dateivorgang ist fehlgeschlagen.",
+  error_corrupt_data: "Die gespeicherten Daten sind beschädigt und unlesbar.",
+  error_invalid_input: "Dieser Wert wird nicht akzeptiert.",
+  error_out_of_scope: "Diese Datei wurde nicht über Cerfini geöffnet. Öffnen Sie sie über das Menü Datei oder geben Sie zuerst ihren Ordner frei.",
+  error_invalid_pdf: "Diese Datei ist kein PDF, das Cerfini verarbeiten kann.",
+  error_certificate: "Das Zertifikat kann nicht verwendet werden.",
+  error_authentication_failed: "Passphrase oder Passwort ist falsch.",
+  error_vault_locked: "Entsperren Sie zuerst Ihre Unterschriften.",
+  error_network: "Der Zeitstempel-Server ist nicht erreichbar.",
+  error_unavailable: "Diese Funktion ist auf diesem System nicht verfügbar.",
+  error_newer_version: "Diese Daten wurden von einer neueren Cerfini-Version gespeichert. Aktualisieren Sie die App, um sie zu verwenden.",
+  error_unknown: "Ein unerwarteter Fehler ist aufgetreten."
 };
@@ -68,6 +68,13 @@ export const en = {
   templates_apply: "Apply",
   templates_delete: "Delete template",
   templates_rename_hint: "Double-click to rename",
+  templates_anchor_missing: "Some anchor phrases were not found in this document. The items tied to them stay where the template placed them:\n{phrases}",
+  anchor_title: "Anchor phrase",
+  anchor_hint: "Tie the selected item to a phrase of the document: templates saved afterwards place it next to that phrase, wherever it moves.",
+  anchor_placeholder: "Phrase, e.g. Signature of the client",
+  anchor_set: "Anchor",
+  anchor_remove: "Remove the anchor",
+  anchor_not_on_page: "\"{text}\" does not appear on the page of the selected item.",
   signatures: "Signatures",
   remove_signature: "Remove signature",
   signatures_hint: "Import a PNG (ideal) or JPG image.",
@@ -88,7 +95,50 @@ export const en = {
   export_success: "PDF exported to:\n{path}",
   export_failed: "Export failed while generating the PDF. See console for details.",
   export_tauri_failed: "Tauri export failed. A direct download will be attempted.",
+  export_protect: "Protect the export with a password",
+  export_unprotect: "Export without a password",
+  export_protect_prompt: "Password needed to open the exported PDF:",
+  pdf_password_prompt: "\"{name}\" is password-protected. Password:",
+  pdf_password_retry: "Incorrect password for \"{name}\". Try again:",
+  signature_report_title: "Digital signatures",
+  signature_report_view: "{count} digital signature(s) — details",
+  signature_report_signer_unknown: "Unknown signer",
+  signature_status_valid: "Valid, and the signer is trusted",
+  signature_status_untrusted: "Intact, but the signer is not among your trusted certificates",
+  signature_status_modified: "Intact, but the document was changed after signing",
+  signature_status_invalid: "Broken: the signed content was altered or the signature is damaged",
+  signature_status_unsupported: "Cannot be checked: unsupported algorithm",
+  signature_report_signed_at: "Signed on {date} (as claimed by the signer)",
+  signature_report_timestamped_at: "Timestamped on {date}",
+  signature_report_partial: "Covers an earlier revision of the document",
+  signature_report_edit_warning: "Editing and exporting this document will invalidate these signatures.",
+  signed_edit_confirm: "This document is digitally signed. Exporting it with any change will invalidate its signatures. Edit it anyway?",
   no_pdf: "No PDF loaded",
   file_label: "File: {name}",
-  export_note: "Export = flattened PDF (text + image embedded)"
+  export_note: "Export = flattened PDF (text + image embedded)",
+  scan_cleanup_title: "Clean up the scan",
+  scan_cleanup_original: "Original",
+  scan_cleanup_cleaned: "Cleaned",
+  scan_cleanup_auto: "Automatic threshold",
+  scan_cleanup_threshold: "Threshold",
+  scan_cleanup_ink: "Ink",
+  scan_cleanup_ink_original: "Original",
+  scan_cleanup_ink_black: "Black",
+  scan_cleanup_ink_blue: "Blue",
+  scan_cleanup_use: "Use cleaned image",
+  scan_cleanup_keep: "Keep original",
+  error_not_found: "The file could not be found.",
+  error_permission_denied: "Access to the file was denied.",
+  error_io: "A file operation failed.",
+  error_corrupt_data: "The saved data is damaged and could not be read.",
+  error_invalid_input: "This value is not accepted.",
+  error_out_of_scope: "This file was not opened through Cerfini. Open it from the File menu or approve its folder first.",
+  error_invalid_pdf: "This file is not a PDF Cerfini can process.",
+  error_certificate: "The certificate could not be used.",
+  error_authentication_failed: "The passphrase or password is incorrect.",
+  error_vault_locked: "Unlock your signatures first.",
+  error_network: "The timestamp server could not be reached.",
+  error_unavailable: "This feature is not available on this system.",
+  error_newer_version: "This data was saved by a newer version of Cerfini. Update the app to use it.",
+  error_unknown: "An unexpected error occurred."
 } as const;
@@ -65,6 +65,13 @@ export const es: Translations = {
   templates_apply: "Aplicar",
   templates_delete: "Eliminar plantilla",
   templates_rename_hint: "Doble clic para renombrar",
+  templates_anchor_missing: "Algunas frases de anclaje no se encuentran en este documento. Los elementos vinculados quedan donde los colocó la plantilla:\n{phrases}",
+  anchor_title: "Frase de anclaje",
+  anchor_hint: "Vincule el elemento seleccionado a una frase del documento: las plantillas guardadas después lo colocarán junto a esa frase, esté donde esté.",
+  anchor_placeholder: "Frase, p. ej. Firma del cliente",
+  anchor_set: "Anclar",
+  anchor_remove: "Quitar el anclaje",
+  anchor_not_on_page: "«{text}» no aparece en la página del elemento seleccionado.",
   signatures: "Firmas",
   remove_signature: "Eliminar firma",
   signatures_hint: "Importa una imagen PNG (ideal) o JPG.",
@@ -85,7 +92,50 @@ export const es: Translations = {
   export_success: "PDF exportado a:\n{path}",
   export_failed: "La exportación falló al generar el PDF. Ver consola para detalles.",
   export_tauri_failed: "Falló la exportación con Tauri. Se intentará descarga directa.",
+  export_protect: "Proteger la exportación con una contraseña",
+  export_unprotect: "Exportar sin contraseña",
+  export_protect_prompt: "Contraseña para abrir el PDF exportado:",
+  pdf_password_prompt: "«{name}» está protegido con contraseña. Contraseña:",
+  pdf_password_retry: "Contraseña incorrecta para «{name}». Inténtelo de nuevo:",
+  signature_report_title: "Firmas digitales",
+  signature_report_view: "{count} firma(s) digital(es) — detalles",
+  signature_report_signer_unknown: "Firmante desconocido",
+  signature_status_valid: "Válida, firmante de confianza",
+  signature_status_untrusted: "Intacta, pero el firmante no está entre sus certificados de confianza",
+  signature_status_modified: "Intacta, pero el documento se modificó después de la firma",
+  signature_status_invalid: "Rota: el contenido firmado fue alterado o la firma está dañada",
+  signature_status_unsupported: "No se puede verificar: algoritmo no compatible",
+  signature_report_signed_at: "Firmado el {date} (según el firmante)",
+  signature_report_timestamped_at: "Sellado de tiempo el {date}",
+  signature_report_partial: "Abarca una revisión anterior del documento",
+  signature_report_edit_warning: "Editar y exportar este documento invalidará estas firmas.",
+  signed_edit_confirm: "Este documento está firmado digitalmente. Exportarlo con cualquier cambio invalidará sus firmas. ¿Editarlo de todos modos?",
   no_pdf: "Ningún PDF cargado",
   file_label: "Archivo: {name}",
-  export_note: "Exportar = PDF aplanado (texto + imagen incrustados)"
+  export_note: "Exportar = PDF aplanado (texto + imagen incrustados)",
+  scan_cleanup_title: "Limpiar el escaneo",
+  scan_cleanup_original: "Original",
+  scan_cleanup_cleaned: "Limpia",
+  scan_cleanup_auto: "Umbral automático",
+  scan_cleanup_threshold: "Umbral",
+  scan_cleanup_ink: "Tinta",
+  scan_cleanup_ink_original: "Original",
+  scan_cleanup_ink_black: "Negra",
+  scan_cleanup_ink_blue: "Azul",
+  scan_cleanup_use: "Usar la imagen limpia",
+  scan_cleanup_keep: "Conservar el original",
+  error_not_found: "No se encontró el archivo.",
+  error_permission_denied: "Se denegó el acceso al archivo.",
+  error_io: "Falló una operación con el archivo.",
+  error_corrupt_data: "Los datos guardados están dañados y no se pueden leer.",
+  error_invalid_input: "Este valor no es válido.",
+  error_out_of_scope: "Este archivo no se abrió con Cerfini. Ábrelo desde el menú Archivo o aprueba primero su carpeta.",
+  error_invalid_pdf: "Este archivo no es un PDF que Cerfini pueda procesar.",
+  error_certificate: "No se puede usar el certificado.",
+  error_authentication_failed: "La frase de contraseña o la contraseña es incorrecta.",
+  error_vault_locked: "Desbloquea primero tus firmas.",
+  error_network: "No se pudo contactar con el servidor de sellado de tiempo.",
+  error_unavailable: "Esta función no está disponible en este sistema.",
+  error_newer_version: "Estos datos se guardaron con una versión más reciente de Cerfini. Actualiza la aplicación para usarlos.",
+  error_unknown: "Se produjo un error inesperado."
 };
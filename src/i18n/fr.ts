@@ -65,6 +65,13 @@ export const fr: Translations = {
   templates_apply: "Appliquer",
   templates_delete: "Supprimer le template",
   templates_rename_hint: "Double-clic pour renommer",
+  templates_anchor_missing: "Certaines phrases d'ancrage sont introuvables dans ce document. Les éléments qui leur sont liés restent à la place prévue par le template:\n{phrases}",
+  anchor_title: "Phrase d'ancrage",
+  anchor_hint: "Lie l'élément sélectionné à une phrase du document : les templates enregistrés ensuite le placeront à côté de cette phrase, où qu'elle se trouve.",
+  anchor_placeholder: "Phrase, p. ex. Signature du client",
+  anchor_set: "Ancrer",
+  anchor_remove: "Retirer l'ancrage",
+  anchor_not_on_page: "« {text} » n'apparaît pas sur la page de l'élément sélectionné.",
   signatures: "Signatures",
   remove_signature: "Supprimer la signature",
   signatures_hint: "Importer une image PNG (idéal) ou JPG.",
@@ -85,7 +92,50 @@ export const fr: Translations = {
   export_success: "PDF exporté vers:\n{path}",
   export_failed: "L'export a échoué pendant la génération du PDF. Consulte la console pour le détail.",
   export_tauri_failed: "L'export Tauri a échoué. Un téléchargement direct va être tenté.",
+  export_protect: "Protéger l'export par un mot de passe",
+  export_unprotect: "Exporter sans mot de passe",
+  export_protect_prompt: "Mot de passe pour ouvrir le PDF exporté :",
+  pdf_password_prompt: "« {name} » est protégé par un mot de passe. Mot de passe :",
+  pdf_password_retry: "Mot de passe incorrect pour « {name} ». Réessayez :",
+  signature_report_title: "Signatures numériques",
+  signature_report_view: "{count} signature(s) numérique(s) — détails",
+  signature_report_signer_unknown: "Signataire inconnu",
+  signature_status_valid: "Valide, signataire de confiance",
+  signature_status_untrusted: "Intacte, mais le signataire ne fait pas partie de vos certificats de confiance",
+  signature_status_modified: "Intacte, mais le document a été modifié après la signature",
+  signature_status_invalid: "Rompue : le contenu signé a été altéré ou la signature est endommagée",
+  signature_status_unsupported: "Vérification impossible : algorithme non pris en charge",
+  signature_report_signed_at: "Signé le {date} (selon le signataire)",
+  signature_report_timestamped_at: "Horodaté le {date}",
+  signature_report_partial: "Porte sur une révision antérieure du document",
+  signature_report_edit_warning: "Modifier puis exporter ce document invalidera ces signatures.",
+  signed_edit_confirm: "Ce document est signé numériquement. L'exporter avec la moindre modification invalidera ses signatures. Le modifier quand même ?",
   no_pdf: "Aucun PDF chargé",
   file_label: "Fichier: {name}",
-  export_note: "Export = PDF aplati (texte + image intégrés)"
+  export_note: "Export = PDF aplati (texte + image intégrés)",
+  scan_cleanup_title: "Nettoyer le scan",
+  scan_cleanup_original: "Original",
+  scan_cleanup_cleaned: "Nettoyé",
+  scan_cleanup_auto: "Seuil automatique",
+  scan_cleanup_threshold: "Seuil",
+  scan_cleanup_ink: "Encre",
+  scan_cleanup_ink_original: "D'origine",
+  scan_cleanup_ink_black: "Noire",
+  scan_cleanup_ink_blue: "Bleue",
+  scan_cleanup_use: "Utiliser l'image nettoyée",
+  scan_cleanup_keep: "Garder l'original",
+  error_not_found: "Fichier introuvable.",
+  error_permission_denied: "Accès au fichier refusé.",
+  error_io: "Une opération sur un fichier a échoué.",
+  error_corrupt_data: "Les données enregistrées sont endommagées et illisibles.",
+  error_invalid_input: "Cette valeur n'est pas acceptée.",
+  error_out_of_scope: "Ce fichier n'a pas été ouvert via Cerfini. Ouvrez-le depuis le menu Fichier ou approuvez d'abord son dossier.",
+  error_invalid_pdf: "Ce fichier n'est pas un PDF que Cerfini peut traiter.",
+  error_certificate: "Le certificat est inutilisable.",
+  error_authentication_failed: "Phrase secrète ou mot de passe incorrect.",
+  error_vault_locked: "Déverrouillez d'abord vos signatures.",
+  error_network: "Le serveur d'horodatage est injoignable.",
+  error_unavailable: "Cette fonction n'est pas disponible sur ce système.",
+  error_newer_version: "Ces données ont été enregistrées par une version plus récente de Cerfini. Mettez l'application à jour pour les utiliser.",
+  error_unknown: "Une erreur inattendue s'est produite."
 };
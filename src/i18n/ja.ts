@@ -65,6 +65,13 @@ export const ja: Translations = {
   templates_apply: "適用",
   templates_delete: "テンプレートを削除",
   templates_rename_hint: "ダブルクリックで名前変更",
+  templates_anchor_missing: "この文書に一部のアンカー語句が見つかりません。関連する項目はテンプレートの位置のままです：\n{phrases}",
+  anchor_title: "アンカーの語句",
+  anchor_hint: "選択した項目を文書内の語句に関連付けます。以後に保存したテンプレートは、その語句がどこにあってもその横に配置します。",
+  anchor_placeholder: "語句（例：お客様の署名）",
+  anchor_set: "アンカー",
+  anchor_remove: "アンカーを解除",
+  anchor_not_on_page: "「{text}」は選択した項目のページにありません。",
   signatures: "署名",
   remove_signature: "署名を削除",
   signatures_hint: "PNG（推奨）または JPG 画像を読み込んでください。",
@@ -85,7 +92,50 @@ export const ja: Translations = {
   export_success: "PDF を書き出しました：\n{path}",
   export_failed: "PDF の生成中に書き出しに失敗しました。コンソールを確認してください。",
   export_tauri_failed: "Tauri の書き出しに失敗しました。直接ダウンロードを試みます。",
+  export_protect: "書き出しをパスワードで保護",
+  export_unprotect: "パスワードなしで書き出す",
+  export_protect_prompt: "書き出した PDF を開くためのパスワード：",
+  pdf_password_prompt: "「{name}」はパスワードで保護されています。パスワード：",
+  pdf_password_retry: "「{name}」のパスワードが違います。もう一度入力してください：",
+  signature_report_title: "デジタル署名",
+  signature_report_view: "デジタル署名 {count} 件 — 詳細",
+  signature_report_signer_unknown: "不明な署名者",
+  signature_status_valid: "有効（信頼できる署名者）",
+  signature_status_untrusted: "改ざんはありませんが、署名者は信頼済み証明書に含まれていません",
+  signature_status_modified: "改ざんはありませんが、署名後に文書が変更されています",
+  signature_status_invalid: "無効：署名された内容が改ざんされたか、署名が破損しています",
+  signature_status_unsupported: "検証できません：未対応のアルゴリズム",
+  signature_report_signed_at: "{date} に署名（署名者の申告）",
+  signature_report_timestamped_at: "{date} にタイムスタンプ",
+  signature_report_partial: "文書の以前のリビジョンが対象です",
+  signature_report_edit_warning: "この文書を編集して書き出すと、これらの署名は無効になります。",
+  signed_edit_confirm: "この文書にはデジタル署名があります。変更して書き出すと署名は無効になります。それでも編集しますか？",
   no_pdf: "PDF 未読み込み",
   file_label: "ファイル：{name}",
-  export_note: "書き出し = フラット化PDF（テキスト + 画像埋め込み）"
+  export_note: "書き出し = フラット化PDF（テキスト + 画像埋め込み）",
+  scan_cleanup_title: "スキャンをクリーンアップ",
+  scan_cleanup_original: "元の画像",
+  scan_cleanup_cleaned: "クリーンアップ後",
+  scan_cleanup_auto: "自動しきい値",
+  scan_cleanup_threshold: "しきい値",
+  scan_cleanup_ink: "インク",
+  scan_cleanup_ink_original: "元の色",
+  scan_cleanup_ink_black: "黒",
+  scan_cleanup_ink_blue: "青",
+  scan_cleanup_use: "クリーンアップ後の画像を使用",
+  scan_cleanup_keep: "元の画像を保持",
+  error_not_found: "ファイルが見つかりません。",
+  error_permission_denied: "ファイルへのアクセスが拒否されました。",
+  error_io: "ファイル操作に失敗しました。",
+  error_corrupt_data: "保存されたデータが破損しているため読み込めません。",
+  error_invalid_input: "この値は受け付けられません。",
+  error_out_of_scope: "このファイルは Cerfini から開かれていません。「ファイル」メニューから開くか、先にフォルダーを許可してください。",
+  error_invalid_pdf: "このファイルは Cerfini で処理できる PDF ではありません。",
+  error_certificate: "証明書を使用できません。",
+  error_authentication_failed: "パスフレーズまたはパスワードが正しくありません。",
+  error_vault_locked: "先に署名のロックを解除してください。",
+  error_network: "タイムスタンプサーバーに接続できません。",
+  error_unavailable: "この機能はこのシステムでは利用できません。",
+  error_newer_version: "このデータは新しいバージョンの Cerfini で保存されています。使用するにはアプリを更新してください。",
+  error_unknown: "予期しないエラーが発生しました。"
 };
@@ -65,6 +65,13 @@ export const uk: Translations = {
   templates_apply: "Застосувати",
   templates_delete: "Видалити шаблон",
   templates_rename_hint: "Подвійний клік для перейменування",
+  templates_anchor_missing: "Деякі фрази прив'язки не знайдено в цьому документі. Пов'язані елементи залишаються там, де їх розмістив шаблон:\n{phrases}",
+  anchor_title: "Фраза прив'язки",
+  anchor_hint: "Прив'яжіть вибраний елемент до фрази документа: шаблони, збережені після цього, розміщуватимуть його поруч із цією фразою, де б вона не була.",
+  anchor_placeholder: "Фраза, напр. Підпис клієнта",
+  anchor_set: "Прив'язати",
+  anchor_remove: "Зняти прив'язку",
+  anchor_not_on_page: "«{text}» немає на сторінці вибраного елемента.",
   signatures: "Підписи",
   remove_signature: "Видалити підпис",
   signatures_hint: "Імпортуйте PNG (краще) або JPG.",
@@ -85,7 +92,50 @@ export const uk: Translations = {
   export_success: "PDF експортовано до:\n{path}",
   export_failed: "Експорт не вдався під час створення PDF. Дивіться консоль.",
   export_tauri_failed: "Експорт Tauri не вдався. Буде спроба прямого завантаження.",
+  export_protect: "Захистити експорт паролем",
+  export_unprotect: "Експортувати без пароля",
+  export_protect_prompt: "Пароль для відкриття експортованого PDF:",
+  pdf_password_prompt: "«{name}» захищено паролем. Пароль:",
+  pdf_password_retry: "Неправильний пароль для «{name}». Спробуйте ще раз:",
+  signature_report_title: "Цифрові підписи",
+  signature_report_view: "Цифрових підписів: {count} — докладно",
+  signature_report_signer_unknown: "Невідомий підписант",
+  signature_status_valid: "Дійсний, підписант довірений",
+  signature_status_untrusted: "Цілий, але підписанта немає серед ваших довірених сертифікатів",
+  signature_status_modified: "Цілий, але документ змінено після підписання",
+  signature_status_invalid: "Пошкоджений: підписаний вміст змінено або підпис пошкоджено",
+  signature_status_unsupported: "Неможливо перевірити: алгоритм не підтримується",
+  signature_report_signed_at: "Підписано {date} (за словами підписанта)",
+  signature_report_timestamped_at: "Позначка часу: {date}",
+  signature_report_partial: "Стосується попередньої редакції документа",
+  signature_report_edit_warning: "Редагування й експорт цього документа зроблять ці підписи недійсними.",
+  signed_edit_confirm: "Цей документ має цифрові підписи. Експорт із будь-якою зміною зробить їх недійсними. Усе одно редагувати?",
   no_pdf: "PDF не завантажено",
   file_label: "Файл: {name}",
-  export_note: "Експорт = плаский PDF (текст + зображення вбудовано)"
+  export_note: "Експорт = плаский PDF (текст + зображення вбудовано)",
+  scan_cleanup_title: "Очистити скан",
+  scan_cleanup_original: "Оригінал",
+  scan_cleanup_cleaned: "Очищене",
+  scan_cleanup_auto: "Автоматичний поріг",
+  scan_cleanup_threshold: "Поріг",
+  scan_cleanup_ink: "Чорнило",
+  scan_cleanup_ink_original: "Оригінальне",
+  scan_cleanup_ink_black: "Чорне",
+  scan_cleanup_ink_blue: "Синє",
+  scan_cleanup_use: "Використати очищене зображення",
+  scan_cleanup_keep: "Залишити оригінал",
+  error_not_found: "Файл не знайдено.",
+  error_permission_denied: "Доступ до файлу заборонено.",
+  error_io: "Не вдалося виконати операцію з файлом.",
+  error_corrupt_data: "Збережені дані пошкоджені й не можуть бути прочитані.",
+  error_invalid_input: "Це значення неприпустиме.",
+  error_out_of_scope: "Цей файл не було відкрито через Cerfini. Відкрийте його з меню «Файл» або спершу схваліть його теку.",
+  error_invalid_pdf: "Цей файл не є PDF, який Cerfini може обробити.",
+  error_certificate: "Сертифікат не можна використати.",
+  error_authentication_failed: "Неправильна парольна фраза або пароль.",
+  error_vault_locked: "Спершу розблокуйте свої підписи.",
+  error_network: "Не вдалося зв'язатися із сервером позначок часу.",
+  error_unavailable: "Ця функція недоступна в цій системі.",
+  error_newer_version: "Ці дані збережено новішою версією Cerfini. Оновіть застосунок, щоб їх використати.",
+  error_unknown: "Сталася неочікувана помилка."
 };
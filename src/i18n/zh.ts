@@ -65,6 +65,13 @@ export const zh: Translations = {
   templates_apply: "应用",
   templates_delete: "删除模板",
   templates_rename_hint: "双击重命名",
+  templates_anchor_missing: "本文档中找不到部分锚点短语，相关元素保留在模板中的位置：\n{phrases}",
+  anchor_title: "锚定短语",
+  anchor_hint: "将所选项目与文档中的短语关联：之后保存的模板会将其放在该短语旁边，无论短语移到哪里。",
+  anchor_placeholder: "短语，例如 客户签名",
+  anchor_set: "锚定",
+  anchor_remove: "移除锚定",
+  anchor_not_on_page: "“{text}”未出现在所选项目所在的页面上。",
   signatures: "签名",
   remove_signature: "删除签名",
   signatures_hint: "导入 PNG（推荐）或 JPG 图片。",
@@ -85,7 +92,50 @@ export const zh: Translations = {
   export_success: "PDF 已导出到：\n{path}",
   export_failed: "生成 PDF 时导出失败。详见控制台。",
   export_tauri_failed: "Tauri 导出失败，将尝试直接下载。",
+  export_protect: "使用密码保护导出文件",
+  export_unprotect: "不使用密码导出",
+  export_protect_prompt: "打开导出 PDF 所需的密码：",
+  pdf_password_prompt: "“{name}” 受密码保护。密码：",
+  pdf_password_retry: "“{name}” 的密码不正确。请重试：",
+  signature_report_title: "数字签名",
+  signature_report_view: "{count} 个数字签名 — 详情",
+  signature_report_signer_unknown: "未知签名者",
+  signature_status_valid: "有效，签名者受信任",
+  signature_status_untrusted: "完好，但签名者不在您的受信任证书中",
+  signature_status_modified: "完好，但文档在签名后被修改",
+  signature_status_invalid: "已损坏：签名内容被篡改或签名受损",
+  signature_status_unsupported: "无法验证：不支持的算法",
+  signature_report_signed_at: "签名于 {date}（签名者声明）",
+  signature_report_timestamped_at: "时间戳：{date}",
+  signature_report_partial: "仅涵盖文档的较早版本",
+  signature_report_edit_warning: "编辑并导出此文档将使这些签名失效。",
+  signed_edit_confirm: "此文档带有数字签名。导出任何修改都会使签名失效。仍要编辑吗？",
   no_pdf: "未加载 PDF",
   file_label: "文件：{name}",
-  export_note: "导出 = 扁平化 PDF（文本 + 图片嵌入）"
+  export_note: "导出 = 扁平化 PDF（文本 + 图片嵌入）",
+  scan_cleanup_title: "清理扫描件",
+  scan_cleanup_original: "原图",
+  scan_cleanup_cleaned: "清理后",
+  scan_cleanup_auto: "自动阈值",
+  scan_cleanup_threshold: "阈值",
+  scan_cleanup_ink: "墨水",
+  scan_cleanup_ink_original: "原色",
+  scan_cleanup_ink_black: "黑色",
+  scan_cleanup_ink_blue: "蓝色",
+  scan_cleanup_use: "使用清理后的图像",
+  scan_cleanup_keep: "保留原图",
+  error_not_found: "找不到该文件。",
+  error_permission_denied: "访问文件被拒绝。",
+  error_io: "文件操作失败。",
+  error_corrupt_data: "已保存的数据已损坏，无法读取。",
+  error_invalid_input: "不接受该值。",
+  error_out_of_scope: "该文件不是通过 Cerfini 打开的。请从“文件”菜单打开，或先批准其所在文件夹。",
+  error_invalid_pdf: "该文件不是 Cerfini 可以处理的 PDF。",
+  error_certificate: "无法使用该证书。",
+  error_authentication_failed: "口令或密码不正确。",
+  error_vault_locked: "请先解锁您的签名。",
+  error_network: "无法连接时间戳服务器。",
+  error_unavailable: "此系统不支持该功能。",
+  error_newer_version: "这些数据由较新版本的 Cerfini 保存。请更新应用后再使用。",
+  error_unknown: "发生意外错误。"
 };
@@ -0,0 +1,48 @@
+import { describe, expect, it } from "vitest";
+import { pickAnchor, withAnchors, type SearchHit } from "./anchors";
+import type { SignatureItem } from "../types";
+
+function sig(overrides: Partial<SignatureItem> = {}): SignatureItem {
+  return {
+    id: "sig-item",
+    type: "signature",
+    page: 2,
+    rect: { x: 72, y: 240, w: 150, h: 50 },
+    signatureId: "sig-1",
+    ...overrides
+  };
+}
+
+function hit(page: number, x: number, y: number): SearchHit {
+  return { page, rects: [{ x, y, w: 80, h: 12 }], context: "Lu et approuvé" };
+}
+
+describe("pickAnchor", () => {
+  it("picks the closest match on the item's page and counts it across the document", () => {
+    const hits = [hit(1, 72, 300), hit(2, 300, 300), hit(2, 72, 300)];
+    expect(pickAnchor("lu et approuve", hits, sig())).toEqual({
+      text: "lu et approuve",
+      occurrence: 3,
+      page: 2,
+      origin: { x: 72, y: 300 }
+    });
+  });
+
+  it("returns null when the phrase is not on the item's page", () => {
+    expect(pickAnchor("lu et approuve", [hit(1, 72, 300)], sig())).toBeNull();
+  });
+});
+
+describe("withAnchors", () => {
+  it("stores the current offset from the phrase", () => {
+    const anchors = { "sig-item": { text: "Lu et approuvé", occurrence: 3, page: 2, origin: { x: 72, y: 300 } } };
+    const [item] = withAnchors([sig({ rect: { x: 80, y: 230, w: 150, h: 50 } })], anchors);
+    expect(item.anchor).toEqual({ text: "Lu et approuvé", occurrence: 3, offset: { x: 8, y: -70 } });
+  });
+
+  it("leaves items without a phrase, or moved to another page, unanchored", () => {
+    const anchors = { "sig-item": { text: "Lu et approuvé", occurrence: 1, page: 1, origin: { x: 72, y: 300 } } };
+    const items = withAnchors([sig(), sig({ id: "other" })], anchors);
+    expect(items.every((item) => item.anchor === undefined)).toBe(true);
+  });
+});
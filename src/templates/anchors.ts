@@ -0,0 +1,64 @@
+import type { Item, PdfRect } from "../types";
+import type { TemplateItem } from "./types";
+
+/** `search::SearchHit` of the Rust side, as returned by `search_pdf`. */
+export type SearchHit = {
+  page: number;
+  /** One rect per line the match spans, in PDF points. */
+  rects: PdfRect[];
+  context: string;
+};
+
+/**
+ * A phrase an item of the open document is tied to : the match the
+ * user picked, kept until the template is saved so the offset reflects
+ * wherever the item was moved in the meantime.
+ */
+export type PickedAnchor = {
+  text: string;
+  /** 1-indexed among every match of the document. */
+  occurrence: number;
+  page: number;
+  /** Bottom-left corner of the match's first line. */
+  origin: { x: number; y: number };
+};
+
+/**
+ * The match of `text` closest to `item` on the item's page, or null
+ * when the phrase does not appear there. `hits` are all the matches of
+ * the document, in reading order.
+ */
+export function pickAnchor(text: string, hits: SearchHit[], item: Item): PickedAnchor | null {
+  let best: PickedAnchor | null = null;
+  let bestDistance = Infinity;
+  for (let index = 0; index < hits.length; index++) {
+    const hit = hits[index];
+    const first = hit.rects[0];
+    if (hit.page !== item.page || !first) continue;
+    const distance = Math.hypot(first.x - item.rect.x, first.y - item.rect.y);
+    if (distance < bestDistance) {
+      bestDistance = distance;
+      best = { text, occurrence: index + 1, page: hit.page, origin: { x: first.x, y: first.y } };
+    }
+  }
+  return best;
+}
+
+/**
+ * `items` as stored in a template : those with a picked phrase get an
+ * anchor holding their current offset from it.
+ */
+export function withAnchors(items: Item[], anchors: Record<string, PickedAnchor>): TemplateItem[] {
+  return items.map((item) => {
+    const anchor = anchors[item.id];
+    if (!anchor || anchor.page !== item.page) return item;
+    return {
+      ...item,
+      anchor: {
+        text: anchor.text,
+        occurrence: anchor.occurrence,
+        offset: { x: item.rect.x - anchor.origin.x, y: item.rect.y - anchor.origin.y }
+      }
+    };
+  });
+}
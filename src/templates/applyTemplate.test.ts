@@ -1,7 +1,7 @@
 import { describe, expect, it } from "vitest";
 import { applyTemplate } from "./applyTemplate";
-import type { Template } from "./types";
-import type { Item, TextItem, SignatureItem, LineItem, Paraph } from "../types";
+import type { Template, TemplateItem } from "./types";
+import type { TextItem, SignatureItem, LineItem, Paraph } from "../types";
 
 function txt(overrides: Partial<TextItem> = {}): TextItem {
   return {
@@ -20,7 +20,7 @@ function txt(overrides: Partial<TextItem> = {}): TextItem {
   };
 }
 
-function emptyTemplate(items: Item[] = [], paraph: Paraph | null = null): Template {
+function emptyTemplate(items: TemplateItem[] = [], paraph: Paraph | null = null): Template {
   return {
     id: "tpl-1",
     name: "Test template",
@@ -126,6 +126,15 @@ describe("applyTemplate", () => {
     expect(line.start.x).toBe(10);
   });
 
+  it("drops anchors from the materialized items", () => {
+    const template = emptyTemplate([
+      { ...txt(), anchor: { text: "Lu et approuvé", offset: { x: 0, y: -40 } } }
+    ]);
+    const result = applyTemplate({ template, locale: "en" });
+    expect("anchor" in result.items[0]).toBe(false);
+    expect(template.items[0].anchor?.text).toBe("Lu et approuvé");
+  });
+
   it("preserves signature items' signatureId reference", () => {
     const sig: SignatureItem = {
       id: "tpl-sig",
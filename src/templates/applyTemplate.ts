@@ -46,6 +46,9 @@ export function applyTemplate(args: ApplyTemplateArgs): ApplyResult {
   const items: Item[] = args.template.items.map((it) => {
     const cloned = deepClone(it);
     cloned.id = uid();
+    // Anchors only matter to the template ; by now the host has had
+    // them resolved (or not) against the open document.
+    delete cloned.anchor;
     if (cloned.type === "text" && cloned.autoDate) {
       cloned.value = dateStr;
     }
@@ -1,5 +1,22 @@
 import type { Item, Paraph } from "../types";
 
+/**
+ * Ties an item to a phrase of the target document rather than to a
+ * fixed spot. When the template is applied, the Rust side
+ * (`resolve_template_anchors`) looks the phrase up in the PDF's text
+ * layer and moves the item to its page, `offset` away from the
+ * phrase's bottom-left corner (PDF points).
+ */
+export type TemplateAnchor = {
+  /** Matched ignoring case, accents and line breaks. */
+  text: string;
+  offset?: { x: number; y: number };
+  /** 1-indexed match ; negative counts from the end (-1 = last). Defaults to 1. */
+  occurrence?: number;
+};
+
+export type TemplateItem = Item & { anchor?: TemplateAnchor };
+
 /**
  * A saved overlay configuration. The user creates one by clicking
  * "Save as template" with the current document state ; later, they
@@ -15,7 +32,7 @@ export type Template = {
   name: string;
   /** ISO timestamp ; the modal sorts most-recent-first and shows it. */
   updatedAt: string;
-  items: Item[];
+  items: TemplateItem[];
   /** Persisted paraph configuration, or null if the user didn't have one. */
   paraph: Paraph | null;
 };
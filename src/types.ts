@@ -11,6 +11,27 @@ export type SignatureAsset = {
   dataUrl: string; // preview
   naturalW: number;
   naturalH: number;
+  /** Pen strokes of a drawn signature ; the native export draws them
+   *  as vectors instead of the PNG. */
+  ink?: InkDrawing;
+};
+
+/** One pen sample, in CSS pixels from the top left of the pad. */
+export type InkPoint = {
+  x: number;
+  y: number;
+  /** 0–1 as reported by the pointer (0.5 for a mouse). */
+  pressure: number;
+  /** Milliseconds since the first point of the drawing. */
+  t: number;
+};
+
+export type InkDrawing = {
+  width: number;
+  height: number;
+  penWidth: number;
+  color: string;
+  strokes: InkPoint[][];
 };
 
 export type TextItem = {
@@ -97,6 +118,50 @@ export type Item = TextItem | SignatureItem | CheckItem | EllipseItem | LineItem
  * `page` field : the rect applies to every page, and editing it on any
  * page updates the master.
  */
+/** `verify::CertificateSummary` of the Rust side ; dates in Unix
+ *  seconds. */
+export type CertificateSummary = {
+  subject: string;
+  issuer: string;
+  serial: string;
+  fingerprint: string;
+  notBefore: number;
+  notAfter: number;
+};
+
+export type SignatureReport = {
+  fieldName: string | null;
+  subFilter: string | null;
+  /** Raw `/M` entry, as claimed by the signer. */
+  signingTime: string | null;
+  /** Certified by a trusted timestamp, Unix seconds. */
+  timestamp: number | null;
+  byteRangeValid: boolean;
+  coversWholeDocument: boolean;
+  integrity: "valid" | "invalid" | "unsupported";
+  signer: CertificateSummary | null;
+  chain: CertificateSummary[];
+  trusted: boolean;
+  /** Diagnostics of the Rust side, not translated. */
+  modifications: string[];
+  problems: string[];
+};
+
+export type VerificationReport = {
+  signatures: SignatureReport[];
+  editingInvalidatesSignatures: boolean;
+};
+
+/** `crypt::Protection` of the Rust side : options of a protected
+ *  export, each denied permission opt-in. */
+export type PdfProtection = {
+  userPassword?: string;
+  ownerPassword?: string;
+  noPrint?: boolean;
+  noModify?: boolean;
+  noCopy?: boolean;
+};
+
 export type Paraph = {
   /** Id of the `SignatureAsset` in the paraphs gallery. */
   assetId: string;
@@ -0,0 +1,36 @@
+import { describe, expect, it } from "vitest";
+import { describeCommandError, isCommandError } from "./commandError";
+import { makeTranslator } from "../i18n";
+
+const t = makeTranslator("en");
+
+describe("isCommandError", () => {
+  it("recognizes the serialized Rust error", () => {
+    expect(isCommandError({ kind: "io", code: "store_write_failed" })).toBe(true);
+  });
+
+  it("rejects plain strings and unrelated objects", () => {
+    expect(isCommandError("lecture impossible")).toBe(false);
+    expect(isCommandError({ message: "boom" })).toBe(false);
+    expect(isCommandError(null)).toBe(false);
+  });
+});
+
+describe("describeCommandError", () => {
+  it("translates the kind and appends the path", () => {
+    const message = describeCommandError(t, {
+      kind: "out_of_scope",
+      code: "path_out_of_scope",
+      path: "/home/me/.ssh/id_rsa.pdf"
+    });
+    expect(message).toBe(`${t("error_out_of_scope")}\n/home/me/.ssh/id_rsa.pdf`);
+  });
+
+  it("omits the path line when there is none", () => {
+    expect(describeCommandError(t, { kind: "vault_locked", code: "vault_locked" })).toBe(t("error_vault_locked"));
+  });
+
+  it("falls back to the generic message for anything else", () => {
+    expect(describeCommandError(t, new Error("dialog closed"))).toBe(t("error_unknown"));
+  });
+});
@@ -0,0 +1,51 @@
+import type { TranslationKey } from "../i18n";
+
+/** `ErrorKind` of the Rust side (`src-tauri/src/error.rs`), snake_case. */
+export type CommandErrorKind =
+  | "not_found"
+  | "permission_denied"
+  | "io"
+  | "corrupt_data"
+  | "invalid_input"
+  | "out_of_scope"
+  | "invalid_pdf"
+  | "certificate"
+  | "authentication_failed"
+  | "vault_locked"
+  | "network"
+  | "unavailable"
+  | "newer_version";
+
+/**
+ * What every Tauri command rejects with. `code` is finer than `kind`
+ * (e.g. `store_write_failed`) ; `details` is the raw diagnostic, meant
+ * for the console rather than for users.
+ */
+export type CommandError = {
+  kind: CommandErrorKind;
+  code: string;
+  path?: string;
+  ioKind?: string;
+  details?: string;
+};
+
+export function isCommandError(value: unknown): value is CommandError {
+  return (
+    typeof value === "object" &&
+    value !== null &&
+    typeof (value as CommandError).kind === "string" &&
+    typeof (value as CommandError).code === "string"
+  );
+}
+
+/**
+ * User-facing message for a rejected `invoke`, translated from the
+ * error kind, with the offending path on its own line when there is
+ * one. Anything that is not a `CommandError` (plugin failures, thrown
+ * strings) gets the generic message.
+ */
+export function describeCommandError(t: (key: TranslationKey) => string, err: unknown): string {
+  if (!isCommandError(err)) return t("error_unknown");
+  const message = t(`error_${err.kind}` as TranslationKey);
+  return err.path ? `${message}\n${err.path}` : message;
+}